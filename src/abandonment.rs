@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use crate::grid::{Grid, RadiusShape};
+use crate::heatmaps::Heatmaps;
+use crate::menu::outside_pause;
+use crate::notifications::Notification;
+use crate::simulation::{Brownout, GameSpeed, Resources, SimulationSet, SIMULATION_TICK_SECONDS};
+use crate::town::{BuildingType, TownMap, ZoneType, TOWN_GRID_SIZE};
+use crate::GameState;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+pub struct AbandonmentPlugin;
+
+/// This plugin lets developed zones fall into disuse while they go without services
+impl Plugin for AbandonmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Abandonment>()
+            .add_systems(
+                Update,
+                neglect_zones.in_set(SimulationSet::Abandonment).run_if(in_state(GameState::TownView)),
+            )
+            .add_systems(OnExit(GameState::TownView), clear_abandonment.run_if(outside_pause));
+    }
+}
+
+// Seconds of game time a developed cell holds out without services before it loses a level
+const ABANDONMENT_GRACE_SECONDS: f32 = 30.0;
+
+// A cell needs a road within this many cells
+const ROAD_ACCESS_DISTANCE: i32 = 3;
+
+// Crime and pollution residents and businesses won't put up with for long
+const CRIME_LIMIT: f32 = 0.7;
+const POLLUTION_LIMIT: f32 = 0.7;
+
+#[derive(Resource, Default)]
+pub struct Abandonment {
+    // Seconds of game time each developed cell has gone without services
+    neglect: HashMap<IVec2, f32>,
+    // Cells that lost development and still go without, they don't develop again until served
+    abandoned: HashSet<IVec2>,
+}
+
+impl Abandonment {
+    pub fn is_abandoned(&self, pos: IVec2) -> bool {
+        self.abandoned.contains(&pos)
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.abandoned.iter().copied()
+    }
+}
+
+// Count up how long zones go without power, water or a road, or put up with crime or pollution,
+// and take a level of density from the ones that held out for the grace period. Population and
+// income follow from the capacity lost
+#[allow(clippy::too_many_arguments)]
+fn neglect_zones(
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    mut timer: Local<Timer>,
+    mut town_map: ResMut<TownMap>,
+    heatmaps: Res<Heatmaps>,
+    brownout: Res<Brownout>,
+    resources: Res<Resources>,
+    mut abandonment: ResMut<Abandonment>,
+    mut notifications: EventWriter<Notification>,
+) {
+    // Initialize timer if needed
+    if timer.duration() == Duration::ZERO {
+        *timer = Timer::from_seconds(SIMULATION_TICK_SECONDS, TimerMode::Repeating);
+    }
+
+    // Only update once per tick of game time
+    timer.tick(speed.delta(&time));
+    if !timer.just_finished() {
+        return;
+    }
+
+    let roads: HashSet<IVec2> = town_map
+        .iter()
+        .filter(|cell| cell.building.is_road())
+        .map(|cell| cell.position)
+        .collect();
+    let failing: HashSet<IVec2> = town_map
+        .iter()
+        .filter(|cell| cell.zone != ZoneType::None && cell.building == BuildingType::None)
+        .map(|cell| cell.position)
+        .filter(|&pos| {
+            let unpowered = brownout.is_dark(pos) || heatmaps.power(pos) <= 0.0;
+            let dry = resources.water.shortage() || heatmaps.water(pos) <= 0.0;
+            let cut_off = !Grid::cells_in_radius(pos, ROAD_ACCESS_DISTANCE, RadiusShape::Manhattan, TOWN_GRID_SIZE)
+                .iter()
+                .any(|cell| roads.contains(cell));
+            let unlivable = heatmaps.crime(pos) > CRIME_LIMIT || heatmaps.pollution(pos) > POLLUTION_LIMIT;
+            unpowered || dry || cut_off || unlivable
+        })
+        .collect();
+
+    // Cells served again are given a fresh start
+    abandonment.neglect.retain(|pos, _| failing.contains(pos));
+    abandonment.abandoned.retain(|pos| failing.contains(pos));
+
+    let mut declined = false;
+    for pos in failing {
+        let Some(cell) = town_map.get(pos) else {
+            continue;
+        };
+        if cell.density == 0 {
+            continue;
+        }
+        let neglect = abandonment.neglect.entry(pos).or_insert(0.0);
+        *neglect += SIMULATION_TICK_SECONDS;
+        if *neglect < ABANDONMENT_GRACE_SECONDS {
+            continue;
+        }
+
+        abandonment.neglect.remove(&pos);
+        abandonment.abandoned.insert(pos);
+        if let Some(cell) = town_map.cell_mut(pos) {
+            cell.density -= 1;
+        }
+        declined = true;
+    }
+
+    if declined {
+        notifications.send(Notification::warning(
+            "Zones are being abandoned, they need power, water, a road nearby and little crime or pollution",
+        ));
+    }
+}
+
+// Abandoned cells belong to the town they were left in
+fn clear_abandonment(mut abandonment: ResMut<Abandonment>) {
+    *abandonment = Abandonment::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{layout_cell, HeadlessSimulation};
+
+    #[test]
+    fn district_cut_off_from_power_is_abandoned() {
+        // A built up street of homes with power and water across the road
+        let plant = IVec2::new(14, 19);
+        let homes: Vec<IVec2> = (10..20).map(|x| IVec2::new(x, 21)).collect();
+        let mut cells: Vec<_> = (10..20)
+            .map(|x| layout_cell(x, 20, ZoneType::None, BuildingType::Road))
+            .collect();
+        for home in &homes {
+            let mut cell = layout_cell(home.x, home.y, ZoneType::Residential, BuildingType::None);
+            cell.density = 2;
+            cells.push(cell);
+        }
+        cells.push(layout_cell(plant.x, plant.y, ZoneType::None, BuildingType::PowerPlant));
+        cells.push(layout_cell(15, 19, ZoneType::None, BuildingType::WaterTower));
+
+        let mut simulation = HeadlessSimulation::new(&cells, 5);
+        simulation.run_ticks(10);
+        let housing = simulation.population().housing;
+        let world = simulation.app_mut().world_mut();
+        assert_eq!(world.resource::<Abandonment>().cells().count(), 0);
+
+        world.resource_mut::<TownMap>().cell_mut(plant).unwrap().building = BuildingType::None;
+        simulation.run_ticks((ABANDONMENT_GRACE_SECONDS / SIMULATION_TICK_SECONDS) as u32 + 5);
+
+        let world = simulation.app_mut().world();
+        let abandonment = world.resource::<Abandonment>();
+        let town_map = world.resource::<TownMap>();
+        for &home in &homes {
+            assert!(abandonment.is_abandoned(home), "{home} is still lived in");
+            assert!(town_map.get(home).unwrap().density < 2);
+        }
+        assert!(simulation.population().housing < housing);
+    }
+}
@@ -1,5 +1,7 @@
 use bevy::prelude::{ButtonInput, KeyCode, Res};
 
+use crate::actions::key_bindings::{InputAction, KeyBindings};
+
 pub enum GameControl {
     Up,
     Down,
@@ -8,26 +10,23 @@ pub enum GameControl {
 }
 
 impl GameControl {
-    pub fn pressed(&self, keyboard_input: &Res<ButtonInput<KeyCode>>) -> bool {
-        match self {
-            GameControl::Up => {
-                keyboard_input.pressed(KeyCode::KeyW) || keyboard_input.pressed(KeyCode::ArrowUp)
-            }
-            GameControl::Down => {
-                keyboard_input.pressed(KeyCode::KeyS) || keyboard_input.pressed(KeyCode::ArrowDown)
-            }
-            GameControl::Left => {
-                keyboard_input.pressed(KeyCode::KeyA) || keyboard_input.pressed(KeyCode::ArrowLeft)
-            }
-            GameControl::Right => {
-                keyboard_input.pressed(KeyCode::KeyD) || keyboard_input.pressed(KeyCode::ArrowRight)
-            }
-        }
+    pub fn pressed(&self, keyboard_input: &Res<ButtonInput<KeyCode>>, key_bindings: &KeyBindings) -> bool {
+        let action = match self {
+            GameControl::Up => InputAction::MoveUp,
+            GameControl::Down => InputAction::MoveDown,
+            GameControl::Left => InputAction::MoveLeft,
+            GameControl::Right => InputAction::MoveRight,
+        };
+        key_bindings.pressed(action, keyboard_input)
     }
 }
 
-pub fn get_movement(control: GameControl, input: &Res<ButtonInput<KeyCode>>) -> f32 {
-    if control.pressed(input) {
+pub fn get_movement(
+    control: GameControl,
+    input: &Res<ButtonInput<KeyCode>>,
+    key_bindings: &KeyBindings,
+) -> f32 {
+    if control.pressed(input, key_bindings) {
         1.0
     } else {
         0.0
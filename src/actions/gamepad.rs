@@ -0,0 +1,105 @@
+use bevy::input::gamepad::{GamepadAxisType, GamepadButtonType};
+use bevy::prelude::*;
+
+// Stick deflection below this is treated as resting
+const STICK_DEADZONE: f32 = 0.15;
+
+// Logical pixels per second the right stick moves the cursor at full deflection
+const CURSOR_SPEED: f32 = 600.0;
+
+// World units per second the left stick pans the camera at full deflection and unit zoom
+const PAN_SPEED: f32 = 400.0;
+
+// Fraction the triggers zoom per second when fully pressed, and the zoom limits
+const ZOOM_SPEED: f32 = 1.5;
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 8.0;
+
+// First connected gamepad, everything falls back to keyboard and mouse without one
+fn active_gamepad(gamepads: &Gamepads) -> Option<Gamepad> {
+    gamepads.iter().next()
+}
+
+fn stick(axes: &Axis<GamepadAxis>, gamepad: Gamepad, x: GamepadAxisType, y: GamepadAxisType) -> Vec2 {
+    let value = Vec2::new(
+        axes.get(GamepadAxis::new(gamepad, x)).unwrap_or(0.0),
+        axes.get(GamepadAxis::new(gamepad, y)).unwrap_or(0.0),
+    );
+    if value.length() < STICK_DEADZONE {
+        Vec2::ZERO
+    } else {
+        value
+    }
+}
+
+// Move the cursor with the right stick and turn the face buttons into mouse clicks, confirm (South)
+// being a left click and cancel (East) a right click. Runs before the UI looks at the mouse, so
+// menus, buttons and the town tools all work from the gamepad
+pub fn gamepad_cursor(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    mut mouse_button_input: ResMut<ButtonInput<MouseButton>>,
+    mut windows: Query<&mut Window>,
+    time: Res<Time>,
+) {
+    let Some(gamepad) = active_gamepad(&gamepads) else {
+        return;
+    };
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+
+    let movement = stick(&axes, gamepad, GamepadAxisType::RightStickX, GamepadAxisType::RightStickY);
+    if movement != Vec2::ZERO {
+        let size = Vec2::new(window.width(), window.height());
+        let cursor = window.cursor_position().unwrap_or(size / 2.0);
+        // Window coordinates grow downwards, the stick's upwards
+        let moved = cursor + Vec2::new(movement.x, -movement.y) * CURSOR_SPEED * time.delta_seconds();
+        window.set_cursor_position(Some(moved.clamp(Vec2::ZERO, size)));
+    }
+
+    for (button, mouse_button) in [
+        (GamepadButtonType::South, MouseButton::Left),
+        (GamepadButtonType::East, MouseButton::Right),
+    ] {
+        let button = GamepadButton::new(gamepad, button);
+        if buttons.just_pressed(button) {
+            mouse_button_input.press(mouse_button);
+        } else if buttons.just_released(button) {
+            mouse_button_input.release(mouse_button);
+        }
+    }
+}
+
+// Pan the camera with the left stick and zoom with the triggers, right in and left out
+pub fn gamepad_camera(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    triggers: Res<Axis<GamepadButton>>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection), With<Camera2d>>,
+    time: Res<Time>,
+) {
+    let Some(gamepad) = active_gamepad(&gamepads) else {
+        return;
+    };
+    let Ok((mut transform, mut projection)) = cameras.get_single_mut() else {
+        return;
+    };
+
+    let pan = stick(&axes, gamepad, GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY);
+    if pan != Vec2::ZERO {
+        transform.translation += (pan * PAN_SPEED * projection.scale * time.delta_seconds()).extend(0.0);
+    }
+
+    let trigger = |button| {
+        triggers
+            .get(GamepadButton::new(gamepad, button))
+            .unwrap_or(0.0)
+    };
+    let zoom = trigger(GamepadButtonType::LeftTrigger2) - trigger(GamepadButtonType::RightTrigger2);
+    if zoom.abs() > STICK_DEADZONE {
+        let scale = projection.scale * (1.0 + zoom * ZOOM_SPEED * time.delta_seconds());
+        projection.scale = scale.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+
+// Everything the player can do from the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum InputAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Pause,
+    NormalSpeed,
+    FastSpeed,
+    FastestSpeed,
+    Save,
+    Budget,
+    History,
+    EventLog,
+    LandValueOverlay,
+    CongestionOverlay,
+    CycleOverlay,
+    GridLines,
+    Legend,
+    FinishRoute,
+    // Backs out of whatever is in progress, e.g. a bus route, the select tool or a paste
+    #[serde(alias = "CancelRoute")]
+    Cancel,
+    Screenshot,
+    // Held while taking a screenshot to leave out the UI
+    CleanScreenshot,
+    ExportTownImage,
+    ExportTownLayout,
+    ImportTownLayout,
+    PauseMenu,
+    // Both held with Ctrl
+    CopySelection,
+    Paste,
+    RotateRoad,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 28] = [
+        InputAction::MoveUp,
+        InputAction::MoveDown,
+        InputAction::MoveLeft,
+        InputAction::MoveRight,
+        InputAction::Pause,
+        InputAction::NormalSpeed,
+        InputAction::FastSpeed,
+        InputAction::FastestSpeed,
+        InputAction::Save,
+        InputAction::Budget,
+        InputAction::History,
+        InputAction::EventLog,
+        InputAction::LandValueOverlay,
+        InputAction::CongestionOverlay,
+        InputAction::CycleOverlay,
+        InputAction::GridLines,
+        InputAction::Legend,
+        InputAction::FinishRoute,
+        InputAction::Cancel,
+        InputAction::Screenshot,
+        InputAction::CleanScreenshot,
+        InputAction::ExportTownImage,
+        InputAction::ExportTownLayout,
+        InputAction::ImportTownLayout,
+        InputAction::PauseMenu,
+        InputAction::CopySelection,
+        InputAction::Paste,
+        InputAction::RotateRoad,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            InputAction::MoveUp => "Move up",
+            InputAction::MoveDown => "Move down",
+            InputAction::MoveLeft => "Move left",
+            InputAction::MoveRight => "Move right",
+            InputAction::Pause => "Pause",
+            InputAction::NormalSpeed => "Normal speed",
+            InputAction::FastSpeed => "Fast speed",
+            InputAction::FastestSpeed => "Fastest speed",
+            InputAction::Save => "Save",
+            InputAction::Budget => "Budget",
+            InputAction::History => "History graphs",
+            InputAction::EventLog => "Event log",
+            InputAction::LandValueOverlay => "Land value overlay",
+            InputAction::CongestionOverlay => "Traffic overlay",
+            InputAction::CycleOverlay => "Next overlay",
+            InputAction::GridLines => "Grid lines",
+            InputAction::Legend => "Color legend",
+            InputAction::FinishRoute => "Finish bus route",
+            InputAction::Cancel => "Cancel",
+            InputAction::Screenshot => "Screenshot",
+            InputAction::CleanScreenshot => "Hold for no UI",
+            InputAction::ExportTownImage => "Export town image",
+            InputAction::ExportTownLayout => "Export town layout",
+            InputAction::ImportTownLayout => "Import town layout",
+            InputAction::PauseMenu => "Pause menu",
+            InputAction::CopySelection => "Copy selection (Ctrl)",
+            InputAction::Paste => "Paste (Ctrl)",
+            InputAction::RotateRoad => "Turn one-way road",
+        }
+    }
+
+    fn default_key(&self) -> KeyCode {
+        match self {
+            InputAction::MoveUp => KeyCode::KeyW,
+            InputAction::MoveDown => KeyCode::KeyS,
+            InputAction::MoveLeft => KeyCode::KeyA,
+            InputAction::MoveRight => KeyCode::KeyD,
+            InputAction::Pause => KeyCode::Space,
+            InputAction::NormalSpeed => KeyCode::Digit1,
+            InputAction::FastSpeed => KeyCode::Digit2,
+            InputAction::FastestSpeed => KeyCode::Digit3,
+            InputAction::Save => KeyCode::F5,
+            InputAction::Budget => KeyCode::KeyB,
+            InputAction::History => KeyCode::KeyH,
+            InputAction::EventLog => KeyCode::KeyN,
+            InputAction::LandValueOverlay => KeyCode::KeyL,
+            InputAction::CongestionOverlay => KeyCode::KeyT,
+            InputAction::CycleOverlay => KeyCode::KeyO,
+            InputAction::GridLines => KeyCode::KeyG,
+            InputAction::Legend => KeyCode::KeyK,
+            InputAction::FinishRoute => KeyCode::Enter,
+            InputAction::Cancel => KeyCode::Escape,
+            InputAction::Screenshot => KeyCode::F12,
+            InputAction::CleanScreenshot => KeyCode::ShiftLeft,
+            InputAction::ExportTownImage => KeyCode::F10,
+            InputAction::ExportTownLayout => KeyCode::F9,
+            InputAction::ImportTownLayout => KeyCode::F8,
+            InputAction::PauseMenu => KeyCode::KeyP,
+            InputAction::CopySelection => KeyCode::KeyC,
+            InputAction::Paste => KeyCode::KeyV,
+            InputAction::RotateRoad => KeyCode::KeyR,
+        }
+    }
+}
+
+// Key bound to each action, actions missing from a saved file keep their default key
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KeyBindings {
+    keys: HashMap<InputAction, KeyCode>,
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: InputAction) -> KeyCode {
+        self.keys.get(&action).copied().unwrap_or_else(|| action.default_key())
+    }
+
+    pub fn pressed(&self, action: InputAction, input: &ButtonInput<KeyCode>) -> bool {
+        input.pressed(self.key(action))
+    }
+
+    pub fn just_pressed(&self, action: InputAction, input: &ButtonInput<KeyCode>) -> bool {
+        input.just_pressed(self.key(action))
+    }
+
+    // Bind a key to an action, refused with the action already using the key
+    pub fn rebind(&mut self, action: InputAction, key: KeyCode) -> Result<(), InputAction> {
+        if let Some(other) = InputAction::ALL
+            .into_iter()
+            .find(|other| *other != action && self.key(*other) == key)
+        {
+            return Err(other);
+        }
+        self.keys.insert(action, key);
+        Ok(())
+    }
+}
+
+// Short name of a key for the UI, e.g. "B" for `KeyCode::KeyB`
+pub fn key_name(key: KeyCode) -> String {
+    let name = format!("{:?}", key);
+    name.strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(&name)
+        .to_string()
+}
@@ -1,24 +1,39 @@
-use bevy::math::Vec3Swizzles;
+use bevy::input::InputSystem;
 use bevy::prelude::*;
+use bevy::ui::UiSystem;
 
 use crate::actions::game_control::{get_movement, GameControl};
-use crate::player::Player;
+use crate::actions::gamepad::{gamepad_camera, gamepad_cursor};
 use crate::GameState;
 
 mod game_control;
+pub(crate) mod gamepad;
+mod key_bindings;
 
-pub const FOLLOW_EPSILON: f32 = 5.;
+pub use key_bindings::{key_name, InputAction, KeyBindings};
 
 pub struct ActionsPlugin;
 
 // This plugin listens for keyboard input and converts the input into Actions
 // Actions can then be used as a resource in other systems to act on the player input.
+// A connected gamepad drives the cursor, mouse buttons and camera.
 impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Actions>().add_systems(
-            Update,
-            set_movement_actions.run_if(in_state(GameState::Playing)),
-        );
+        app.init_resource::<Actions>()
+            .init_resource::<KeyBindings>()
+            .add_systems(
+                Update,
+                set_movement_actions.run_if(in_state(GameState::TownView)),
+            )
+            .add_systems(
+                PreUpdate,
+                gamepad_cursor.after(InputSystem).before(UiSystem::Focus),
+            )
+            .add_systems(
+                Update,
+                gamepad_camera
+                    .run_if(in_state(GameState::IslandView).or_else(in_state(GameState::TownView))),
+            );
     }
 }
 
@@ -30,28 +45,15 @@ pub struct Actions {
 pub fn set_movement_actions(
     mut actions: ResMut<Actions>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    touch_input: Res<Touches>,
-    player: Query<&Transform, With<Player>>,
-    camera: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    key_bindings: Res<KeyBindings>,
 ) {
-    let mut player_movement = Vec2::new(
-        get_movement(GameControl::Right, &keyboard_input)
-            - get_movement(GameControl::Left, &keyboard_input),
-        get_movement(GameControl::Up, &keyboard_input)
-            - get_movement(GameControl::Down, &keyboard_input),
+    let player_movement = Vec2::new(
+        get_movement(GameControl::Right, &keyboard_input, &key_bindings)
+            - get_movement(GameControl::Left, &keyboard_input, &key_bindings),
+        get_movement(GameControl::Up, &keyboard_input, &key_bindings)
+            - get_movement(GameControl::Down, &keyboard_input, &key_bindings),
     );
 
-    if let Some(touch_position) = touch_input.first_pressed_position() {
-        let (camera, camera_transform) = camera.single();
-        if let Some(touch_position) = camera.viewport_to_world_2d(camera_transform, touch_position)
-        {
-            let diff = touch_position - player.single().translation.xy();
-            if diff.length() > FOLLOW_EPSILON {
-                player_movement = diff.normalize();
-            }
-        }
-    }
-
     if player_movement != Vec2::ZERO {
         actions.player_movement = Some(player_movement.normalize());
     } else {
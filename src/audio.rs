@@ -1,8 +1,9 @@
-use crate::actions::{set_movement_actions, Actions};
 use crate::loading::AudioAssets;
 use crate::GameState;
 use bevy::prelude::*;
 use bevy_kira_audio::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 pub struct InternalAudioPlugin;
 
@@ -10,47 +11,187 @@ pub struct InternalAudioPlugin;
 impl Plugin for InternalAudioPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(AudioPlugin)
-            .add_systems(OnEnter(GameState::Playing), start_audio)
-            .add_systems(
-                Update,
-                control_flying_sound
-                    .after(set_movement_actions)
-                    .run_if(in_state(GameState::Playing)),
-            );
+            .init_resource::<AudioSettings>()
+            .init_resource::<Music>()
+            .add_event::<BuildSound>()
+            .add_systems(Update, play_build_sounds.run_if(in_state(GameState::TownView)))
+            .add_systems(Update, update_music_volume);
+        // Every state past loading picks its own music, crossfading from the previous one.
+        // The pause menu keeps the paused view's music
+        for state in [
+            GameState::Menu,
+            GameState::IslandView,
+            GameState::TownView,
+            GameState::SaveSlots,
+            GameState::Settings,
+            GameState::GameOver,
+        ] {
+            app.add_systems(OnEnter(state), switch_music);
+        }
     }
 }
 
-#[derive(Resource)]
-struct FlyingAudio(Handle<AudioInstance>);
+// Seconds the old music fades out while the new one fades in
+const MUSIC_CROSSFADE_SECONDS: f32 = 1.5;
 
-fn start_audio(mut commands: Commands, audio_assets: Res<AudioAssets>, audio: Res<Audio>) {
-    audio.pause();
-    let handle = audio
-        .play(audio_assets.flying.clone())
-        .looped()
-        .with_volume(0.3)
-        .handle();
-    commands.insert_resource(FlyingAudio(handle));
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MusicTrack {
+    Menu,
+    Island,
+    Town,
 }
 
-fn control_flying_sound(
-    actions: Res<Actions>,
-    audio: Res<FlyingAudio>,
+impl MusicTrack {
+    // Track played in a state, None for silence
+    fn for_state(state: &GameState) -> Option<MusicTrack> {
+        match state {
+            GameState::Loading | GameState::GameOver | GameState::Paused => None,
+            GameState::Menu | GameState::SaveSlots | GameState::Settings => Some(MusicTrack::Menu),
+            GameState::IslandView => Some(MusicTrack::Island),
+            GameState::TownView => Some(MusicTrack::Town),
+        }
+    }
+
+    fn source(&self, audio_assets: &AudioAssets) -> Handle<AudioSource> {
+        match self {
+            MusicTrack::Menu => audio_assets.menu_music.clone(),
+            MusicTrack::Island => audio_assets.island_music.clone(),
+            MusicTrack::Town => audio_assets.town_music.clone(),
+        }
+    }
+}
+
+// Music that's currently playing
+#[derive(Resource, Default)]
+struct Music {
+    playing: Option<(MusicTrack, Handle<AudioInstance>)>,
+}
+
+// Seconds that have to pass between two build sounds, so painting many cells doesn't turn into noise
+const BUILD_SOUND_INTERVAL_SECONDS: f32 = 0.15;
+
+// Volumes between 0 and 1, music plays at `master * music` and sound effects at `master * sfx`
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+    // Silences everything without losing the volumes
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master: 1.0,
+            music: 0.4,
+            sfx: 0.5,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    fn master_volume(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.master
+        }
+    }
+
+    pub fn music_volume(&self) -> f64 {
+        (self.master_volume() * self.music) as f64
+    }
+
+    pub fn sfx_volume(&self) -> f64 {
+        (self.master_volume() * self.sfx) as f64
+    }
+}
+
+// Sent by the town tools to give feedback on a click
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildSound {
+    Zone,
+    Building,
+    // A building was cleared to make room for a zone
+    Demolish,
+    // The placement was refused
+    Refused,
+}
+
+// Fade out the music of the previous state and fade in the one of the new state
+fn switch_music(
+    state: Res<State<GameState>>,
+    audio_assets: Res<AudioAssets>,
+    audio: Res<Audio>,
+    settings: Res<AudioSettings>,
+    mut music: ResMut<Music>,
     mut audio_instances: ResMut<Assets<AudioInstance>>,
 ) {
-    if let Some(instance) = audio_instances.get_mut(&audio.0) {
-        match instance.state() {
-            PlaybackState::Paused { .. } => {
-                if actions.player_movement.is_some() {
-                    instance.resume(AudioTween::default());
-                }
-            }
-            PlaybackState::Playing { .. } => {
-                if actions.player_movement.is_none() {
-                    instance.pause(AudioTween::default());
-                }
-            }
-            _ => {}
+    let track = MusicTrack::for_state(state.get());
+    if music.playing.as_ref().map(|(playing, _)| *playing) == track {
+        return;
+    }
+    let fade = AudioTween::linear(Duration::from_secs_f32(MUSIC_CROSSFADE_SECONDS));
+
+    if let Some((_, instance)) = music.playing.take() {
+        if let Some(instance) = audio_instances.get_mut(&instance) {
+            instance.stop(fade.clone());
         }
     }
+    if let Some(track) = track {
+        let instance = audio
+            .play(track.source(&audio_assets))
+            .looped()
+            .with_volume(settings.music_volume())
+            .fade_in(fade)
+            .handle();
+        music.playing = Some((track, instance));
+    }
+}
+
+fn update_music_volume(
+    settings: Res<AudioSettings>,
+    music: Res<Music>,
+    mut audio_instances: ResMut<Assets<AudioInstance>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(instance) = music
+        .playing
+        .as_ref()
+        .and_then(|(_, instance)| audio_instances.get_mut(instance))
+    {
+        instance.set_volume(settings.music_volume(), AudioTween::default());
+    }
+}
+
+fn play_build_sounds(
+    mut events: EventReader<BuildSound>,
+    audio_assets: Res<AudioAssets>,
+    audio: Res<Audio>,
+    settings: Res<AudioSettings>,
+    time: Res<Time>,
+    mut last_played: Local<Option<f32>>,
+) {
+    // Only the last sound of a frame is worth playing
+    let Some(sound) = events.read().last().copied() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    if last_played.is_some_and(|last| now - last < BUILD_SOUND_INTERVAL_SECONDS) {
+        return;
+    }
+    *last_played = Some(now);
+
+    let source = match sound {
+        BuildSound::Zone => &audio_assets.zone,
+        BuildSound::Building => &audio_assets.build,
+        BuildSound::Demolish => &audio_assets.demolish,
+        BuildSound::Refused => &audio_assets.refused,
+    };
+    audio.play(source.clone()).with_volume(settings.sfx_volume());
 }
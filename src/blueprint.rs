@@ -0,0 +1,367 @@
+use bevy::prelude::*;
+use crate::actions::{key_name, InputAction, KeyBindings};
+use crate::clock::TimeOfDay;
+use crate::export::town_file_stem;
+use crate::notifications::Notification;
+use crate::selection::{spawn_paste_ghost, Clipboard, PasteGhost};
+use crate::town::{check_cells, BuildingType, CellSave, ZoneType, TOWN_GRID_SIZE};
+use crate::GameState;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct BlueprintPlugin;
+
+/// This plugin keeps a library of copied areas as blueprint files, to be pasted again in any town
+impl Plugin for BlueprintPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (toggle_blueprint_palette, save_blueprint, pick_blueprint).run_if(in_state(GameState::TownView)),
+        );
+    }
+}
+
+// Directory the blueprints are kept in, relative to the working directory
+const BLUEPRINT_DIRECTORY: &str = "blueprints";
+
+// Area of zones and buildings saved for reuse, cells positioned from its lowest corner
+#[derive(Serialize, Deserialize)]
+pub struct Blueprint {
+    pub name: String,
+    pub width: i32,
+    pub height: i32,
+    // One character per cell, rows from the top, see `thumbnail_char`
+    pub thumbnail: Vec<String>,
+    pub cells: Vec<CellSave>,
+}
+
+impl Blueprint {
+    // Blueprint of copied cells, sized to fit them
+    pub fn new(name: String, cells: Vec<CellSave>) -> Self {
+        let size = cells
+            .iter()
+            .fold(IVec2::ZERO, |size, cell| size.max(cell.position + IVec2::ONE));
+        let mut thumbnail = vec![vec!['.'; size.x as usize]; size.y as usize];
+        for cell in cells.iter() {
+            thumbnail[(size.y - 1 - cell.position.y) as usize][cell.position.x as usize] = thumbnail_char(cell);
+        }
+        Blueprint {
+            name,
+            width: size.x,
+            height: size.y,
+            thumbnail: thumbnail.into_iter().map(String::from_iter).collect(),
+            cells,
+        }
+    }
+}
+
+fn thumbnail_char(cell: &CellSave) -> char {
+    match (cell.building, cell.zone) {
+        (building, _) if building.is_road() => '#',
+        (BuildingType::None, ZoneType::Residential) => 'R',
+        (BuildingType::None, ZoneType::Commercial) => 'C',
+        (BuildingType::None, ZoneType::Industrial) => 'I',
+        (BuildingType::None, ZoneType::None) => '.',
+        _ => '*',
+    }
+}
+
+pub fn write_blueprint(path: &Path, blueprint: &Blueprint) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(blueprint).map_err(|error| error.to_string())?;
+    fs::write(path, contents).map_err(|error| error.to_string())
+}
+
+// Read a blueprint, refusing ones larger than the town grid or with cells outside their own size,
+// listed twice or too dense
+pub fn read_blueprint(path: &Path) -> Result<Blueprint, String> {
+    let contents = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let blueprint: Blueprint = serde_json::from_str(&contents).map_err(|error| error.to_string())?;
+
+    let (width, height) = (blueprint.width, blueprint.height);
+    if width < 1 || height < 1 || width > TOWN_GRID_SIZE as i32 || height > TOWN_GRID_SIZE as i32 {
+        return Err(format!("{width}x{height} doesn't fit the {TOWN_GRID_SIZE}x{TOWN_GRID_SIZE} grid"));
+    }
+    check_cells(&blueprint.cells, width, height, "blueprint")?;
+    Ok(blueprint)
+}
+
+// Every readable blueprint in the directory with the file it came from, by name
+fn list_blueprints() -> Vec<(PathBuf, Blueprint)> {
+    let Ok(entries) = fs::read_dir(BLUEPRINT_DIRECTORY) else {
+        return Vec::new();
+    };
+    let mut blueprints: Vec<(PathBuf, Blueprint)> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+        .filter_map(|path| match read_blueprint(&path) {
+            Ok(blueprint) => Some((path, blueprint)),
+            Err(error) => {
+                error!("Skipping the blueprint {}: {error}", path.display());
+                None
+            }
+        })
+        .collect();
+    blueprints.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+    blueprints
+}
+
+// Toolbar button opening and closing the palette
+#[derive(Component)]
+struct BlueprintsButton;
+
+#[derive(Component)]
+struct BlueprintPalette;
+
+// Saves the copied cells as a new blueprint
+#[derive(Component)]
+struct SaveBlueprintButton;
+
+// Palette entry pasting the blueprint in the file
+#[derive(Component)]
+struct BlueprintEntry(PathBuf);
+
+// Spawn the toolbar button for the blueprint palette
+pub fn spawn_blueprints_button(parent: &mut ChildBuilder) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(80.0),
+                    height: Val::Px(40.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::srgb(0.2, 0.3, 0.5).into(),
+                ..default()
+            },
+            BlueprintsButton,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Blueprints",
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+// Open or close the palette with the toolbar button
+fn toggle_blueprint_palette(
+    mut commands: Commands,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<BlueprintsButton>)>,
+    palettes: Query<Entity, With<BlueprintPalette>>,
+) {
+    if !buttons.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        return;
+    }
+    match palettes.get_single() {
+        Ok(palette) => commands.entity(palette).despawn_recursive(),
+        Err(_) => spawn_blueprint_palette(&mut commands),
+    }
+}
+
+fn spawn_blueprint_palette(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(60.0),
+                    right: Val::Px(10.0),
+                    max_height: Val::Percent(70.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    padding: UiRect::all(Val::Px(6.0)),
+                    overflow: Overflow::clip_y(),
+                    ..default()
+                },
+                background_color: Color::srgba(0.1, 0.1, 0.1, 0.85).into(),
+                ..default()
+            },
+            Interaction::default(),
+            BlueprintPalette,
+        ))
+        .with_children(|parent| {
+            spawn_palette_button(parent, "Save copied cells".to_string(), SaveBlueprintButton);
+            let blueprints = list_blueprints();
+            if blueprints.is_empty() {
+                parent.spawn(TextBundle::from_section(
+                    "No blueprints yet, copy cells and save them here",
+                    TextStyle {
+                        font_size: 14.0,
+                        color: Color::srgb(0.7, 0.7, 0.7),
+                        ..default()
+                    },
+                ));
+            }
+            for (path, blueprint) in blueprints {
+                let label = format!(
+                    "{} ({} x {})\n{}",
+                    blueprint.name,
+                    blueprint.width,
+                    blueprint.height,
+                    blueprint.thumbnail.join("\n")
+                );
+                spawn_palette_button(parent, label, BlueprintEntry(path));
+            }
+        });
+}
+
+fn spawn_palette_button(parent: &mut ChildBuilder, label: String, extra: impl Bundle) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    min_width: Val::Px(160.0),
+                    padding: UiRect::all(Val::Px(6.0)),
+                    ..default()
+                },
+                background_color: Color::srgb(0.25, 0.25, 0.25).into(),
+                ..default()
+            },
+            extra,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 14.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+// Write the copied cells to a new blueprint file and show it in the palette
+fn save_blueprint(
+    mut commands: Commands,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<SaveBlueprintButton>)>,
+    clipboard: Res<Clipboard>,
+    palettes: Query<Entity, With<BlueprintPalette>>,
+    mut notifications: EventWriter<Notification>,
+) {
+    if !buttons.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        return;
+    }
+    if clipboard.cells.is_empty() {
+        notifications.send(Notification::info("Nothing to save, copy a selection with Ctrl+C first"));
+        return;
+    }
+
+    let name = format!("Blueprint {}", list_blueprints().len() + 1);
+    let path = PathBuf::from(BLUEPRINT_DIRECTORY)
+        .join(town_file_stem(&name))
+        .with_extension("json");
+    let blueprint = Blueprint::new(name, clipboard.cells.clone());
+    let result = fs::create_dir_all(BLUEPRINT_DIRECTORY)
+        .map_err(|error| error.to_string())
+        .and_then(|_| write_blueprint(&path, &blueprint));
+    match result {
+        Ok(()) => {
+            notifications.send(Notification::info(format!("{} saved to {}", blueprint.name, path.display())));
+            for palette in palettes.iter() {
+                commands.entity(palette).despawn_recursive();
+            }
+            spawn_blueprint_palette(&mut commands);
+        }
+        Err(error) => {
+            error!("Failed to save the blueprint: {error}");
+            notifications.send(Notification::warning(format!("Could not save the blueprint: {error}")));
+        }
+    }
+}
+
+// Copy the picked blueprint and start pasting it, the file is read again in case it was edited
+#[allow(clippy::too_many_arguments)]
+fn pick_blueprint(
+    mut commands: Commands,
+    entries: Query<(&Interaction, &BlueprintEntry), Changed<Interaction>>,
+    mut clipboard: ResMut<Clipboard>,
+    time_of_day: Res<TimeOfDay>,
+    ghosts: Query<Entity, With<PasteGhost>>,
+    palettes: Query<Entity, With<BlueprintPalette>>,
+    key_bindings: Res<KeyBindings>,
+    mut notifications: EventWriter<Notification>,
+) {
+    for (interaction, entry) in entries.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let blueprint = match read_blueprint(&entry.0) {
+            Ok(blueprint) => blueprint,
+            Err(error) => {
+                error!("Failed to load the blueprint {}: {error}", entry.0.display());
+                notifications.send(Notification::warning(format!("Could not load the blueprint: {error}")));
+                continue;
+            }
+        };
+        clipboard.cells = blueprint.cells;
+        for entity in ghosts.iter().chain(palettes.iter()) {
+            commands.entity(entity).despawn_recursive();
+        }
+        spawn_paste_ghost(&mut commands, &clipboard.cells, time_of_day.season());
+        notifications.send(Notification::info(format!(
+            "Pasting {}, click to place it and {} to stop",
+            blueprint.name,
+            key_name(key_bindings.key(InputAction::Cancel))
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{layout_cell, read_back};
+
+    fn write_and_read(test: &str, blueprint: &Blueprint) -> Result<Blueprint, String> {
+        read_back(
+            &format!("blueprint_{test}"),
+            |path| write_blueprint(path, blueprint).unwrap(),
+            read_blueprint,
+        )
+    }
+
+    // Two cells side by side, a road and a home
+    fn corner() -> Blueprint {
+        Blueprint::new(
+            "Corner".to_string(),
+            vec![
+                layout_cell(0, 0, ZoneType::None, BuildingType::Road),
+                layout_cell(1, 0, ZoneType::Residential, BuildingType::None),
+            ],
+        )
+    }
+
+    #[test]
+    fn blueprint_round_trips_through_a_file() {
+        let read = write_and_read("round_trip", &corner()).unwrap();
+        assert_eq!((read.width, read.height), (2, 1));
+        assert_eq!(read.thumbnail, vec!["#R".to_string()]);
+        assert_eq!(read.cells.len(), 2);
+    }
+
+    #[test]
+    fn blueprint_larger_than_the_grid_is_refused() {
+        let mut blueprint = corner();
+        blueprint.width = TOWN_GRID_SIZE as i32 + 1;
+        let error = write_and_read("too_large", &blueprint).err().unwrap();
+        assert!(error.contains("doesn't fit"), "{error}");
+
+        blueprint.width = 0;
+        assert!(write_and_read("empty", &blueprint).is_err());
+    }
+
+    #[test]
+    fn cells_are_checked_against_the_blueprint_size() {
+        let mut blueprint = corner();
+        blueprint.cells.push(layout_cell(0, 1, ZoneType::Commercial, BuildingType::None));
+        let error = write_and_read("outside", &blueprint).err().unwrap();
+        assert!(error.contains("outside the 2x1 blueprint"), "{error}");
+    }
+}
@@ -0,0 +1,264 @@
+use bevy::prelude::*;
+use crate::actions::{InputAction, KeyBindings};
+use crate::hud::format_thousands;
+use crate::simulation::{Economy, Loans, LOAN_AMOUNT, MAX_TAX_RATE};
+use crate::town::{BuildingType, TownMap, ZoneType};
+use crate::widgets::{spawn_slider, Slider};
+use crate::GameState;
+
+pub struct BudgetPlugin;
+
+/// This plugin shows the budget panel, where the tax rates are set
+impl Plugin for BudgetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (toggle_budget_panel, apply_tax_slider, take_loan, update_budget_text)
+                .run_if(in_state(GameState::TownView)),
+        );
+    }
+}
+
+#[derive(Component)]
+struct BudgetPanel;
+
+// Toolbar button opening and closing the budget panel
+#[derive(Component)]
+struct BudgetButton;
+
+// Sets the tax rate of a zone type
+#[derive(Component)]
+struct TaxRateSlider(ZoneType);
+
+// Zone types with their own tax rate, in the order of their sliders
+const TAXED_ZONES: [ZoneType; 3] = [ZoneType::Residential, ZoneType::Commercial, ZoneType::Industrial];
+
+// Button in the budget panel borrowing money from the bank
+#[derive(Component)]
+struct LoanButton;
+
+// Read-outs in the budget panel
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum BudgetField {
+    TaxRate(ZoneType),
+    Income,
+    Expenses,
+    Maintenance,
+    Mining,
+    Trade,
+    Net,
+    Loans,
+}
+
+// Spawn the toolbar button for the budget panel
+pub fn spawn_budget_button(parent: &mut ChildBuilder) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(80.0),
+                    height: Val::Px(40.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::srgb(0.2, 0.4, 0.2).into(),
+                ..default()
+            },
+            BudgetButton,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Budget",
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+// Open or close the budget panel with B or the toolbar button
+fn toggle_budget_panel(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<BudgetButton>)>,
+    panels: Query<Entity, With<BudgetPanel>>,
+    economy: Res<Economy>,
+) {
+    let pressed = buttons.iter().any(|interaction| *interaction == Interaction::Pressed);
+    if !pressed && !key_bindings.just_pressed(InputAction::Budget, &keyboard_input) {
+        return;
+    }
+
+    if let Ok(panel) = panels.get_single() {
+        commands.entity(panel).despawn_recursive();
+        return;
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(10.0),
+                    top: Val::Px(50.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    row_gap: Val::Px(6.0),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: Color::srgba(0.1, 0.1, 0.1, 0.85).into(),
+                ..default()
+            },
+            Interaction::default(),
+            BudgetPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Budget",
+                TextStyle {
+                    font_size: 22.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+            for zone in TAXED_ZONES {
+                spawn_budget_text(parent, BudgetField::TaxRate(zone));
+                spawn_slider(parent, economy.tax_rate(zone) / MAX_TAX_RATE, TaxRateSlider(zone));
+            }
+            spawn_budget_text(parent, BudgetField::Income);
+            spawn_budget_text(parent, BudgetField::Expenses);
+            spawn_budget_text(parent, BudgetField::Maintenance);
+            spawn_budget_text(parent, BudgetField::Mining);
+            spawn_budget_text(parent, BudgetField::Trade);
+            spawn_budget_text(parent, BudgetField::Net);
+            spawn_budget_text(parent, BudgetField::Loans);
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(200.0),
+                            height: Val::Px(30.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::srgb(0.2, 0.2, 0.4).into(),
+                        ..default()
+                    },
+                    LoanButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        format!("Take loan (${})", format_thousands(LOAN_AMOUNT)),
+                        TextStyle {
+                            font_size: 16.0,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ));
+                });
+        });
+}
+
+fn spawn_budget_text(parent: &mut ChildBuilder, field: BudgetField) {
+    parent.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        ),
+        field,
+    ));
+}
+
+// Write the slider positions to the tax rates
+fn apply_tax_slider(
+    sliders: Query<(&Slider, &TaxRateSlider), Changed<Slider>>,
+    mut economy: ResMut<Economy>,
+) {
+    for (slider, tax_rate) in sliders.iter() {
+        economy.set_tax_rate(tax_rate.0, slider.value * MAX_TAX_RATE);
+    }
+}
+
+// Borrow money when the loan button is pressed, unless the limit is reached
+fn take_loan(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<LoanButton>)>,
+    mut economy: ResMut<Economy>,
+    mut loans: ResMut<Loans>,
+) {
+    for interaction in buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            loans.borrow(&mut economy);
+        }
+    }
+}
+
+// Refresh the read-outs from the economy
+fn update_budget_text(
+    economy: Res<Economy>,
+    loans: Res<Loans>,
+    town_map: Res<TownMap>,
+    mut texts: Query<(&mut Text, &BudgetField)>,
+) {
+    for (mut text, field) in texts.iter_mut() {
+        let section = &mut text.sections[0];
+        match field {
+            BudgetField::TaxRate(zone) => {
+                section.value = format!("{:?} tax: {:.0}%", zone, economy.tax_rate(*zone) * 100.0);
+            }
+            BudgetField::Income => {
+                section.value = format!("Taxed income: ${}", format_thousands(economy.tax_income()));
+            }
+            BudgetField::Expenses => {
+                section.value = format!("Expenses: ${}", format_thousands(economy.expenses));
+            }
+            BudgetField::Maintenance => {
+                // Broken down by the types of buildings that cost anything to run
+                let mut value = format!("Maintenance: ${}", format_thousands(economy.maintenance));
+                for building in BuildingType::ALL.into_iter().filter(|building| building.upkeep() > 0) {
+                    let count = town_map.iter().filter(|cell| cell.building == building).count() as i32;
+                    if count > 0 {
+                        value.push_str(&format!(
+                            "\n  {} x{}: ${}",
+                            building.label(),
+                            count,
+                            format_thousands(count * building.upkeep())
+                        ));
+                    }
+                }
+                section.value = value;
+            }
+            BudgetField::Mining => {
+                section.value = format!("Mining: ${}", format_thousands(economy.mining));
+            }
+            BudgetField::Trade => {
+                section.value = format!("Trade: ${}", format_thousands(economy.trade));
+            }
+            BudgetField::Net => {
+                let net = economy.net_income();
+                section.value = format!("Net: ${}", format_thousands(net));
+                section.style.color = if net < 0 {
+                    Color::srgb(0.9, 0.2, 0.2)
+                } else {
+                    Color::srgb(0.2, 0.9, 0.2)
+                };
+            }
+            BudgetField::Loans => {
+                section.value = format!(
+                    "Loans: ${} of ${} (paying ${})",
+                    format_thousands(loans.principal),
+                    format_thousands(Loans::limit(&economy)),
+                    format_thousands(loans.payment()),
+                );
+            }
+        }
+    }
+}
@@ -1,36 +1,551 @@
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
 use bevy::prelude::*;
-use crate::town::{TownCell, ZoneType, BuildingType, TOWN_GRID_SIZE};
+use crate::town::{
+    grid_to_world, world_to_grid, RoadChanged, Town, TownCell, TownMap, ZoneType, BuildingType, TOWN_CELL_SPACING,
+    TOWN_GRID_SIZE,
+};
+use crate::traffic_lights::TrafficLights;
+use crate::clock::TimeOfDay;
+use crate::coverage::compute_coverage;
+use crate::departments::DepartmentBonuses;
 use crate::grid::Grid;
+use crate::notifications::Notification;
+use crate::rng::GameRng;
+use crate::simulation::{GameSpeed, ParkCoverage, Resources, SimulationSet};
+use crate::transit::TransitRoutes;
+use crate::menu::outside_pause;
 use crate::GameState;
 use rand::prelude::*;
+use std::time::Duration;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
 
 pub struct CitizenPlugin;
 
 impl Plugin for CitizenPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                spawn_citizens,
-                update_citizens,
-                spawn_vehicles,
-                update_vehicles,
-            ).run_if(in_state(GameState::TownView)),
+        app.init_resource::<PathCache>()
+            .init_resource::<SelectedCitizen>()
+            .init_resource::<WealthDistribution>()
+            .init_resource::<Congestion>()
+            .init_resource::<TrafficPollution>()
+            .init_resource::<Commutes>()
+            .add_event::<CitizenBorn>()
+            .add_event::<CitizenDied>()
+            .register_diagnostic(Diagnostic::new(PATH_CACHE_HITS).with_suffix(" hits"))
+            .register_diagnostic(Diagnostic::new(PATH_CACHE_MISSES).with_suffix(" misses"))
+            .add_systems(
+                Update,
+                (
+                    spawn_citizens,
+                    measure_commutes,
+                    update_citizens,
+                    update_lifecycle,
+                    update_wealth,
+                    invalidate_path_cache,
+                    spawn_vehicles,
+                    reroute_vehicles,
+                    update_vehicles,
+                    report_path_cache_diagnostics,
+                    select_citizen,
+                    update_citizen_panel,
+                )
+                    .chain()
+                    .in_set(SimulationSet::Citizens)
+                    .run_if(in_state(GameState::TownView)),
+            )
+            .add_systems(OnExit(GameState::TownView), despawn_citizens.run_if(outside_pause));
+    }
+}
+
+// Diagnostics for the path cache, shown by the `LogDiagnosticsPlugin` in debug builds
+pub const PATH_CACHE_HITS: DiagnosticPath = DiagnosticPath::const_new("path_cache/hits");
+pub const PATH_CACHE_MISSES: DiagnosticPath = DiagnosticPath::const_new("path_cache/misses");
+
+// Maximum number of routes kept in the path cache
+const PATH_CACHE_CAPACITY: usize = 256;
+
+// Cache of road routes keyed by (start, goal), evicting the least recently used entry when full
+#[derive(Resource, Default)]
+pub struct PathCache {
+    entries: HashMap<(IVec2, IVec2), CachedPath>,
+    clock: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+struct CachedPath {
+    path: Option<Vec<IVec2>>,
+    last_used: u64,
+}
+
+impl PathCache {
+    // Look up a route, counting the hit or miss. The outer `Option` is `None` on a miss,
+    // the inner one is `None` if the route is known to be unreachable
+    pub fn get(&mut self, start: IVec2, goal: IVec2) -> Option<Option<Vec<IVec2>>> {
+        self.clock += 1;
+        match self.entries.get_mut(&(start, goal)) {
+            Some(entry) => {
+                entry.last_used = self.clock;
+                self.hits += 1;
+                Some(entry.path.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, start: IVec2, goal: IVec2, path: Option<Vec<IVec2>>) {
+        if self.entries.len() >= PATH_CACHE_CAPACITY {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.clock += 1;
+        self.entries.insert(
+            (start, goal),
+            CachedPath {
+                path,
+                last_used: self.clock,
+            },
         );
     }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+// How far a destination may be from a road for a vehicle to drive there
+const MAX_ROAD_ACCESS_DISTANCE: i32 = 3;
+
+// Chance per vehicle spawn for a delivery instead of a citizen's trip
+const TRUCK_TRIP_CHANCE: f32 = 0.2;
+
+// Pollution a car leaves per second on a road cell, which fades at the decay rate per second
+const TRAFFIC_POLLUTION_RATE: f32 = 0.05;
+const TRAFFIC_POLLUTION_DECAY: f32 = 0.02;
+
+// Seconds a vehicle waits behind another before driving on regardless, so traffic can't lock up
+const MAX_VEHICLE_WAIT_SECONDS: f32 = 3.0;
+
+// Vehicles a road cell carries before traffic starts to slow down
+const CONGESTION_CAPACITY: f32 = 2.0;
+
+// Speed lost on a fully congested cell, capped so vehicles never stall
+const MAX_CONGESTION_SLOWDOWN: f32 = 0.7;
+
+// Congestion level from which vehicles look for a detour
+const JAM_THRESHOLD: f32 = 0.5;
+
+// Extra path cost of a fully congested road cell
+const CONGESTION_PATH_COST: f32 = 16.0;
+// Cost of driving through a road cell with the traffic flowing freely, less on faster roads
+const ROAD_PATH_COST: f32 = 4.0;
+// Cost of driving through an empty highway cell, the least any cell costs
+pub(crate) const MIN_PATH_COST: i32 = 2;
+
+// Happiness a commuting citizen loses per second stuck in full congestion
+const COMMUTE_HAPPINESS_PENALTY: f32 = 0.02;
+
+// Happiness lost each time a citizen finds no way to get to work
+const UNREACHABLE_WORK_HAPPINESS_PENALTY: f32 = 0.05;
+
+// Happiness regained per second at home right next to a park, less further away
+const PARK_HAPPINESS_RATE: f32 = 0.01;
+
+// Road cells from home to work from which a commute wears on a citizen the most
+const LONG_COMMUTE_CELLS: f32 = 40.0;
+// Happiness a citizen with a long commute loses per second at home, less for shorter ones
+const COMMUTE_LENGTH_HAPPINESS_RATE: f32 = 0.005;
+
+// Number of vehicles on each road cell, updated as vehicles move
+#[derive(Resource, PartialEq)]
+pub struct Congestion {
+    counts: [[u32; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+    // Vehicles a cell carries before slowing down, raised by the Transportation department
+    capacity: f32,
+    // Multiplier on the capacity from the road type of each cell, only kept where there's traffic
+    road_capacity: [[f32; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+}
+
+impl Default for Congestion {
+    fn default() -> Self {
+        Congestion {
+            counts: [[0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+            capacity: CONGESTION_CAPACITY,
+            road_capacity: [[1.0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+        }
+    }
+}
+
+impl Congestion {
+    // Count a vehicle taking up the given share of a road with the given capacity multiplier
+    fn add(&mut self, pos: IVec2, weight: u32, road_capacity: f32) {
+        if Grid::is_in_bounds(pos, TOWN_GRID_SIZE) {
+            self.counts[pos.y as usize][pos.x as usize] += weight;
+            self.road_capacity[pos.y as usize][pos.x as usize] = road_capacity;
+        }
+    }
+
+    // Congestion of a cell, 0 when the traffic flows freely and 1 when fully jammed
+    pub fn level(&self, pos: IVec2) -> f32 {
+        if !Grid::is_in_bounds(pos, TOWN_GRID_SIZE) {
+            return 0.0;
+        }
+        let (x, y) = (pos.x as usize, pos.y as usize);
+        self.level_of(self.counts[y][x], self.road_capacity[y][x])
+    }
+
+    fn level_of(&self, count: u32, road_capacity: f32) -> f32 {
+        let capacity = self.capacity * road_capacity;
+        ((count as f32 - capacity).max(0.0) / capacity).min(1.0)
+    }
+
+    // Traffic through a cell, 0 when empty and 1 once it's at capacity
+    pub fn traffic(&self, pos: IVec2) -> f32 {
+        if !Grid::is_in_bounds(pos, TOWN_GRID_SIZE) {
+            return 0.0;
+        }
+        let (x, y) = (pos.x as usize, pos.y as usize);
+        (self.counts[y][x] as f32 / (self.capacity * self.road_capacity[y][x])).min(1.0)
+    }
+
+    // Multiplier on the speed of traffic through a cell
+    pub fn speed_factor(&self, pos: IVec2) -> f32 {
+        1.0 - MAX_CONGESTION_SLOWDOWN * self.level(pos)
+    }
+
+    pub fn is_jammed(&self, pos: IVec2) -> bool {
+        self.level(pos) >= JAM_THRESHOLD
+    }
+
+    // Cost of driving through a cell for route planning, what it costs with the traffic flowing
+    // freely and more the more jammed it is
+    pub(crate) fn path_cost(&self, town_map: &TownMap, pos: IVec2) -> i32 {
+        travel_cost(town_map, pos) + (self.level(pos) * CONGESTION_PATH_COST).round() as i32
+    }
+
+    // Average congestion over the cells that carry any traffic
+    pub fn average(&self) -> f32 {
+        let (sum, busy) = self
+            .counts
+            .iter()
+            .flatten()
+            .zip(self.road_capacity.iter().flatten())
+            .filter(|(&count, _)| count > 0)
+            .fold((0.0, 0), |(sum, busy), (&count, &road_capacity)| {
+                (sum + self.level_of(count, road_capacity), busy + 1)
+            });
+        if busy > 0 {
+            sum / busy as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+// Cost of driving through a cell with the traffic flowing freely, at least `MIN_PATH_COST`.
+// Faster roads cost less, so long trips take the highway
+pub(crate) fn travel_cost(town_map: &TownMap, pos: IVec2) -> i32 {
+    let speed = town_map.get(pos).map_or(1.0, |cell| cell.building.road_speed());
+    (ROAD_PATH_COST / speed).round() as i32
+}
+
+// Exhaust left on road cells by passing vehicles, between 0 and 1
+#[derive(Resource)]
+pub struct TrafficPollution {
+    values: [[f32; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+}
+
+impl Default for TrafficPollution {
+    fn default() -> Self {
+        TrafficPollution {
+            values: [[0.0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+        }
+    }
+}
+
+impl TrafficPollution {
+    fn add(&mut self, pos: IVec2, amount: f32) {
+        let value = &mut self.values[pos.y as usize][pos.x as usize];
+        *value = (*value + amount).min(1.0);
+    }
+
+    fn decay(&mut self, delta_seconds: f32) {
+        let factor = (1.0 - TRAFFIC_POLLUTION_DECAY * delta_seconds).max(0.0);
+        for value in self.values.iter_mut().flatten() {
+            *value *= factor;
+        }
+    }
+
+    pub fn get(&self, pos: IVec2) -> f32 {
+        self.values[pos.y as usize][pos.x as usize]
+    }
+
+    // Average pollution over the cells that have any
+    pub fn average(&self) -> f32 {
+        let (sum, polluted) = self
+            .values
+            .iter()
+            .flatten()
+            .filter(|&&value| value > 0.0)
+            .fold((0.0, 0), |(sum, polluted), &value| (sum + value, polluted + 1));
+        if polluted > 0 {
+            sum / polluted as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+// Pools citizen names are drawn from. Names point into these instead of owning a string
+const FIRST_NAMES: [&str; 16] = [
+    "Alice", "Bob", "Carla", "David", "Elena", "Farid", "Greta", "Hiro",
+    "Ines", "Jonas", "Kemal", "Lena", "Marek", "Nadia", "Oscar", "Priya",
+];
+const LAST_NAMES: [&str; 12] = [
+    "Anders", "Baker", "Costa", "Dubois", "Eriksen", "Fischer",
+    "Garcia", "Hughes", "Ito", "Jensen", "Kowalski", "Lopez",
+];
+
+// How close (in world units) a click has to be to a citizen to select it
+const CITIZEN_PICK_RADIUS: f32 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CitizenName {
+    pub first: &'static str,
+    pub last: &'static str,
+}
+
+impl CitizenName {
+    fn random(rng: &mut impl Rng) -> Self {
+        CitizenName {
+            first: FIRST_NAMES[rng.gen_range(0..FIRST_NAMES.len())],
+            last: LAST_NAMES[rng.gen_range(0..LAST_NAMES.len())],
+        }
+    }
+}
+
+impl fmt::Display for CitizenName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.first, self.last)
+    }
+}
+
+// Years a citizen ages per in-game day, sped up so generations pass within a session
+const YEARS_PER_DAY: f32 = 2.0;
+
+// Age citizens move in or can have children from
+const ADULT_AGE: f32 = 18.0;
+
+// Citizens moving in are younger than this
+const MOVE_IN_MAX_AGE: f32 = 60.0;
+
+// Citizens stop having children at this age
+const PARENT_MAX_AGE: f32 = 45.0;
+
+// Chance per year for an adult to have a child in a perfectly happy town
+const BIRTH_RATE: f32 = 0.1;
+
+// Chance per year to die at any age, rising from `OLD_AGE` until certain at `LIFESPAN`
+const BASE_DEATH_RATE: f32 = 0.005;
+const OLD_AGE: f32 = 60.0;
+const LIFESPAN: f32 = 100.0;
+
+// Citizens living within this many cells of a hospital die less often
+const HOSPITAL_COVERAGE_RADIUS: i32 = 10;
+const HOSPITAL_DEATH_MULTIPLIER: f32 = 0.5;
+
+// Citizens living within this many cells of a school count as educated
+const SCHOOL_COVERAGE_RADIUS: i32 = 8;
+
+// Sent when a citizen is born in town
+#[derive(Event)]
+pub struct CitizenBorn;
+
+// Sent when a citizen dies of old age
+#[derive(Event)]
+pub struct CitizenDied {
+    pub name: CitizenName,
+    pub age: f32,
+}
+
+// How well off a citizen is, driven by employment and education
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wealth {
+    Low,
+    Medium,
+    High,
+}
+
+impl Wealth {
+    fn assess(employed: bool, educated: bool) -> Self {
+        match (employed, educated) {
+            (false, _) => Wealth::Low,
+            (true, false) => Wealth::Medium,
+            (true, true) => Wealth::High,
+        }
+    }
+
+    // Multiplier on the taxes a citizen pays
+    pub fn tax_multiplier(&self) -> f32 {
+        match self {
+            Wealth::Low => 0.6,
+            Wealth::Medium => 1.0,
+            Wealth::High => 1.8,
+        }
+    }
+}
+
+// How far a citizen's way to work is over the roads, measured again only when the home, the
+// workplace or the roads change
+#[derive(Component)]
+pub struct Commute {
+    home: IVec2,
+    workplace: Option<IVec2>,
+    // Cells walked through, None without work or a road leading there
+    pub length: Option<usize>,
+}
+
+impl Commute {
+    // How much the commute wears on the citizen, 0 without one and 1 for a long one
+    pub fn burden(&self) -> f32 {
+        self.length.map_or(0.0, |length| (length as f32 / LONG_COMMUTE_CELLS).min(1.0))
+    }
+}
+
+// Average length of the citizens' commutes
+#[derive(Resource, Default, PartialEq)]
+pub struct Commutes {
+    pub average_length: f32,
+}
+
+impl Commutes {
+    // How much the average commute wears on the town, 0 when everyone works next door
+    pub fn burden(&self) -> f32 {
+        (self.average_length / LONG_COMMUTE_CELLS).min(1.0)
+    }
+}
+
+// Number of citizens in each wealth class
+#[derive(Resource, Default)]
+pub struct WealthDistribution {
+    pub low: i32,
+    pub medium: i32,
+    pub high: i32,
+}
+
+impl WealthDistribution {
+    pub fn total(&self) -> i32 {
+        self.low + self.medium + self.high
+    }
+
+    // Fraction of citizens in a wealth class, 0 without citizens
+    pub fn share(&self, wealth: Wealth) -> f32 {
+        let count = match wealth {
+            Wealth::Low => self.low,
+            Wealth::Medium => self.medium,
+            Wealth::High => self.high,
+        };
+        if self.total() > 0 {
+            count as f32 / self.total() as f32
+        } else {
+            0.0
+        }
+    }
+
+    // Average tax multiplier over all citizens, 1 without citizens
+    pub fn tax_multiplier(&self) -> f32 {
+        if self.total() == 0 {
+            return 1.0;
+        }
+        [Wealth::Low, Wealth::Medium, Wealth::High]
+            .iter()
+            .map(|wealth| self.share(*wealth) * wealth.tax_multiplier())
+            .sum()
+    }
 }
 
 // Citizen component
 #[derive(Component)]
 pub struct Citizen {
+    pub name: CitizenName,
+    pub wealth: Wealth,
+    // Age in years, advancing with game time
+    pub age: f32,
     pub home: IVec2,
     pub workplace: Option<IVec2>,
     pub destination: IVec2,
+    // Cells walked through on the way to the destination, empty when no roads lead there
+    pub path: Vec<IVec2>,
+    pub path_index: usize,
     pub state: CitizenState,
     pub happiness: f32,
     pub timer: Timer,
 }
 
+impl Citizen {
+    // Head out from a cell to the destination along the roads. Returns false, leaving the citizen
+    // where it is, when no road leads there
+    fn set_off(&mut self, state: CitizenState, from: IVec2, destination: IVec2, town_map: &TownMap) -> bool {
+        let Some(path) = walking_path(town_map, from, destination) else {
+            return false;
+        };
+        self.path = path;
+        self.path_index = 0;
+        self.destination = destination;
+        self.state = state;
+        true
+    }
+
+    // Head home from a cell, going straight there if the roads no longer lead back so nobody
+    // gets stranded
+    fn head_home(&mut self, from: IVec2, town_map: &TownMap) {
+        self.path = walking_path(town_map, from, self.home).unwrap_or_default();
+        self.path_index = 0;
+        self.destination = self.home;
+        self.state = CitizenState::GoingHome;
+    }
+}
+
+// Cells to walk through from one cell to another over the roads
+fn walking_path(town_map: &TownMap, from: IVec2, to: IVec2) -> Option<Vec<IVec2>> {
+    let walkable = |pos: IVec2| {
+        pos == from || pos == to || town_map.get(pos).is_some_and(|cell| cell.building.is_road())
+    };
+    Grid::find_path_weighted(from, to, walkable, |_| 1, 1, TOWN_GRID_SIZE)
+}
+
+// Move a citizen up to the given distance along its path, or straight at the destination without
+// one. Returns true once the citizen has arrived
+fn walk(citizen: &mut Citizen, transform: &mut Transform, distance: f32) -> bool {
+    let target = grid_to_world(
+        citizen.path.get(citizen.path_index).copied().unwrap_or(citizen.destination),
+        1.0,
+    );
+    let offset = target - transform.translation;
+    if offset.length() > distance {
+        transform.translation += offset.normalize() * distance;
+        return false;
+    }
+    transform.translation = target;
+    if citizen.path_index < citizen.path.len() {
+        citizen.path_index += 1;
+    }
+    citizen.path_index >= citizen.path.len()
+}
+
 // Citizen state
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CitizenState {
@@ -41,23 +556,129 @@ pub enum CitizenState {
     Shopping,
 }
 
+// Kind of vehicle, deciding its looks, speed and how much traffic it makes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VehicleKind {
+    Car,
+    Truck,
+    Bus,
+    Emergency,
+}
+
+impl VehicleKind {
+    pub fn size(&self) -> Vec2 {
+        match self {
+            VehicleKind::Car | VehicleKind::Emergency => Vec2::new(6.0, 3.0),
+            VehicleKind::Truck => Vec2::new(8.0, 4.0),
+            VehicleKind::Bus => Vec2::new(10.0, 4.0),
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            VehicleKind::Car => Color::srgb(0.8, 0.2, 0.2),
+            VehicleKind::Truck => Color::srgb(0.55, 0.4, 0.25),
+            VehicleKind::Bus => Color::srgb(0.95, 0.75, 0.1),
+            VehicleKind::Emergency => Color::srgb(0.95, 0.95, 1.0),
+        }
+    }
+
+    pub fn speed_range(&self) -> Range<f32> {
+        match self {
+            VehicleKind::Car => 30.0..50.0,
+            VehicleKind::Truck => 20.0..30.0,
+            VehicleKind::Bus => 20.0..28.0,
+            VehicleKind::Emergency => 55.0..70.0,
+        }
+    }
+
+    // Speed through traffic slowing everyone down by the given factor, emergency vehicles get waved through
+    fn speed_in_traffic(&self, speed: f32, traffic_factor: f32) -> f32 {
+        if *self == VehicleKind::Emergency {
+            speed
+        } else {
+            speed * traffic_factor
+        }
+    }
+
+    // How many cars worth of road space the vehicle takes up
+    pub fn congestion_weight(&self) -> u32 {
+        match self {
+            VehicleKind::Truck | VehicleKind::Bus => 2,
+            VehicleKind::Car | VehicleKind::Emergency => 1,
+        }
+    }
+
+    // Pollution the vehicle leaves on the road per second, relative to a car
+    pub fn pollution(&self) -> f32 {
+        match self {
+            VehicleKind::Car | VehicleKind::Emergency => 1.0,
+            VehicleKind::Truck => 3.0,
+            VehicleKind::Bus => 1.5,
+        }
+    }
+}
+
+// Vehicle that stays put at the end of its path until whoever sent it gives it a new one,
+// like buses on their route and emergency vehicles
+#[derive(Component)]
+pub struct Dispatched;
+
 // Vehicle component
 #[derive(Component)]
 pub struct Vehicle {
+    pub kind: VehicleKind,
     pub start: IVec2,
     pub destination: IVec2,
     pub path: Vec<IVec2>,
     pub path_index: usize,
     pub speed: f32,
+    // Seconds spent waiting for the cell ahead to clear
+    pub waiting: f32,
+}
+
+// Vehicle on each road cell along with the cell it's heading for, the lowest id wins when several share one
+#[derive(Default)]
+struct Occupancy {
+    cells: HashMap<IVec2, (Entity, Option<IVec2>)>,
+}
+
+impl Occupancy {
+    fn occupy(&mut self, cell: IVec2, entity: Entity, heading: Option<IVec2>) {
+        self.cells
+            .entry(cell)
+            .and_modify(|occupant| {
+                if entity < occupant.0 {
+                    *occupant = (entity, heading);
+                }
+            })
+            .or_insert((entity, heading));
+    }
+
+    // Whether another vehicle holds the next cell. When two face each other the lower id goes first
+    fn blocks(&self, entity: Entity, current: IVec2, next: IVec2) -> bool {
+        match self.cells.get(&next) {
+            Some(&(occupant, heading)) if occupant != entity => {
+                let head_on = heading == Some(current);
+                !(head_on && entity < occupant)
+            }
+            _ => false,
+        }
+    }
 }
 
 // Spawn citizens based on residential zones
+#[allow(clippy::too_many_arguments)]
 fn spawn_citizens(
     mut commands: Commands,
-    town_cells: Query<&TownCell>,
+    town_map: Res<TownMap>,
     citizens: Query<&Citizen>,
     time: Res<Time>,
+    speed: Res<GameSpeed>,
     mut timer: Local<Timer>,
+    mut game_rng: ResMut<GameRng>,
+    bonuses: Res<DepartmentBonuses>,
+    resources: Res<Resources>,
 ) {
     // Initialize timer if needed
     if timer.duration() == Duration::ZERO {
@@ -65,36 +686,40 @@ fn spawn_citizens(
     }
     
     // Only spawn citizens periodically
-    timer.tick(time.delta());
+    timer.tick(speed.delta(&time));
     if !timer.just_finished() {
         return;
     }
     
+    // Nobody moves into a town that runs short of water
+    if resources.water.shortage() {
+        return;
+    }
+    
     // Find residential zones
-    let residential_zones: Vec<IVec2> = town_cells
+    let residential_zones: Vec<&TownCell> = town_map
         .iter()
         .filter(|cell| cell.zone == ZoneType::Residential)
-        .map(|cell| cell.position)
         .collect();
     
     // Find commercial and industrial zones for workplaces
-    let workplaces: Vec<IVec2> = town_cells
+    let workplaces: Vec<IVec2> = town_map
         .iter()
         .filter(|cell| cell.zone == ZoneType::Commercial || cell.zone == ZoneType::Industrial)
         .map(|cell| cell.position)
         .collect();
     
-    // Don't spawn more citizens than we have residential capacity
-    let max_citizens = residential_zones.len() * 5; // Each residential zone can house 5 citizens
-    if citizens.iter().count() >= max_citizens {
+    // Don't spawn more citizens than we have residential capacity, denser zones house more
+    let max_citizens: i32 = residential_zones.iter().map(|cell| bonuses.housing(cell.capacity())).sum();
+    if citizens.iter().count() as i32 >= max_citizens {
         return;
     }
     
     // Spawn new citizens in residential zones
-    let mut rng = rand::thread_rng();
+    let rng = &mut game_rng.rng;
     if !residential_zones.is_empty() {
         // Randomly select a residential zone
-        let home = residential_zones[rng.gen_range(0..residential_zones.len())];
+        let home = residential_zones[rng.gen_range(0..residential_zones.len())].position;
         
         // Assign a workplace if available
         let workplace = if !workplaces.is_empty() {
@@ -103,66 +728,233 @@ fn spawn_citizens(
             None
         };
         
-        // Spawn the citizen
-        commands.spawn((
-            SpriteBundle {
-                sprite: Sprite {
-                    color: Color::rgb(0.9, 0.9, 0.9),
-                    custom_size: Some(Vec2::new(3.0, 3.0)),
-                    ..default()
-                },
-                transform: Transform::from_translation(Vec3::new(
-                    (home.x as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-                    (home.y as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-                    1.0,
-                )),
+        let age = rng.gen_range(ADULT_AGE..MOVE_IN_MAX_AGE);
+        let wealth = Wealth::assess(workplace.is_some(), false);
+        spawn_citizen(&mut commands, rng, home, workplace, age, wealth);
+    }
+}
+
+fn spawn_citizen(
+    commands: &mut Commands,
+    rng: &mut impl Rng,
+    home: IVec2,
+    workplace: Option<IVec2>,
+    age: f32,
+    wealth: Wealth,
+) {
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::srgb(0.9, 0.9, 0.9),
+                custom_size: Some(Vec2::new(3.0, 3.0)),
                 ..default()
             },
-            Citizen {
-                home,
-                workplace,
-                destination: home,
-                state: CitizenState::AtHome,
-                happiness: 0.5,
-                timer: Timer::from_seconds(rng.gen_range(5.0..15.0), TimerMode::Once),
-            },
-        ));
+            transform: Transform::from_translation(grid_to_world(home, 1.0)),
+            ..default()
+        },
+        Citizen {
+            name: CitizenName::random(rng),
+            wealth,
+            age,
+            home,
+            workplace,
+            destination: home,
+            path: Vec::new(),
+            path_index: 0,
+            state: CitizenState::AtHome,
+            happiness: 0.5,
+            timer: Timer::from_seconds(rng.gen_range(5.0..15.0), TimerMode::Once),
+        },
+    ));
+}
+
+// Positions of all cells with the given building
+fn building_positions(town_map: &TownMap, building: BuildingType) -> Vec<IVec2> {
+    town_map
+        .iter()
+        .filter(|cell| cell.building == building)
+        .map(|cell| cell.position)
+        .collect()
+}
+
+// Chance per year for a citizen of the given age to die, reduced near a hospital
+fn yearly_death_chance(age: f32, near_hospital: bool) -> f64 {
+    let frailty = ((age - OLD_AGE) / (LIFESPAN - OLD_AGE)).clamp(0.0, 1.0);
+    let chance = BASE_DEATH_RATE + (1.0 - BASE_DEATH_RATE) * frailty * frailty;
+    let chance = if near_hospital {
+        chance * HOSPITAL_DEATH_MULTIPLIER
+    } else {
+        chance
+    };
+    chance as f64
+}
+
+// Age citizens with game time, letting the old ones die and adults have children while there is room
+#[allow(clippy::too_many_arguments)]
+fn update_lifecycle(
+    mut commands: Commands,
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    time_of_day: Res<TimeOfDay>,
+    town: Res<Town>,
+    mut citizens: Query<(Entity, &mut Citizen)>,
+    town_map: Res<TownMap>,
+    mut timer: Local<Timer>,
+    mut game_rng: ResMut<GameRng>,
+    mut born_events: EventWriter<CitizenBorn>,
+    mut died_events: EventWriter<CitizenDied>,
+    bonuses: Res<DepartmentBonuses>,
+    resources: Res<Resources>,
+) {
+    if timer.duration() == Duration::ZERO {
+        *timer = Timer::from_seconds(1.0, TimerMode::Repeating);
+    }
+
+    timer.tick(speed.delta(&time));
+    if !timer.just_finished() {
+        return;
+    }
+
+    let years = timer.duration().as_secs_f32() / time_of_day.seconds_per_day * YEARS_PER_DAY;
+    let hospitals = building_positions(&town_map, BuildingType::Hospital);
+    let hospital_radius = (HOSPITAL_COVERAGE_RADIUS as f32 * bonuses.hospital_radius).round() as i32;
+    let hospital_coverage = compute_coverage(&hospitals, hospital_radius, TOWN_GRID_SIZE);
+    let capacity: i32 = town_map
+        .iter()
+        .filter(|cell| cell.zone == ZoneType::Residential)
+        .map(|cell| bonuses.housing(cell.capacity()))
+        .sum();
+    let mut living = citizens.iter().count() as i32;
+    let rng = &mut game_rng.rng;
+
+    let mut newborn_homes = Vec::new();
+    for (entity, mut citizen) in citizens.iter_mut() {
+        citizen.age += years;
+
+        let near_hospital = hospital_coverage.contains_key(&citizen.home);
+        let death_chance =
+            yearly_death_chance(citizen.age, near_hospital) * (bonuses.death_rate * years) as f64;
+        if rng.gen_bool(death_chance.min(1.0)) {
+            died_events.send(CitizenDied {
+                name: citizen.name,
+                age: citizen.age,
+            });
+            commands.entity(entity).despawn_recursive();
+            living -= 1;
+            continue;
+        }
+
+        // Happier towns with free housing have more children, as long as there's water
+        if (ADULT_AGE..PARENT_MAX_AGE).contains(&citizen.age) && living < capacity && !resources.water.shortage() {
+            let birth_chance = (BIRTH_RATE * town.happiness.clamp(0.0, 1.0) * years) as f64;
+            if rng.gen_bool(birth_chance.min(1.0)) {
+                // Children grow up in their parent's wealth class
+                newborn_homes.push((citizen.home, citizen.wealth));
+                living += 1;
+            }
+        }
+    }
+
+    for (home, wealth) in newborn_homes {
+        spawn_citizen(&mut commands, rng, home, None, 0.0, wealth);
+        born_events.send(CitizenBorn);
+    }
+}
+
+// Reassess the wealth of adults from their job and schooling and count the classes
+fn update_wealth(
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    mut timer: Local<Timer>,
+    mut citizens: Query<&mut Citizen>,
+    town_map: Res<TownMap>,
+    mut distribution: ResMut<WealthDistribution>,
+    bonuses: Res<DepartmentBonuses>,
+) {
+    if timer.duration() == Duration::ZERO {
+        *timer = Timer::from_seconds(1.0, TimerMode::Repeating);
+    }
+
+    timer.tick(speed.delta(&time));
+    if !timer.just_finished() {
+        return;
     }
+
+    let schools = building_positions(&town_map, BuildingType::School);
+    let school_radius = (SCHOOL_COVERAGE_RADIUS as f32 * bonuses.school_radius).round() as i32;
+    let school_coverage = compute_coverage(&schools, school_radius, TOWN_GRID_SIZE);
+    *distribution = WealthDistribution::default();
+    for mut citizen in citizens.iter_mut() {
+        if citizen.age >= ADULT_AGE {
+            let educated = school_coverage.contains_key(&citizen.home);
+            citizen.wealth = Wealth::assess(citizen.workplace.is_some(), educated);
+        }
+        match citizen.wealth {
+            Wealth::Low => distribution.low += 1,
+            Wealth::Medium => distribution.medium += 1,
+            Wealth::High => distribution.high += 1,
+        }
+    }
+}
+
+// Happiness after resting at home for a while with the given park coverage and commute burden
+fn rest_near_park(happiness: f32, park: f32, commute: f32, delta_seconds: f32) -> f32 {
+    let rate = PARK_HAPPINESS_RATE * park - COMMUTE_LENGTH_HAPPINESS_RATE * commute;
+    (happiness + rate * delta_seconds).clamp(0.0, 1.0)
 }
 
 // Update citizen behavior
+#[allow(clippy::too_many_arguments)]
 fn update_citizens(
-    mut commands: Commands,
     time: Res<Time>,
-    mut citizens: Query<(Entity, &mut Citizen, &mut Transform)>,
-    town_cells: Query<&TownCell>,
+    speed: Res<GameSpeed>,
+    mut citizens: Query<(&mut Citizen, &mut Transform, Option<&Commute>)>,
+    town_map: Res<TownMap>,
+    time_of_day: Res<TimeOfDay>,
+    congestion: Res<Congestion>,
+    parks: Res<ParkCoverage>,
+    mut notifications: EventWriter<Notification>,
+    mut game_rng: ResMut<GameRng>,
 ) {
-    let mut rng = rand::thread_rng();
+    let rng = &mut game_rng.rng;
     
-    for (entity, mut citizen, mut transform) in citizens.iter_mut() {
+    for (mut citizen, mut transform, commute) in citizens.iter_mut() {
         // Update timer
-        citizen.timer.tick(time.delta());
+        citizen.timer.tick(speed.delta(&time));
         
         // Handle citizen state
         match citizen.state {
             CitizenState::AtHome => {
+                // A park nearby makes up for the commute, the further away work is the less so
+                let park = parks.get(citizen.home);
+                let commute = commute.map_or(0.0, Commute::burden);
+                citizen.happiness = rest_near_park(citizen.happiness, park, commute, speed.delta_seconds(&time));
+                
                 if citizen.timer.just_finished() {
-                    // Decide what to do next
-                    if citizen.workplace.is_some() && rng.gen_bool(0.7) {
-                        // Go to work
-                        citizen.destination = citizen.workplace.unwrap();
-                        citizen.state = CitizenState::GoingToWork;
-                    } else {
+                    // Decide what to do next, most citizens leave for work in the morning
+                    let work_chance = if time_of_day.is_morning() { 0.9 } else { 0.05 };
+                    if let Some(workplace) = citizen.workplace.filter(|_| rng.gen_bool(work_chance)) {
+                        // Go to work, a workplace no road leads to keeps the citizen home
+                        let home = citizen.home;
+                        if !citizen.set_off(CitizenState::GoingToWork, home, workplace, &town_map) {
+                            citizen.happiness = (citizen.happiness - UNREACHABLE_WORK_HAPPINESS_PENALTY).max(0.0);
+                            notifications.send(Notification::warning(
+                                "Citizens can't reach their work, connect homes and workplaces by road",
+                            ));
+                        }
+                    } else if time_of_day.is_shopping_time() && rng.gen_bool(0.3) {
                         // Go shopping
-                        let commercial_zones: Vec<IVec2> = town_cells
+                        let commercial_zones: Vec<IVec2> = town_map
                             .iter()
                             .filter(|cell| cell.zone == ZoneType::Commercial)
                             .map(|cell| cell.position)
                             .collect();
                         
                         if !commercial_zones.is_empty() {
-                            citizen.destination = commercial_zones[rng.gen_range(0..commercial_zones.len())];
-                            citizen.state = CitizenState::Shopping;
+                            let shop = commercial_zones[rng.gen_range(0..commercial_zones.len())];
+                            // A shop no road leads to is skipped, there's always the next trip
+                            let home = citizen.home;
+                            citizen.set_off(CitizenState::Shopping, home, shop, &town_map);
                         }
                     }
                     
@@ -171,65 +963,54 @@ fn update_citizens(
                 }
             }
             CitizenState::GoingToWork => {
-                // Move towards workplace
-                let workplace_pos = Vec3::new(
-                    (citizen.destination.x as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-                    (citizen.destination.y as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-                    1.0,
-                );
-                
-                let direction = (workplace_pos - transform.translation).normalize();
-                transform.translation += direction * 20.0 * time.delta_seconds();
+                // Commutes take longer through traffic, which wears on the citizen
+                let cell = world_to_grid(transform.translation.truncate()).unwrap_or_default();
+                let congestion_level = congestion.level(cell);
+                citizen.happiness = (citizen.happiness
+                    - COMMUTE_HAPPINESS_PENALTY * congestion_level * speed.delta_seconds(&time))
+                    .max(0.0);
+                let walk_speed = 20.0 * congestion.speed_factor(cell);
                 
-                // Check if arrived
-                if transform.translation.distance(workplace_pos) < 5.0 {
-                    transform.translation = workplace_pos;
+                // Move towards workplace
+                if walk(&mut citizen, &mut transform, walk_speed * speed.delta_seconds(&time)) {
                     citizen.state = CitizenState::AtWork;
                     citizen.timer = Timer::from_seconds(rng.gen_range(20.0..40.0), TimerMode::Once);
                 }
             }
             CitizenState::AtWork => {
                 if citizen.timer.just_finished() {
-                    // Go home after work
-                    citizen.destination = citizen.home;
-                    citizen.state = CitizenState::GoingHome;
-                    citizen.timer = Timer::from_seconds(rng.gen_range(5.0..10.0), TimerMode::Once);
+                    if time_of_day.is_evening_or_night() {
+                        // Go home after work
+                        let workplace = citizen.destination;
+                        citizen.head_home(workplace, &town_map);
+                        citizen.timer = Timer::from_seconds(rng.gen_range(5.0..10.0), TimerMode::Once);
+                    } else {
+                        // Keep working until the evening
+                        citizen.timer = Timer::from_seconds(rng.gen_range(2.0..5.0), TimerMode::Once);
+                    }
                 }
             }
             CitizenState::GoingHome => {
-                // Move towards home
-                let home_pos = Vec3::new(
-                    (citizen.home.x as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-                    (citizen.home.y as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-                    1.0,
-                );
-                
-                let direction = (home_pos - transform.translation).normalize();
-                transform.translation += direction * 20.0 * time.delta_seconds();
+                // Commutes take longer through traffic, which wears on the citizen
+                let cell = world_to_grid(transform.translation.truncate()).unwrap_or_default();
+                let congestion_level = congestion.level(cell);
+                citizen.happiness = (citizen.happiness
+                    - COMMUTE_HAPPINESS_PENALTY * congestion_level * speed.delta_seconds(&time))
+                    .max(0.0);
+                let walk_speed = 20.0 * congestion.speed_factor(cell);
                 
-                // Check if arrived
-                if transform.translation.distance(home_pos) < 5.0 {
-                    transform.translation = home_pos;
+                // Move towards home
+                if walk(&mut citizen, &mut transform, walk_speed * speed.delta_seconds(&time)) {
                     citizen.state = CitizenState::AtHome;
                     citizen.timer = Timer::from_seconds(rng.gen_range(10.0..30.0), TimerMode::Once);
                 }
             }
             CitizenState::Shopping => {
                 // Move towards shopping destination
-                let shop_pos = Vec3::new(
-                    (citizen.destination.x as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-                    (citizen.destination.y as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-                    1.0,
-                );
-                
-                let direction = (shop_pos - transform.translation).normalize();
-                transform.translation += direction * 20.0 * time.delta_seconds();
-                
-                // Check if arrived
-                if transform.translation.distance(shop_pos) < 5.0 {
+                if walk(&mut citizen, &mut transform, 20.0 * speed.delta_seconds(&time)) {
                     // Shop for a while, then go home
-                    citizen.destination = citizen.home;
-                    citizen.state = CitizenState::GoingHome;
+                    let shop = citizen.destination;
+                    citizen.head_home(shop, &town_map);
                     citizen.timer = Timer::from_seconds(rng.gen_range(5.0..10.0), TimerMode::Once);
                 }
             }
@@ -238,13 +1019,19 @@ fn update_citizens(
 }
 
 // Spawn vehicles based on citizen movement
+#[allow(clippy::too_many_arguments)]
 fn spawn_vehicles(
     mut commands: Commands,
     citizens: Query<&Citizen>,
-    town_cells: Query<&TownCell>,
+    town_map: Res<TownMap>,
     vehicles: Query<&Vehicle>,
     time: Res<Time>,
+    speed: Res<GameSpeed>,
     mut timer: Local<Timer>,
+    mut path_cache: ResMut<PathCache>,
+    congestion: Res<Congestion>,
+    transit_routes: Res<TransitRoutes>,
+    mut game_rng: ResMut<GameRng>,
 ) {
     // Initialize timer if needed
     if timer.duration() == Duration::ZERO {
@@ -252,7 +1039,7 @@ fn spawn_vehicles(
     }
     
     // Only spawn vehicles periodically
-    timer.tick(time.delta());
+    timer.tick(speed.delta(&time));
     if !timer.just_finished() {
         return;
     }
@@ -265,74 +1052,273 @@ fn spawn_vehicles(
     
     // Don't spawn too many vehicles
     let max_vehicles = 10;
-    if vehicles.iter().count() >= max_vehicles || traveling_citizens.is_empty() {
+    if vehicles.iter().count() >= max_vehicles {
         return;
     }
     
     // Find road cells
-    let road_cells: Vec<&TownCell> = town_cells
+    let road_cells: Vec<&TownCell> = town_map
         .iter()
-        .filter(|cell| cell.building == BuildingType::Road)
+        .filter(|cell| cell.building.is_road())
         .collect();
     
     if road_cells.is_empty() {
         return;
     }
     
-    // Randomly select a traveling citizen
-    let mut rng = rand::thread_rng();
-    let citizen = traveling_citizens[rng.gen_range(0..traveling_citizens.len())];
+    let positions = |predicate: fn(&TownCell) -> bool| -> Vec<IVec2> {
+        town_map.iter().filter(|cell| predicate(cell)).map(|cell| cell.position).collect()
+    };
+    let industrial = positions(|cell| cell.zone == ZoneType::Industrial);
+    let commercial = positions(|cell| cell.zone == ZoneType::Commercial);
+    
+    // Pick a trip: deliveries from industry to shops, otherwise a traveling citizen
+    // takes the car unless a bus route serves the trip
+    let rng = &mut game_rng.rng;
+    let (kind, origin, destination) = if rng.gen::<f32>() < TRUCK_TRIP_CHANCE
+        && !industrial.is_empty()
+        && !commercial.is_empty()
+    {
+        (
+            VehicleKind::Truck,
+            *industrial.choose(rng).unwrap(),
+            *commercial.choose(rng).unwrap(),
+        )
+    } else if let Some(citizen) = traveling_citizens.choose(rng) {
+        if transit_routes.serves(citizen.home, citizen.destination) {
+            return;
+        }
+        (VehicleKind::Car, citizen.home, citizen.destination)
+    } else {
+        return;
+    };
+    
+    // Any road close enough to either end can serve it
+    let roads_near = |position: IVec2| -> Vec<IVec2> {
+        road_cells
+            .iter()
+            .map(|cell| cell.position)
+            .filter(|pos| Grid::manhattan_distance(*pos, position) <= MAX_ROAD_ACCESS_DISTANCE)
+            .collect()
+    };
+    let dest_roads = roads_near(destination);
+    let mut start_roads = roads_near(origin);
+    start_roads.sort_by_key(|pos| (Grid::manhattan_distance(*pos, origin), pos.y, pos.x));
     
-    // Find nearest road to start and destination
-    let start_road = find_nearest_road(&road_cells, citizen.home);
-    let dest_road = find_nearest_road(&road_cells, citizen.destination);
+    // Find a path along roads, keeping to the direction of one-way ones
+    let can_drive = |from: IVec2, to: IVec2| -> bool {
+        town_map.get(to).is_some_and(|cell| cell.building.is_road()) && town_map.follows_traffic(from, to)
+    };
     
-    if let (Some(start), Some(dest)) = (start_road, dest_road) {
-        // Find a path along roads
-        let is_road = |pos: IVec2| -> bool {
-            road_cells.iter().any(|cell| cell.position == pos)
+    // Start from the closest road by the origin that's actually connected to the destination,
+    // reusing a previously computed route when possible. Otherwise route to whichever
+    // destination road the start reaches first, the quickest way there
+    let mut route = None;
+    for &start in start_roads.iter() {
+        let path = match path_cache.get(start, destination) {
+            Some(path) => path,
+            None => {
+                let path = Grid::find_path_to_nearest_directed(start, &dest_roads, can_drive, TOWN_GRID_SIZE)
+                    .and_then(|nearest| nearest.last().copied())
+                    .and_then(|goal| {
+                        Grid::find_path_directed(
+                            start,
+                            goal,
+                            can_drive,
+                            |cell| travel_cost(&town_map, cell),
+                            MIN_PATH_COST,
+                            TOWN_GRID_SIZE,
+                        )
+                    });
+                path_cache.insert(start, destination, path.clone());
+                path
+            }
         };
-        
-        if let Some(path) = Grid::find_path(start.position, dest.position, is_road, TOWN_GRID_SIZE) {
-            if !path.is_empty() {
-                // Spawn a vehicle
-                commands.spawn((
-                    SpriteBundle {
-                        sprite: Sprite {
-                            color: Color::rgb(0.8, 0.2, 0.2),
-                            custom_size: Some(Vec2::new(6.0, 3.0)),
-                            ..default()
-                        },
-                        transform: Transform::from_translation(Vec3::new(
-                            (start.position.x as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-                            (start.position.y as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-                            0.5,
-                        )),
-                        ..default()
-                    },
-                    Vehicle {
-                        start: start.position,
-                        destination: dest.position,
-                        path,
-                        path_index: 0,
-                        speed: rng.gen_range(30.0..50.0),
-                    },
-                ));
+        if let Some(path) = path {
+            route = Some((start, path));
+            break;
+        }
+    }
+    
+    let Some((start, path)) = route else {
+        return;
+    };
+    
+    // Detour around jams on the usual route, the detour isn't cached since jams clear up
+    let path = match path.last() {
+        Some(&goal) if path.iter().any(|&cell| congestion.is_jammed(cell)) => {
+            Grid::find_path_directed(
+                start,
+                goal,
+                can_drive,
+                |cell| congestion.path_cost(&town_map, cell),
+                MIN_PATH_COST,
+                TOWN_GRID_SIZE,
+            )
+            .unwrap_or(path)
+        }
+        _ => path,
+    };
+    
+    if let Some(&dest) = path.last() {
+        // Spawn a vehicle
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: kind.color(),
+                    custom_size: Some(kind.size()),
+                    ..default()
+                },
+                transform: Transform::from_translation(grid_to_world(start, 0.5)),
+                ..default()
+            },
+            Vehicle {
+                kind,
+                start,
+                destination: dest,
+                path,
+                path_index: 0,
+                speed: rng.gen_range(kind.speed_range()),
+                waiting: 0.0,
+            },
+        ));
+    }
+}
+
+// Measure the commutes of citizens who moved, changed jobs or had the roads change under them,
+// and keep the town's average up to date
+fn measure_commutes(
+    mut commands: Commands,
+    citizens: Query<(Entity, &Citizen, Option<&Commute>)>,
+    town_map: Res<TownMap>,
+    mut road_events: EventReader<RoadChanged>,
+    mut commutes: ResMut<Commutes>,
+) {
+    let roads_changed = road_events.read().count() > 0;
+    let (mut total, mut commuters) = (0, 0);
+    for (entity, citizen, commute) in citizens.iter() {
+        let length = match commute {
+            Some(commute)
+                if !roads_changed && commute.home == citizen.home && commute.workplace == citizen.workplace =>
+            {
+                commute.length
+            }
+            _ => {
+                let length = citizen
+                    .workplace
+                    .and_then(|workplace| walking_path(&town_map, citizen.home, workplace))
+                    .map(|path| path.len());
+                commands.entity(entity).insert(Commute {
+                    home: citizen.home,
+                    workplace: citizen.workplace,
+                    length,
+                });
+                length
             }
+        };
+        if let Some(length) = length {
+            total += length;
+            commuters += 1;
         }
     }
+    let average_length = if commuters > 0 { total as f32 / commuters as f32 } else { 0.0 };
+    commutes.set_if_neq(Commutes { average_length });
+}
+
+// Drop cached routes whenever the road network changes
+fn invalidate_path_cache(mut road_events: EventReader<RoadChanged>, mut path_cache: ResMut<PathCache>) {
+    if road_events.read().next().is_some() {
+        road_events.clear();
+        path_cache.clear();
+    }
+}
+
+// Send vehicles whose way ahead lost a road or now runs against a one-way road along a new route,
+// taking off those left without one
+fn reroute_vehicles(
+    mut commands: Commands,
+    mut road_events: EventReader<RoadChanged>,
+    town_map: Res<TownMap>,
+    mut vehicles: Query<(Entity, &mut Vehicle)>,
+) {
+    let is_road = |pos: IVec2| town_map.get(pos).is_some_and(|cell| cell.building.is_road());
+    let changed: Vec<IVec2> = road_events.read().map(|event| event.position).collect();
+    if changed.is_empty() {
+        return;
+    }
+    let removed: Vec<IVec2> = changed.into_iter().filter(|pos| !is_road(*pos)).collect();
+
+    for (entity, mut vehicle) in vehicles.iter_mut() {
+        let ahead = &vehicle.path[vehicle.path_index.min(vehicle.path.len())..];
+        if !ahead.iter().any(|cell| removed.contains(cell))
+            && ahead.windows(2).all(|step| town_map.follows_traffic(step[0], step[1]))
+        {
+            continue;
+        }
+        let (Some(&from), Some(&to)) = (ahead.first(), ahead.last()) else {
+            continue;
+        };
+        // The vehicle may stand on the removed road, and its destination needn't be a road
+        let passable = |pos: IVec2| pos == from || pos == to || is_road(pos);
+        let can_drive = |step_from: IVec2, step_to: IVec2| {
+            passable(step_to) && town_map.follows_traffic(step_from, step_to)
+        };
+        match Grid::find_path_directed(from, to, can_drive, |_| 1, 1, TOWN_GRID_SIZE) {
+            Some(path) => {
+                vehicle.path = path;
+                vehicle.path_index = 0;
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+}
+
+// Publish the path cache counters as diagnostics
+fn report_path_cache_diagnostics(mut diagnostics: Diagnostics, path_cache: Res<PathCache>) {
+    diagnostics.add_measurement(&PATH_CACHE_HITS, || path_cache.hits as f64);
+    diagnostics.add_measurement(&PATH_CACHE_MISSES, || path_cache.misses as f64);
 }
 
 // Update vehicle movement
+#[allow(clippy::too_many_arguments)]
 fn update_vehicles(
     mut commands: Commands,
     time: Res<Time>,
-    mut vehicles: Query<(Entity, &mut Vehicle, &mut Transform)>,
+    speed: Res<GameSpeed>,
+    mut vehicles: Query<(Entity, &mut Vehicle, &mut Transform, Has<Dispatched>)>,
+    mut congestion: ResMut<Congestion>,
+    mut pollution: ResMut<TrafficPollution>,
+    bonuses: Res<DepartmentBonuses>,
+    town_map: Res<TownMap>,
+    lights: Res<TrafficLights>,
 ) {
-    for (entity, mut vehicle, mut transform) in vehicles.iter_mut() {
-        if vehicle.path_index >= vehicle.path.len() - 1 {
-            // Vehicle has reached its destination, despawn it
-            commands.entity(entity).despawn();
+    // Count the vehicles on each road cell, only touching the resource when the traffic changed.
+    // Bigger vehicles take up more of the road and pollute more, wider roads carry more of them
+    let delta_seconds = speed.delta_seconds(&time);
+    pollution.decay(delta_seconds);
+    let mut counts = Congestion {
+        capacity: CONGESTION_CAPACITY * bonuses.road_capacity,
+        ..default()
+    };
+    let mut occupancy = Occupancy::default();
+    for (entity, vehicle, _, _) in vehicles.iter() {
+        if let Some(cell) = vehicle.path.get(vehicle.path_index) {
+            if Grid::is_in_bounds(*cell, TOWN_GRID_SIZE) {
+                let road_capacity = town_map.get(*cell).map_or(1.0, |cell| cell.building.road_capacity());
+                counts.add(*cell, vehicle.kind.congestion_weight(), road_capacity);
+                pollution.add(*cell, vehicle.kind.pollution() * TRAFFIC_POLLUTION_RATE * delta_seconds);
+            }
+            occupancy.occupy(*cell, entity, vehicle.path.get(vehicle.path_index + 1).copied());
+        }
+    }
+    congestion.set_if_neq(counts);
+    
+    for (entity, mut vehicle, mut transform, dispatched) in vehicles.iter_mut() {
+        if vehicle.path_index + 1 >= vehicle.path.len() {
+            // Vehicle has reached its destination, despawn it. Dispatched ones wait for new orders
+            if !dispatched {
+                commands.entity(entity).despawn();
+            }
             continue;
         }
         
@@ -340,21 +1326,36 @@ fn update_vehicles(
         let current = vehicle.path[vehicle.path_index];
         let next = vehicle.path[vehicle.path_index + 1];
         
+        // Wait for the vehicle ahead to clear the next cell. Anyone stuck too long (e.g. in a loop
+        // around a block) pushes on through, and emergency vehicles don't wait
+        if occupancy.blocks(entity, current, next)
+            && vehicle.kind != VehicleKind::Emergency
+            && vehicle.waiting < MAX_VEHICLE_WAIT_SECONDS
+        {
+            vehicle.waiting += delta_seconds;
+            continue;
+        }
+        vehicle.waiting = 0.0;
+        
         // Convert to world positions
-        let current_pos = Vec3::new(
-            (current.x as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-            (current.y as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-            0.5,
-        );
-        let next_pos = Vec3::new(
-            (next.x as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-            (next.y as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-            0.5,
-        );
+        let current_pos = grid_to_world(current, 0.5);
+        let next_pos = grid_to_world(next, 0.5);
         
-        // Calculate direction and move
+        // Stop at a red light on the way into an intersection. Vehicles already pulling into it
+        // drive on, and emergency vehicles don't stop
+        if !lights.allows(current, next)
+            && vehicle.kind != VehicleKind::Emergency
+            && transform.translation.distance(next_pos) > TOWN_CELL_SPACING / 2.0
+        {
+            continue;
+        }
+        
+        // Calculate direction and move, quicker on faster roads and slowed down by the traffic
+        // on the current cell
         let direction = (next_pos - current_pos).normalize();
-        transform.translation += direction * vehicle.speed * time.delta_seconds();
+        let road_speed = town_map.get(current).map_or(1.0, |cell| cell.building.road_speed());
+        let vehicle_speed = vehicle.kind.speed_in_traffic(vehicle.speed * road_speed, congestion.speed_factor(current));
+        transform.translation += direction * vehicle_speed * delta_seconds;
         
         // Rotate the vehicle to face the direction of travel
         let angle = direction.y.atan2(direction.x);
@@ -367,10 +1368,467 @@ fn update_vehicles(
     }
 }
 
-// Helper function to find the nearest road to a position
-fn find_nearest_road<'a>(road_cells: &[&'a TownCell], position: IVec2) -> Option<&'a TownCell> {
-    road_cells
+// Citizens belong to the shown town, the next town spawns its own
+fn despawn_citizens(
+    mut commands: Commands,
+    citizens: Query<Entity, Or<(With<Citizen>, With<Vehicle>)>>,
+    mut path_cache: ResMut<PathCache>,
+    mut congestion: ResMut<Congestion>,
+    mut pollution: ResMut<TrafficPollution>,
+) {
+    *congestion = Congestion::default();
+    *pollution = TrafficPollution::default();
+    for entity in citizens.iter() {
+        commands.entity(entity).despawn();
+    }
+    path_cache.clear();
+}
+
+// Citizen whose details are shown in the citizen panel
+#[derive(Resource, Default)]
+pub struct SelectedCitizen(pub Option<Entity>);
+
+#[derive(Component)]
+struct CitizenPanel;
+
+// Spawn the (initially hidden) citizen details panel above the toolbar
+pub fn spawn_citizen_panel(commands: &mut Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(10.0),
+                bottom: Val::Px(60.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            background_color: Color::srgba(0.1, 0.1, 0.1, 0.85).into(),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        CitizenPanel,
+    ));
+}
+
+// Select the citizen closest to a click, taking the click away from the town tools
+pub(crate) fn select_citizen(
+    mut mouse_button_input: ResMut<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    ui_interactions: Query<&Interaction, With<Node>>,
+    citizens: Query<(Entity, &Transform), With<Citizen>>,
+    mut selected: ResMut<SelectedCitizen>,
+) {
+    if !mouse_button_input.just_pressed(MouseButton::Left)
+        || ui_interactions.iter().any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), camera_q.get_single()) else {
+        return;
+    };
+    let Some(world_position) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+    else {
+        return;
+    };
+
+    let closest = citizens
         .iter()
-        .min_by_key(|cell| Grid::manhattan_distance(cell.position, position))
-        .copied()
+        .map(|(entity, transform)| (entity, transform.translation.truncate().distance(world_position)))
+        .filter(|&(_, distance)| distance <= CITIZEN_PICK_RADIUS)
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+
+    selected.0 = closest.map(|(entity, _)| entity);
+    if selected.0.is_some() {
+        mouse_button_input.clear_just_pressed(MouseButton::Left);
+    }
+}
+
+fn update_citizen_panel(
+    mut selected: ResMut<SelectedCitizen>,
+    citizens: Query<&Citizen>,
+    mut panel: Query<(&mut Text, &mut Visibility), With<CitizenPanel>>,
+) {
+    let Ok((mut text, mut visibility)) = panel.get_single_mut() else {
+        return;
+    };
+
+    // Citizens can disappear while selected
+    let Some(citizen) = selected.0.and_then(|entity| citizens.get(entity).ok()) else {
+        selected.0 = None;
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    let workplace = match citizen.workplace {
+        Some(workplace) => format!("works at ({}, {})", workplace.x, workplace.y),
+        None => "unemployed".to_string(),
+    };
+    text.sections[0].value = format!(
+        "{}, {:.0}\n{:?}, {:?} wealth\nlives at ({}, {}), {}\nhappiness {:.0}%",
+        citizen.name,
+        citizen.age,
+        citizen.state,
+        citizen.wealth,
+        citizen.home.x,
+        citizen.home.y,
+        workplace,
+        citizen.happiness * 100.0,
+    );
+    *visibility = Visibility::Visible;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+
+    // A citizen at home with a job at the given workplace
+    fn commuter(home: IVec2, workplace: IVec2) -> Citizen {
+        Citizen {
+            name: CitizenName { first: "Ada", last: "Smith" },
+            wealth: Wealth::Medium,
+            age: 30.0,
+            home,
+            workplace: Some(workplace),
+            destination: home,
+            path: Vec::new(),
+            path_index: 0,
+            state: CitizenState::AtHome,
+            happiness: 0.5,
+            timer: Timer::from_seconds(1.0, TimerMode::Once),
+        }
+    }
+
+    #[test]
+    fn path_cache_counts_hits_and_misses() {
+        let mut cache = PathCache::default();
+        let (start, goal) = (IVec2::new(0, 0), IVec2::new(3, 0));
+        assert_eq!(cache.get(start, goal), None);
+        cache.insert(start, goal, Some(vec![start, goal]));
+        assert_eq!(cache.get(start, goal), Some(Some(vec![start, goal])));
+        // Unreachable routes are remembered too
+        cache.insert(goal, start, None);
+        assert_eq!(cache.get(goal, start), Some(None));
+        assert_eq!((cache.hits, cache.misses), (2, 1));
+    }
+
+    #[test]
+    fn full_path_cache_evicts_the_least_recently_used_route() {
+        let mut cache = PathCache::default();
+        let route = |index: usize| (IVec2::new(index as i32, 0), IVec2::new(0, index as i32));
+        for index in 0..PATH_CACHE_CAPACITY {
+            let (start, goal) = route(index);
+            cache.insert(start, goal, None);
+        }
+        // The first route is used again, leaving the second one the oldest
+        let (start, goal) = route(0);
+        assert!(cache.get(start, goal).is_some());
+
+        let (start, goal) = route(PATH_CACHE_CAPACITY);
+        cache.insert(start, goal, None);
+        assert_eq!(cache.entries.len(), PATH_CACHE_CAPACITY);
+        let (start, goal) = route(1);
+        assert_eq!(cache.get(start, goal), None);
+        let (start, goal) = route(0);
+        assert!(cache.get(start, goal).is_some());
+    }
+
+    #[test]
+    fn closed_town_population_settles_within_its_homes() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(200)))
+            .insert_resource(GameRng::new(7))
+            .insert_resource(Town {
+                happiness: 1.0,
+                ..default()
+            })
+            .init_resource::<GameSpeed>()
+            .init_resource::<TimeOfDay>()
+            .init_resource::<DepartmentBonuses>()
+            .init_resource::<Resources>()
+            .add_event::<CitizenBorn>()
+            .add_event::<CitizenDied>()
+            .add_systems(Update, (spawn_citizens, update_lifecycle.after(spawn_citizens)));
+        let mut town_map = TownMap::default();
+        for x in 0..4 {
+            let cell = town_map.cell_mut(IVec2::new(x, 0)).unwrap();
+            cell.zone = ZoneType::Residential;
+            cell.accessible = true;
+        }
+        let homes: i32 = town_map.iter().map(TownCell::capacity).sum();
+        app.insert_resource(town_map);
+
+        // Nobody moves in or is born once the homes are full, so the town fills up and stays full
+        let mut citizens = app.world_mut().query::<&Citizen>();
+        let mut counts = Vec::new();
+        for _ in 0..2000 {
+            app.update();
+            let count = citizens.iter(app.world()).count() as i32;
+            assert!(count <= homes, "{count} citizens in {homes} homes");
+            counts.push(count);
+        }
+        assert!(counts[1500..].iter().all(|&count| count * 10 >= homes * 9), "{:?} in {homes} homes", &counts[1500..]);
+    }
+
+    #[test]
+    fn wealth_comes_from_a_job_and_schooling() {
+        assert_eq!(Wealth::assess(false, true), Wealth::Low);
+        assert_eq!(Wealth::assess(true, false), Wealth::Medium);
+        assert_eq!(Wealth::assess(true, true), Wealth::High);
+    }
+
+    #[test]
+    fn town_tax_multiplier_averages_the_wealth_classes() {
+        assert_eq!(WealthDistribution::default().tax_multiplier(), 1.0);
+        let distribution = WealthDistribution { low: 1, medium: 2, high: 1 };
+        assert_eq!(distribution.share(Wealth::Medium), 0.5);
+        assert!((distribution.tax_multiplier() - (0.6 + 2.0 + 1.8) / 4.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn congestion_rises_past_capacity_and_tops_out_when_jammed() {
+        let congestion = Congestion::default();
+        let capacity = CONGESTION_CAPACITY as u32;
+        assert_eq!(congestion.level_of(0, 1.0), 0.0);
+        assert_eq!(congestion.level_of(capacity, 1.0), 0.0);
+        assert!(congestion.level_of(capacity + 1, 1.0) > 0.0);
+        assert_eq!(congestion.level_of(capacity * 2, 1.0), 1.0);
+        assert_eq!(congestion.level_of(capacity * 10, 1.0), 1.0);
+
+        // Wider roads from the Transportation department carry more before slowing down
+        let widened = Congestion {
+            capacity: CONGESTION_CAPACITY * 2.0,
+            ..default()
+        };
+        assert_eq!(widened.level_of(capacity * 2, 1.0), 0.0);
+    }
+
+    #[test]
+    fn avenues_and_highways_carry_more_traffic() {
+        let mut congestion = Congestion::default();
+        let (road, highway) = (IVec2::new(1, 1), IVec2::new(2, 1));
+        let load = CONGESTION_CAPACITY as u32 * 2;
+        congestion.add(road, load, BuildingType::Road.road_capacity());
+        congestion.add(highway, load, BuildingType::Highway.road_capacity());
+        assert_eq!(congestion.level(road), 1.0);
+        assert_eq!(congestion.level(highway), 0.0);
+        assert!(congestion.traffic(highway) < congestion.traffic(road));
+        assert!(BuildingType::Avenue.road_capacity() > BuildingType::Road.road_capacity());
+        assert!(BuildingType::Highway.road_capacity() > BuildingType::Avenue.road_capacity());
+    }
+
+    #[test]
+    fn long_trips_take_the_highway_and_short_ones_the_street() {
+        // A street along y = 10 and a highway along y = 12, joined by ramps at both ends
+        let mut town_map = TownMap::default();
+        for x in 0..31 {
+            town_map.cell_mut(IVec2::new(x, 10)).unwrap().building = BuildingType::Road;
+            town_map.cell_mut(IVec2::new(x, 12)).unwrap().building = BuildingType::Highway;
+        }
+        for x in [0, 30] {
+            town_map.cell_mut(IVec2::new(x, 11)).unwrap().building = BuildingType::Road;
+        }
+        assert_eq!(travel_cost(&town_map, IVec2::new(5, 12)), MIN_PATH_COST);
+        let drivable = |pos: IVec2| town_map.get(pos).is_some_and(|cell| cell.building.is_road());
+        let route = |from: IVec2, to: IVec2| {
+            Grid::find_path_weighted(from, to, drivable, |cell| travel_cost(&town_map, cell), MIN_PATH_COST, TOWN_GRID_SIZE)
+                .unwrap()
+        };
+
+        let long = route(IVec2::new(0, 10), IVec2::new(30, 10));
+        assert!(long.contains(&IVec2::new(15, 12)), "the long trip kept to the street");
+        let short = route(IVec2::new(0, 10), IVec2::new(3, 10));
+        assert_eq!(short.len(), 4);
+    }
+
+    #[test]
+    fn jammed_traffic_slows_down_without_stalling() {
+        let mut congestion = Congestion::default();
+        let (free, jammed) = (IVec2::new(1, 1), IVec2::new(2, 1));
+        congestion.counts[jammed.y as usize][jammed.x as usize] = CONGESTION_CAPACITY as u32 * 10;
+
+        assert_eq!(congestion.speed_factor(free), 1.0);
+        assert!(!congestion.is_jammed(free));
+        assert!(congestion.is_jammed(jammed));
+        assert!((congestion.speed_factor(jammed) - (1.0 - MAX_CONGESTION_SLOWDOWN)).abs() < 1e-6);
+        assert!(congestion.speed_factor(jammed) > 0.0);
+        // Cells off the map carry no traffic
+        assert_eq!(congestion.level(IVec2::new(-1, 0)), 0.0);
+    }
+
+    #[test]
+    fn vehicle_speeds_stay_within_their_caps() {
+        let kinds = [VehicleKind::Car, VehicleKind::Truck, VehicleKind::Bus, VehicleKind::Emergency];
+        let car = VehicleKind::Car.speed_range();
+        for kind in [VehicleKind::Truck, VehicleKind::Bus] {
+            assert!(kind.speed_range().end <= car.start, "{kind:?} can outrun a car");
+        }
+        assert!(VehicleKind::Emergency.speed_range().start >= car.end);
+
+        let jammed = 1.0 - MAX_CONGESTION_SLOWDOWN;
+        for kind in kinds {
+            let range = kind.speed_range();
+            assert!(range.start > 0.0 && range.start < range.end);
+            assert!(kind.speed_in_traffic(range.start, jammed) > 0.0, "{kind:?} stalls in a jam");
+            assert!(kind.speed_in_traffic(range.end, 1.0) <= range.end);
+        }
+        assert_eq!(VehicleKind::Emergency.speed_in_traffic(60.0, jammed), 60.0);
+        assert!(VehicleKind::Car.speed_in_traffic(40.0, jammed) < 40.0);
+    }
+
+    #[test]
+    fn lower_id_goes_first_when_vehicles_meet_head_on() {
+        let (first, second) = (Entity::from_raw(1), Entity::from_raw(2));
+        let (a, b, c) = (IVec2::new(0, 0), IVec2::new(1, 0), IVec2::new(2, 0));
+        let mut occupancy = Occupancy::default();
+        occupancy.occupy(a, first, Some(b));
+        occupancy.occupy(b, second, Some(a));
+
+        assert!(!occupancy.blocks(first, a, b));
+        assert!(occupancy.blocks(second, b, a));
+        // Nothing holds the cell beyond
+        assert!(!occupancy.blocks(second, b, c));
+    }
+
+    #[test]
+    fn vehicles_queue_behind_the_one_ahead() {
+        let (leader, follower) = (Entity::from_raw(5), Entity::from_raw(1));
+        let (a, b, c) = (IVec2::new(0, 0), IVec2::new(1, 0), IVec2::new(2, 0));
+        let mut occupancy = Occupancy::default();
+        occupancy.occupy(b, leader, Some(c));
+        occupancy.occupy(a, follower, Some(b));
+
+        // Following in the same direction, a lower id doesn't help
+        assert!(occupancy.blocks(follower, a, b));
+        assert!(!occupancy.blocks(leader, b, c));
+
+        // Sharing a cell, the lower id holds it
+        occupancy.occupy(b, follower, Some(c));
+        assert!(occupancy.blocks(leader, a, b));
+        assert!(!occupancy.blocks(follower, a, b));
+    }
+
+    #[test]
+    fn vehicles_detour_around_a_one_way_road_against_them() {
+        use crate::headless::{layout_cell, HeadlessSimulation};
+        use crate::town::RoadDirection;
+
+        // A block with streets along y = 20 and y = 22, joined at both ends
+        let mut cells = Vec::new();
+        for x in 10..17 {
+            cells.push(layout_cell(x, 20, ZoneType::None, BuildingType::Road));
+            cells.push(layout_cell(x, 22, ZoneType::None, BuildingType::Road));
+        }
+        cells.push(layout_cell(10, 21, ZoneType::None, BuildingType::Road));
+        cells.push(layout_cell(16, 21, ZoneType::None, BuildingType::Road));
+
+        let mut simulation = HeadlessSimulation::new(&cells, 3);
+        let world = simulation.app_mut().world_mut();
+        let straight: Vec<IVec2> = (10..17).rev().map(|x| IVec2::new(x, 20)).collect();
+        let vehicle = world
+            .spawn((
+                Transform::from_translation(grid_to_world(straight[0], 0.5)),
+                Vehicle {
+                    kind: VehicleKind::Car,
+                    start: straight[0],
+                    destination: IVec2::new(10, 20),
+                    path: straight,
+                    path_index: 0,
+                    speed: 1.0,
+                    waiting: 0.0,
+                },
+                Dispatched,
+            ))
+            .id();
+
+        // Heading west, the middle of the street turns one-way to the east under the car
+        let one_way = IVec2::new(13, 20);
+        world.resource_mut::<TownMap>().cell_mut(one_way).unwrap().building =
+            BuildingType::OneWayRoad(RoadDirection::East);
+        world.send_event(RoadChanged { position: one_way });
+        simulation.run_ticks(1);
+
+        let world = simulation.app_mut().world_mut();
+        let path = world.get::<Vehicle>(vehicle).unwrap().path.clone();
+        assert!(!path.contains(&one_way));
+        assert!(path.contains(&IVec2::new(13, 22)), "the car didn't go around the block");
+        let town_map = world.resource::<TownMap>();
+        assert!(path.windows(2).all(|step| town_map.follows_traffic(step[0], step[1])));
+        assert!(!town_map.follows_traffic(IVec2::new(14, 20), one_way));
+        assert!(town_map.follows_traffic(IVec2::new(12, 20), one_way));
+    }
+
+    #[test]
+    fn citizens_living_by_a_park_are_happier() {
+        let parks = ParkCoverage::from_parks([IVec2::new(5, 5)]);
+        let (near, far) = (IVec2::new(5, 6), IVec2::new(5, 15));
+        assert!(parks.get(near) > 0.0);
+        assert_eq!(parks.get(far), 0.0);
+
+        let (mut near_happiness, mut far_happiness) = (0.5, 0.5);
+        for _ in 0..10 {
+            near_happiness = rest_near_park(near_happiness, parks.get(near), 0.0, 1.0);
+            far_happiness = rest_near_park(far_happiness, parks.get(far), 0.0, 1.0);
+        }
+        assert!(near_happiness > far_happiness);
+        assert_eq!(far_happiness, 0.5);
+        assert_eq!(rest_near_park(1.0, 1.0, 0.0, 10.0), 1.0);
+        // A long commute wears on citizens, a park only makes up for some of it
+        assert!(rest_near_park(0.5, parks.get(near), 1.0, 1.0) < rest_near_park(0.5, parks.get(near), 0.0, 1.0));
+        assert!(rest_near_park(0.5, parks.get(far), 1.0, 1.0) < 0.5);
+    }
+
+    #[test]
+    fn citizens_only_set_off_for_work_a_road_leads_to() {
+        let (home, workplace) = (IVec2::new(2, 2), IVec2::new(2, 6));
+        let mut town_map = TownMap::default();
+        let mut citizen = commuter(home, workplace);
+        assert!(!citizen.set_off(CitizenState::GoingToWork, home, workplace, &town_map));
+        assert_eq!(citizen.state, CitizenState::AtHome);
+        assert_eq!(citizen.destination, home);
+
+        for y in 3..6 {
+            town_map.cell_mut(IVec2::new(2, y)).unwrap().building = BuildingType::Road;
+        }
+        assert!(citizen.set_off(CitizenState::GoingToWork, home, workplace, &town_map));
+        assert_eq!(citizen.state, CitizenState::GoingToWork);
+        assert_eq!(citizen.destination, workplace);
+        assert_eq!(citizen.path.last(), Some(&workplace));
+    }
+
+    fn commute(length: Option<usize>) -> Commute {
+        Commute {
+            home: IVec2::ZERO,
+            workplace: length.map(|_| IVec2::new(5, 0)),
+            length,
+        }
+    }
+
+    #[test]
+    fn commute_burden_grows_with_length_up_to_a_long_one() {
+        assert_eq!(commute(None).burden(), 0.0);
+        assert_eq!(commute(Some(0)).burden(), 0.0);
+        assert_eq!(commute(Some(10)).burden(), 0.25);
+        assert_eq!(commute(Some(40)).burden(), 1.0);
+        assert_eq!(commute(Some(400)).burden(), 1.0);
+    }
+
+    #[test]
+    fn town_commute_burden_follows_the_average_length() {
+        assert_eq!(Commutes { average_length: 0.0 }.burden(), 0.0);
+        assert_eq!(Commutes { average_length: 20.0 }.burden(), 0.5);
+        assert_eq!(Commutes { average_length: 80.0 }.burden(), 1.0);
+    }
 }
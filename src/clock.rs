@@ -0,0 +1,195 @@
+use bevy::color::Mix;
+use bevy::prelude::*;
+use crate::simulation::{GameSpeed, SimulationSet};
+use crate::menu::outside_pause;
+use crate::GameState;
+use std::f32::consts::TAU;
+use serde::{Deserialize, Serialize};
+
+pub struct ClockPlugin;
+
+/// This plugin advances the in-game clock and lights the town according to the time of day
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TimeOfDay>()
+            .add_event::<SeasonChanged>()
+            .add_systems(
+                Update,
+                (advance_clock, tint_clear_color)
+                    .chain()
+                    .in_set(SimulationSet::Clock)
+                    .run_if(in_state(GameState::TownView)),
+            )
+            .add_systems(OnExit(GameState::TownView), reset_clear_color.run_if(outside_pause));
+    }
+}
+
+// Hours of the day citizens prefer to head to work
+pub const MORNING_START_HOUR: f32 = 6.0;
+pub const MORNING_END_HOUR: f32 = 10.0;
+
+// From this hour on citizens head home from work
+pub const EVENING_START_HOUR: f32 = 17.0;
+
+// Shops are visited between these hours
+pub const SHOPPING_START_HOUR: f32 = 9.0;
+pub const SHOPPING_END_HOUR: f32 = 20.0;
+
+// Background color at noon, matching the clear color set up in `main`
+const DAYLIGHT_CLEAR_COLOR: Color = Color::linear_rgb(0.4, 0.4, 0.4);
+
+// Fraction of daylight left at midnight
+const MIDNIGHT_BRIGHTNESS: f32 = 0.25;
+
+// Seasons of the year, each lasting `TimeOfDay::days_per_season` days
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Season {
+    #[default]
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    // Multiplier on the town's power consumption, heating drives it up in winter
+    pub fn power_demand(&self) -> f32 {
+        match self {
+            Season::Winter => 1.5,
+            Season::Autumn => 1.2,
+            _ => 1.0,
+        }
+    }
+
+    // Multiplier on the population growth rate
+    pub fn growth(&self) -> f32 {
+        match self {
+            Season::Spring => 1.2,
+            Season::Summer => 1.0,
+            Season::Autumn => 0.9,
+            Season::Winter => 0.7,
+        }
+    }
+
+    // Multiplier on the land value bonus of parks, which are most attractive in summer
+    pub fn park_appeal(&self) -> f32 {
+        match self {
+            Season::Spring => 1.1,
+            Season::Summer => 1.3,
+            Season::Autumn => 0.9,
+            Season::Winter => 0.5,
+        }
+    }
+
+    // Seasonal tint for greenery like parks and forests
+    pub fn tint_foliage(&self, color: Color) -> Color {
+        let (tint, amount) = match self {
+            Season::Spring => return color,
+            Season::Summer => (Srgba::rgb(0.0, 0.4, 0.0), 0.3),
+            Season::Autumn => (Srgba::rgb(0.8, 0.4, 0.1), 0.6),
+            Season::Winter => (Srgba::rgb(0.9, 0.9, 0.95), 0.6),
+        };
+        color.to_srgba().mix(&tint, amount).into()
+    }
+}
+
+// Sent when a new season starts, which `TimeOfDay::season` then returns
+#[derive(Event)]
+pub struct SeasonChanged;
+
+// In-game clock, wrapping every 24 hours
+#[derive(Resource, Clone, Serialize, Deserialize)]
+pub struct TimeOfDay {
+    pub hours: f32,
+    // Days passed since the game started
+    pub day: u32,
+    // Real-time seconds a full in-game day lasts at 1x speed
+    pub seconds_per_day: f32,
+    pub days_per_season: u32,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        TimeOfDay {
+            hours: 8.0,
+            day: 0,
+            seconds_per_day: 240.0,
+            days_per_season: 7,
+        }
+    }
+}
+
+impl TimeOfDay {
+    pub fn season(&self) -> Season {
+        match (self.day / self.days_per_season.max(1)) % 4 {
+            0 => Season::Spring,
+            1 => Season::Summer,
+            2 => Season::Autumn,
+            _ => Season::Winter,
+        }
+    }
+
+    pub fn is_morning(&self) -> bool {
+        (MORNING_START_HOUR..MORNING_END_HOUR).contains(&self.hours)
+    }
+
+    pub fn is_evening_or_night(&self) -> bool {
+        self.hours >= EVENING_START_HOUR || self.hours < MORNING_START_HOUR
+    }
+
+    pub fn is_shopping_time(&self) -> bool {
+        (SHOPPING_START_HOUR..SHOPPING_END_HOUR).contains(&self.hours)
+    }
+
+    // Amount of daylight, 0 at midnight and 1 at noon
+    pub fn daylight(&self) -> f32 {
+        0.5 - 0.5 * (self.hours / 24.0 * TAU).cos()
+    }
+
+    // Days passed since the game started, including the current partial day
+    pub fn elapsed_days(&self) -> f32 {
+        self.day as f32 + self.hours / 24.0
+    }
+
+    // Clock time as "HH:MM"
+    pub fn formatted(&self) -> String {
+        let minutes = (self.hours * 60.0) as u32;
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+
+// Advance the clock with game time, starting a new day (and maybe season) at midnight
+pub(crate) fn advance_clock(
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    mut time_of_day: ResMut<TimeOfDay>,
+    mut season_events: EventWriter<SeasonChanged>,
+) {
+    let hours_per_second = 24.0 / time_of_day.seconds_per_day;
+    time_of_day.hours += speed.delta_seconds(&time) * hours_per_second;
+
+    while time_of_day.hours >= 24.0 {
+        time_of_day.hours -= 24.0;
+        let season = time_of_day.season();
+        time_of_day.day += 1;
+        if time_of_day.season() != season {
+            season_events.send(SeasonChanged);
+        }
+    }
+}
+
+// Darken the background at night
+fn tint_clear_color(time_of_day: Res<TimeOfDay>, mut clear_color: ResMut<ClearColor>) {
+    let brightness = MIDNIGHT_BRIGHTNESS + (1.0 - MIDNIGHT_BRIGHTNESS) * time_of_day.daylight();
+    let daylight = DAYLIGHT_CLEAR_COLOR.to_linear();
+    clear_color.0 = Color::linear_rgb(
+        daylight.red * brightness,
+        daylight.green * brightness,
+        daylight.blue * brightness,
+    );
+}
+
+// Restore the normal background outside of the town view
+fn reset_clear_color(mut clear_color: ResMut<ClearColor>) {
+    clear_color.0 = DAYLIGHT_CLEAR_COLOR;
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use crate::grid::Grid;
+use crate::notifications::Notification;
+use crate::town::{BuildingType, TownMap, TOWN_GRID_SIZE};
+use crate::GameState;
+use std::collections::HashSet;
+
+pub struct ConnectivityPlugin;
+
+/// This plugin works out which cells the roads connect to the Town Hall, for the connectivity overlay
+impl Plugin for ConnectivityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Connectivity>()
+            .add_systems(Update, update_connectivity.run_if(in_state(GameState::TownView)));
+    }
+}
+
+#[derive(Resource, Default, PartialEq)]
+pub struct Connectivity {
+    // Roads reaching the Town Hall, with the cells along them and the Town Hall itself
+    connected: HashSet<IVec2>,
+    // Road networks that don't reach the Town Hall
+    pub disconnected_networks: usize,
+}
+
+impl Connectivity {
+    pub fn is_connected(&self, pos: IVec2) -> bool {
+        self.connected.contains(&pos)
+    }
+}
+
+// Split the roads into networks whenever the town changes and keep those touching the Town Hall,
+// telling the player how many don't
+fn update_connectivity(
+    town_map: Res<TownMap>,
+    mut seen_revision: Local<u64>,
+    mut connectivity: ResMut<Connectivity>,
+    mut notifications: EventWriter<Notification>,
+) {
+    if town_map.revision() == *seen_revision {
+        return;
+    }
+    *seen_revision = town_map.revision();
+
+    let is_road = |pos: IVec2| town_map.get(pos).is_some_and(|cell| cell.building.is_road());
+    let town_halls: Vec<IVec2> = town_map
+        .iter()
+        .filter(|cell| cell.building == BuildingType::TownHall)
+        .map(|cell| cell.position)
+        .collect();
+    let mut roads = HashSet::new();
+    let mut disconnected_networks = 0;
+    for network in Grid::connected_components(is_road, TOWN_GRID_SIZE) {
+        let reaches_town_hall = town_halls
+            .iter()
+            .any(|&town_hall| Grid::get_orthogonal_positions(town_hall).iter().any(|pos| network.contains(pos)));
+        if reaches_town_hall {
+            roads.extend(network);
+        } else {
+            disconnected_networks += 1;
+        }
+    }
+
+    // Cells are served by a connected road right next to them
+    let mut connected = roads.clone();
+    connected.extend(
+        town_map
+            .iter()
+            .map(|cell| cell.position)
+            .filter(|&pos| Grid::get_orthogonal_positions(pos).iter().any(|neighbor| roads.contains(neighbor))),
+    );
+
+    if disconnected_networks > 0 && disconnected_networks != connectivity.disconnected_networks {
+        notifications.send(Notification::warning(format!(
+            "{} road network{} not connected to the Town Hall",
+            disconnected_networks,
+            if disconnected_networks == 1 { " is" } else { "s are" }
+        )));
+    }
+    connectivity.set_if_neq(Connectivity {
+        connected,
+        disconnected_networks,
+    });
+}
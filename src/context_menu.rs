@@ -0,0 +1,186 @@
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+use crate::actions::{InputAction, KeyBindings};
+use crate::town::{cursor_grid_position, BuildingType, CellCommand, TownMap, ZoneType};
+use crate::tooltip::InspectedCell;
+use crate::GameState;
+
+pub struct ContextMenuPlugin;
+
+/// This plugin opens a menu of actions for the town cell that was right-clicked
+impl Plugin for ContextMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (open_context_menu, handle_context_menu).run_if(in_state(GameState::TownView)),
+        );
+    }
+}
+
+// Covers the screen behind an open menu, so the click closing it doesn't reach the cells below
+#[derive(Component)]
+struct ContextMenu;
+
+#[derive(Component, Clone, Copy)]
+enum ContextMenuButton {
+    Demolish(IVec2),
+    Inspect(IVec2),
+    Upgrade(IVec2),
+    GoToIsland,
+}
+
+impl ContextMenuButton {
+    fn label(&self) -> &'static str {
+        match self {
+            ContextMenuButton::Demolish(_) => "Demolish",
+            ContextMenuButton::Inspect(_) => "Inspect",
+            ContextMenuButton::Upgrade(_) => "Upgrade",
+            ContextMenuButton::GoToIsland => "Go to Island",
+        }
+    }
+}
+
+// Open the menu for the cell under the cursor on a right click, or close the one already open.
+// Empty space outside the town only offers the way back to the island
+#[allow(clippy::too_many_arguments)]
+fn open_context_menu(
+    mut commands: Commands,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    town_map: Res<TownMap>,
+    menus: Query<Entity, With<ContextMenu>>,
+    ui_interactions: Query<&Interaction, (With<Node>, Without<ContextMenu>)>,
+) {
+    if let Ok(menu) = menus.get_single() {
+        if mouse_button_input.just_pressed(MouseButton::Right)
+            || key_bindings.just_pressed(InputAction::Cancel, &keyboard_input)
+        {
+            commands.entity(menu).despawn_recursive();
+        }
+        return;
+    }
+    if !mouse_button_input.just_pressed(MouseButton::Right)
+        || ui_interactions.iter().any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), camera_q.get_single()) else {
+        return;
+    };
+    let Some(cursor_position) = window.cursor_position() else {
+        return;
+    };
+    let cell = cursor_grid_position(window, camera, camera_transform).and_then(|position| town_map.get(position));
+    let buttons = match cell {
+        Some(cell) if cell.building != BuildingType::None || cell.zone != ZoneType::None => {
+            let mut buttons = vec![ContextMenuButton::Demolish(cell.position), ContextMenuButton::Inspect(cell.position)];
+            if cell.building.is_department() {
+                buttons.push(ContextMenuButton::Upgrade(cell.position));
+            }
+            buttons
+        }
+        Some(cell) => vec![ContextMenuButton::Inspect(cell.position), ContextMenuButton::GoToIsland],
+        None => vec![ContextMenuButton::GoToIsland],
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(50),
+                ..default()
+            },
+            Interaction::default(),
+            ContextMenu,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px(cursor_position.x),
+                        top: Val::Px(cursor_position.y),
+                        flex_direction: FlexDirection::Column,
+                        padding: UiRect::all(Val::Px(4.0)),
+                        row_gap: Val::Px(2.0),
+                        ..default()
+                    },
+                    background_color: Color::srgba(0.1, 0.1, 0.1, 0.9).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for button in buttons {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(120.0),
+                                        height: Val::Px(28.0),
+                                        justify_content: JustifyContent::Start,
+                                        align_items: AlignItems::Center,
+                                        padding: UiRect::horizontal(Val::Px(8.0)),
+                                        ..default()
+                                    },
+                                    background_color: Color::srgb(0.25, 0.25, 0.25).into(),
+                                    ..default()
+                                },
+                                button,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    button.label(),
+                                    TextStyle {
+                                        font_size: 16.0,
+                                        color: Color::WHITE,
+                                        ..default()
+                                    },
+                                ));
+                            });
+                    }
+                });
+        });
+}
+
+// Carry out the chosen action and close the menu, a click next to it just closes it
+fn handle_context_menu(
+    mut commands: Commands,
+    menus: Query<(Entity, &Interaction), With<ContextMenu>>,
+    buttons: Query<(&Interaction, &ContextMenuButton), Changed<Interaction>>,
+    mut cell_commands: EventWriter<CellCommand>,
+    mut inspected: ResMut<InspectedCell>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Ok((menu, menu_interaction)) = menus.get_single() else {
+        return;
+    };
+    let mut close = *menu_interaction == Interaction::Pressed;
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *button {
+            ContextMenuButton::Demolish(position) => {
+                cell_commands.send(CellCommand::Demolish(position));
+            }
+            ContextMenuButton::Inspect(position) => inspected.0 = Some(position),
+            ContextMenuButton::Upgrade(position) => {
+                cell_commands.send(CellCommand::Upgrade(position));
+            }
+            ContextMenuButton::GoToIsland => next_state.set(GameState::IslandView),
+        }
+        close = true;
+    }
+    if close {
+        commands.entity(menu).despawn_recursive();
+    }
+}
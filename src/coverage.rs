@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+use crate::grid::{Grid, RadiusShape};
+use std::collections::HashMap;
+
+// Coverage a cell can reach however many sources overlap on it
+pub const MAX_COVERAGE: f32 = 1.0;
+
+// Strength of an effect buildings project over a radius, by cell. Each source gives 1 on its own
+// cell falling off linearly to 0 just past the radius, overlapping sources add up to `MAX_COVERAGE`.
+// Cells out of reach of every source are left out
+pub fn compute_coverage(sources: &[IVec2], radius: i32, size: usize) -> HashMap<IVec2, f32> {
+    let mut coverage = HashMap::new();
+    for &source in sources {
+        for pos in Grid::cells_in_radius(source, radius, RadiusShape::Manhattan, size) {
+            let falloff = 1.0 - Grid::manhattan_distance(pos, source) as f32 / (radius + 1) as f32;
+            let value = coverage.entry(pos).or_insert(0.0);
+            *value = (*value + falloff).min(MAX_COVERAGE);
+        }
+    }
+    coverage
+}
+
+// Coverage of a cell, 0 where no source reaches
+pub fn coverage_at(coverage: &HashMap<IVec2, f32>, pos: IVec2) -> f32 {
+    coverage.get(&pos).copied().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coverage_falls_off_with_the_manhattan_distance() {
+        let source = IVec2::new(5, 5);
+        let coverage = compute_coverage(&[source], 3, 16);
+        assert_eq!(coverage_at(&coverage, source), 1.0);
+        assert_eq!(coverage_at(&coverage, IVec2::new(6, 5)), 0.75);
+        // Diagonal neighbors are two steps away, as far as the cells two across
+        assert_eq!(coverage_at(&coverage, IVec2::new(6, 6)), 0.5);
+        assert_eq!(coverage_at(&coverage, IVec2::new(5, 3)), 0.5);
+        assert_eq!(coverage_at(&coverage, IVec2::new(8, 5)), 0.25);
+    }
+
+    #[test]
+    fn nothing_is_covered_past_the_radius() {
+        let source = IVec2::new(5, 5);
+        let coverage = compute_coverage(&[source], 2, 16);
+        assert_eq!(coverage.len(), 13);
+        assert!(!coverage.contains_key(&IVec2::new(8, 5)));
+        assert_eq!(coverage_at(&coverage, IVec2::new(8, 5)), 0.0);
+        assert_eq!(coverage_at(&coverage, IVec2::new(7, 6)), 0.0);
+        assert!(compute_coverage(&[], 2, 16).is_empty());
+    }
+
+    #[test]
+    fn overlapping_sources_add_up_to_the_cap() {
+        let sources = [IVec2::new(4, 4), IVec2::new(5, 4)];
+        let coverage = compute_coverage(&sources, 3, 16);
+        // Each source alone covers the other's cell, together they'd go past the cap
+        assert_eq!(coverage_at(&coverage, IVec2::new(4, 4)), MAX_COVERAGE);
+        assert_eq!(coverage_at(&coverage, IVec2::new(5, 4)), MAX_COVERAGE);
+        // Below the cap they add up, cells only one of them reaches keep its coverage
+        assert_eq!(coverage_at(&coverage, IVec2::new(2, 4)), 0.75);
+        assert_eq!(coverage_at(&coverage, IVec2::new(8, 4)), 0.25);
+        assert!(coverage.values().all(|value| *value <= MAX_COVERAGE));
+    }
+
+    #[test]
+    fn coverage_is_clipped_at_the_grid_edge() {
+        let coverage = compute_coverage(&[IVec2::ZERO], 2, 16);
+        assert_eq!(coverage.len(), 6);
+        assert!(coverage.keys().all(|pos| Grid::is_in_bounds(*pos, 16)));
+        assert_eq!(coverage_at(&coverage, IVec2::new(-1, 0)), 0.0);
+        assert!((coverage_at(&coverage, IVec2::new(1, 1)) - 1.0 / 3.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use crate::grid::Grid;
+use crate::town::{BuildingType, TownMap};
+use crate::simulation::SimulationSet;
+use crate::GameState;
+use std::collections::HashMap;
+
+pub struct DepartmentsPlugin;
+
+/// This plugin keeps track of the Town Hall departments and the upgrades attached to them
+impl Plugin for DepartmentsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Departments>()
+            .init_resource::<DepartmentBonuses>()
+            .add_systems(
+                Update,
+                (count_departments, update_bonuses)
+                    .chain()
+                    .in_set(SimulationSet::Departments)
+                    .run_if(in_state(GameState::TownView)),
+            );
+    }
+}
+
+// Boost of a department's effect just for being built, and for every upgrade attached to it
+const DEPARTMENT_BONUS: f32 = 0.25;
+const UPGRADE_BONUS: f32 = 0.1;
+
+// Happiness Social Services add per unit of boost, e.g. 0.05 for a department without upgrades
+const SOCIAL_SERVICES_HAPPINESS: f32 = 0.2;
+
+pub const DEPARTMENTS: [BuildingType; 7] = [
+    BuildingType::LawAndOrder,
+    BuildingType::Education,
+    BuildingType::Transportation,
+    BuildingType::Health,
+    BuildingType::Energy,
+    BuildingType::Housing,
+    BuildingType::SocialServices,
+];
+
+impl BuildingType {
+    pub fn is_department(&self) -> bool {
+        DEPARTMENTS.contains(self)
+    }
+}
+
+// Departments attached to the Town Hall, with the number of upgrades on each
+#[derive(Resource, Default)]
+pub struct Departments {
+    pub upgrades: HashMap<BuildingType, u32>,
+}
+
+impl Departments {
+    // Multiplier on the effect a department boosts, 1 while it isn't built
+    pub fn multiplier(&self, department: BuildingType) -> f32 {
+        match self.upgrades.get(&department) {
+            Some(&upgrades) => 1.0 + DEPARTMENT_BONUS + UPGRADE_BONUS * upgrades as f32,
+            None => 1.0,
+        }
+    }
+}
+
+// What the departments do for the town, derived from `Departments`
+#[derive(Resource)]
+pub struct DepartmentBonuses {
+    // Multipliers on the reach of hospitals and the chance for citizens to die (Health)
+    pub hospital_radius: f32,
+    pub death_rate: f32,
+    // Multiplier on the reach of schools (Education)
+    pub school_radius: f32,
+    // Multiplier on the output of power plants (Energy)
+    pub power_output: f32,
+    // Multiplier on the citizens residential cells house (Housing)
+    pub housing_capacity: f32,
+    // Multipliers on the traffic roads carry before congesting and on how long traffic lights
+    // take to cycle (Transportation)
+    pub road_capacity: f32,
+    pub light_cycle: f32,
+    // Added to the happiness the town strives for (Social Services)
+    pub happiness: f32,
+    // Multiplier on the chance of crimes (Law and Order)
+    pub crime_rate: f32,
+}
+
+impl Default for DepartmentBonuses {
+    fn default() -> Self {
+        DepartmentBonuses {
+            hospital_radius: 1.0,
+            death_rate: 1.0,
+            school_radius: 1.0,
+            power_output: 1.0,
+            housing_capacity: 1.0,
+            road_capacity: 1.0,
+            light_cycle: 1.0,
+            happiness: 0.0,
+            crime_rate: 1.0,
+        }
+    }
+}
+
+impl DepartmentBonuses {
+    fn from_departments(departments: &Departments) -> Self {
+        let health = departments.multiplier(BuildingType::Health);
+        let transportation = departments.multiplier(BuildingType::Transportation);
+        DepartmentBonuses {
+            hospital_radius: health,
+            death_rate: 1.0 / health,
+            school_radius: departments.multiplier(BuildingType::Education),
+            power_output: departments.multiplier(BuildingType::Energy),
+            housing_capacity: departments.multiplier(BuildingType::Housing),
+            road_capacity: transportation,
+            light_cycle: 1.0 / transportation,
+            happiness: SOCIAL_SERVICES_HAPPINESS
+                * (departments.multiplier(BuildingType::SocialServices) - 1.0),
+            crime_rate: 1.0 / departments.multiplier(BuildingType::LawAndOrder),
+        }
+    }
+
+    // Citizens a residential cell houses with the Housing bonus applied
+    pub fn housing(&self, capacity: i32) -> i32 {
+        (capacity as f32 * self.housing_capacity).round() as i32
+    }
+}
+
+// Recount the departments and their upgrades whenever the town changes. Departments cut off from
+// the Town Hall and upgrades cut off from their department stop counting
+fn count_departments(
+    town_map: Res<TownMap>,
+    mut seen_revision: Local<u64>,
+    mut departments: ResMut<Departments>,
+) {
+    if town_map.revision() == *seen_revision {
+        return;
+    }
+    *seen_revision = town_map.revision();
+    let building_at = |pos: IVec2| town_map.get(pos).map(|cell| cell.building);
+    let next_to = |pos: IVec2, building: BuildingType| {
+        Grid::get_orthogonal_positions(pos)
+            .into_iter()
+            .any(|neighbor| building_at(neighbor) == Some(building))
+    };
+
+    let mut upgrades = HashMap::new();
+    for cell in town_map.iter() {
+        if cell.building.is_department() && next_to(cell.position, BuildingType::TownHall) {
+            let count = Grid::get_orthogonal_positions(cell.position)
+                .into_iter()
+                .filter(|neighbor| building_at(*neighbor) == Some(BuildingType::Upgrade))
+                .count() as u32;
+            *upgrades.entry(cell.building).or_insert(0) += count;
+        }
+    }
+    departments.upgrades = upgrades;
+}
+
+fn update_bonuses(departments: Res<Departments>, mut bonuses: ResMut<DepartmentBonuses>) {
+    if departments.is_changed() {
+        *bonuses = DepartmentBonuses::from_departments(&departments);
+    }
+}
@@ -0,0 +1,435 @@
+use bevy::prelude::*;
+use crate::clock::TimeOfDay;
+use crate::grid::Grid;
+use crate::island::Island;
+use crate::notifications::Notification;
+use crate::rng::GameRng;
+use crate::simulation::{Economy, GameSpeed, SimulationSet};
+use crate::tilemap::TownTiles;
+use crate::town::{
+    get_cell_color, grid_to_world, BuildingType, OverlayMode, SelectedTown, TownCell, TownMap, ZoneType,
+    TOWN_CELL_SPACING,
+    TOWN_GRID_SIZE,
+};
+use crate::menu::outside_pause;
+use crate::GameState;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub struct DisastersPlugin;
+
+/// This plugin now and then strikes the town with earthquakes, floods and blackouts
+impl Plugin for DisastersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Disasters>()
+            .add_systems(
+                Update,
+                (
+                    start_disasters,
+                    strike_disasters,
+                    shake_camera,
+                    recede_flood_water,
+                    update_blackout_shade,
+                )
+                    .chain()
+                    .in_set(SimulationSet::Disasters)
+                    .run_if(in_state(GameState::TownView)),
+            )
+            .add_systems(OnExit(GameState::TownView), end_disasters.run_if(outside_pause));
+    }
+}
+
+// Seconds of game time between chances for a disaster
+const DISASTER_INTERVAL_SECONDS: f32 = 60.0;
+
+// Seconds of game time between the warning and the disaster striking
+const DISASTER_WARNING_SECONDS: f32 = 8.0;
+
+// Earthquakes damage cells in a square this many cells out from the epicenter, closer to it
+// more likely
+const EARTHQUAKE_RADIUS: i32 = 6;
+const EARTHQUAKE_SHAKE_SECONDS: f32 = 2.0;
+const EARTHQUAKE_SHAKE_AMPLITUDE: f32 = 6.0;
+
+// Floods reach this many cells into the town from the water they come from
+const FLOOD_DEPTH: std::ops::RangeInclusive<i32> = 3..=8;
+const FLOOD_DAMAGE_CHANCE: f64 = 0.6;
+const FLOOD_WATER_SECONDS: f32 = 12.0;
+
+const BLACKOUT_SECONDS: f32 = 20.0;
+
+// Share of a damaged building's cost paid to rebuild the town around it, plus a flat cost for
+// every cell that has to be cleared
+const RECOVERY_COST_SHARE: f32 = 0.25;
+const CLEANUP_COST_PER_CELL: i32 = 20;
+const BLACKOUT_REPAIR_COST_PER_PLANT: i32 = 150;
+
+// How often disasters strike, chosen on the settings screen. Off makes for a calm builder
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DisasterFrequency {
+    Off,
+    Rare,
+    #[default]
+    Normal,
+    Frequent,
+}
+
+impl DisasterFrequency {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DisasterFrequency::Off => "Off",
+            DisasterFrequency::Rare => "Rare",
+            DisasterFrequency::Normal => "Normal",
+            DisasterFrequency::Frequent => "Frequent",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            DisasterFrequency::Off => DisasterFrequency::Rare,
+            DisasterFrequency::Rare => DisasterFrequency::Normal,
+            DisasterFrequency::Normal => DisasterFrequency::Frequent,
+            DisasterFrequency::Frequent => DisasterFrequency::Off,
+        }
+    }
+
+    // Chance for a disaster every `DISASTER_INTERVAL_SECONDS`
+    fn chance(&self) -> f64 {
+        match self {
+            DisasterFrequency::Off => 0.0,
+            DisasterFrequency::Rare => 0.05,
+            DisasterFrequency::Normal => 0.15,
+            DisasterFrequency::Frequent => 0.35,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Disaster {
+    Earthquake { epicenter: IVec2 },
+    // Water coming in from the town's edge in `direction`, e.g. (0, 1) for the north side
+    Flood { direction: IVec2, depth: i32 },
+    Blackout,
+}
+
+// Disaster that was announced and is about to strike, and the effects of past ones
+#[derive(Resource, Default)]
+pub struct Disasters {
+    pending: Option<(Disaster, Timer)>,
+    shaking: Option<Timer>,
+    blackout: Option<Timer>,
+}
+
+impl Disasters {
+    // Power plants produce nothing while the grid is down
+    pub fn blackout(&self) -> bool {
+        self.blackout.is_some()
+    }
+}
+
+// Water left on a flooded cell, fading as it recedes
+#[derive(Component)]
+struct FloodWater {
+    timer: Timer,
+}
+
+// Darkens the screen during a blackout
+#[derive(Component)]
+struct BlackoutShade;
+
+fn direction_name(direction: IVec2) -> &'static str {
+    match (direction.x, direction.y) {
+        (0, 1) => "north",
+        (0, -1) => "south",
+        (1, 0) => "east",
+        _ => "west",
+    }
+}
+
+// Sides of the town bordering the sea or a river on the island, where floods come from
+fn flood_directions(island: Option<&Island>, town: Option<&SelectedTown>) -> Vec<IVec2> {
+    let (Some(island), Some(town)) = (island, town) else {
+        return Vec::new();
+    };
+    island.water_sides(town.0)
+}
+
+// Now and then pick a disaster the town is exposed to and warn about it
+#[allow(clippy::too_many_arguments)]
+fn start_disasters(
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    mut timer: Local<Timer>,
+    frequency: Res<DisasterFrequency>,
+    mut disasters: ResMut<Disasters>,
+    mut game_rng: ResMut<GameRng>,
+    town_map: Res<TownMap>,
+    island: Option<Res<Island>>,
+    selected_town: Option<Res<SelectedTown>>,
+    mut notifications: EventWriter<Notification>,
+) {
+    if timer.duration() == Duration::ZERO {
+        *timer = Timer::from_seconds(DISASTER_INTERVAL_SECONDS, TimerMode::Repeating);
+    }
+
+    timer.tick(speed.delta(&time));
+    if !timer.just_finished() || disasters.pending.is_some() || disasters.blackout() {
+        return;
+    }
+
+    let rng = &mut game_rng.rng;
+    if !rng.gen_bool(frequency.chance()) {
+        return;
+    }
+
+    let developed: Vec<IVec2> = town_map
+        .iter()
+        .filter(|cell| cell.density > 0 || (cell.building != BuildingType::None && !cell.building.is_road()))
+        .map(|cell| cell.position)
+        .collect();
+    let has_power_plant = town_map.iter().any(|cell| cell.building == BuildingType::PowerPlant);
+    let flood_directions = flood_directions(island.as_deref(), selected_town.as_deref());
+
+    let mut candidates = Vec::new();
+    if let Some(&epicenter) = developed.choose(rng) {
+        candidates.push(Disaster::Earthquake { epicenter });
+    }
+    if let Some(&direction) = flood_directions.choose(rng) {
+        candidates.push(Disaster::Flood {
+            direction,
+            depth: rng.gen_range(FLOOD_DEPTH),
+        });
+    }
+    if has_power_plant {
+        candidates.push(Disaster::Blackout);
+    }
+    let Some(&disaster) = candidates.choose(rng) else {
+        return;
+    };
+
+    let warning = match disaster {
+        Disaster::Earthquake { epicenter } => {
+            format!("Tremors felt around ({}, {}), an earthquake is coming", epicenter.x, epicenter.y)
+        }
+        Disaster::Flood { direction, .. } => {
+            format!("The water is rising on the {} side of town", direction_name(direction))
+        }
+        Disaster::Blackout => "The power grid is overloaded, a blackout is coming".to_string(),
+    };
+    notifications.send(Notification::warning(warning));
+    disasters.pending = Some((
+        disaster,
+        Timer::from_seconds(DISASTER_WARNING_SECONDS, TimerMode::Once),
+    ));
+}
+
+// Let the announced disaster strike, damaging the town and charging the recovery
+#[allow(clippy::too_many_arguments)]
+fn strike_disasters(
+    mut commands: Commands,
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    mut disasters: ResMut<Disasters>,
+    mut game_rng: ResMut<GameRng>,
+    mut town_map: ResMut<TownMap>,
+    mut tiles: ResMut<TownTiles>,
+    mut economy: ResMut<Economy>,
+    mut overlay: ResMut<OverlayMode>,
+    time_of_day: Res<TimeOfDay>,
+    mut notifications: EventWriter<Notification>,
+) {
+    let delta = speed.delta(&time);
+    if disasters.shaking.as_mut().is_some_and(|timer| timer.tick(time.delta()).finished()) {
+        disasters.shaking = None;
+    }
+    if disasters.blackout.as_mut().is_some_and(|timer| timer.tick(delta).finished()) {
+        disasters.blackout = None;
+        notifications.send(Notification::info("Power is back on"));
+    }
+
+    let Some((disaster, timer)) = disasters.pending.as_mut() else {
+        return;
+    };
+    if !timer.tick(delta).finished() {
+        return;
+    }
+    let disaster = *disaster;
+    disasters.pending = None;
+
+    let rng = &mut game_rng.rng;
+    // Cells are checked on a copy, only the damaged ones count as changed
+    let cells: Vec<TownCell> = town_map.iter().copied().collect();
+    let mut damaged = 0;
+    let mut cost = 0;
+    let message = match disaster {
+        Disaster::Earthquake { epicenter } => {
+            for cell in cells {
+                let distance = Grid::chebyshev_distance(cell.position, epicenter);
+                let chance = 1.0 - distance as f64 / (EARTHQUAKE_RADIUS + 1) as f64;
+                if distance > EARTHQUAKE_RADIUS || !rng.gen_bool(chance) {
+                    continue;
+                }
+                if cell.density == 0 && (cell.building == BuildingType::None || cell.building.is_road()) {
+                    continue;
+                }
+                let Some(cell) = town_map.cell_mut(cell.position) else {
+                    continue;
+                };
+                // Roads and bridges crack but stay usable, everything else collapses
+                if !cell.building.is_road() {
+                    cost += (cell.building.cost() as f32 * RECOVERY_COST_SHARE) as i32;
+                    cell.building = BuildingType::None;
+                }
+                cell.density = 0;
+                cost += CLEANUP_COST_PER_CELL;
+                damaged += 1;
+                if *overlay == OverlayMode::None {
+                    tiles.set(cell.position, get_cell_color(cell, None, time_of_day.season()));
+                }
+            }
+            disasters.shaking = Some(Timer::from_seconds(EARTHQUAKE_SHAKE_SECONDS, TimerMode::Once));
+            format!("An earthquake damaged {} cells", damaged)
+        }
+        Disaster::Flood { direction, depth } => {
+            let edge = TOWN_GRID_SIZE as i32 - 1;
+            // Distance of a cell from the edge the water comes in over
+            let from_edge = |pos: IVec2| match (direction.x, direction.y) {
+                (0, 1) => edge - pos.y,
+                (0, -1) => pos.y,
+                (1, 0) => edge - pos.x,
+                _ => pos.x,
+            };
+            for cell in cells {
+                if from_edge(cell.position) >= depth {
+                    continue;
+                }
+                commands.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::srgba(0.1, 0.3, 0.9, 0.5),
+                            custom_size: Some(Vec2::splat(TOWN_CELL_SPACING)),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(grid_to_world(cell.position, 1.5)),
+                        ..default()
+                    },
+                    FloodWater {
+                        timer: Timer::from_seconds(FLOOD_WATER_SECONDS, TimerMode::Once),
+                    },
+                ));
+                // Floods wash out homes and businesses, buildings and roads hold
+                if cell.zone == ZoneType::None || cell.density == 0 || !rng.gen_bool(FLOOD_DAMAGE_CHANCE) {
+                    continue;
+                }
+                let Some(cell) = town_map.cell_mut(cell.position) else {
+                    continue;
+                };
+                cell.density -= 1;
+                cost += CLEANUP_COST_PER_CELL;
+                damaged += 1;
+                if *overlay == OverlayMode::None {
+                    tiles.set(cell.position, get_cell_color(cell, None, time_of_day.season()));
+                }
+            }
+            format!(
+                "A flood from the {} washed out {} cells",
+                direction_name(direction),
+                damaged
+            )
+        }
+        Disaster::Blackout => {
+            let plants = cells
+                .iter()
+                .filter(|cell| cell.building == BuildingType::PowerPlant)
+                .count() as i32;
+            cost = plants * BLACKOUT_REPAIR_COST_PER_PLANT;
+            disasters.blackout = Some(Timer::from_seconds(BLACKOUT_SECONDS, TimerMode::Once));
+            format!("Blackout! Power plants are down for {:.0} seconds", BLACKOUT_SECONDS)
+        }
+    };
+    if *overlay != OverlayMode::None && damaged > 0 {
+        // Let the overlay repaint the damaged cells
+        overlay.set_changed();
+    }
+
+    economy.funds -= cost;
+    notifications.send(Notification::critical(format!("{message}, recovery costs ${cost}")));
+}
+
+// Shake the camera while an earthquake strikes, undoing last frame's offset first so panning still works
+fn shake_camera(
+    time: Res<Time>,
+    disasters: Res<Disasters>,
+    mut offset: Local<Vec2>,
+    mut cameras: Query<&mut Transform, With<Camera2d>>,
+) {
+    let Ok(mut transform) = cameras.get_single_mut() else {
+        return;
+    };
+    transform.translation -= offset.extend(0.0);
+    *offset = match &disasters.shaking {
+        Some(timer) => {
+            let t = time.elapsed_seconds();
+            let strength = EARTHQUAKE_SHAKE_AMPLITUDE * timer.fraction_remaining();
+            Vec2::new((t * 53.0).sin(), (t * 41.0).cos()) * strength
+        }
+        None => Vec2::ZERO,
+    };
+    transform.translation += offset.extend(0.0);
+}
+
+fn recede_flood_water(
+    mut commands: Commands,
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    mut water: Query<(Entity, &mut FloodWater, &mut Sprite)>,
+) {
+    for (entity, mut flood, mut sprite) in water.iter_mut() {
+        if flood.timer.tick(speed.delta(&time)).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        sprite.color.set_alpha(0.5 * flood.timer.fraction_remaining());
+    }
+}
+
+fn update_blackout_shade(
+    mut commands: Commands,
+    disasters: Res<Disasters>,
+    shades: Query<Entity, With<BlackoutShade>>,
+) {
+    match (disasters.blackout(), shades.get_single()) {
+        (true, Err(_)) => {
+            commands.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: Color::srgba(0.0, 0.0, 0.1, 0.35).into(),
+                    // Below the rest of the UI
+                    z_index: ZIndex::Global(-1),
+                    ..default()
+                },
+                BlackoutShade,
+            ));
+        }
+        (false, Ok(shade)) => commands.entity(shade).despawn_recursive(),
+        _ => {}
+    }
+}
+
+// Disasters don't carry over to the next town shown
+fn end_disasters(
+    mut commands: Commands,
+    mut disasters: ResMut<Disasters>,
+    water: Query<Entity, With<FloodWater>>,
+) {
+    *disasters = Disasters::default();
+    for entity in water.iter() {
+        commands.entity(entity).despawn();
+    }
+}
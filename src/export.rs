@@ -0,0 +1,300 @@
+use bevy::prelude::*;
+use bevy::utils::SystemTime;
+use crate::actions::{InputAction, KeyBindings};
+use crate::notifications::Notification;
+use crate::simulation::{Economy, Population};
+use crate::clock::TimeOfDay;
+use crate::grid::Grid;
+use crate::tilemap::TownTiles;
+use crate::town::{
+    can_place, can_zone, check_cells, load_layout, road_changed, BuildingType, CellSave, Construction, RoadChanged,
+    SelectedTown, Town, TownCell, TownMap, Towns, ZoneType, TOWN_GRID_SIZE,
+};
+use crate::transit::TransitRoutes;
+use crate::GameState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct ExportPlugin;
+
+/// This plugin writes the shown town's layout to JSON and CSV files for other tools, and reads
+/// a layout back from JSON
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (export_town, import_town).run_if(in_state(GameState::TownView)),
+        );
+    }
+}
+
+// Directory the exported layouts are written to, relative to the working directory
+const EXPORT_DIRECTORY: &str = "exports";
+
+// Layout imported into the shown town, in the exported JSON format
+const IMPORT_PATH: &str = "imports/town.json";
+
+// Layout of a town with its headline numbers, as exported
+#[derive(Serialize, Deserialize)]
+pub struct TownExport {
+    pub name: String,
+    pub population: i32,
+    pub funds: i32,
+    pub happiness: f32,
+    // Every cell of the grid, row by row from the bottom
+    pub cells: Vec<CellSave>,
+}
+
+// File name for something written out about a town, from its name and the current time
+pub fn town_file_stem(name: &str) -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    let name = if name.is_empty() { "town" } else { name.as_str() };
+    format!("{}_{}", name, since_epoch.as_secs())
+}
+
+pub fn export_town_json(path: &Path, export: &TownExport) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(export).map_err(|error| error.to_string())?;
+    fs::write(path, contents).map_err(|error| error.to_string())
+}
+
+// Read a layout exported as JSON, refusing cells off the grid, listed twice or too dense.
+// Cells left out of the file are empty
+pub fn import_town_json(path: &Path) -> Result<TownExport, String> {
+    let contents = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let export: TownExport = serde_json::from_str(&contents).map_err(|error| error.to_string())?;
+
+    let size = TOWN_GRID_SIZE as i32;
+    check_cells(&export.cells, size, size, "grid")?;
+    Ok(export)
+}
+
+// Check a layout against the placement rules on the ground of the shown town. Every building is
+// checked as if placed on the cleared cell among the others, and zoned cells must be zonable
+pub fn check_layout(town_map: &TownMap, cells: &[CellSave]) -> Result<(), String> {
+    let buildings: HashMap<IVec2, BuildingType> = cells.iter().map(|cell| (cell.position, cell.building)).collect();
+    let town_halls = cells.iter().filter(|cell| cell.building == BuildingType::TownHall).count();
+    for cell in cells.iter() {
+        let pos = cell.position;
+        let Some(ground) = town_map.get(pos) else {
+            return Err(format!("cell ({}, {}) is outside the {TOWN_GRID_SIZE}x{TOWN_GRID_SIZE} grid", pos.x, pos.y));
+        };
+        let cleared = TownCell {
+            zone: ZoneType::None,
+            building: BuildingType::None,
+            density: 0,
+            ..*ground
+        };
+        let neighbors: Vec<BuildingType> = Grid::get_orthogonal_positions(pos)
+            .into_iter()
+            .filter_map(|neighbor| buildings.get(&neighbor).copied())
+            .collect();
+        let other_town_halls = town_halls - usize::from(cell.building == BuildingType::TownHall);
+        let placed = can_place(cell.building, &cleared, &neighbors, other_town_halls).and_then(|_| {
+            if cell.zone == ZoneType::None {
+                Ok(())
+            } else {
+                can_zone(&TownCell { building: cell.building, ..cleared })
+            }
+        });
+        placed.map_err(|error| format!("cell ({}, {}): {error}", pos.x, pos.y))?;
+    }
+    Ok(())
+}
+
+// One row per cell, the summary numbers don't fit the table and are left to the JSON
+pub fn export_town_csv(path: &Path, export: &TownExport) -> Result<(), String> {
+    let mut contents = String::from("x,y,zone,building,density\n");
+    for cell in export.cells.iter() {
+        contents.push_str(&format!(
+            "{},{},{:?},{:?},{}\n",
+            cell.position.x, cell.position.y, cell.zone, cell.building, cell.density
+        ));
+    }
+    fs::write(path, contents).map_err(|error| error.to_string())
+}
+
+// Write the shown town to a JSON and a CSV file
+#[allow(clippy::too_many_arguments)]
+fn export_town(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    town_map: Res<TownMap>,
+    towns: Res<Towns>,
+    selected_town: Option<Res<SelectedTown>>,
+    population: Res<Population>,
+    economy: Res<Economy>,
+    town: Res<Town>,
+    mut notifications: EventWriter<Notification>,
+) {
+    if !key_bindings.just_pressed(InputAction::ExportTownLayout, &keyboard_input) {
+        return;
+    }
+
+    let name = selected_town
+        .and_then(|selected_town| towns.towns.get(&selected_town.0))
+        .map_or(String::new(), |town| town.name.clone());
+    let stem = PathBuf::from(EXPORT_DIRECTORY).join(town_file_stem(&name));
+    let export = TownExport {
+        name,
+        population: population.total,
+        funds: economy.funds,
+        happiness: town.happiness,
+        cells: town_map
+            .iter()
+            .map(|cell| CellSave {
+                position: cell.position,
+                zone: cell.zone,
+                building: cell.building,
+                density: cell.density,
+            })
+            .collect(),
+    };
+
+    let (json, csv) = (stem.with_extension("json"), stem.with_extension("csv"));
+    let result = fs::create_dir_all(EXPORT_DIRECTORY)
+        .map_err(|error| error.to_string())
+        .and_then(|_| export_town_json(&json, &export))
+        .and_then(|_| export_town_csv(&csv, &export));
+    match result {
+        Ok(()) => notifications.send(Notification::info(format!(
+            "Town layout exported to {} and {}",
+            json.display(),
+            csv.display()
+        ))),
+        Err(error) => {
+            error!("Failed to export the town layout: {error}");
+            notifications.send(Notification::warning(format!("Could not export the town layout: {error}")))
+        }
+    };
+}
+
+// Replace the shown town's layout with the one in the import file. Buildings going up and bus
+// routes are dropped, the headline numbers in the file are left to the simulation
+#[allow(clippy::too_many_arguments)]
+fn import_town(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut town_map: ResMut<TownMap>,
+    mut tiles: ResMut<TownTiles>,
+    mut construction: ResMut<Construction>,
+    mut transit_routes: ResMut<TransitRoutes>,
+    time_of_day: Res<TimeOfDay>,
+    mut road_events: EventWriter<RoadChanged>,
+    mut notifications: EventWriter<Notification>,
+) {
+    if !key_bindings.just_pressed(InputAction::ImportTownLayout, &keyboard_input) {
+        return;
+    }
+
+    let imported = import_town_json(Path::new(IMPORT_PATH))
+        .and_then(|export| check_layout(&town_map, &export.cells).map(|_| export));
+    match imported {
+        Ok(export) => {
+            let before: Vec<(IVec2, BuildingType)> = town_map.iter().map(|cell| (cell.position, cell.building)).collect();
+            construction.sites.clear();
+            transit_routes.routes.clear();
+            load_layout(&mut town_map, &mut tiles, &export.cells, time_of_day.season());
+
+            // Cached paths and vehicles on the way are rerouted around the new roads
+            for (position, old) in before {
+                let new = town_map.get(position).map_or(BuildingType::None, |cell| cell.building);
+                if road_changed(old, new) {
+                    road_events.send(RoadChanged { position });
+                }
+            }
+            notifications.send(Notification::info(format!("Town layout imported from {IMPORT_PATH}")));
+        }
+        Err(error) => {
+            error!("Failed to import the town layout: {error}");
+            notifications.send(Notification::warning(format!("Could not import the town layout: {error}")));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{layout_cell, read_back};
+    use crate::town::PlaceError;
+
+    fn write_and_import(test: &str, cells: Vec<CellSave>) -> Result<TownExport, String> {
+        let export = TownExport {
+            name: "Testville".to_string(),
+            population: 0,
+            funds: 0,
+            happiness: 0.5,
+            cells,
+        };
+        read_back(
+            &format!("import_{test}"),
+            |path| export_town_json(path, &export).unwrap(),
+            import_town_json,
+        )
+    }
+
+    #[test]
+    fn cells_are_checked_against_the_grid() {
+        let cells = vec![layout_cell(TOWN_GRID_SIZE as i32, 0, ZoneType::None, BuildingType::Road)];
+        let error = write_and_import("off_grid", cells).err().unwrap();
+        assert!(error.contains("outside"), "{error}");
+    }
+
+    #[test]
+    fn valid_layout_is_imported() {
+        let cells = vec![
+            layout_cell(5, 5, ZoneType::None, BuildingType::TownHall),
+            layout_cell(6, 5, ZoneType::None, BuildingType::Education),
+            layout_cell(5, 6, ZoneType::Residential, BuildingType::None),
+        ];
+        let export = write_and_import("valid", cells).unwrap();
+        assert!(check_layout(&TownMap::default(), &export.cells).is_ok());
+    }
+
+    #[test]
+    fn second_town_hall_is_refused() {
+        let cells = vec![
+            layout_cell(5, 5, ZoneType::None, BuildingType::TownHall),
+            layout_cell(9, 9, ZoneType::None, BuildingType::TownHall),
+        ];
+        let error = check_layout(&TownMap::default(), &cells).unwrap_err();
+        assert!(error.contains(&PlaceError::SecondTownHall.to_string()), "{error}");
+    }
+
+    #[test]
+    fn department_away_from_the_town_hall_is_refused() {
+        let cells = vec![
+            layout_cell(5, 5, ZoneType::None, BuildingType::TownHall),
+            layout_cell(7, 5, ZoneType::None, BuildingType::Education),
+        ];
+        let error = check_layout(&TownMap::default(), &cells).unwrap_err();
+        assert!(error.starts_with("cell (7, 5)"), "{error}");
+        assert!(error.contains(&PlaceError::NoAdjacentTownHall.to_string()), "{error}");
+    }
+
+    #[test]
+    fn homes_over_water_are_refused() {
+        let mut town_map = TownMap::default();
+        town_map.set_water(&[IVec2::new(4, 4)]);
+
+        let zoned = vec![layout_cell(4, 4, ZoneType::Residential, BuildingType::None)];
+        let error = check_layout(&town_map, &zoned).unwrap_err();
+        assert!(error.contains(&PlaceError::OverWater.to_string()), "{error}");
+
+        let built = vec![layout_cell(4, 4, ZoneType::None, BuildingType::School)];
+        assert!(check_layout(&town_map, &built).is_err());
+
+        // The same cells are fine on dry ground, and bridges need the water
+        assert!(check_layout(&TownMap::default(), &zoned).is_ok());
+        let bridge = vec![layout_cell(4, 4, ZoneType::None, BuildingType::Bridge)];
+        assert!(check_layout(&town_map, &bridge).is_ok());
+        assert!(check_layout(&TownMap::default(), &bridge).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+use crate::citizen::WealthDistribution;
+use crate::clock::TimeOfDay;
+use crate::history::History;
+use crate::notifications::Notifications;
+use crate::hud::format_thousands;
+use crate::island::Island;
+use crate::milestones::Milestones;
+use crate::rng::GameRng;
+use crate::simulation::{
+    Bankruptcy, Demand, Economy, GameSpeed, LandValue, Loans, Population, Resources,
+};
+use crate::town::{SelectedTown, Town, Towns};
+use crate::GameState;
+
+pub struct GameOverPlugin;
+
+/// This plugin shows the final stats after the town went bankrupt and lets the player start over
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::GameOver), setup_game_over)
+            .add_systems(Update, click_restart_button.run_if(in_state(GameState::GameOver)))
+            .add_systems(OnExit(GameState::GameOver), (cleanup_game_over, restart_game));
+    }
+}
+
+#[derive(Component)]
+struct GameOver;
+
+#[derive(Component)]
+struct RestartButton;
+
+const BUTTON_COLOR: Color = Color::linear_rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED_COLOR: Color = Color::linear_rgb(0.25, 0.25, 0.25);
+
+fn setup_game_over(
+    mut commands: Commands,
+    population: Res<Population>,
+    economy: Res<Economy>,
+    town: Res<Town>,
+    time_of_day: Res<TimeOfDay>,
+) {
+    commands.spawn((Camera2dBundle::default(), GameOver));
+
+    let stats = [
+        format!("Survived {} days", time_of_day.day),
+        format!("Population: {}", format_thousands(population.total)),
+        format!("Funds: ${}", format_thousands(economy.funds)),
+        format!("Happiness: {:.0}%", town.happiness * 100.0),
+    ];
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            },
+            GameOver,
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section(
+                "Bankrupt!",
+                TextStyle {
+                    font_size: 60.0,
+                    color: Color::linear_rgb(0.9, 0.2, 0.2),
+                    ..default()
+                },
+            ));
+            for line in stats {
+                children.spawn(TextBundle::from_section(
+                    line,
+                    TextStyle {
+                        font_size: 24.0,
+                        color: Color::linear_rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ));
+            }
+            children
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(180.0),
+                            height: Val::Px(50.0),
+                            margin: UiRect::top(Val::Px(20.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: BUTTON_COLOR.into(),
+                        ..default()
+                    },
+                    RestartButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Restart",
+                        TextStyle {
+                            font_size: 40.0,
+                            color: Color::linear_rgb(0.9, 0.9, 0.9),
+                            ..default()
+                        },
+                    ));
+                });
+        });
+}
+
+fn click_restart_button(
+    mut next_state: ResMut<NextState<GameState>>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<RestartButton>),
+    >,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => next_state.set(GameState::Menu),
+            Interaction::Hovered => *color = BUTTON_HOVERED_COLOR.into(),
+            Interaction::None => *color = BUTTON_COLOR.into(),
+        }
+    }
+}
+
+fn cleanup_game_over(mut commands: Commands, game_over: Query<Entity, With<GameOver>>) {
+    for entity in game_over.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn restart_game(mut commands: Commands) {
+    reset_game(&mut commands);
+}
+
+// Start the next game from scratch, the island is generated again when it's next shown
+pub fn reset_game(commands: &mut Commands) {
+    commands.insert_resource(Population::default());
+    commands.insert_resource(Economy::default());
+    commands.insert_resource(Resources::default());
+    commands.insert_resource(Demand::default());
+    commands.insert_resource(WealthDistribution::default());
+    commands.insert_resource(LandValue::default());
+    commands.insert_resource(Town::default());
+    commands.insert_resource(Loans::default());
+    commands.insert_resource(Bankruptcy::default());
+    commands.insert_resource(GameSpeed::default());
+    commands.insert_resource(TimeOfDay::default());
+    commands.insert_resource(Milestones::default());
+    commands.insert_resource(History::default());
+    commands.insert_resource(Notifications::default());
+    commands.insert_resource(GameRng::default());
+    commands.remove_resource::<Island>();
+    commands.insert_resource(Towns::default());
+    commands.remove_resource::<SelectedTown>();
+}
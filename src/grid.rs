@@ -1,16 +1,30 @@
 use bevy::prelude::*;
+use std::collections::HashSet;
 
 pub struct GridPlugin;
 
 impl Plugin for GridPlugin {
-    fn build(&self, app: &mut App) {
+    fn build(&self, _app: &mut App) {
         // No systems needed for now, this is just a utility module
     }
 }
 
-// Grid cell marker trait
-pub trait GridCell {
-    fn position(&self) -> IVec2;
+// Shape of a radius around a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RadiusShape {
+    // Diamond of cells within the Manhattan distance
+    Manhattan,
+    // Square of cells within the Chebyshev distance
+    Chebyshev,
+}
+
+// Blocked corners a diagonal step may pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CornerCutting {
+    // One of the two orthogonal cells beside the step may be blocked, but not both
+    OneCorner,
+    // Both orthogonal cells beside the step have to be open
+    NoCorners,
 }
 
 // Grid utility functions
@@ -57,13 +71,302 @@ impl Grid {
         (pos1.x - pos2.x).abs() + (pos1.y - pos2.y).abs()
     }
     
+    // Calculate Chebyshev distance between two positions, the number of steps when
+    // diagonal moves are allowed
+    pub fn chebyshev_distance(pos1: IVec2, pos2: IVec2) -> i32 {
+        (pos1.x - pos2.x).abs().max((pos1.y - pos2.y).abs())
+    }
+    
+    // Calculate the straight-line distance between two positions
+    pub fn euclidean_distance(pos1: IVec2, pos2: IVec2) -> f32 {
+        (Grid::euclidean_distance_sq(pos1, pos2) as f32).sqrt()
+    }
+    
+    // Squared straight-line distance, enough for comparing distances without the square root
+    pub fn euclidean_distance_sq(pos1: IVec2, pos2: IVec2) -> i32 {
+        (pos1 - pos2).length_squared()
+    }
+    
+    // Get every cell on the straight line between two positions (inclusive),
+    // as an 8-connected chain using Bresenham's algorithm
+    pub fn line(from: IVec2, to: IVec2) -> Vec<IVec2> {
+        let dx = (to.x - from.x).abs();
+        let dy = -(to.y - from.y).abs();
+        let step_x = if from.x < to.x { 1 } else { -1 };
+        let step_y = if from.y < to.y { 1 } else { -1 };
+        
+        let mut cells = Vec::with_capacity((dx.max(-dy) + 1) as usize);
+        let mut current = from;
+        let mut error = dx + dy;
+        
+        loop {
+            cells.push(current);
+            if current == to {
+                break;
+            }
+            
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                current.x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                current.y += step_y;
+            }
+        }
+        
+        cells
+    }
+    
+    // Get every in-bounds cell within the radius of a position, including the position itself
+    pub fn cells_in_radius(center: IVec2, radius: i32, shape: RadiusShape, size: usize) -> Vec<IVec2> {
+        let mut cells = Vec::new();
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                if shape == RadiusShape::Manhattan && dx.abs() + dy.abs() > radius {
+                    continue;
+                }
+                let pos = center + IVec2::new(dx, dy);
+                if Grid::is_in_bounds(pos, size) {
+                    cells.push(pos);
+                }
+            }
+        }
+        cells
+    }
+    
+    // Get every in-bounds cell at exactly the radius from a position, the outline of `cells_in_radius`.
+    // A radius of 0 is just the position itself
+    pub fn ring(center: IVec2, radius: i32, shape: RadiusShape, size: usize) -> Vec<IVec2> {
+        let mut cells = Vec::new();
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let pos = center + IVec2::new(dx, dy);
+                let distance = match shape {
+                    RadiusShape::Manhattan => Grid::manhattan_distance(pos, center),
+                    RadiusShape::Chebyshev => Grid::chebyshev_distance(pos, center),
+                };
+                if distance == radius && Grid::is_in_bounds(pos, size) {
+                    cells.push(pos);
+                }
+            }
+        }
+        cells
+    }
+    
+    // Get all orthogonally connected cells reachable from the start for which the predicate holds.
+    // Uses an explicit stack so large regions can't overflow the call stack
+    pub fn flood_fill(start: IVec2, predicate: impl Fn(IVec2) -> bool, size: usize) -> HashSet<IVec2> {
+        let mut filled = HashSet::new();
+        if !Grid::is_in_bounds(start, size) || !predicate(start) {
+            return filled;
+        }
+        
+        let mut stack = vec![start];
+        filled.insert(start);
+        while let Some(current) = stack.pop() {
+            for neighbor in Grid::get_orthogonal_positions(current) {
+                if Grid::is_in_bounds(neighbor, size) && !filled.contains(&neighbor) && predicate(neighbor) {
+                    filled.insert(neighbor);
+                    stack.push(neighbor);
+                }
+            }
+        }
+        
+        filled
+    }
+    
+    // Split the cells for which the predicate holds into orthogonally connected regions,
+    // e.g. road networks that don't reach each other
+    pub fn connected_components(predicate: impl Fn(IVec2) -> bool, size: usize) -> Vec<HashSet<IVec2>> {
+        let mut components: Vec<HashSet<IVec2>> = Vec::new();
+        let mut labeled = HashSet::new();
+        for y in 0..size as i32 {
+            for x in 0..size as i32 {
+                let pos = IVec2::new(x, y);
+                if labeled.contains(&pos) || !predicate(pos) {
+                    continue;
+                }
+                let component = Grid::flood_fill(pos, &predicate, size);
+                labeled.extend(component.iter().copied());
+                components.push(component);
+            }
+        }
+        components
+    }
+    
     // Find a path between two positions using A* algorithm
-    pub fn find_path<T: GridCell>(
+    pub fn find_path(
         start: IVec2,
         goal: IVec2,
         is_accessible: impl Fn(IVec2) -> bool,
         size: usize,
     ) -> Option<Vec<IVec2>> {
+        Grid::a_star(start, goal, |_, to| is_accessible(to), |_| 1, 1, size, None).map(|(path, _)| path)
+    }
+    
+    // Find a path that may also move diagonally, passing as many blocked corners as allowed
+    pub fn find_path_diagonal(
+        start: IVec2,
+        goal: IVec2,
+        is_accessible: impl Fn(IVec2) -> bool,
+        corner_cutting: CornerCutting,
+        size: usize,
+    ) -> Option<Vec<IVec2>> {
+        Grid::a_star(start, goal, |_, to| is_accessible(to), |_| 1, 1, size, Some(corner_cutting))
+            .map(|(path, _)| path)
+    }
+    
+    // Find the cheapest path where entering a cell costs `cost_fn(cell)` instead of 1,
+    // e.g. to make highways cheaper than local roads. `min_cost` is the least any cell costs,
+    // scaling the heuristic by it keeps it admissible
+    pub fn find_path_weighted(
+        start: IVec2,
+        goal: IVec2,
+        is_accessible: impl Fn(IVec2) -> bool,
+        cost_fn: impl Fn(IVec2) -> i32,
+        min_cost: i32,
+        size: usize,
+    ) -> Option<Vec<IVec2>> {
+        Grid::find_path_cost(start, goal, is_accessible, cost_fn, min_cost, size).map(|(path, _)| path)
+    }
+    
+    // Find the cheapest path like `find_path_weighted`, along with its total cost: what entering
+    // every cell after the start costs
+    pub fn find_path_cost(
+        start: IVec2,
+        goal: IVec2,
+        is_accessible: impl Fn(IVec2) -> bool,
+        cost_fn: impl Fn(IVec2) -> i32,
+        min_cost: i32,
+        size: usize,
+    ) -> Option<(Vec<IVec2>, i32)> {
+        Grid::a_star(start, goal, |_, to| is_accessible(to), cost_fn, min_cost, size, None)
+    }
+    
+    // Find the cheapest path like `find_path_weighted`, where `can_step(from, to)` decides each
+    // step on its own rather than each cell, e.g. one-way streets that can't be driven against
+    // their arrow
+    pub fn find_path_directed(
+        start: IVec2,
+        goal: IVec2,
+        can_step: impl Fn(IVec2, IVec2) -> bool,
+        cost_fn: impl Fn(IVec2) -> i32,
+        min_cost: i32,
+        size: usize,
+    ) -> Option<Vec<IVec2>> {
+        Grid::a_star(start, goal, can_step, cost_fn, min_cost, size, None).map(|(path, _)| path)
+    }
+    
+    // Find a path to whichever of the goals is reachable in the fewest steps,
+    // using a single breadth-first search from the start
+    pub fn find_path_to_nearest(
+        start: IVec2,
+        goals: &[IVec2],
+        is_accessible: impl Fn(IVec2) -> bool,
+        size: usize,
+    ) -> Option<Vec<IVec2>> {
+        Grid::find_path_to_nearest_directed(start, goals, |_, to| is_accessible(to), size)
+    }
+    
+    // Find a path to the nearest goal like `find_path_to_nearest`, deciding each step
+    // with `can_step(from, to)` like `find_path_directed`
+    pub fn find_path_to_nearest_directed(
+        start: IVec2,
+        goals: &[IVec2],
+        can_step: impl Fn(IVec2, IVec2) -> bool,
+        size: usize,
+    ) -> Option<Vec<IVec2>> {
+        use std::collections::{HashMap, HashSet, VecDeque};
+        
+        let goals: HashSet<IVec2> = goals.iter().copied().collect();
+        if goals.is_empty() {
+            return None;
+        }
+        
+        let mut queue = VecDeque::new();
+        let mut came_from = HashMap::new();
+        queue.push_back(start);
+        came_from.insert(start, start);
+        
+        while let Some(current) = queue.pop_front() {
+            if goals.contains(&current) {
+                // Reconstruct path
+                let mut path = vec![current];
+                let mut position = current;
+                while position != start {
+                    position = came_from[&position];
+                    path.push(position);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            
+            for neighbor in Grid::get_orthogonal_positions(current) {
+                if !Grid::is_in_bounds(neighbor, size)
+                    || came_from.contains_key(&neighbor)
+                    || !can_step(current, neighbor)
+                {
+                    continue;
+                }
+                came_from.insert(neighbor, current);
+                queue.push_back(neighbor);
+            }
+        }
+        
+        None // No goal reachable
+    }
+    
+    // Find the cell closest to the start in steps that matches the predicate, searching outward
+    // through passable cells. Matching cells count as reached even when they aren't passable,
+    // like a workplace at the end of a road
+    pub fn bfs_nearest(
+        start: IVec2,
+        predicate: impl Fn(IVec2) -> bool,
+        is_passable: impl Fn(IVec2) -> bool,
+        size: usize,
+    ) -> Option<IVec2> {
+        use std::collections::VecDeque;
+        
+        if !Grid::is_in_bounds(start, size) {
+            return None;
+        }
+        
+        let mut queue = VecDeque::new();
+        let mut visited = HashSet::new();
+        queue.push_back(start);
+        visited.insert(start);
+        
+        while let Some(current) = queue.pop_front() {
+            if predicate(current) {
+                return Some(current);
+            }
+            if current != start && !is_passable(current) {
+                continue;
+            }
+            
+            for neighbor in Grid::get_orthogonal_positions(current) {
+                if Grid::is_in_bounds(neighbor, size) && visited.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        
+        None // Nothing reachable matches
+    }
+    
+    // A* search shared by the path finding functions, returning the path and its cost.
+    // Diagonal steps are only taken when given how they may cut corners
+    fn a_star(
+        start: IVec2,
+        goal: IVec2,
+        can_step: impl Fn(IVec2, IVec2) -> bool,
+        cost_fn: impl Fn(IVec2) -> i32,
+        min_cost: i32,
+        size: usize,
+        diagonal: Option<CornerCutting>,
+    ) -> Option<(Vec<IVec2>, i32)> {
         use std::collections::{BinaryHeap, HashMap};
         use std::cmp::Ordering;
         
@@ -87,6 +390,17 @@ impl Grid {
             }
         }
         
+        // Diagonal steps cost the same as orthogonal ones, so the Chebyshev distance
+        // is the admissible heuristic when diagonals are allowed
+        let heuristic = |pos: IVec2| -> i32 {
+            let steps = if diagonal.is_some() {
+                Grid::chebyshev_distance(pos, goal)
+            } else {
+                Grid::manhattan_distance(pos, goal)
+            };
+            steps * min_cost
+        };
+        
         let mut open_set = BinaryHeap::new();
         let mut came_from = HashMap::new();
         let mut g_score = HashMap::new();
@@ -94,7 +408,7 @@ impl Grid {
         g_score.insert(start, 0);
         open_set.push(Node {
             position: start,
-            f_score: Grid::manhattan_distance(start, goal),
+            f_score: heuristic(start),
         });
         
         while let Some(current) = open_set.pop() {
@@ -107,21 +421,43 @@ impl Grid {
                     current = prev;
                 }
                 path.reverse();
-                return Some(path);
+                return Some((path, g_score[&goal]));
             }
             
             let current_g = *g_score.get(&current.position).unwrap_or(&i32::MAX);
             
-            for neighbor in Grid::get_orthogonal_positions(current.position) {
-                if !Grid::is_in_bounds(neighbor, size) || !is_accessible(neighbor) {
+            let neighbors: Vec<IVec2> = if diagonal.is_some() {
+                Grid::get_adjacent_positions(current.position).to_vec()
+            } else {
+                Grid::get_orthogonal_positions(current.position).to_vec()
+            };
+            
+            for neighbor in neighbors {
+                if !Grid::is_in_bounds(neighbor, size) || !can_step(current.position, neighbor) {
                     continue;
                 }
                 
-                let tentative_g = current_g + 1;
+                // Don't cut diagonally past more blocked orthogonal cells than allowed
+                let step = neighbor - current.position;
+                if step.x != 0 && step.y != 0 {
+                    let side_a = IVec2::new(neighbor.x, current.position.y);
+                    let side_b = IVec2::new(current.position.x, neighbor.y);
+                    let open_a = Grid::is_in_bounds(side_a, size) && can_step(current.position, side_a);
+                    let open_b = Grid::is_in_bounds(side_b, size) && can_step(current.position, side_b);
+                    let passable = match diagonal {
+                        Some(CornerCutting::NoCorners) => open_a && open_b,
+                        _ => open_a || open_b,
+                    };
+                    if !passable {
+                        continue;
+                    }
+                }
+                
+                let tentative_g = current_g + cost_fn(neighbor);
                 if tentative_g < *g_score.get(&neighbor).unwrap_or(&i32::MAX) {
                     came_from.insert(neighbor, current.position);
                     g_score.insert(neighbor, tentative_g);
-                    let f_score = tentative_g + Grid::manhattan_distance(neighbor, goal);
+                    let f_score = tentative_g + heuristic(neighbor);
                     open_set.push(Node {
                         position: neighbor,
                         f_score,
@@ -133,3 +469,305 @@ impl Grid {
         None // No path found
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every cell open but the blocked ones
+    fn open_except(blocked: &[IVec2]) -> impl Fn(IVec2) -> bool + '_ {
+        move |pos| !blocked.contains(&pos)
+    }
+
+    // Whether every step of a path is to an adjacent cell, orthogonal or diagonal
+    fn is_chain(path: &[IVec2]) -> bool {
+        path.windows(2).all(|step| Grid::are_adjacent(step[0], step[1]))
+    }
+
+    #[test]
+    fn diagonal_path_crosses_open_ground_in_chebyshev_steps() {
+        let (start, goal) = (IVec2::new(0, 0), IVec2::new(4, 2));
+        let path = Grid::find_path_diagonal(start, goal, open_except(&[]), CornerCutting::NoCorners, 8).unwrap();
+        assert_eq!(path.len(), 5);
+        assert_eq!((path[0], path[4]), (start, goal));
+        assert!(is_chain(&path));
+
+        // Without diagonals the same trip takes the Manhattan distance
+        let path = Grid::find_path(start, goal, open_except(&[]), 8).unwrap();
+        assert_eq!(path.len(), 7);
+    }
+
+    #[test]
+    fn diagonal_path_goes_around_a_wall() {
+        let wall: Vec<IVec2> = (0..4).map(|y| IVec2::new(2, y)).collect();
+        let walk = |size| {
+            Grid::find_path_diagonal(IVec2::ZERO, IVec2::new(4, 0), open_except(&wall), CornerCutting::OneCorner, size)
+        };
+        let path = walk(6).unwrap();
+        assert!(path.iter().all(|pos| !wall.contains(pos)));
+        assert!(path.contains(&IVec2::new(2, 4)));
+        assert!(is_chain(&path));
+        // On a grid the wall spans there's no way around
+        assert_eq!(walk(4), None);
+    }
+
+    // Entering the bottom row costs 5, the rows above it 1, like a slow street beside a highway
+    fn slow_bottom_row(pos: IVec2) -> i32 {
+        if pos.y == 0 {
+            5
+        } else {
+            1
+        }
+    }
+
+    #[test]
+    fn weighted_path_takes_the_cheaper_detour() {
+        let path =
+            Grid::find_path_weighted(IVec2::ZERO, IVec2::new(4, 0), open_except(&[]), slow_bottom_row, 1, 5).unwrap();
+        let expected: Vec<IVec2> = [IVec2::ZERO]
+            .into_iter()
+            .chain((0..5).map(|x| IVec2::new(x, 1)))
+            .chain([IVec2::new(4, 0)])
+            .collect();
+        assert_eq!(path, expected);
+    }
+
+    #[test]
+    fn weighted_path_with_uniform_costs_is_the_shortest() {
+        let path = Grid::find_path_weighted(IVec2::ZERO, IVec2::new(3, 3), open_except(&[]), |_| 2, 2, 5).unwrap();
+        assert_eq!(path.len(), 7);
+    }
+
+    // Steps along the bottom row may only head east, like a one-way street
+    fn eastbound_bottom_row(from: IVec2, to: IVec2) -> bool {
+        from.y != 0 || to.y != 0 || to.x > from.x
+    }
+
+    #[test]
+    fn directed_path_goes_around_a_one_way_street() {
+        let east = Grid::find_path_directed(IVec2::ZERO, IVec2::new(4, 0), eastbound_bottom_row, |_| 1, 1, 5).unwrap();
+        assert_eq!(east.len(), 5);
+
+        // Heading back west has to leave the bottom row
+        let west = Grid::find_path_directed(IVec2::new(4, 0), IVec2::ZERO, eastbound_bottom_row, |_| 1, 1, 5).unwrap();
+        assert_eq!(west.len(), 7);
+        assert!(west.windows(2).all(|step| eastbound_bottom_row(step[0], step[1])));
+
+        let nearest = Grid::find_path_to_nearest_directed(IVec2::new(4, 0), &[IVec2::ZERO], eastbound_bottom_row, 5);
+        assert_eq!(nearest.map(|path| path.len()), Some(7));
+    }
+
+    #[test]
+    fn nearest_goal_is_the_closest_by_road_not_by_distance() {
+        // The goal at (2, 0) is behind a wall, the one at (0, 4) is reached first
+        let wall = [IVec2::new(1, 0), IVec2::new(1, 1), IVec2::new(1, 2), IVec2::new(1, 3)];
+        let goals = [IVec2::new(2, 0), IVec2::new(0, 4)];
+        let path = Grid::find_path_to_nearest(IVec2::ZERO, &goals, open_except(&wall), 5).unwrap();
+        assert_eq!(path.last(), Some(&IVec2::new(0, 4)));
+        assert_eq!(path.len(), 5);
+        assert_eq!(Grid::find_path_to_nearest(IVec2::ZERO, &[], open_except(&wall), 5), None);
+    }
+
+    #[test]
+    fn line_covers_horizontal_vertical_and_diagonal_runs() {
+        let horizontal: Vec<IVec2> = (1..5).map(|x| IVec2::new(x, 2)).collect();
+        assert_eq!(Grid::line(IVec2::new(1, 2), IVec2::new(4, 2)), horizontal);
+        let vertical: Vec<IVec2> = (0..4).rev().map(|y| IVec2::new(3, y)).collect();
+        assert_eq!(Grid::line(IVec2::new(3, 3), IVec2::new(3, 0)), vertical);
+        let diagonal: Vec<IVec2> = (0..4).map(|i| IVec2::new(i, i)).collect();
+        assert_eq!(Grid::line(IVec2::ZERO, IVec2::new(3, 3)), diagonal);
+        assert_eq!(Grid::line(IVec2::new(2, 2), IVec2::new(2, 2)), vec![IVec2::new(2, 2)]);
+    }
+
+    #[test]
+    fn shallow_line_is_a_chain_with_one_cell_per_column() {
+        let line = Grid::line(IVec2::ZERO, IVec2::new(6, 2));
+        assert_eq!(line.len(), 7);
+        assert_eq!((line[0], line[6]), (IVec2::ZERO, IVec2::new(6, 2)));
+        assert!(is_chain(&line));
+        assert!(line.iter().enumerate().all(|(x, pos)| pos.x == x as i32));
+    }
+
+    #[test]
+    fn flood_fill_stays_inside_its_region() {
+        // A wall down the middle of a 5x5 grid, open only at the top
+        let wall: Vec<IVec2> = (0..4).map(|y| IVec2::new(2, y)).collect();
+        let filled = Grid::flood_fill(IVec2::ZERO, open_except(&wall), 5);
+        assert_eq!(filled.len(), 25 - wall.len());
+
+        let closed: Vec<IVec2> = (0..5).map(|y| IVec2::new(2, y)).collect();
+        let filled = Grid::flood_fill(IVec2::ZERO, open_except(&closed), 5);
+        assert_eq!(filled.len(), 10);
+        assert!(filled.iter().all(|pos| pos.x < 2));
+    }
+
+    #[test]
+    fn flood_fill_from_a_refused_cell_is_empty() {
+        assert!(Grid::flood_fill(IVec2::ZERO, open_except(&[IVec2::ZERO]), 5).is_empty());
+        assert!(Grid::flood_fill(IVec2::new(-1, 0), open_except(&[]), 5).is_empty());
+    }
+
+    #[test]
+    fn flood_fill_handles_a_large_grid() {
+        assert_eq!(Grid::flood_fill(IVec2::ZERO, open_except(&[]), 500).len(), 500 * 500);
+    }
+
+    #[test]
+    fn radius_zero_is_just_the_center() {
+        for shape in [RadiusShape::Manhattan, RadiusShape::Chebyshev] {
+            assert_eq!(Grid::cells_in_radius(IVec2::new(3, 3), 0, shape, 8), vec![IVec2::new(3, 3)]);
+        }
+    }
+
+    #[test]
+    fn radius_one_is_a_diamond_or_a_square() {
+        assert_eq!(Grid::cells_in_radius(IVec2::new(3, 3), 1, RadiusShape::Manhattan, 8).len(), 5);
+        assert_eq!(Grid::cells_in_radius(IVec2::new(3, 3), 1, RadiusShape::Chebyshev, 8).len(), 9);
+        assert_eq!(Grid::cells_in_radius(IVec2::new(3, 3), 2, RadiusShape::Manhattan, 8).len(), 13);
+        assert_eq!(Grid::cells_in_radius(IVec2::new(3, 3), 2, RadiusShape::Chebyshev, 8).len(), 25);
+    }
+
+    #[test]
+    fn radius_is_clipped_at_the_grid_edge() {
+        let corner = Grid::cells_in_radius(IVec2::ZERO, 2, RadiusShape::Chebyshev, 8);
+        assert_eq!(corner.len(), 9);
+        assert!(corner.iter().all(|pos| Grid::is_in_bounds(*pos, 8)));
+        assert_eq!(Grid::cells_in_radius(IVec2::ZERO, 2, RadiusShape::Manhattan, 8).len(), 6);
+    }
+
+    #[test]
+    fn chebyshev_distance_counts_diagonal_steps_as_one() {
+        assert_eq!(Grid::chebyshev_distance(IVec2::ZERO, IVec2::ZERO), 0);
+        assert_eq!(Grid::chebyshev_distance(IVec2::ZERO, IVec2::new(3, 3)), 3);
+        assert_eq!(Grid::chebyshev_distance(IVec2::new(1, 5), IVec2::new(4, 2)), 3);
+        assert_eq!(Grid::chebyshev_distance(IVec2::new(-2, 0), IVec2::new(3, 1)), 5);
+    }
+
+    #[test]
+    fn euclidean_distance_is_the_straight_line() {
+        assert_eq!(Grid::euclidean_distance(IVec2::ZERO, IVec2::new(3, 4)), 5.0);
+        assert_eq!(Grid::euclidean_distance_sq(IVec2::ZERO, IVec2::new(3, 4)), 25);
+        assert_eq!(Grid::euclidean_distance_sq(IVec2::new(2, 2), IVec2::new(1, 1)), 2);
+        assert!((Grid::euclidean_distance(IVec2::ZERO, IVec2::ONE) - 2f32.sqrt()).abs() < 1e-6);
+
+        // Shorter than the Manhattan distance off the axes, the same along them
+        assert_eq!(Grid::manhattan_distance(IVec2::ZERO, IVec2::new(3, 4)), 7);
+        assert_eq!(Grid::euclidean_distance(IVec2::new(2, 5), IVec2::new(2, 9)), 4.0);
+        assert_eq!(Grid::manhattan_distance(IVec2::new(2, 5), IVec2::new(2, 9)), 4);
+        assert_eq!(Grid::euclidean_distance(IVec2::new(-3, 1), IVec2::new(4, 1)), 7.0);
+        assert_eq!(Grid::manhattan_distance(IVec2::new(-3, 1), IVec2::new(4, 1)), 7);
+    }
+
+    #[test]
+    fn squared_euclidean_distance_orders_like_the_distance() {
+        let origin = IVec2::new(5, 5);
+        let mut by_distance: Vec<IVec2> = (0..10).flat_map(|x| (0..10).map(move |y| IVec2::new(x, y))).collect();
+        let mut by_squared = by_distance.clone();
+        by_distance.sort_by(|a, b| {
+            Grid::euclidean_distance(origin, *a).total_cmp(&Grid::euclidean_distance(origin, *b))
+        });
+        by_squared.sort_by_key(|pos| Grid::euclidean_distance_sq(origin, *pos));
+        let distances = |positions: &[IVec2]| -> Vec<f32> {
+            positions.iter().map(|pos| Grid::euclidean_distance(origin, *pos)).collect()
+        };
+        assert_eq!(distances(&by_distance), distances(&by_squared));
+    }
+
+    #[test]
+    fn path_cost_with_unit_costs_is_the_length_minus_one() {
+        let wall = [IVec2::new(1, 0), IVec2::new(1, 1)];
+        let (path, cost) =
+            Grid::find_path_cost(IVec2::ZERO, IVec2::new(2, 0), open_except(&wall), |_| 1, 1, 4).unwrap();
+        assert_eq!(cost, path.len() as i32 - 1);
+        assert_eq!(cost, 6);
+    }
+
+    #[test]
+    fn path_cost_adds_up_the_cells_entered() {
+        let (_, cost) =
+            Grid::find_path_cost(IVec2::ZERO, IVec2::new(4, 0), open_except(&[]), slow_bottom_row, 1, 5).unwrap();
+        assert_eq!(cost, 10);
+        // Staying put costs nothing
+        let (path, cost) =
+            Grid::find_path_cost(IVec2::ZERO, IVec2::ZERO, open_except(&[]), slow_bottom_row, 1, 5).unwrap();
+        assert_eq!((path, cost), (vec![IVec2::ZERO], 0));
+    }
+
+    #[test]
+    fn diagonal_step_past_one_blocked_corner_depends_on_the_strictness() {
+        let blocked = [IVec2::new(1, 0)];
+        let path = Grid::find_path_diagonal(IVec2::ZERO, IVec2::ONE, open_except(&blocked), CornerCutting::OneCorner, 2);
+        assert_eq!(path, Some(vec![IVec2::ZERO, IVec2::ONE]));
+
+        // Not cutting any corner means going around it
+        let path = Grid::find_path_diagonal(IVec2::ZERO, IVec2::ONE, open_except(&blocked), CornerCutting::NoCorners, 2);
+        assert_eq!(path, Some(vec![IVec2::ZERO, IVec2::Y, IVec2::ONE]));
+    }
+
+    #[test]
+    fn diagonal_step_between_two_blocked_corners_is_never_taken() {
+        let blocked = [IVec2::new(1, 0), IVec2::new(0, 1)];
+        for corner_cutting in [CornerCutting::OneCorner, CornerCutting::NoCorners] {
+            let path = Grid::find_path_diagonal(IVec2::ZERO, IVec2::ONE, open_except(&blocked), corner_cutting, 2);
+            assert_eq!(path, None);
+        }
+    }
+
+    #[test]
+    fn bfs_nearest_finds_the_closest_reachable_match() {
+        let wall = [IVec2::new(1, 0), IVec2::new(1, 1), IVec2::new(1, 2), IVec2::new(1, 3)];
+        let is_target = |pos: IVec2| pos == IVec2::new(2, 0) || pos == IVec2::new(0, 3);
+        assert_eq!(Grid::bfs_nearest(IVec2::ZERO, is_target, open_except(&wall), 5), Some(IVec2::new(0, 3)));
+        // Matches count as reached even when they can't be passed through
+        assert_eq!(Grid::bfs_nearest(IVec2::ZERO, |pos| pos == IVec2::X, open_except(&wall), 5), Some(IVec2::X));
+    }
+
+    #[test]
+    fn bfs_nearest_without_a_reachable_match_is_none() {
+        let closed: Vec<IVec2> = (0..5).map(|y| IVec2::new(1, y)).collect();
+        assert_eq!(Grid::bfs_nearest(IVec2::ZERO, |pos| pos.x == 3, open_except(&closed), 5), None);
+        assert_eq!(Grid::bfs_nearest(IVec2::new(-1, 0), |_| true, open_except(&[]), 5), None);
+    }
+
+    #[test]
+    fn connected_components_split_separate_road_networks() {
+        // Two horizontal streets and a lone cell, none of them touching
+        let roads: Vec<IVec2> = (0..4)
+            .map(|x| IVec2::new(x, 0))
+            .chain((1..5).map(|x| IVec2::new(x, 2)))
+            .chain([IVec2::new(0, 4)])
+            .collect();
+        let mut components = Grid::connected_components(|pos| roads.contains(&pos), 5);
+        components.sort_by_key(|component| component.len());
+        let sizes: Vec<usize> = components.iter().map(|component| component.len()).collect();
+        assert_eq!(sizes, vec![1, 4, 4]);
+        assert!(components[0].contains(&IVec2::new(0, 4)));
+    }
+
+    #[test]
+    fn connected_components_of_nothing_is_empty() {
+        assert!(Grid::connected_components(|_| false, 5).is_empty());
+        assert_eq!(Grid::connected_components(|_| true, 5).len(), 1);
+    }
+
+    #[test]
+    fn ring_is_the_outline_of_the_radius() {
+        let center = IVec2::new(4, 4);
+        for shape in [RadiusShape::Manhattan, RadiusShape::Chebyshev] {
+            assert_eq!(Grid::ring(center, 0, shape, 9), vec![center]);
+        }
+        let chebyshev = Grid::ring(center, 1, RadiusShape::Chebyshev, 9);
+        assert_eq!(chebyshev.len(), 8);
+        assert!(chebyshev.iter().all(|pos| Grid::are_adjacent(*pos, center)));
+        assert_eq!(Grid::ring(center, 1, RadiusShape::Manhattan, 9).len(), 4);
+        assert_eq!(Grid::ring(center, 3, RadiusShape::Chebyshev, 9).len(), 24);
+        assert_eq!(Grid::ring(center, 3, RadiusShape::Manhattan, 9).len(), 12);
+    }
+
+    #[test]
+    fn ring_is_clipped_at_the_grid_edge() {
+        let ring = Grid::ring(IVec2::ZERO, 2, RadiusShape::Chebyshev, 9);
+        assert_eq!(ring.len(), 5);
+        assert!(ring.iter().all(|pos| Grid::is_in_bounds(*pos, 9)));
+    }
+}
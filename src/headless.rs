@@ -0,0 +1,197 @@
+use bevy::ecs::schedule::ExecutorKind;
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use crate::abandonment::AbandonmentPlugin;
+use crate::actions::KeyBindings;
+use crate::citizen::{Citizen, CitizenPlugin};
+use crate::clock::{ClockPlugin, TimeOfDay};
+use crate::departments::DepartmentsPlugin;
+use crate::disasters::Disasters;
+use crate::heatmaps::HeatmapsPlugin;
+use crate::notifications::Notification;
+use crate::rng::GameRng;
+use crate::simulation::{Brownout, SimulationPlugin, SimulationSet, SIMULATION_TICK_SECONDS};
+use crate::tilemap::TownTiles;
+use crate::traffic_lights::TrafficLightsPlugin;
+use crate::town::{load_layout, update_town_simulation, OverlayMode, RoadChanged, TownMap};
+use crate::trade::Trade;
+use crate::transit::TransitRoutes;
+use crate::GameState;
+use std::time::Duration;
+
+pub use crate::simulation::{Economy, Population};
+pub use crate::town::{BuildingType, CellSave, Town, ZoneType};
+
+// Frames the simulation is stepped in per tick, so citizens and vehicles move in small steps
+const FRAMES_PER_TICK: u32 = 10;
+
+// The town simulation without a window, rendering or input, stepped by hand for balancing runs and tests.
+// Disasters, trade and bus routes are left out so runs only depend on the layout and the seed
+pub struct HeadlessSimulation {
+    app: App,
+}
+
+impl HeadlessSimulation {
+    // Simulate a town laid out as the given cells, those left out are empty
+    pub fn new(cells: &[CellSave], seed: u64) -> Self {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                SIMULATION_TICK_SECONDS / FRAMES_PER_TICK as f32,
+            )))
+            .insert_state(GameState::TownView)
+            .insert_resource(GameRng::new(seed))
+            // Resources the town view, input and other services would otherwise provide
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<ClearColor>()
+            .init_resource::<TownMap>()
+            .init_resource::<TownTiles>()
+            .init_resource::<OverlayMode>()
+            .init_resource::<Disasters>()
+            .init_resource::<Trade>()
+            .init_resource::<TransitRoutes>()
+            .add_event::<Notification>()
+            .add_event::<RoadChanged>()
+            .add_plugins((
+                SimulationPlugin,
+                CitizenPlugin,
+                ClockPlugin,
+                DepartmentsPlugin,
+                HeatmapsPlugin,
+                AbandonmentPlugin,
+                TrafficLightsPlugin,
+            ))
+            .add_systems(
+                Update,
+                update_town_simulation
+                    .in_set(SimulationSet::Growth)
+                    .run_if(in_state(GameState::TownView)),
+            );
+        // Run the systems one at a time, so those sharing the random number generator draw from it
+        // in the same order on every update rather than whichever thread gets there first
+        app.edit_schedule(Update, |schedule| {
+            schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        });
+        app.finish();
+        app.cleanup();
+
+        let world = app.world_mut();
+        let season = world.resource::<TimeOfDay>().season();
+        world.resource_scope(|world, mut town_map: Mut<TownMap>| {
+            load_layout(&mut town_map, &mut world.resource_mut::<TownTiles>(), cells, season);
+        });
+        HeadlessSimulation { app }
+    }
+
+    // Advance the simulation by a number of ticks, a tick being one step of the economy and resources
+    pub fn run_ticks(&mut self, ticks: u32) {
+        for _ in 0..ticks * FRAMES_PER_TICK {
+            self.app.update();
+        }
+    }
+
+    // Whether the town is still simulated, it stops on going bankrupt
+    pub fn is_running(&self) -> bool {
+        *self.app.world().resource::<State<GameState>>().get() == GameState::TownView
+    }
+
+    pub fn population(&self) -> &Population {
+        self.app.world().resource::<Population>()
+    }
+
+    // Citizens living in the town, counted from the ones walking around rather than the statistic
+    pub fn citizens(&mut self) -> usize {
+        let world = self.app.world_mut();
+        world.query::<&Citizen>().iter(world).count()
+    }
+
+    pub fn economy(&self) -> &Economy {
+        self.app.world().resource::<Economy>()
+    }
+
+    pub fn town(&self) -> &Town {
+        self.app.world().resource::<Town>()
+    }
+
+    // Cells of the town as they developed, in the same form they're laid out in
+    pub fn cells(&self) -> Vec<CellSave> {
+        self.app
+            .world()
+            .resource::<TownMap>()
+            .iter()
+            .map(|cell| CellSave {
+                position: cell.position,
+                zone: cell.zone,
+                building: cell.building,
+                density: cell.density,
+            })
+            .collect()
+    }
+
+    // Clear a cell of its zone and building, as if bulldozed
+    pub fn clear_cell(&mut self, pos: IVec2) {
+        if let Some(cell) = self.app.world_mut().resource_mut::<TownMap>().cell_mut(pos) {
+            cell.zone = ZoneType::None;
+            cell.building = BuildingType::None;
+            cell.density = 0;
+        }
+    }
+
+    // Zones cut off from power while demand can't be met
+    pub fn brownout(&self) -> &[IVec2] {
+        self.app.world().resource::<Brownout>().cells()
+    }
+
+    // The app itself, for resources and entities the accessors above don't cover
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+}
+
+// A cell of a town layout, for tests to lay out the towns they run
+#[cfg(test)]
+pub(crate) fn layout_cell(x: i32, y: i32, zone: ZoneType, building: BuildingType) -> CellSave {
+    CellSave {
+        position: IVec2::new(x, y),
+        zone,
+        building,
+        density: 0,
+    }
+}
+
+// Write a file to its own directory under the system's temp directory and read it back, so
+// tests don't share one
+#[cfg(test)]
+pub(crate) fn read_back<T>(
+    test: &str,
+    write: impl FnOnce(&std::path::Path),
+    read: impl FnOnce(&std::path::Path) -> T,
+) -> T {
+    let directory = std::env::temp_dir().join(format!("bevy_game_{}_{}", test, std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("layout.json");
+    write(&path);
+    let read = read(&path);
+    std::fs::remove_dir_all(&directory).ok();
+    read
+}
+
+// A street of homes, shops and workplaces with power and water at its end
+#[cfg(test)]
+pub(crate) fn street() -> Vec<CellSave> {
+    let mut cells = Vec::new();
+    for x in 10..20 {
+        cells.push(layout_cell(x, 20, ZoneType::None, BuildingType::Road));
+        cells.push(layout_cell(x, 21, ZoneType::Residential, BuildingType::None));
+    }
+    for x in 10..15 {
+        cells.push(layout_cell(x, 19, ZoneType::Commercial, BuildingType::None));
+        cells.push(layout_cell(x + 5, 19, ZoneType::Industrial, BuildingType::None));
+    }
+    cells.push(layout_cell(20, 21, ZoneType::None, BuildingType::PowerPlant));
+    cells.push(layout_cell(20, 19, ZoneType::None, BuildingType::WaterTower));
+    cells
+}
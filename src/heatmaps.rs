@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+use crate::citizen::{Citizen, TrafficPollution};
+use crate::coverage::compute_coverage;
+use crate::departments::DepartmentBonuses;
+use crate::grid::{Grid, RadiusShape};
+use crate::simulation::{Garbage, SimulationSet};
+use crate::town::{BuildingType, TownMap, ZoneType, MAX_DENSITY, TOWN_GRID_SIZE};
+use crate::GameState;
+use std::time::Duration;
+
+pub struct HeatmapsPlugin;
+
+/// This plugin keeps per-cell grids of simulation data for the town overlays
+impl Plugin for HeatmapsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Heatmaps>()
+            .add_systems(
+                Update,
+                update_heatmaps.in_set(SimulationSet::Heatmaps).run_if(in_state(GameState::TownView)),
+            );
+    }
+}
+
+// Seconds between recomputing the grids, they change slowly and are only looked at
+const HEATMAP_INTERVAL_SECONDS: f32 = 1.0;
+
+// Reach of the buildings supplying power and water
+const POWER_PLANT_RADIUS: i32 = 12;
+const WATER_TOWER_RADIUS: i32 = 10;
+
+// Police keep crime down within this many cells
+const POLICE_RADIUS: i32 = 10;
+
+// Crime on a developed cell without police nearby, growing with its density
+const BASE_CRIME: f32 = 0.3;
+const DENSITY_CRIME: f32 = 0.2;
+
+// Pollution spread by industry and power plants, falling off over the radius
+const POLLUTION_RADIUS: i32 = 4;
+const INDUSTRIAL_POLLUTION: f32 = 0.3;
+const POWER_PLANT_POLLUTION: f32 = 0.8;
+const LANDFILL_POLLUTION: f32 = 0.5;
+// Pollution on a cell buried in garbage
+const GARBAGE_POLLUTION: f32 = 0.5;
+
+type HeatmapGrid<T> = [[T; TOWN_GRID_SIZE]; TOWN_GRID_SIZE];
+
+// Per-cell values in [0, 1] behind the overlays
+#[derive(Resource)]
+pub struct Heatmaps {
+    pub pollution: HeatmapGrid<f32>,
+    pub crime: HeatmapGrid<f32>,
+    // Average happiness of the citizens living on a cell, None where nobody lives
+    pub happiness: HeatmapGrid<Option<f32>>,
+    pub power: HeatmapGrid<f32>,
+    pub water: HeatmapGrid<f32>,
+}
+
+impl Default for Heatmaps {
+    fn default() -> Self {
+        Heatmaps {
+            pollution: [[0.0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+            crime: [[0.0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+            happiness: [[None; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+            power: [[0.0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+            water: [[0.0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+        }
+    }
+}
+
+fn at<T: Copy>(grid: &HeatmapGrid<T>, pos: IVec2) -> T {
+    grid[pos.y as usize][pos.x as usize]
+}
+
+impl Heatmaps {
+    pub fn pollution(&self, pos: IVec2) -> f32 {
+        at(&self.pollution, pos)
+    }
+
+    pub fn crime(&self, pos: IVec2) -> f32 {
+        at(&self.crime, pos)
+    }
+
+    pub fn happiness(&self, pos: IVec2) -> Option<f32> {
+        at(&self.happiness, pos)
+    }
+
+    pub fn power(&self, pos: IVec2) -> f32 {
+        at(&self.power, pos)
+    }
+
+    pub fn water(&self, pos: IVec2) -> f32 {
+        at(&self.water, pos)
+    }
+}
+
+// Coverage of the given buildings as a grid for the overlays
+fn coverage_grid(sources: &[IVec2], radius: i32) -> HeatmapGrid<f32> {
+    let mut grid: HeatmapGrid<f32> = [[0.0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE];
+    for (pos, value) in compute_coverage(sources, radius, TOWN_GRID_SIZE) {
+        grid[pos.y as usize][pos.x as usize] = value;
+    }
+    grid
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_heatmaps(
+    time: Res<Time>,
+    mut timer: Local<Timer>,
+    town_map: Res<TownMap>,
+    citizens: Query<&Citizen>,
+    traffic_pollution: Res<TrafficPollution>,
+    garbage: Res<Garbage>,
+    bonuses: Res<DepartmentBonuses>,
+    mut heatmaps: ResMut<Heatmaps>,
+) {
+    if timer.duration() == Duration::ZERO {
+        *timer = Timer::from_seconds(HEATMAP_INTERVAL_SECONDS, TimerMode::Repeating);
+    }
+
+    timer.tick(time.delta());
+    if !timer.just_finished() {
+        return;
+    }
+
+    let positions = |building: BuildingType| -> Vec<IVec2> {
+        town_map
+            .iter()
+            .filter(|cell| cell.building == building)
+            .map(|cell| cell.position)
+            .collect()
+    };
+    let power = coverage_grid(&positions(BuildingType::PowerPlant), POWER_PLANT_RADIUS);
+    let water = coverage_grid(&positions(BuildingType::WaterTower), WATER_TOWER_RADIUS);
+    let police = coverage_grid(&positions(BuildingType::Police), POLICE_RADIUS);
+
+    let mut pollution = [[0.0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE];
+    let mut crime = [[0.0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE];
+    for cell in town_map.iter() {
+        let pos = cell.position;
+        let emitted = if cell.building == BuildingType::PowerPlant {
+            POWER_PLANT_POLLUTION
+        } else if cell.building == BuildingType::Landfill {
+            LANDFILL_POLLUTION
+        } else if cell.zone == ZoneType::Industrial {
+            INDUSTRIAL_POLLUTION
+        } else {
+            0.0
+        };
+        if emitted > 0.0 {
+            for target in Grid::cells_in_radius(pos, POLLUTION_RADIUS, RadiusShape::Manhattan, TOWN_GRID_SIZE) {
+                let falloff = 1.0 - Grid::manhattan_distance(pos, target) as f32 / (POLLUTION_RADIUS + 1) as f32;
+                pollution[target.y as usize][target.x as usize] += emitted * falloff;
+            }
+        }
+
+        if cell.zone != ZoneType::None && cell.density > 0 {
+            let density = cell.density as f32 / MAX_DENSITY as f32;
+            let unpoliced = 1.0 - at(&police, pos);
+            crime[pos.y as usize][pos.x as usize] =
+                ((BASE_CRIME + DENSITY_CRIME * density) * unpoliced * bonuses.crime_rate).clamp(0.0, 1.0);
+        }
+    }
+    for (y, row) in pollution.iter_mut().enumerate() {
+        for (x, value) in row.iter_mut().enumerate() {
+            let pos = IVec2::new(x as i32, y as i32);
+            let waste = GARBAGE_POLLUTION * garbage.level(pos);
+            *value = (*value + traffic_pollution.get(pos) + waste).clamp(0.0, 1.0);
+        }
+    }
+
+    let mut happiness_sums = [[(0.0, 0); TOWN_GRID_SIZE]; TOWN_GRID_SIZE];
+    for citizen in citizens.iter() {
+        let (sum, count) = &mut happiness_sums[citizen.home.y as usize][citizen.home.x as usize];
+        *sum += citizen.happiness;
+        *count += 1;
+    }
+    let happiness = happiness_sums.map(|row| {
+        row.map(|(sum, count)| (count > 0).then(|| (sum / count as f32).clamp(0.0, 1.0)))
+    });
+
+    *heatmaps = Heatmaps {
+        pollution,
+        crime,
+        happiness,
+        power,
+        water,
+    };
+}
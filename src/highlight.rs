@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+use crate::menu::outside_pause;
+use crate::selection::drag_selection;
+use crate::tooltip::InspectedCell;
+use crate::town::{cursor_grid_position, grid_to_world, TOWN_CELL_SPACING};
+use crate::GameState;
+
+pub struct HighlightPlugin;
+
+/// This plugin highlights the town cell under the cursor, or the one selected by clicking it
+impl Plugin for HighlightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            // Clicks dragging out a selection don't select the cell
+            (select_cell.after(drag_selection), update_highlight.after(select_cell))
+                .run_if(in_state(GameState::TownView)),
+        )
+        .add_systems(OnExit(GameState::TownView), despawn_highlight.run_if(outside_pause));
+    }
+}
+
+const HOVER_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.3);
+const SELECTED_COLOR: Color = Color::srgba(1.0, 0.9, 0.2, 0.45);
+
+// Drawn over the cell, a sprite in the world so it grows and shrinks with the zoom
+#[derive(Component)]
+struct CellHighlight;
+
+// A click on a cell selects it and shows its details, on top of whatever the tool does there
+fn select_cell(
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    ui_interactions: Query<&Interaction, With<Node>>,
+    mut inspected: ResMut<InspectedCell>,
+) {
+    if !mouse_button_input.just_pressed(MouseButton::Left)
+        || ui_interactions.iter().any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), camera_q.get_single()) else {
+        return;
+    };
+    if let Some(position) = cursor_grid_position(window, camera, camera_transform) {
+        inspected.0 = Some(position);
+    }
+}
+
+// Keep the highlight on the selected cell, or on the cell under the cursor while nothing is
+// selected. It's hidden while the cursor is over the UI or off the grid
+fn update_highlight(
+    mut commands: Commands,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    ui_interactions: Query<&Interaction, With<Node>>,
+    inspected: Res<InspectedCell>,
+    mut highlights: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<CellHighlight>>,
+) {
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), camera_q.get_single()) else {
+        return;
+    };
+    let over_ui = ui_interactions.iter().any(|interaction| *interaction != Interaction::None);
+    let target = match inspected.0 {
+        Some(position) => Some((position, SELECTED_COLOR)),
+        None if over_ui => None,
+        None => cursor_grid_position(window, camera, camera_transform).map(|position| (position, HOVER_COLOR)),
+    };
+
+    let Ok((mut transform, mut sprite, mut visibility)) = highlights.get_single_mut() else {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: HOVER_COLOR,
+                    custom_size: Some(Vec2::splat(TOWN_CELL_SPACING)),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            CellHighlight,
+        ));
+        return;
+    };
+    match target {
+        Some((position, color)) => {
+            transform.translation = grid_to_world(position, 3.0);
+            sprite.color = color;
+            *visibility = Visibility::Visible;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+fn despawn_highlight(mut commands: Commands, highlights: Query<Entity, With<CellHighlight>>) {
+    for entity in highlights.iter() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,317 @@
+use bevy::prelude::*;
+use crate::actions::{InputAction, KeyBindings};
+use crate::hud::format_thousands;
+use crate::simulation::{Economy, GameSpeed, Population};
+use crate::town::Town;
+use crate::GameState;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+pub struct HistoryPlugin;
+
+/// This plugin records how the town's key numbers develop and graphs them on request
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<History>()
+            .init_resource::<HistoryView>()
+            .add_systems(
+                Update,
+                (
+                    record_history,
+                    toggle_history_panel,
+                    click_history_button,
+                    draw_history_graph.after(record_history).after(click_history_button),
+                ).run_if(in_state(GameState::TownView)),
+            );
+    }
+}
+
+// Seconds of game time between two samples
+const HISTORY_SAMPLE_SECONDS: f32 = 5.0;
+
+// Samples kept per metric, an hour of game time. Older ones are dropped
+const HISTORY_CAPACITY: usize = 720;
+
+// Samples a graph can show: 5 minutes, 20 minutes or the whole hour
+const HISTORY_WINDOWS: [usize; 3] = [60, 240, HISTORY_CAPACITY];
+
+const GRAPH_WIDTH: f32 = 280.0;
+const GRAPH_HEIGHT: f32 = 120.0;
+const GRAPH_COLOR: Color = Color::srgb(0.3, 0.9, 0.4);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Metric {
+    Population,
+    Funds,
+    Happiness,
+}
+
+impl Metric {
+    const ALL: [Metric; 3] = [Metric::Population, Metric::Funds, Metric::Happiness];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Metric::Population => "Population",
+            Metric::Funds => "Funds",
+            Metric::Happiness => "Happiness",
+        }
+    }
+
+    fn format(&self, value: f32) -> String {
+        match self {
+            Metric::Population => format_thousands(value as i32),
+            Metric::Funds => format!("${}", format_thousands(value as i32)),
+            Metric::Happiness => format!("{:.0}%", value * 100.0),
+        }
+    }
+}
+
+// Recent samples of the town's key numbers, oldest first
+#[derive(Resource, Default)]
+pub struct History {
+    population: VecDeque<f32>,
+    funds: VecDeque<f32>,
+    happiness: VecDeque<f32>,
+}
+
+impl History {
+    fn samples(&self, metric: Metric) -> &VecDeque<f32> {
+        match metric {
+            Metric::Population => &self.population,
+            Metric::Funds => &self.funds,
+            Metric::Happiness => &self.happiness,
+        }
+    }
+
+    fn push(&mut self, population: f32, funds: f32, happiness: f32) {
+        for (samples, value) in [
+            (&mut self.population, population),
+            (&mut self.funds, funds),
+            (&mut self.happiness, happiness),
+        ] {
+            if samples.len() == HISTORY_CAPACITY {
+                samples.pop_front();
+            }
+            samples.push_back(value);
+        }
+    }
+}
+
+// What the history panel shows
+#[derive(Resource)]
+struct HistoryView {
+    metric: Metric,
+    // Index into HISTORY_WINDOWS
+    window: usize,
+}
+
+impl Default for HistoryView {
+    fn default() -> Self {
+        HistoryView {
+            metric: Metric::Population,
+            window: 0,
+        }
+    }
+}
+
+#[derive(Component)]
+struct HistoryPanel;
+
+// Empty node the graph is drawn over, it has no background so the gizmos show through
+#[derive(Component)]
+struct HistoryGraph;
+
+// Range and window of the shown graph
+#[derive(Component)]
+struct HistoryCaption;
+
+#[derive(Component, Clone, Copy)]
+enum HistoryButton {
+    Metric(Metric),
+    Window,
+}
+
+fn record_history(
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    mut timer: Local<Timer>,
+    population: Option<Res<Population>>,
+    economy: Res<Economy>,
+    town: Res<Town>,
+    mut history: ResMut<History>,
+) {
+    if timer.duration() == Duration::ZERO {
+        *timer = Timer::from_seconds(HISTORY_SAMPLE_SECONDS, TimerMode::Repeating);
+    }
+
+    timer.tick(speed.delta(&time));
+    if !timer.just_finished() {
+        return;
+    }
+    let population = population.map_or(0, |population| population.total);
+    history.push(population as f32, economy.funds as f32, town.happiness);
+}
+
+// Open and close the history panel with its key
+fn toggle_history_panel(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    panels: Query<Entity, With<HistoryPanel>>,
+) {
+    if !key_bindings.just_pressed(InputAction::History, &keyboard_input) {
+        return;
+    }
+    if let Ok(panel) = panels.get_single() {
+        commands.entity(panel).despawn_recursive();
+        return;
+    }
+
+    let text_style = TextStyle {
+        font_size: 14.0,
+        color: Color::WHITE,
+        ..default()
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(10.0),
+                    bottom: Val::Px(60.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(6.0)),
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                ..default()
+            },
+            Interaction::default(),
+            HistoryPanel,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    let buttons = Metric::ALL
+                        .into_iter()
+                        .map(|metric| (metric.label(), HistoryButton::Metric(metric)))
+                        .chain([("Window", HistoryButton::Window)]);
+                    for (label, button) in buttons {
+                        row.spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    padding: UiRect::axes(Val::Px(6.0), Val::Px(2.0)),
+                                    ..default()
+                                },
+                                background_color: Color::srgba(0.2, 0.2, 0.2, 0.8).into(),
+                                ..default()
+                            },
+                            button,
+                        ))
+                        .with_children(|button| {
+                            button.spawn(TextBundle::from_section(label, text_style.clone()));
+                        });
+                    }
+                });
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Px(GRAPH_WIDTH),
+                        height: Val::Px(GRAPH_HEIGHT),
+                        ..default()
+                    },
+                    ..default()
+                },
+                HistoryGraph,
+            ));
+            parent.spawn((TextBundle::from_section("", text_style), HistoryCaption));
+        });
+}
+
+fn click_history_button(
+    buttons: Query<(&Interaction, &HistoryButton), Changed<Interaction>>,
+    mut view: ResMut<HistoryView>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match *button {
+            HistoryButton::Metric(metric) => view.metric = metric,
+            HistoryButton::Window => view.window = (view.window + 1) % HISTORY_WINDOWS.len(),
+        }
+    }
+}
+
+// Draw the picked metric over the graph node. Gizmos live in the world, so the node's screen
+// rectangle is mapped through the camera
+fn draw_history_graph(
+    mut gizmos: Gizmos,
+    history: Res<History>,
+    view: Res<HistoryView>,
+    graphs: Query<(&Node, &GlobalTransform), With<HistoryGraph>>,
+    mut captions: Query<&mut Text, With<HistoryCaption>>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+) {
+    let Ok((node, node_transform)) = graphs.get_single() else {
+        return;
+    };
+    let Ok((camera, camera_transform)) = camera_q.get_single() else {
+        return;
+    };
+
+    let window = HISTORY_WINDOWS[view.window];
+    let samples = history.samples(view.metric);
+    let shown: Vec<f32> = samples.iter().skip(samples.len().saturating_sub(window)).copied().collect();
+    let min = shown.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = shown.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+
+    for mut text in captions.iter_mut() {
+        text.sections[0].value = if shown.is_empty() {
+            format!("{}: no data yet", view.metric.label())
+        } else {
+            format!(
+                "{}: {} to {}, last {} min",
+                view.metric.label(),
+                view.metric.format(min),
+                view.metric.format(max),
+                (window as f32 * HISTORY_SAMPLE_SECONDS / 60.0).round()
+            )
+        };
+    }
+
+    let size = node.size();
+    let top_left = node_transform.translation().truncate() - size / 2.0;
+    let to_world = |x: f32, y: f32| camera.viewport_to_world_2d(camera_transform, top_left + Vec2::new(x, y));
+
+    let corners = [(0.0, 0.0), (size.x, 0.0), (size.x, size.y), (0.0, size.y), (0.0, 0.0)];
+    let frame: Option<Vec<Vec2>> = corners.into_iter().map(|(x, y)| to_world(x, y)).collect();
+    if let Some(frame) = frame {
+        gizmos.linestrip_2d(frame, Color::srgba(1.0, 1.0, 1.0, 0.3));
+    }
+    if shown.len() < 2 {
+        return;
+    }
+
+    // A flat line sits in the middle instead of dividing by zero
+    let range = max - min;
+    let points: Option<Vec<Vec2>> = shown
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let x = index as f32 / (window - 1) as f32 * size.x;
+            let height = if range > 0.0 { (value - min) / range } else { 0.5 };
+            to_world(x, (1.0 - height) * size.y)
+        })
+        .collect();
+    if let Some(points) = points {
+        gizmos.linestrip_2d(points, GRAPH_COLOR);
+    }
+}
@@ -0,0 +1,196 @@
+use bevy::prelude::*;
+use crate::citizen::{Wealth, WealthDistribution};
+use crate::clock::TimeOfDay;
+use crate::simulation::{Demand, Economy, GameSpeed, Population};
+use crate::town::{Town, ZoneType};
+use crate::GameState;
+
+pub struct HudPlugin;
+
+/// This plugin keeps the stats bar at the top of the town view up to date
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (update_hud, update_demand_bars).run_if(in_state(GameState::TownView)),
+        );
+    }
+}
+
+// Stat shown by a text in the stats bar
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum HudField {
+    Population,
+    Funds,
+    Happiness,
+    Wealth,
+    Speed,
+    Clock,
+}
+
+// Bar in the stats bar showing the demand for a zone type, as in the RCI bars of city builders
+#[derive(Component)]
+struct DemandBar(ZoneType);
+
+// Height of the demand bars at full demand
+const DEMAND_BAR_HEIGHT: f32 = 30.0;
+
+// Spawn the stats bar along the top of the screen
+pub fn spawn_hud(commands: &mut Commands) {
+    commands
+        .spawn((NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Px(40.0),
+                position_type: PositionType::Absolute,
+                top: Val::Px(0.0),
+                justify_content: JustifyContent::SpaceEvenly,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: Color::srgba(0.1, 0.1, 0.1, 0.7).into(),
+            ..default()
+        }, Interaction::default()))
+        .with_children(|parent| {
+            for field in [HudField::Population, HudField::Funds, HudField::Happiness, HudField::Wealth, HudField::Speed, HudField::Clock] {
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font_size: 20.0,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ),
+                    field,
+                ));
+            }
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        height: Val::Px(DEMAND_BAR_HEIGHT),
+                        align_items: AlignItems::FlexEnd,
+                        column_gap: Val::Px(3.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (zone, color) in [
+                        (ZoneType::Residential, Color::srgb(0.0, 0.8, 0.0)),
+                        (ZoneType::Commercial, Color::srgb(0.2, 0.4, 1.0)),
+                        (ZoneType::Industrial, Color::srgb(0.9, 0.9, 0.0)),
+                    ] {
+                        parent.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    width: Val::Px(8.0),
+                                    height: Val::Px(0.0),
+                                    ..default()
+                                },
+                                background_color: color.into(),
+                                ..default()
+                            },
+                            DemandBar(zone),
+                        ));
+                    }
+                });
+        });
+}
+
+// Grow the demand bars with the demand for their zone types, none below zero
+fn update_demand_bars(demand: Res<Demand>, mut bars: Query<(&mut Style, &DemandBar)>) {
+    if !demand.is_changed() {
+        return;
+    }
+    for (mut style, bar) in bars.iter_mut() {
+        let value = match bar.0 {
+            ZoneType::Residential => demand.residential,
+            ZoneType::Commercial => demand.commercial,
+            ZoneType::Industrial => demand.industrial,
+            ZoneType::None => 0.0,
+        };
+        style.height = Val::Px(DEMAND_BAR_HEIGHT * value.clamp(0.0, 1.0));
+    }
+}
+
+// Refresh the stats bar from the simulation resources
+fn update_hud(
+    population: Res<Population>,
+    economy: Res<Economy>,
+    town: Res<Town>,
+    speed: Res<GameSpeed>,
+    time_of_day: Res<TimeOfDay>,
+    wealth: Res<WealthDistribution>,
+    mut texts: Query<(&mut Text, &HudField)>,
+) {
+    for (mut text, field) in texts.iter_mut() {
+        let section = &mut text.sections[0];
+        match field {
+            HudField::Population => {
+                section.value = format!(
+                    "Population: {} / {}",
+                    format_thousands(population.total),
+                    format_thousands(population.housing)
+                );
+                // Nearly full housing is shown in orange
+                section.style.color = if population.housing_shortage() > 0.0 {
+                    Color::srgb(1.0, 0.6, 0.2)
+                } else {
+                    Color::WHITE
+                };
+            }
+            HudField::Funds => {
+                section.value = format!("Funds: ${}", format_thousands(economy.funds));
+                // Debt is shown in red
+                section.style.color = if economy.funds < 0 {
+                    Color::srgb(0.9, 0.2, 0.2)
+                } else {
+                    Color::WHITE
+                };
+            }
+            HudField::Happiness => {
+                section.value = format!("Happiness: {:.0}%", town.happiness * 100.0);
+            }
+            HudField::Wealth => {
+                section.value = format!(
+                    "Wealth: {:.0}% low / {:.0}% medium / {:.0}% high",
+                    wealth.share(Wealth::Low) * 100.0,
+                    wealth.share(Wealth::Medium) * 100.0,
+                    wealth.share(Wealth::High) * 100.0,
+                );
+            }
+            HudField::Speed => {
+                section.value = if speed.paused {
+                    "Paused".to_string()
+                } else {
+                    format!("Speed: {}x", speed.multiplier)
+                };
+            }
+            HudField::Clock => {
+                section.value = format!(
+                    "{:?}, day {} {}",
+                    time_of_day.season(),
+                    time_of_day.day + 1,
+                    time_of_day.formatted()
+                );
+            }
+        }
+    }
+}
+
+// Format a number with thousands separators, e.g. -1234567 -> "-1,234,567"
+pub fn format_thousands(value: i32) -> String {
+    let digits = value.unsigned_abs().to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3 + 1);
+    if value < 0 {
+        formatted.push('-');
+    }
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
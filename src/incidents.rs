@@ -0,0 +1,373 @@
+use bevy::prelude::*;
+use crate::citizen::{Dispatched, Vehicle, VehicleKind};
+use crate::clock::TimeOfDay;
+use crate::departments::DepartmentBonuses;
+use crate::grid::Grid;
+use crate::notifications::Notification;
+use crate::rng::GameRng;
+use crate::simulation::{GameSpeed, SimulationSet};
+use crate::tilemap::TownTiles;
+use crate::town::{get_cell_color, grid_to_world, BuildingType, OverlayMode, Town, TownMap, ZoneType, TOWN_GRID_SIZE};
+use crate::menu::outside_pause;
+use crate::GameState;
+use rand::prelude::*;
+use std::time::Duration;
+
+pub struct IncidentsPlugin;
+
+/// This plugin starts fires, crimes and medical emergencies and sends service vehicles to them
+impl Plugin for IncidentsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_incidents,
+                dispatch_responders,
+                update_responders,
+                // Runs after dispatching so new responders already exist
+                worsen_incidents,
+            )
+                .chain()
+                .in_set(SimulationSet::Incidents)
+                .run_if(in_state(GameState::TownView)),
+        )
+        .add_systems(OnExit(GameState::TownView), despawn_incidents.run_if(outside_pause));
+    }
+}
+
+// Seconds of game time between chances for a new incident
+const INCIDENT_INTERVAL_SECONDS: f32 = 10.0;
+const INCIDENT_CHANCE: f64 = 0.3;
+
+// How far a service building or an incident may be from the road a vehicle uses
+const MAX_ROAD_ACCESS_DISTANCE: i32 = 3;
+
+// Severity an unattended incident gains per second, it does its damage at 1
+const INCIDENT_WORSEN_RATE: f32 = 0.02;
+
+// Happiness lost when a crime or medical emergency goes unanswered
+const INCIDENT_HAPPINESS_PENALTY: f32 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncidentKind {
+    Fire,
+    Crime,
+    Medical,
+}
+
+impl IncidentKind {
+    // Building that sends vehicles to this kind of incident
+    pub fn responder(&self) -> BuildingType {
+        match self {
+            IncidentKind::Fire => BuildingType::Fire,
+            IncidentKind::Crime => BuildingType::Police,
+            IncidentKind::Medical => BuildingType::Hospital,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            IncidentKind::Fire => "Fire",
+            IncidentKind::Crime => "A crime",
+            IncidentKind::Medical => "A medical emergency",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            IncidentKind::Fire => Color::srgb(1.0, 0.4, 0.0),
+            IncidentKind::Crime => Color::srgb(0.3, 0.3, 1.0),
+            IncidentKind::Medical => Color::srgb(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+// Something going wrong on a town cell, marked by a sprite above it
+#[derive(Component)]
+pub struct Incident {
+    pub kind: IncidentKind,
+    pub position: IVec2,
+    // Grows while nobody responds, the incident does its damage at 1
+    pub severity: f32,
+    pub responder: Option<Entity>,
+}
+
+// Emergency vehicle heading to an incident, or back to its station once it's handled
+#[derive(Component)]
+struct Responder {
+    incident: Entity,
+    returning: bool,
+}
+
+// Now and then start an incident on a developed cell
+#[allow(clippy::too_many_arguments)]
+fn spawn_incidents(
+    mut commands: Commands,
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    mut timer: Local<Timer>,
+    town_map: Res<TownMap>,
+    incidents: Query<&Incident>,
+    mut game_rng: ResMut<GameRng>,
+    bonuses: Res<DepartmentBonuses>,
+) {
+    if timer.duration() == Duration::ZERO {
+        *timer = Timer::from_seconds(INCIDENT_INTERVAL_SECONDS, TimerMode::Repeating);
+    }
+
+    timer.tick(speed.delta(&time));
+    if !timer.just_finished() {
+        return;
+    }
+
+    let rng = &mut game_rng.rng;
+    if !rng.gen_bool(INCIDENT_CHANCE) {
+        return;
+    }
+    let candidates: Vec<IVec2> = town_map
+        .iter()
+        .filter(|cell| cell.zone != ZoneType::None && cell.density > 0)
+        .map(|cell| cell.position)
+        .filter(|position| !incidents.iter().any(|incident| incident.position == *position))
+        .collect();
+    let Some(&position) = candidates.choose(rng) else {
+        return;
+    };
+    // Law and Order makes crimes rarer than the other incidents
+    let kind = *[
+        (IncidentKind::Fire, 1.0),
+        (IncidentKind::Crime, bonuses.crime_rate),
+        (IncidentKind::Medical, 1.0),
+    ]
+    .choose_weighted(rng, |(_, weight)| *weight)
+    .map(|(kind, _)| kind)
+    .unwrap();
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(Vec2::new(6.0, 6.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(grid_to_world(position, 2.0))
+                .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_4)),
+            ..default()
+        },
+        Incident {
+            kind,
+            position,
+            severity: 0.0,
+            responder: None,
+        },
+    ));
+}
+
+// Nearest road to a position within reach of it
+fn access_road(roads: &[IVec2], position: IVec2) -> Option<IVec2> {
+    roads
+        .iter()
+        .copied()
+        .filter(|road| Grid::manhattan_distance(*road, position) <= MAX_ROAD_ACCESS_DISTANCE)
+        .min_by_key(|road| Grid::manhattan_distance(*road, position))
+}
+
+// Route from the road by the nearest station connected to the incident to the road by the incident
+fn responder_route(roads: &[IVec2], stations: &[IVec2], incident: IVec2) -> Option<Vec<IVec2>> {
+    let incident_road = access_road(roads, incident)?;
+    let station_roads: Vec<IVec2> = stations
+        .iter()
+        .filter_map(|station| access_road(roads, *station))
+        .collect();
+
+    // Searching outwards from the incident finds the closest station that's connected to it.
+    // Responders may drive against one-way traffic
+    let is_road = |pos: IVec2| roads.contains(&pos);
+    let mut path = Grid::find_path_to_nearest(incident_road, &station_roads, is_road, TOWN_GRID_SIZE)?;
+    path.reverse();
+    Some(path)
+}
+
+// Send a vehicle from the nearest connected service building to every unanswered incident
+fn dispatch_responders(
+    mut commands: Commands,
+    mut incidents: Query<(Entity, &mut Incident)>,
+    town_map: Res<TownMap>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    if incidents.iter().all(|(_, incident)| incident.responder.is_some()) {
+        return;
+    }
+
+    let roads: Vec<IVec2> = town_map
+        .iter()
+        .filter(|cell| cell.building.is_road())
+        .map(|cell| cell.position)
+        .collect();
+
+    for (entity, mut incident) in incidents.iter_mut() {
+        if incident.responder.is_some() {
+            continue;
+        }
+        let stations: Vec<IVec2> = town_map
+            .iter()
+            .filter(|cell| cell.building == incident.kind.responder())
+            .map(|cell| cell.position)
+            .collect();
+        let Some(path) = responder_route(&roads, &stations, incident.position) else {
+            continue;
+        };
+
+        let kind = VehicleKind::Emergency;
+        let responder = commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: kind.color(),
+                        custom_size: Some(kind.size()),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(grid_to_world(path[0], 0.5)),
+                    ..default()
+                },
+                Vehicle {
+                    kind,
+                    start: path[0],
+                    destination: *path.last().unwrap(),
+                    speed: game_rng.rng.gen_range(kind.speed_range()),
+                    path,
+                    path_index: 0,
+                    waiting: 0.0,
+                },
+                Dispatched,
+                Responder {
+                    incident: entity,
+                    returning: false,
+                },
+            ))
+            .id();
+        incident.responder = Some(responder);
+    }
+}
+
+// Resolve incidents when their responder arrives, then drive back to the station
+fn update_responders(
+    mut commands: Commands,
+    mut responders: Query<(Entity, &mut Responder, &mut Vehicle)>,
+    incidents: Query<Entity, With<Incident>>,
+) {
+    for (entity, mut responder, mut vehicle) in responders.iter_mut() {
+        if vehicle.path_index + 1 < vehicle.path.len() {
+            continue;
+        }
+        if responder.returning {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        if let Ok(incident) = incidents.get(responder.incident) {
+            commands.entity(incident).despawn();
+        }
+        responder.returning = true;
+        let vehicle = vehicle.as_mut();
+        vehicle.path.reverse();
+        vehicle.path_index = 0;
+        vehicle.waiting = 0.0;
+        std::mem::swap(&mut vehicle.start, &mut vehicle.destination);
+    }
+}
+
+// Let incidents without a responder grow, doing their damage once they get out of hand
+#[allow(clippy::too_many_arguments)]
+fn worsen_incidents(
+    mut commands: Commands,
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    mut incidents: Query<(Entity, &mut Incident, &mut Sprite)>,
+    responders: Query<(), With<Responder>>,
+    mut town_map: ResMut<TownMap>,
+    mut tiles: ResMut<TownTiles>,
+    mut town: ResMut<Town>,
+    overlay: Res<OverlayMode>,
+    time_of_day: Res<TimeOfDay>,
+    mut notifications: EventWriter<Notification>,
+) {
+    for (entity, mut incident, mut sprite) in incidents.iter_mut() {
+        // A responder can vanish with the town's vehicles, the next dispatch sends a new one
+        if incident.responder.is_some_and(|responder| responders.get(responder).is_err()) {
+            incident.responder = None;
+        }
+        if incident.responder.is_some() {
+            continue;
+        }
+
+        incident.severity += INCIDENT_WORSEN_RATE * speed.delta_seconds(&time);
+        sprite.custom_size = Some(Vec2::splat(6.0 + 6.0 * incident.severity.min(1.0)));
+        if incident.severity < 1.0 {
+            continue;
+        }
+
+        match incident.kind {
+            // Fires burn down whatever was built on the cell
+            IncidentKind::Fire => {
+                if let Some(cell) = town_map.cell_mut(incident.position) {
+                    let burnt_down = match cell.building {
+                        building if building == BuildingType::None || building.is_road() => cell.zone.label(),
+                        building => building.label(),
+                    };
+                    notifications.send(Notification::critical(format!(
+                        "A fire burnt down {} at ({}, {})",
+                        burnt_down.to_lowercase(),
+                        cell.position.x,
+                        cell.position.y
+                    )));
+                    cell.density = 0;
+                    if !cell.building.is_road() {
+                        cell.building = BuildingType::None;
+                    }
+                    if *overlay == OverlayMode::None {
+                        tiles.set(cell.position, get_cell_color(cell, None, time_of_day.season()));
+                    }
+                }
+            }
+            IncidentKind::Crime | IncidentKind::Medical => {
+                notifications.send(Notification::warning(format!(
+                    "{} at ({}, {}) went unanswered",
+                    incident.kind.label(),
+                    incident.position.x,
+                    incident.position.y
+                )));
+                town.happiness = (town.happiness - INCIDENT_HAPPINESS_PENALTY).max(0.0);
+            }
+        }
+        commands.entity(entity).despawn();
+    }
+}
+
+fn despawn_incidents(mut commands: Commands, incidents: Query<Entity, With<Incident>>) {
+    for entity in incidents.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn responders_come_from_the_nearest_connected_station() {
+        // A main road along the bottom, and a stretch of road cut off from it
+        let mut roads: Vec<IVec2> = (0..10).map(|x| IVec2::new(x, 0)).collect();
+        roads.extend([IVec2::new(4, 3), IVec2::new(5, 3)]);
+        // The station right above the incident only reaches the cut off road
+        let stations = [IVec2::new(4, 4), IVec2::new(9, 1), IVec2::new(1, 1)];
+
+        let path = responder_route(&roads, &stations, IVec2::new(4, 1)).unwrap();
+        assert_eq!(path.first(), Some(&IVec2::new(1, 0)));
+        assert_eq!(path.last(), Some(&IVec2::new(4, 0)));
+        assert_eq!(path.len(), 4);
+
+        // Without a connected station nobody comes
+        assert_eq!(responder_route(&roads, &stations[..1], IVec2::new(4, 1)), None);
+    }
+}
@@ -1,151 +1,625 @@
+use bevy::input::keyboard::{Key, KeyboardInput};
 use bevy::prelude::*;
+use crate::clock::{Season, TimeOfDay};
+use crate::grid::Grid;
+use crate::simulation::Waterfront;
+use crate::notifications::Notification;
+use crate::rng::GameRng;
+use crate::town::{SelectedTown, Towns, TOWN_GRID_SIZE};
+use crate::menu::outside_pause;
 use crate::GameState;
+use rand::prelude::*;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
 
 pub struct IslandPlugin;
 
 /// This plugin handles the island map view and functionality
 impl Plugin for IslandPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::IslandView), setup_island)
+        app.init_resource::<IslandSize>()
+            .init_resource::<IslandMapFile>()
+            .add_systems(OnEnter(GameState::IslandView), setup_island.run_if(outside_pause))
             .add_systems(
                 Update,
                 (
                     handle_island_interaction,
+                    type_town_name,
                 ).run_if(in_state(GameState::IslandView)),
             )
-            .add_systems(OnExit(GameState::IslandView), cleanup_island);
+            .add_systems(OnExit(GameState::IslandView), cleanup_island.run_if(outside_pause));
     }
 }
 
-// Island grid size
-pub const ISLAND_GRID_SIZE: usize = 20;
+// Longest name a town can be given
+const MAX_TOWN_NAME_LENGTH: usize = 20;
+
+// World units between the centers of two island cells
+pub const ISLAND_CELL_SPACING: f32 = 32.0;
+
+// Height in world units the island view fits on screen before the camera zooms out
+const ISLAND_VIEW_HEIGHT: f32 = 680.0;
+
+// Size of newly generated islands, picked in the menu
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IslandSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl IslandSize {
+    // Cells along each side of the island grid
+    pub fn cells(self) -> usize {
+        match self {
+            IslandSize::Small => 14,
+            IslandSize::Medium => 20,
+            IslandSize::Large => 28,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            IslandSize::Small => IslandSize::Medium,
+            IslandSize::Medium => IslandSize::Large,
+            IslandSize::Large => IslandSize::Small,
+        }
+    }
+}
+
+// Heightmap picked in the menu to build the island from, generated from the seed without one
+#[derive(Resource, Default, Debug, Clone)]
+pub struct IslandMapFile(pub Option<PathBuf>);
+
+// Why the last island couldn't be set up, shown in the menu it went back to
+#[derive(Resource, Debug, Clone)]
+pub struct IslandMapError(pub String);
 
 // Island cell types
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IslandCellType {
     Water,
     Land,
     Forest,
     Mountain,
+    River,
     Town,
 }
 
+impl IslandCellType {
+    pub const ALL: [IslandCellType; 6] = [
+        IslandCellType::Water,
+        IslandCellType::Land,
+        IslandCellType::Forest,
+        IslandCellType::Mountain,
+        IslandCellType::River,
+        IslandCellType::Town,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            IslandCellType::Water => "Water",
+            IslandCellType::Land => "Land",
+            IslandCellType::Forest => "Forest",
+            IslandCellType::Mountain => "Mountain",
+            IslandCellType::River => "River",
+            IslandCellType::Town => "Town",
+        }
+    }
+}
+
 // Island cell component
 #[derive(Component)]
 pub struct IslandCell {
     pub position: IVec2,
-    pub cell_type: IslandCellType,
-    pub owned: bool,
+}
+
+// Minable resource found on some mountains
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Deposit {
+    // Adds to the town's funds
+    Ore,
+    // Adds to the town's goods
+    Stone,
+}
+
+impl Deposit {
+    // Funds or goods an owned deposit yields per simulation tick
+    pub fn yield_per_tick(self) -> i32 {
+        match self {
+            Deposit::Ore => 25,
+            Deposit::Stone => 10,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            Deposit::Ore => Color::srgb(0.9, 0.6, 0.2),
+            Deposit::Stone => Color::srgb(0.8, 0.8, 0.8),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DepositSite {
+    pub position: IVec2,
+    pub deposit: Deposit,
 }
 
 // Island resource
-#[derive(Resource)]
+#[derive(Resource, Clone, Serialize, Deserialize)]
 pub struct Island {
-    pub grid: [[IslandCellType; ISLAND_GRID_SIZE]; ISLAND_GRID_SIZE],
+    // Indexed as grid[y][x]
+    pub grid: Vec<Vec<IslandCellType>>,
     pub owned_cells: Vec<IVec2>,
     pub towns: Vec<IVec2>,
+    #[serde(default)]
+    pub deposits: Vec<DepositSite>,
 }
 
-impl Default for Island {
-    fn default() -> Self {
-        // Create a default island with water around the edges and some land in the middle
-        let mut grid = [[IslandCellType::Water; ISLAND_GRID_SIZE]; ISLAND_GRID_SIZE];
-        
-        // Create some land in the middle
-        for x in 5..15 {
-            for y in 5..15 {
-                grid[y][x] = IslandCellType::Land;
-                
-                // Add some variety
-                if (x + y) % 7 == 0 {
-                    grid[y][x] = IslandCellType::Forest;
-                }
-                if (x * y) % 13 == 0 {
-                    grid[y][x] = IslandCellType::Mountain;
-                }
+impl Island {
+    // Generate an island from layered value noise, lowered towards the map edges so it's surrounded by water
+    pub fn generate(seed: u64, size: usize) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let octaves: Vec<(ValueNoise, f32)> = NOISE_OCTAVES
+            .iter()
+            .map(|&(frequency, amplitude)| (ValueNoise::new(&mut rng, frequency), amplitude))
+            .collect();
+
+        let mut grid = vec![vec![IslandCellType::Water; size]; size];
+        let mut elevation = vec![vec![0.0; size]; size];
+        for y in COAST_MARGIN..size.saturating_sub(COAST_MARGIN) {
+            for x in COAST_MARGIN..size.saturating_sub(COAST_MARGIN) {
+                // Position relative to the center, -1..1 across the map
+                let position = (Vec2::new(x as f32, y as f32) + 0.5) / size as f32;
+                let from_center = (position * 2.0 - 1.0).length();
+
+                let noise: f32 = octaves
+                    .iter()
+                    .map(|(noise, amplitude)| noise.sample(position) * amplitude)
+                    .sum();
+                elevation[y][x] = noise * (1.0 - from_center * from_center).max(0.0);
+
+                grid[y][x] = terrain(elevation[y][x], [SEA_LEVEL, FOREST_ELEVATION, MOUNTAIN_ELEVATION]);
             }
         }
-        
+
+        // Carve rivers from randomly picked mountains down to the sea
+        let mountains: Vec<IVec2> = (0..size)
+            .flat_map(|y| (0..size).map(move |x| IVec2::new(x as i32, y as i32)))
+            .filter(|position| grid[position.y as usize][position.x as usize] == IslandCellType::Mountain)
+            .collect();
+        let river_count = rng.gen_range(1..=MAX_RIVERS);
+        for &source in mountains.choose_multiple(&mut rng, river_count) {
+            Island::carve_river(&mut grid, &elevation, source);
+        }
+
+        // Place deposits on some of the mountains the rivers left alone
+        let deposits = mountains
+            .into_iter()
+            .filter(|position| grid[position.y as usize][position.x as usize] == IslandCellType::Mountain)
+            .filter_map(|position| {
+                if rng.gen::<f32>() >= DEPOSIT_CHANCE {
+                    return None;
+                }
+                let deposit = if rng.gen_bool(0.5) { Deposit::Ore } else { Deposit::Stone };
+                Some(DepositSite { position, deposit })
+            })
+            .collect();
+
         Island {
             grid,
             owned_cells: Vec::new(),
             towns: Vec::new(),
+            deposits,
+        }
+    }
+
+    // Build an island from a grayscale image scaled to the grid, brighter pixels are higher ground.
+    // The image's top row is the island's north edge. Heightmaps bring no rivers or deposits
+    pub fn from_heightmap(path: &Path, size: usize) -> Result<Self, String> {
+        let image = image::open(path).map_err(|error| format!("{}: {error}", path.display()))?;
+        if image.width() == 0 || image.height() == 0 {
+            return Err(format!("{}: the image is empty", path.display()));
+        }
+        let heights = image::imageops::resize(
+            &image.to_luma8(),
+            size as u32,
+            size as u32,
+            image::imageops::FilterType::Triangle,
+        );
+
+        let grid = (0..size)
+            .map(|y| {
+                (0..size)
+                    .map(|x| {
+                        let brightness = heights.get_pixel(x as u32, (size - 1 - y) as u32).0[0];
+                        terrain(brightness as f32 / u8::MAX as f32, HEIGHTMAP_LEVELS)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(Island {
+            grid,
+            owned_cells: Vec::new(),
+            towns: Vec::new(),
+            deposits: Vec::new(),
+        })
+    }
+
+    pub fn deposit_at(&self, position: IVec2) -> Option<Deposit> {
+        self.deposits
+            .iter()
+            .find(|site| site.position == position)
+            .map(|site| site.deposit)
+    }
+
+    // Deposits on cells the player owns
+    pub fn owned_deposits(&self) -> impl Iterator<Item = Deposit> + '_ {
+        self.deposits
+            .iter()
+            .filter(|site| self.owned_cells.contains(&site.position))
+            .map(|site| site.deposit)
+    }
+
+    // Funds or goods all owned deposits of a kind yield per simulation tick
+    pub fn owned_yield(&self, deposit: Deposit) -> i32 {
+        self.owned_deposits()
+            .filter(|&owned| owned == deposit)
+            .map(Deposit::yield_per_tick)
+            .sum()
+    }
+
+    pub fn size(&self) -> usize {
+        self.grid.len()
+    }
+
+    // Directions from a cell towards the sea or a river, past the edge of the map is sea too
+    pub fn water_sides(&self, position: IVec2) -> Vec<IVec2> {
+        [IVec2::Y, IVec2::NEG_Y, IVec2::X, IVec2::NEG_X]
+            .into_iter()
+            .filter(|direction| {
+                let neighbor = position + *direction;
+                !Grid::is_in_bounds(neighbor, self.size())
+                    || matches!(
+                        self.grid[neighbor.y as usize][neighbor.x as usize],
+                        IslandCellType::Water | IslandCellType::River
+                    )
+            })
+            .collect()
+    }
+
+    // Ground heights for a town founded at the position, row by row from the bottom. Forest is
+    // rougher ground than open land, and every mountain next to the town makes it rougher still.
+    // The same game seed gives a town the same ground
+    pub fn town_elevation(&self, position: IVec2, seed: u64) -> Vec<f32> {
+        let terrain_at = |pos: IVec2| {
+            Grid::is_in_bounds(pos, self.size()).then(|| self.grid[pos.y as usize][pos.x as usize])
+        };
+        let mountains = Grid::get_orthogonal_positions(position)
+            .into_iter()
+            .filter(|neighbor| terrain_at(*neighbor) == Some(IslandCellType::Mountain))
+            .count();
+        let roughness = match terrain_at(position) {
+            Some(IslandCellType::Forest) => FOREST_ROUGHNESS,
+            Some(IslandCellType::Land) => LAND_ROUGHNESS,
+            _ => 0.0,
+        } + MOUNTAIN_ROUGHNESS * mountains as f32;
+
+        let mut rng = StdRng::seed_from_u64(seed ^ ((position.x as u64) << 32 | position.y as u32 as u64));
+        let noise = ValueNoise::new(&mut rng, TOWN_ELEVATION_FREQUENCY);
+        (0..TOWN_GRID_SIZE * TOWN_GRID_SIZE)
+            .map(|index| {
+                let cell = Vec2::new((index % TOWN_GRID_SIZE) as f32, (index / TOWN_GRID_SIZE) as f32);
+                let position = (cell + 0.5) / TOWN_GRID_SIZE as f32;
+                (noise.sample(position) * roughness).min(1.0)
+            })
+            .collect()
+    }
+
+    // Cells of a town founded at the position that are covered by water. A river next to the town
+    // runs across it from that side, the sea next to it floods the edge facing it
+    pub fn town_water(&self, position: IVec2, seed: u64) -> Vec<IVec2> {
+        let size = TOWN_GRID_SIZE as i32;
+        let mut rng = StdRng::seed_from_u64(seed ^ ((position.y as u64) << 32 | position.x as u32 as u64));
+        let mut water = HashSet::new();
+        for direction in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+            let neighbor = position + direction;
+            if !Grid::is_in_bounds(neighbor, self.size()) {
+                continue;
+            }
+            // Coordinates along the direction and across it
+            let cell = |along: i32, across: i32| {
+                if direction.x != 0 { IVec2::new(along, across) } else { IVec2::new(across, along) }
+            };
+            let noise = ValueNoise::new(&mut rng, TOWN_WATER_FREQUENCY);
+            let wobble = |t: i32| noise.sample(Vec2::new((t as f32 + 0.5) / size as f32, 0.5)) - 0.5;
+            match self.grid[neighbor.y as usize][neighbor.x as usize] {
+                IslandCellType::River => {
+                    for along in 0..size {
+                        let center = size / 2 + (wobble(along) * TOWN_RIVER_MEANDER).round() as i32;
+                        for across in center - TOWN_RIVER_WIDTH / 2..=center + TOWN_RIVER_WIDTH / 2 {
+                            water.insert(cell(along, across));
+                        }
+                    }
+                }
+                IslandCellType::Water => {
+                    for across in 0..size {
+                        let depth = TOWN_SHORE_DEPTH + (wobble(across) * TOWN_SHORE_DEPTH as f32).round() as i32;
+                        for from_edge in 0..depth {
+                            let along = if direction.x + direction.y > 0 { size - 1 - from_edge } else { from_edge };
+                            water.insert(cell(along, across));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut water: Vec<IVec2> = water
+            .into_iter()
+            .filter(|pos| Grid::is_in_bounds(*pos, TOWN_GRID_SIZE))
+            .collect();
+        water.sort_by_key(|pos| (pos.y, pos.x));
+        water
+    }
+
+    // Walk to the lowest neighbor until the river reaches the sea or joins another river.
+    // Neighbors already on this river are skipped, so rivers climb out of basins instead of ending there
+    fn carve_river(grid: &mut [Vec<IslandCellType>], elevation: &[Vec<f32>], source: IVec2) {
+        let size = grid.len();
+        let cell = |position: IVec2| (position.x as usize, position.y as usize);
+        let mut visited = HashSet::new();
+        let mut current = source;
+
+        loop {
+            visited.insert(current);
+            let (x, y) = cell(current);
+            grid[y][x] = IslandCellType::River;
+
+            let next = Grid::get_orthogonal_positions(current)
+                .into_iter()
+                .filter(|&next| Grid::is_in_bounds(next, size) && !visited.contains(&next))
+                .min_by(|&a, &b| {
+                    let (ax, ay) = cell(a);
+                    let (bx, by) = cell(b);
+                    elevation[ay][ax].total_cmp(&elevation[by][bx])
+                });
+            let Some(next) = next else {
+                break;
+            };
+
+            let (x, y) = cell(next);
+            if matches!(grid[y][x], IslandCellType::Water | IslandCellType::River) {
+                break;
+            }
+            current = next;
         }
     }
 }
 
-// Setup the island view
-fn setup_island(mut commands: Commands, mut island: Option<ResMut<Island>>) {
-    // If the island doesn't exist yet, create it
-    if island.is_none() {
-        commands.insert_resource(Island::default());
+// Most rivers carved into a generated island
+const MAX_RIVERS: usize = 2;
+
+// Chance for each mountain to hold a deposit
+const DEPOSIT_CHANCE: f32 = 0.3;
+
+// Noise octaves as (lattice cells across the map, amplitude), amplitudes add up to 1
+const NOISE_OCTAVES: [(usize, f32); 3] = [(3, 0.55), (6, 0.3), (12, 0.15)];
+
+// Elevation thresholds, on the 0..1 scale of the noise
+const SEA_LEVEL: f32 = 0.25;
+const FOREST_ELEVATION: f32 = 0.45;
+const MOUNTAIN_ELEVATION: f32 = 0.58;
+
+// Brightness from 0 to 1 where heightmaps turn from water to land, forest and mountains
+const HEIGHTMAP_LEVELS: [f32; 3] = [0.3, 0.55, 0.75];
+
+// How far the ground of a town rises and falls, on the 0..1 scale of the noise, by the terrain
+// it was founded on and for each mountain next to it
+const LAND_ROUGHNESS: f32 = 0.2;
+const FOREST_ROUGHNESS: f32 = 0.6;
+const MOUNTAIN_ROUGHNESS: f32 = 0.3;
+
+// Lattice cells across a town's grid for its elevation noise
+const TOWN_ELEVATION_FREQUENCY: usize = 4;
+
+// Rivers crossing a town, in town cells, and how far they wander from the middle
+const TOWN_RIVER_WIDTH: i32 = 3;
+const TOWN_RIVER_MEANDER: f32 = 16.0;
+
+// Average depth of the sea along a town's coast, in town cells
+const TOWN_SHORE_DEPTH: i32 = 4;
+
+// Lattice cells along a town's edge for the wobble of its rivers and coast
+const TOWN_WATER_FREQUENCY: usize = 5;
+
+// Terrain at an elevation, given where the land, forest and mountains start
+fn terrain(elevation: f32, [land, forest, mountain]: [f32; 3]) -> IslandCellType {
+    if elevation < land {
+        IslandCellType::Water
+    } else if elevation < forest {
+        IslandCellType::Land
+    } else if elevation < mountain {
+        IslandCellType::Forest
+    } else {
+        IslandCellType::Mountain
     }
+}
+
+// Cells along the map edge that always stay water
+const COAST_MARGIN: usize = 1;
+
+// Random values on a square lattice, smoothly interpolated in between
+struct ValueNoise {
+    frequency: usize,
+    lattice: Vec<f32>,
+}
+
+impl ValueNoise {
+    fn new(rng: &mut StdRng, frequency: usize) -> Self {
+        let points = frequency + 1;
+        ValueNoise {
+            frequency,
+            lattice: (0..points * points).map(|_| rng.gen()).collect(),
+        }
+    }
+
+    // Noise value in 0..1 at a position in 0..1 across the map
+    fn sample(&self, position: Vec2) -> f32 {
+        let scaled = position.clamp(Vec2::ZERO, Vec2::ONE) * self.frequency as f32;
+        let cell = scaled.floor().min(Vec2::splat(self.frequency as f32 - 1.0));
+        let fraction = scaled - cell;
+        // Smoothstep to hide the lattice
+        let t = fraction * fraction * (3.0 - 2.0 * fraction);
+
+        let points = self.frequency + 1;
+        let value = |x: usize, y: usize| self.lattice[y * points + x];
+        let (x, y) = (cell.x as usize, cell.y as usize);
+        let bottom = value(x, y) + (value(x + 1, y) - value(x, y)) * t.x;
+        let top = value(x, y + 1) + (value(x + 1, y + 1) - value(x, y + 1)) * t.x;
+        bottom + (top - bottom) * t.y
+    }
+}
+
+// Setup the island view
+#[allow(clippy::too_many_arguments)]
+fn setup_island(
+    mut commands: Commands,
+    island: Option<Res<Island>>,
+    game_rng: Res<GameRng>,
+    island_size: Res<IslandSize>,
+    map_file: Res<IslandMapFile>,
+    time_of_day: Res<TimeOfDay>,
+    towns: Res<Towns>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // If the island doesn't exist yet, build it from the picked heightmap or generate it from the game's seed.
+    // A heightmap that can't be read sends the player back to the menu
+    let island = match island {
+        Some(island) => island.clone(),
+        None => {
+            let island = match &map_file.0 {
+                Some(path) => Island::from_heightmap(path, island_size.cells()),
+                None => Ok(Island::generate(game_rng.seed, island_size.cells())),
+            };
+            let island = match island {
+                Ok(island) => island,
+                Err(error) => {
+                    error!("Failed to load the island map: {error}");
+                    commands.insert_resource(IslandMapError(error));
+                    next_state.set(GameState::Menu);
+                    return;
+                }
+            };
+            commands.insert_resource(island.clone());
+            island
+        }
+    };
+    let size = island.size();
     
     // Create the island grid visualization
-    for y in 0..ISLAND_GRID_SIZE {
-        for x in 0..ISLAND_GRID_SIZE {
+    for y in 0..size {
+        for x in 0..size {
             let position = IVec2::new(x as i32, y as i32);
-            let cell_type = island
-                .as_ref()
-                .map(|i| i.grid[y][x])
-                .unwrap_or(IslandCellType::Water);
-            
-            let owned = island
-                .as_ref()
-                .map(|i| i.owned_cells.contains(&position))
-                .unwrap_or(false);
+            let cell_type = island.grid[y][x];
+            let owned = island.owned_cells.contains(&position);
             
             // Spawn a sprite for each cell
-            commands.spawn((
+            let mut cell = commands.spawn((
                 SpriteBundle {
                     sprite: Sprite {
-                        color: get_cell_color(cell_type, owned),
+                        color: get_cell_color(cell_type, owned, time_of_day.season()),
                         custom_size: Some(Vec2::new(30.0, 30.0)),
                         ..default()
                     },
                     transform: Transform::from_translation(Vec3::new(
-                        (x as f32 - ISLAND_GRID_SIZE as f32 / 2.0) * 32.0,
-                        (y as f32 - ISLAND_GRID_SIZE as f32 / 2.0) * 32.0,
+                        (x as f32 - size as f32 / 2.0) * ISLAND_CELL_SPACING,
+                        (y as f32 - size as f32 / 2.0) * ISLAND_CELL_SPACING,
                         0.0,
                     )),
                     ..default()
                 },
-                IslandCell {
-                    position,
-                    cell_type,
-                    owned,
-                },
+                IslandCell { position },
             ));
+            
+            // Mark deposits with a small square in their color
+            if let Some(deposit) = island.deposit_at(position) {
+                cell.with_children(|parent| {
+                    parent.spawn(SpriteBundle {
+                        sprite: Sprite {
+                            color: deposit.color(),
+                            custom_size: Some(Vec2::new(10.0, 10.0)),
+                            ..default()
+                        },
+                        transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                        ..default()
+                    });
+                });
+            }
+            
+            // Label towns with their names
+            if let Some(town) = towns.towns.get(&position) {
+                cell.with_children(|parent| {
+                    parent.spawn(Text2dBundle {
+                        text: Text::from_section(
+                            town.name.clone(),
+                            TextStyle {
+                                font_size: 14.0,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ),
+                        transform: Transform::from_xyz(0.0, 22.0, 1.0),
+                        ..default()
+                    });
+                });
+            }
         }
     }
     
-    // Add a camera
-    commands.spawn(Camera2dBundle::default());
+    // Add a camera, zoomed out far enough to show large islands in full
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scale = (size as f32 * ISLAND_CELL_SPACING / ISLAND_VIEW_HEIGHT).max(1.0);
+    commands.spawn(camera);
 }
 
 // Handle island interaction (clicking on cells, etc.)
+#[allow(clippy::too_many_arguments)]
 fn handle_island_interaction(
     mut commands: Commands,
-    mut island: ResMut<Island>,
+    island: Option<ResMut<Island>>,
     mouse_button_input: Res<ButtonInput<MouseButton>>,
     windows: Query<&Window>,
     camera_q: Query<(&Camera, &GlobalTransform)>,
     mut cells: Query<(&mut Sprite, &IslandCell)>,
     mut next_state: ResMut<NextState<GameState>>,
+    time_of_day: Res<TimeOfDay>,
+    mut towns: ResMut<Towns>,
+    game_rng: Res<GameRng>,
+    name_prompts: Query<(), With<TownNamePrompt>>,
 ) {
-    // Handle mouse clicks
-    if mouse_button_input.just_pressed(MouseButton::Left) {
+    // Nothing to click on when the island couldn't be set up
+    let Some(mut island) = island else {
+        return;
+    };
+
+    // Handle mouse clicks, unless a new town is being named
+    if mouse_button_input.just_pressed(MouseButton::Left) && name_prompts.is_empty() {
         let window = windows.single();
         let (camera, camera_transform) = camera_q.single();
         
         if let Some(cursor_position) = window.cursor_position() {
             if let Some(world_position) = camera.viewport_to_world_2d(camera_transform, cursor_position) {
                 // Convert world position to grid position
-                let grid_x = ((world_position.x / 32.0) + ISLAND_GRID_SIZE as f32 / 2.0).floor() as i32;
-                let grid_y = ((world_position.y / 32.0) + ISLAND_GRID_SIZE as f32 / 2.0).floor() as i32;
+                let size = island.size();
+                let grid_x = ((world_position.x / ISLAND_CELL_SPACING) + size as f32 / 2.0).floor() as i32;
+                let grid_y = ((world_position.y / ISLAND_CELL_SPACING) + size as f32 / 2.0).floor() as i32;
                 
                 // Check if the position is within the grid
-                if grid_x >= 0 && grid_x < ISLAND_GRID_SIZE as i32 && grid_y >= 0 && grid_y < ISLAND_GRID_SIZE as i32 {
+                if grid_x >= 0 && grid_x < size as i32 && grid_y >= 0 && grid_y < size as i32 {
                     let position = IVec2::new(grid_x, grid_y);
                     let cell_type = island.grid[grid_y as usize][grid_x as usize];
                     
@@ -159,30 +633,47 @@ fn handle_island_interaction(
                                 // Update the cell color
                                 for (mut sprite, cell) in cells.iter_mut() {
                                     if cell.position == position {
-                                        sprite.color = get_cell_color(cell_type, true);
+                                        sprite.color = get_cell_color(cell_type, true, time_of_day.season());
                                     }
                                 }
                             } else if !island.towns.contains(&position) {
-                                // If it's owned land without a town, found a new town
+                                // If it's owned land without a town, found a new town on ground
+                                // shaped by the terrain it replaces and the water around it
+                                let town_save = towns.towns.entry(position).or_default();
+                                town_save.elevation = island.town_elevation(position, game_rng.seed);
+                                town_save.water = island.town_water(position, game_rng.seed);
                                 island.towns.push(position);
                                 island.grid[grid_y as usize][grid_x as usize] = IslandCellType::Town;
                                 
                                 // Update the cell color
                                 for (mut sprite, cell) in cells.iter_mut() {
                                     if cell.position == position {
-                                        sprite.color = get_cell_color(IslandCellType::Town, true);
+                                        sprite.color = get_cell_color(IslandCellType::Town, true, time_of_day.season());
                                     }
                                 }
                                 
-                                // TODO: Store the selected town and transition to town view
-                                next_state.set(GameState::TownView);
+                                // Ask for a name before showing the new town
+                                let default_name = format!("New Town {}", island.towns.len());
+                                towns.towns.entry(position).or_default().name = default_name.clone();
+                                spawn_town_name_prompt(&mut commands, position, default_name);
                             }
                         }
                         IslandCellType::Town => {
                             // If it's a town, enter town view
-                            // TODO: Store the selected town
+                            commands.insert_resource(SelectedTown(position));
+                            commands.insert_resource(Waterfront { sides: island.water_sides(position) });
                             next_state.set(GameState::TownView);
                         }
+                        // Mountains can be bought for their deposits, but not settled
+                        IslandCellType::Mountain if !island.owned_cells.contains(&position) => {
+                            island.owned_cells.push(position);
+                            
+                            for (mut sprite, cell) in cells.iter_mut() {
+                                if cell.position == position {
+                                    sprite.color = get_cell_color(cell_type, true, time_of_day.season());
+                                }
+                            }
+                        }
                         _ => {}
                     }
                 }
@@ -191,11 +682,138 @@ fn handle_island_interaction(
     }
 }
 
+// Text input asking for the name of a newly founded town
+#[derive(Component)]
+pub(crate) struct TownNamePrompt {
+    position: IVec2,
+    name: String,
+    default_name: String,
+}
+
+#[derive(Component)]
+struct TownNameInput;
+
+fn spawn_town_name_prompt(commands: &mut Commands, position: IVec2, default_name: String) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.0),
+                    ..default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.6).into(),
+                ..default()
+            },
+            TownNamePrompt {
+                position,
+                name: String::new(),
+                default_name,
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Name your town",
+                TextStyle {
+                    font_size: 32.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "_",
+                    TextStyle {
+                        font_size: 28.0,
+                        color: Color::srgb(1.0, 0.85, 0.2),
+                        ..default()
+                    },
+                ),
+                TownNameInput,
+            ));
+            parent.spawn(TextBundle::from_section(
+                "Enter to confirm, Escape to keep the default name",
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::srgb(0.8, 0.8, 0.8),
+                    ..default()
+                },
+            ));
+        });
+}
+
+// Type the new town's name, then show the town once it's confirmed or cancelled
+#[allow(clippy::too_many_arguments)]
+fn type_town_name(
+    mut commands: Commands,
+    mut keyboard_events: EventReader<KeyboardInput>,
+    mut prompts: Query<(Entity, &mut TownNamePrompt)>,
+    mut input_text: Query<&mut Text, With<TownNameInput>>,
+    island: Res<Island>,
+    mut towns: ResMut<Towns>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut notifications: EventWriter<Notification>,
+) {
+    let Ok((entity, mut prompt)) = prompts.get_single_mut() else {
+        keyboard_events.clear();
+        return;
+    };
+
+    let mut done = false;
+    for event in keyboard_events.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Character(characters) => {
+                for character in characters.chars().filter(|character| !character.is_control()) {
+                    if prompt.name.chars().count() < MAX_TOWN_NAME_LENGTH {
+                        prompt.name.push(character);
+                    }
+                }
+            }
+            Key::Space if prompt.name.chars().count() < MAX_TOWN_NAME_LENGTH => {
+                prompt.name.push(' ');
+            }
+            Key::Backspace => {
+                prompt.name.pop();
+            }
+            Key::Enter => done = true,
+            Key::Escape => {
+                prompt.name.clear();
+                done = true;
+            }
+            _ => {}
+        }
+    }
+
+    if done {
+        let name = prompt.name.trim();
+        let name = if name.is_empty() { prompt.default_name.clone() } else { name.to_string() };
+        notifications.send(Notification::success(format!("{} was founded", name)));
+        towns.towns.entry(prompt.position).or_default().name = name;
+        commands.entity(entity).despawn_recursive();
+        commands.insert_resource(SelectedTown(prompt.position));
+        commands.insert_resource(Waterfront { sides: island.water_sides(prompt.position) });
+        next_state.set(GameState::TownView);
+        return;
+    }
+
+    for mut text in input_text.iter_mut() {
+        text.sections[0].value = format!("{}_", prompt.name);
+    }
+}
+
 // Clean up the island view
 fn cleanup_island(mut commands: Commands, query: Query<Entity, With<IslandCell>>, camera: Query<Entity, With<Camera2d>>) {
-    // Remove all island cells
+    // Remove all island cells, with their deposit markers
     for entity in query.iter() {
-        commands.entity(entity).despawn();
+        commands.entity(entity).despawn_recursive();
     }
     
     // Remove the camera
@@ -204,25 +822,123 @@ fn cleanup_island(mut commands: Commands, query: Query<Entity, With<IslandCell>>
     }
 }
 
-// Helper function to get the color for a cell based on its type and ownership
-fn get_cell_color(cell_type: IslandCellType, owned: bool) -> Color {
+// Helper function to get the color for a cell based on its type, ownership and the season
+pub(crate) fn get_cell_color(cell_type: IslandCellType, owned: bool, season: Season) -> Color {
     match cell_type {
-        IslandCellType::Water => Color::rgb(0.0, 0.3, 0.8),
+        IslandCellType::Water => Color::srgb(0.0, 0.3, 0.8),
         IslandCellType::Land => {
             if owned {
-                Color::rgb(0.2, 0.8, 0.2)
+                Color::srgb(0.2, 0.8, 0.2)
             } else {
-                Color::rgb(0.5, 0.5, 0.2)
+                Color::srgb(0.5, 0.5, 0.2)
             }
         }
         IslandCellType::Forest => {
             if owned {
-                Color::rgb(0.0, 0.6, 0.0)
+                season.tint_foliage(Color::srgb(0.0, 0.6, 0.0))
+            } else {
+                season.tint_foliage(Color::srgb(0.0, 0.4, 0.0))
+            }
+        }
+        IslandCellType::Mountain => {
+            if owned {
+                Color::srgb(0.65, 0.45, 0.3)
             } else {
-                Color::rgb(0.0, 0.4, 0.0)
+                Color::srgb(0.5, 0.3, 0.2)
             }
         }
-        IslandCellType::Mountain => Color::rgb(0.5, 0.3, 0.2),
-        IslandCellType::Town => Color::rgb(0.8, 0.2, 0.2),
+        IslandCellType::River => Color::srgb(0.3, 0.6, 1.0),
+        IslandCellType::Town => Color::srgb(0.8, 0.2, 0.2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deposit_positions(island: &Island) -> Vec<IVec2> {
+        island.deposits.iter().map(|site| site.position).collect()
+    }
+
+    #[test]
+    fn same_seed_generates_the_same_island() {
+        for size in [IslandSize::Small, IslandSize::Medium, IslandSize::Large] {
+            let first = Island::generate(1234, size.cells());
+            let second = Island::generate(1234, size.cells());
+            assert_eq!(first.grid, second.grid);
+            assert_eq!(deposit_positions(&first), deposit_positions(&second));
+        }
+    }
+
+    #[test]
+    fn different_seeds_generate_different_islands() {
+        let first = Island::generate(1, IslandSize::Medium.cells());
+        let second = Island::generate(2, IslandSize::Medium.cells());
+        assert_ne!(first.grid, second.grid);
+    }
+
+    #[test]
+    fn generated_island_is_surrounded_by_water() {
+        for seed in 0..20 {
+            let island = Island::generate(seed, IslandSize::Medium.cells());
+            let size = island.size();
+            let edge = (0..size).flat_map(|i| [(i, 0), (i, size - 1), (0, i), (size - 1, i)]);
+            for (x, y) in edge {
+                assert_eq!(island.grid[y][x], IslandCellType::Water, "seed {seed} has land at ({x}, {y})");
+            }
+            assert!(
+                island.grid.iter().flatten().any(|&cell| cell != IslandCellType::Water),
+                "seed {seed} has no land"
+            );
+        }
+    }
+
+    #[test]
+    fn rivers_run_from_inland_down_to_the_sea() {
+        let mut rivers_seen = 0;
+        for seed in 0..20 {
+            let island = Island::generate(seed, IslandSize::Medium.cells());
+            let size = island.size();
+            let cell = |position: IVec2| island.grid[position.y as usize][position.x as usize];
+            let rivers: Vec<IVec2> = (0..size)
+                .flat_map(|y| (0..size).map(move |x| IVec2::new(x as i32, y as i32)))
+                .filter(|&position| cell(position) == IslandCellType::River)
+                .collect();
+            rivers_seen += rivers.len();
+
+            let next_to_sea = |position: IVec2| {
+                Grid::get_orthogonal_positions(position)
+                    .into_iter()
+                    .any(|neighbor| Grid::is_in_bounds(neighbor, size) && cell(neighbor) == IslandCellType::Water)
+            };
+            for &start in &rivers {
+                let river = Grid::flood_fill(start, |position| cell(position) == IslandCellType::River, size);
+                assert!(
+                    river.iter().any(|&position| next_to_sea(position)),
+                    "seed {seed} has a river that never reaches the sea"
+                );
+                assert!(
+                    river.iter().any(|&position| !next_to_sea(position)),
+                    "seed {seed} has a river that starts at the coast"
+                );
+            }
+        }
+        // Islands without mountains have no river to carve
+        assert!(rivers_seen > 0);
+    }
+
+    #[test]
+    fn owned_deposits_yield_every_tick() {
+        let mut island = Island::generate(1234, IslandSize::Small.cells());
+        island.deposits = vec![
+            DepositSite { position: IVec2::new(1, 1), deposit: Deposit::Ore },
+            DepositSite { position: IVec2::new(2, 1), deposit: Deposit::Ore },
+            DepositSite { position: IVec2::new(3, 1), deposit: Deposit::Stone },
+        ];
+        assert_eq!(island.owned_yield(Deposit::Ore), 0);
+
+        island.owned_cells = vec![IVec2::new(1, 1), IVec2::new(3, 1)];
+        assert_eq!(island.owned_yield(Deposit::Ore), Deposit::Ore.yield_per_tick());
+        assert_eq!(island.owned_yield(Deposit::Stone), Deposit::Stone.yield_per_tick());
     }
 }
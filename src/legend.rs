@@ -0,0 +1,207 @@
+use bevy::prelude::*;
+use crate::actions::{InputAction, KeyBindings};
+use crate::clock::{Season, TimeOfDay};
+use crate::island::{self, IslandCellType};
+use crate::town::{self, BuildingType, TownCell, ZoneType};
+use crate::menu::outside_pause;
+use crate::GameState;
+
+pub struct LegendPlugin;
+
+/// This plugin shows what the cell colors of the town and island views mean
+impl Plugin for LegendPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LegendOpen>()
+            .add_systems(OnEnter(GameState::TownView), spawn_town_legend.run_if(outside_pause))
+            .add_systems(OnEnter(GameState::IslandView), spawn_island_legend.run_if(outside_pause))
+            .add_systems(
+                Update,
+                (toggle_legend, update_swatches)
+                    .run_if(in_state(GameState::IslandView).or_else(in_state(GameState::TownView))),
+            )
+            .add_systems(OnExit(GameState::TownView), despawn_legend.run_if(outside_pause))
+            .add_systems(OnExit(GameState::IslandView), despawn_legend.run_if(outside_pause));
+    }
+}
+
+// Whether the legend is expanded, kept when switching views
+#[derive(Resource, Default)]
+struct LegendOpen(bool);
+
+#[derive(Component)]
+struct Legend;
+
+// Header button expanding and collapsing the legend
+#[derive(Component)]
+struct LegendButton;
+
+// List of entries below the header
+#[derive(Component)]
+struct LegendEntries;
+
+// What a swatch shows, its color comes from the same function that paints the cells
+#[derive(Component, Clone, Copy)]
+enum Swatch {
+    Town(ZoneType, BuildingType),
+    Island(IslandCellType, bool),
+}
+
+impl Swatch {
+    fn color(&self, season: Season) -> Color {
+        match *self {
+            Swatch::Town(zone, building) => {
+                let cell = TownCell {
+                    position: IVec2::ZERO,
+                    zone,
+                    building,
+                    density: 0,
+                    accessible: true,
+                    elevation: 0.0,
+                    slope: 0.0,
+                    water: false,
+                };
+                town::get_cell_color(&cell, None, season)
+            }
+            Swatch::Island(cell_type, owned) => island::get_cell_color(cell_type, owned, season),
+        }
+    }
+}
+
+fn spawn_town_legend(commands: Commands, legend_open: Res<LegendOpen>, time_of_day: Res<TimeOfDay>) {
+    // Empty cells show their zone, every other building hides it
+    let zones = ZoneType::ALL
+        .into_iter()
+        .map(|zone| (zone.label().to_string(), Swatch::Town(zone, BuildingType::None)));
+    let buildings = BuildingType::ALL
+        .into_iter()
+        .filter(|building| *building != BuildingType::None)
+        .map(|building| (building.label().to_string(), Swatch::Town(ZoneType::None, building)));
+    let entries: Vec<(String, Swatch)> = zones.chain(buildings).collect();
+    spawn_legend(commands, entries, legend_open.0, time_of_day.season());
+}
+
+fn spawn_island_legend(commands: Commands, legend_open: Res<LegendOpen>, time_of_day: Res<TimeOfDay>) {
+    let season = time_of_day.season();
+    let mut entries = Vec::new();
+    for cell_type in IslandCellType::ALL {
+        let owned = Swatch::Island(cell_type, true);
+        let unowned = Swatch::Island(cell_type, false);
+        // Only list ownership separately for the cells it recolors
+        if owned.color(season) == unowned.color(season) {
+            entries.push((cell_type.label().to_string(), unowned));
+        } else {
+            entries.push((format!("{} (owned)", cell_type.label()), owned));
+            entries.push((cell_type.label().to_string(), unowned));
+        }
+    }
+    spawn_legend(commands, entries, legend_open.0, season);
+}
+
+fn spawn_legend(mut commands: Commands, entries: Vec<(String, Swatch)>, open: bool, season: Season) {
+    let text_style = TextStyle {
+        font_size: 14.0,
+        color: Color::WHITE,
+        ..default()
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(10.0),
+                    top: Val::Px(260.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(6.0)),
+                    row_gap: Val::Px(2.0),
+                    ..default()
+                },
+                background_color: Color::srgba(0.1, 0.1, 0.1, 0.7).into(),
+                ..default()
+            },
+            Interaction::default(),
+            Legend,
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((ButtonBundle {
+                    background_color: Color::NONE.into(),
+                    ..default()
+                }, LegendButton))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Legend", text_style.clone()));
+                });
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            row_gap: Val::Px(2.0),
+                            display: if open { Display::Flex } else { Display::None },
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    LegendEntries,
+                ))
+                .with_children(|parent| {
+                    for (label, swatch) in entries {
+                        parent
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    align_items: AlignItems::Center,
+                                    column_gap: Val::Px(6.0),
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|row| {
+                                row.spawn((
+                                    NodeBundle {
+                                        style: Style {
+                                            width: Val::Px(12.0),
+                                            height: Val::Px(12.0),
+                                            ..default()
+                                        },
+                                        background_color: swatch.color(season).into(),
+                                        ..default()
+                                    },
+                                    swatch,
+                                ));
+                                row.spawn(TextBundle::from_section(label, text_style.clone()));
+                            });
+                    }
+                });
+        });
+}
+
+// Expand or collapse the legend with its header or the legend key
+fn toggle_legend(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<LegendButton>)>,
+    mut legend_open: ResMut<LegendOpen>,
+    mut entries: Query<&mut Style, With<LegendEntries>>,
+) {
+    let clicked = buttons.iter().any(|interaction| *interaction == Interaction::Pressed);
+    if !clicked && !key_bindings.just_pressed(InputAction::Legend, &keyboard_input) {
+        return;
+    }
+    legend_open.0 = !legend_open.0;
+    for mut style in entries.iter_mut() {
+        style.display = if legend_open.0 { Display::Flex } else { Display::None };
+    }
+}
+
+// Follow the seasons, which tint parks and forests
+fn update_swatches(time_of_day: Res<TimeOfDay>, mut swatches: Query<(&Swatch, &mut BackgroundColor)>) {
+    let season = time_of_day.season();
+    for (swatch, mut color) in swatches.iter_mut() {
+        color.set_if_neq(swatch.color(season).into());
+    }
+}
+
+fn despawn_legend(mut commands: Commands, legends: Query<Entity, With<Legend>>) {
+    for entity in legends.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -4,23 +4,85 @@ mod actions;
 mod audio;
 mod loading;
 mod menu;
-mod player;
 mod island;
 mod town;
-mod grid;
+mod tilemap;
+pub mod grid;
+mod coverage;
 mod simulation;
 mod citizen;
+mod minimap;
+mod hud;
+mod tooltip;
+mod clock;
+mod widgets;
+mod budget;
+mod game_over;
+mod milestones;
+mod save;
+mod rng;
+mod transit;
+mod trade;
+mod incidents;
+mod departments;
+mod disasters;
+mod heatmaps;
+mod settings;
+mod screenshot;
+mod export;
+mod legend;
+mod history;
+mod notifications;
+mod transition;
+mod abandonment;
+mod connectivity;
+mod context_menu;
+mod highlight;
+mod selection;
+mod blueprint;
+mod traffic_lights;
+pub mod headless;
 
 use crate::actions::ActionsPlugin;
 use crate::audio::InternalAudioPlugin;
 use crate::loading::LoadingPlugin;
 use crate::menu::MenuPlugin;
-use crate::player::PlayerPlugin;
 use crate::island::IslandPlugin;
 use crate::town::TownPlugin;
+use crate::tilemap::TilemapPlugin;
 use crate::grid::GridPlugin;
 use crate::simulation::SimulationPlugin;
 use crate::citizen::CitizenPlugin;
+use crate::minimap::MinimapPlugin;
+use crate::hud::HudPlugin;
+use crate::tooltip::TooltipPlugin;
+use crate::clock::ClockPlugin;
+use crate::widgets::WidgetsPlugin;
+use crate::budget::BudgetPlugin;
+use crate::game_over::GameOverPlugin;
+use crate::milestones::MilestonesPlugin;
+use crate::save::SavePlugin;
+use crate::transit::TransitPlugin;
+use crate::trade::TradePlugin;
+use crate::incidents::IncidentsPlugin;
+use crate::departments::DepartmentsPlugin;
+use crate::disasters::DisastersPlugin;
+use crate::heatmaps::HeatmapsPlugin;
+use crate::settings::SettingsPlugin;
+use crate::screenshot::ScreenshotPlugin;
+use crate::export::ExportPlugin;
+use crate::legend::LegendPlugin;
+use crate::history::HistoryPlugin;
+use crate::notifications::NotificationsPlugin;
+use crate::transition::TransitionPlugin;
+use crate::abandonment::AbandonmentPlugin;
+use crate::connectivity::ConnectivityPlugin;
+use crate::context_menu::ContextMenuPlugin;
+use crate::highlight::HighlightPlugin;
+use crate::selection::SelectionPlugin;
+use crate::blueprint::BlueprintPlugin;
+use crate::traffic_lights::TrafficLightsPlugin;
+use crate::rng::GameRng;
 
 use bevy::app::App;
 #[cfg(debug_assertions)]
@@ -41,24 +103,70 @@ enum GameState {
     IslandView,
     // Town view shows the detailed town simulation
     TownView,
+    // Lists the save slots to load a game from
+    SaveSlots,
+    // Volume and other options, reached from the menu
+    Settings,
+    // The town went bankrupt, final stats are shown until the player restarts
+    GameOver,
+    // Pause menu over the island or town view, which is kept as it was to be resumed
+    Paused,
 }
 
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.init_state::<GameState>().add_plugins((
-            LoadingPlugin,
-            MenuPlugin,
-            ActionsPlugin,
-            InternalAudioPlugin,
-            PlayerPlugin,
-            IslandPlugin,
-            TownPlugin,
-            GridPlugin,
-            SimulationPlugin,
-            CitizenPlugin,
-        ));
+        app.init_state::<GameState>()
+            .init_resource::<GameRng>()
+            .add_plugins((
+                LoadingPlugin,
+                MenuPlugin,
+                ActionsPlugin,
+                InternalAudioPlugin,
+                IslandPlugin,
+                TownPlugin,
+                GridPlugin,
+                SimulationPlugin,
+                CitizenPlugin,
+                ClockPlugin,
+                GameOverPlugin,
+                MilestonesPlugin,
+                SavePlugin,
+                SettingsPlugin,
+            ))
+            // Fades between the states
+            .add_plugins(TransitionPlugin)
+            // Town services
+            .add_plugins((
+                TilemapPlugin,
+                TransitPlugin,
+                IncidentsPlugin,
+                DepartmentsPlugin,
+                HeatmapsPlugin,
+                DisastersPlugin,
+                TradePlugin,
+                AbandonmentPlugin,
+                ConnectivityPlugin,
+                TrafficLightsPlugin,
+            ))
+            // Tools
+            .add_plugins((ScreenshotPlugin, ExportPlugin))
+            // Town view UI
+            .add_plugins((
+                WidgetsPlugin,
+                MinimapPlugin,
+                HudPlugin,
+                TooltipPlugin,
+                BudgetPlugin,
+                LegendPlugin,
+                HistoryPlugin,
+                NotificationsPlugin,
+                ContextMenuPlugin,
+                HighlightPlugin,
+                SelectionPlugin,
+                BlueprintPlugin,
+            ));
 
         #[cfg(debug_assertions)]
         {
@@ -1,4 +1,5 @@
 use crate::GameState;
+use bevy::asset::LoadState;
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 use bevy_kira_audio::AudioSource;
@@ -15,7 +16,138 @@ impl Plugin for LoadingPlugin {
                 .continue_to_state(GameState::Menu)
                 .load_collection::<AudioAssets>()
                 .load_collection::<TextureAssets>(),
-        );
+        )
+        .add_systems(OnEnter(GameState::Loading), setup_loading_screen)
+        .add_systems(Update, update_loading_screen.run_if(in_state(GameState::Loading)))
+        .add_systems(OnExit(GameState::Loading), cleanup_loading_screen);
+    }
+}
+
+// Paths of every asset in the collections below, to tell how far loading has come
+const ASSET_PATHS: [&str; 9] = [
+    "audio/menu.wav",
+    "audio/island.wav",
+    "audio/town.wav",
+    "audio/build.wav",
+    "audio/zone.wav",
+    "audio/demolish.wav",
+    "audio/refused.wav",
+    "textures/bevy.png",
+    "textures/github.png",
+];
+
+const BAR_WIDTH: f32 = 400.0;
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingBar;
+
+#[derive(Component)]
+struct LoadingText;
+
+fn setup_loading_screen(mut commands: Commands) {
+    commands.spawn((Camera2dBundle::default(), LoadingScreen));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            LoadingScreen,
+        ))
+        .with_children(|children| {
+            children
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(BAR_WIDTH),
+                        height: Val::Px(24.0),
+                        ..default()
+                    },
+                    background_color: Color::linear_rgb(0.15, 0.15, 0.15).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: Color::linear_rgb(0.3, 0.6, 0.3).into(),
+                            ..default()
+                        },
+                        LoadingBar,
+                    ));
+                });
+            children.spawn((
+                TextBundle::from_section(
+                    "Loading 0%",
+                    TextStyle {
+                        font_size: 24.0,
+                        color: Color::linear_rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                )
+                .with_text_justify(JustifyText::Center)
+                .with_style(Style {
+                    margin: UiRect::top(Val::Px(12.0)),
+                    max_width: Val::Px(BAR_WIDTH),
+                    ..default()
+                }),
+                LoadingText,
+            ));
+        });
+}
+
+// Fill the bar with the share of assets loaded. The loading state goes on to the menu by itself
+// once everything is in, a failed asset would keep it waiting so the failure is shown instead
+fn update_loading_screen(
+    asset_server: Res<AssetServer>,
+    mut bar: Query<&mut Style, With<LoadingBar>>,
+    mut text: Query<&mut Text, With<LoadingText>>,
+) {
+    let mut loaded = 0;
+    let mut failed = Vec::new();
+    for path in ASSET_PATHS {
+        // Assets not requested yet count as still loading
+        match asset_server.get_path_id(path).and_then(|id| asset_server.get_load_state(id)) {
+            Some(LoadState::Loaded) => loaded += 1,
+            Some(LoadState::Failed(_)) => failed.push(path),
+            _ => {}
+        }
+    }
+    let progress = loaded as f32 / ASSET_PATHS.len() as f32;
+
+    if let Ok(mut style) = bar.get_single_mut() {
+        style.width = Val::Percent(progress * 100.0);
+    }
+    if let Ok(mut text) = text.get_single_mut() {
+        text.sections[0].value = if failed.is_empty() {
+            format!("Loading {:.0}%", progress * 100.0)
+        } else {
+            format!("Failed to load {}", failed.join(", "))
+        };
+        text.sections[0].style.color = if failed.is_empty() {
+            Color::linear_rgb(0.9, 0.9, 0.9)
+        } else {
+            Color::linear_rgb(0.9, 0.3, 0.3)
+        };
+    }
+}
+
+fn cleanup_loading_screen(mut commands: Commands, screen: Query<Entity, With<LoadingScreen>>) {
+    for entity in screen.iter() {
+        commands.entity(entity).despawn_recursive();
     }
 }
 
@@ -24,8 +156,20 @@ impl Plugin for LoadingPlugin {
 
 #[derive(AssetCollection, Resource)]
 pub struct AudioAssets {
-    #[asset(path = "audio/flying.ogg")]
-    pub flying: Handle<AudioSource>,
+    #[asset(path = "audio/menu.wav")]
+    pub menu_music: Handle<AudioSource>,
+    #[asset(path = "audio/island.wav")]
+    pub island_music: Handle<AudioSource>,
+    #[asset(path = "audio/town.wav")]
+    pub town_music: Handle<AudioSource>,
+    #[asset(path = "audio/build.wav")]
+    pub build: Handle<AudioSource>,
+    #[asset(path = "audio/zone.wav")]
+    pub zone: Handle<AudioSource>,
+    #[asset(path = "audio/demolish.wav")]
+    pub demolish: Handle<AudioSource>,
+    #[asset(path = "audio/refused.wav")]
+    pub refused: Handle<AudioSource>,
 }
 
 #[derive(AssetCollection, Resource)]
@@ -34,12 +178,4 @@ pub struct TextureAssets {
     pub bevy: Handle<Image>,
     #[asset(path = "textures/github.png")]
     pub github: Handle<Image>,
-    // We'll use the bevy logo as a placeholder for now
-    // In a real game, we would have specific textures for these
-    #[asset(path = "textures/bevy.png")]
-    pub town_hall: Handle<Image>,
-    #[asset(path = "textures/bevy.png")]
-    pub power_plant: Handle<Image>,
-    #[asset(path = "textures/bevy.png")]
-    pub water_tower: Handle<Image>,
 }
@@ -1,16 +1,54 @@
+use crate::actions::{InputAction, KeyBindings};
+use crate::island::{IslandMapError, IslandMapFile, IslandSize, TownNamePrompt};
+use crate::game_over::reset_game;
 use crate::loading::TextureAssets;
+use crate::rng::GameRng;
+use crate::save::SaveRequested;
 use crate::GameState;
 use bevy::prelude::*;
+use std::path::PathBuf;
 
 pub struct MenuPlugin;
 
-/// This plugin is responsible for the game menu (containing only one button...)
-/// The menu is only drawn during the State `GameState::Menu` and is removed when that state is exited
+/// This plugin is responsible for the main menu, starting new games and leading to the other screens
+/// The menu is only drawn during the State `GameState::Menu` and is removed when that state is exited.
+/// It also draws the pause menu over the island and town views
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Menu), setup_menu)
-            .add_systems(Update, click_play_button.run_if(in_state(GameState::Menu)))
-            .add_systems(OnExit(GameState::Menu), cleanup_menu);
+        app.init_resource::<PausedFrom>()
+            .init_resource::<MenuFocus>()
+            .init_resource::<NewGameSeed>()
+            .add_systems(OnEnter(GameState::Menu), setup_menu)
+            .add_systems(
+                Update,
+                (
+                    navigate_menu,
+                    type_seed,
+                    click_menu_button.after(navigate_menu),
+                    update_island_size_label,
+                    update_island_map_label,
+                    update_seed_label,
+                )
+                    .run_if(in_state(GameState::Menu)),
+            )
+            .add_systems(OnExit(GameState::Menu), cleanup_menu)
+            .add_systems(OnEnter(GameState::Paused), setup_pause_menu)
+            .add_systems(
+                Update,
+                (
+                    toggle_pause_menu.run_if(
+                        in_state(GameState::IslandView)
+                            .or_else(in_state(GameState::TownView))
+                            .or_else(in_state(GameState::Paused)),
+                    ),
+                    click_pause_button.run_if(in_state(GameState::Paused)),
+                    quit_to_menu.run_if(
+                        resource_exists::<QuitToMenu>
+                            .and_then(in_state(GameState::IslandView).or_else(in_state(GameState::TownView))),
+                    ),
+                ),
+            )
+            .add_systems(OnExit(GameState::Paused), cleanup_pause_menu);
     }
 }
 
@@ -32,9 +70,21 @@ impl Default for ButtonColors {
 #[derive(Component)]
 struct Menu;
 
-fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>) {
+// Heightmap the map button switches to, relative to the working directory
+const HEIGHTMAP_PATH: &str = "maps/heightmap.png";
+
+fn setup_menu(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    island_size: Res<IslandSize>,
+    map_file: Res<IslandMapFile>,
+    seed: Res<NewGameSeed>,
+    map_error: Option<Res<IslandMapError>>,
+    mut focus: ResMut<MenuFocus>,
+) {
     info!("menu");
     commands.spawn(Camera2dBundle::default());
+    focus.0 = None;
     commands
         .spawn((
             NodeBundle {
@@ -51,13 +101,23 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>) {
             Menu,
         ))
         .with_children(|children| {
-            let button_colors = ButtonColors::default();
-            children
-                .spawn((
+            for button in MenuButton::ALL {
+                // There's nothing to quit to in a browser
+                if button == MenuButton::Quit && cfg!(target_arch = "wasm32") {
+                    continue;
+                }
+                let button_colors = ButtonColors::default();
+                let (width, height, font_size) = if button.is_option() {
+                    (240.0, 40.0, 24.0)
+                } else {
+                    (200.0, 50.0, 36.0)
+                };
+                let mut button_entity = children.spawn((
                     ButtonBundle {
                         style: Style {
-                            width: Val::Px(140.0),
-                            height: Val::Px(50.0),
+                            width: Val::Px(width),
+                            height: Val::Px(height),
+                            margin: UiRect::top(Val::Px(10.0)),
                             justify_content: JustifyContent::Center,
                             align_items: AlignItems::Center,
                             ..Default::default()
@@ -66,18 +126,56 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>) {
                         ..Default::default()
                     },
                     button_colors,
-                    ChangeState(GameState::IslandView),
-                ))
-                .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "Play",
+                    button,
+                ));
+                button_entity.with_children(|parent| {
+                    let label = match button {
+                        MenuButton::IslandSize => island_size_label(*island_size),
+                        MenuButton::IslandMap => island_map_label(&map_file),
+                        MenuButton::Seed => seed_label(&seed),
+                        _ => button.label().to_string(),
+                    };
+                    let mut text = parent.spawn(TextBundle::from_section(
+                        label,
                         TextStyle {
-                            font_size: 40.0,
+                            font_size,
                             color: Color::linear_rgb(0.9, 0.9, 0.9),
                             ..default()
                         },
                     ));
+                    match button {
+                        MenuButton::IslandSize => {
+                            text.insert(IslandSizeLabel);
+                        }
+                        MenuButton::IslandMap => {
+                            text.insert(IslandMapLabel);
+                        }
+                        MenuButton::Seed => {
+                            text.insert(SeedLabel);
+                        }
+                        _ => {}
+                    }
                 });
+
+                // Why the picked heightmap couldn't be used, after being sent back here
+                if let (MenuButton::IslandMap, Some(map_error)) = (button, map_error.as_ref()) {
+                    children.spawn(
+                        TextBundle::from_section(
+                            format!("Could not load the island map: {}", map_error.0),
+                            TextStyle {
+                                font_size: 18.0,
+                                color: Color::linear_rgb(0.9, 0.3, 0.3),
+                                ..default()
+                            },
+                        )
+                        .with_style(Style {
+                            margin: UiRect::top(Val::Px(10.0)),
+                            max_width: Val::Px(480.0),
+                            ..default()
+                        }),
+                    );
+                }
+            }
         });
     commands
         .spawn((
@@ -176,35 +274,405 @@ fn setup_menu(mut commands: Commands, textures: Res<TextureAssets>) {
 }
 
 #[derive(Component)]
-struct ChangeState(GameState);
+struct OpenLink(&'static str);
+
+// Buttons of the main menu, from top to bottom. The island options below New Game pick the island
+// it generates
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum MenuButton {
+    NewGame,
+    IslandSize,
+    IslandMap,
+    Seed,
+    LoadGame,
+    Settings,
+    Quit,
+}
+
+impl MenuButton {
+    const ALL: [MenuButton; 7] = [
+        MenuButton::NewGame,
+        MenuButton::IslandSize,
+        MenuButton::IslandMap,
+        MenuButton::Seed,
+        MenuButton::LoadGame,
+        MenuButton::Settings,
+        MenuButton::Quit,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            MenuButton::NewGame => "New Game",
+            MenuButton::IslandSize => "Island size",
+            MenuButton::IslandMap => "Map",
+            MenuButton::Seed => "Seed",
+            MenuButton::LoadGame => "Load Game",
+            MenuButton::Settings => "Settings",
+            MenuButton::Quit => "Quit",
+        }
+    }
+
+    // Options for the new game, drawn smaller than the buttons leading elsewhere
+    fn is_option(self) -> bool {
+        matches!(self, MenuButton::IslandSize | MenuButton::IslandMap | MenuButton::Seed)
+    }
+}
+
+// Button picked with the arrow keys, activated with Enter
+#[derive(Resource, Default)]
+struct MenuFocus(Option<MenuButton>);
+
+// Seed of the island the next new game generates, random until typed in
+#[derive(Resource)]
+struct NewGameSeed(u64);
+
+impl Default for NewGameSeed {
+    fn default() -> Self {
+        NewGameSeed(rand::random())
+    }
+}
 
 #[derive(Component)]
-struct OpenLink(&'static str);
+struct IslandSizeLabel;
+
+fn island_size_label(island_size: IslandSize) -> String {
+    format!("Island size: {:?}", island_size)
+}
+
+#[derive(Component)]
+struct IslandMapLabel;
 
-fn click_play_button(
+fn island_map_label(map_file: &IslandMapFile) -> String {
+    match &map_file.0 {
+        Some(path) => format!("Map: {}", path.file_name().unwrap_or(path.as_os_str()).to_string_lossy()),
+        None => "Map: Generated".to_string(),
+    }
+}
+
+#[derive(Component)]
+struct SeedLabel;
+
+fn seed_label(seed: &NewGameSeed) -> String {
+    format!("Seed: {}", seed.0)
+}
+
+// Move the focus between the menu buttons with the arrow keys
+fn navigate_menu(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut focus: ResMut<MenuFocus>,
+    buttons: Query<(&MenuButton, &Interaction)>,
+) {
+    // Hovering a button moves the focus there, so the keys carry on from the mouse
+    if let Some((button, _)) = buttons.iter().find(|(_, interaction)| **interaction == Interaction::Hovered) {
+        focus.0 = Some(*button);
+    }
+
+    let step: isize = if keyboard_input.just_pressed(KeyCode::ArrowDown) {
+        1
+    } else if keyboard_input.just_pressed(KeyCode::ArrowUp) {
+        -1
+    } else {
+        return;
+    };
+    let shown: Vec<MenuButton> = MenuButton::ALL
+        .into_iter()
+        .filter(|button| buttons.iter().any(|(shown, _)| shown == button))
+        .collect();
+    if shown.is_empty() {
+        return;
+    }
+    let next = match focus.0.and_then(|focused| shown.iter().position(|button| *button == focused)) {
+        Some(index) => (index as isize + step).rem_euclid(shown.len() as isize) as usize,
+        None if step > 0 => 0,
+        None => shown.len() - 1,
+    };
+    focus.0 = Some(shown[next]);
+}
+
+// Type the seed of the next island, Backspace drops the last digit
+fn type_seed(keyboard_input: Res<ButtonInput<KeyCode>>, mut seed: ResMut<NewGameSeed>) {
+    for key in keyboard_input.get_just_pressed() {
+        let digit = match key {
+            KeyCode::Digit0 | KeyCode::Numpad0 => 0,
+            KeyCode::Digit1 | KeyCode::Numpad1 => 1,
+            KeyCode::Digit2 | KeyCode::Numpad2 => 2,
+            KeyCode::Digit3 | KeyCode::Numpad3 => 3,
+            KeyCode::Digit4 | KeyCode::Numpad4 => 4,
+            KeyCode::Digit5 | KeyCode::Numpad5 => 5,
+            KeyCode::Digit6 | KeyCode::Numpad6 => 6,
+            KeyCode::Digit7 | KeyCode::Numpad7 => 7,
+            KeyCode::Digit8 | KeyCode::Numpad8 => 8,
+            KeyCode::Digit9 | KeyCode::Numpad9 => 9,
+            KeyCode::Backspace => {
+                seed.0 /= 10;
+                continue;
+            }
+            _ => continue,
+        };
+        seed.0 = seed.0.saturating_mul(10).saturating_add(digit);
+    }
+}
+
+// Run the clicked button, or the focused one on Enter
+#[allow(clippy::too_many_arguments)]
+fn click_menu_button(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    focus: Res<MenuFocus>,
     mut next_state: ResMut<NextState<GameState>>,
     mut interaction_query: Query<
         (
-            &Interaction,
+            Ref<Interaction>,
             &mut BackgroundColor,
             &ButtonColors,
-            Option<&ChangeState>,
+            Option<&MenuButton>,
             Option<&OpenLink>,
         ),
+        With<Button>,
+    >,
+    mut island_size: ResMut<IslandSize>,
+    mut map_file: ResMut<IslandMapFile>,
+    mut seed: ResMut<NewGameSeed>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    let mut pressed = if keyboard_input.just_pressed(KeyCode::Enter) {
+        focus.0
+    } else {
+        None
+    };
+    for (interaction, mut color, button_colors, button, open_link) in &mut interaction_query {
+        let focused = button.is_some() && button.copied() == focus.0;
+        *color = if *interaction != Interaction::None || focused {
+            button_colors.hovered.into()
+        } else {
+            button_colors.normal.into()
+        };
+        if !interaction.is_changed() || *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some(button) = button {
+            pressed = Some(*button);
+        } else if let Some(link) = open_link {
+            if let Err(error) = webbrowser::open(link.0) {
+                warn!("Failed to open link {error:?}");
+            }
+        }
+    }
+
+    match pressed {
+        // A new game starts from scratch on an island generated from the picked seed
+        Some(MenuButton::NewGame) => {
+            reset_game(&mut commands);
+            commands.insert_resource(GameRng::new(seed.0));
+            next_state.set(GameState::IslandView);
+        }
+        Some(MenuButton::IslandSize) => *island_size = island_size.next(),
+        Some(MenuButton::IslandMap) => {
+            map_file.0 = match map_file.0 {
+                Some(_) => None,
+                None => Some(PathBuf::from(HEIGHTMAP_PATH)),
+            };
+        }
+        Some(MenuButton::Seed) => seed.0 = rand::random(),
+        Some(MenuButton::LoadGame) => next_state.set(GameState::SaveSlots),
+        Some(MenuButton::Settings) => next_state.set(GameState::Settings),
+        Some(MenuButton::Quit) => {
+            app_exit.send(AppExit::Success);
+        }
+        None => {}
+    }
+}
+
+fn update_island_size_label(
+    island_size: Res<IslandSize>,
+    mut labels: Query<&mut Text, With<IslandSizeLabel>>,
+) {
+    if !island_size.is_changed() {
+        return;
+    }
+    for mut text in labels.iter_mut() {
+        text.sections[0].value = island_size_label(*island_size);
+    }
+}
+
+fn update_island_map_label(map_file: Res<IslandMapFile>, mut labels: Query<&mut Text, With<IslandMapLabel>>) {
+    if !map_file.is_changed() {
+        return;
+    }
+    for mut text in labels.iter_mut() {
+        text.sections[0].value = island_map_label(&map_file);
+    }
+}
+
+fn update_seed_label(seed: Res<NewGameSeed>, mut labels: Query<&mut Text, With<SeedLabel>>) {
+    if !seed.is_changed() {
+        return;
+    }
+    for mut text in labels.iter_mut() {
+        text.sections[0].value = seed_label(&seed);
+    }
+}
+
+fn cleanup_menu(mut commands: Commands, menu: Query<Entity, With<Menu>>) {
+    // The map error has been shown once leaving the menu
+    commands.remove_resource::<IslandMapError>();
+    for entity in menu.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// View the game was paused from, resumed without being set up again
+#[derive(Resource, Default)]
+pub struct PausedFrom(pub Option<GameState>);
+
+// Run condition for the views' setup and cleanup, false while going into or out of the pause menu
+// so a paused view is kept as it was
+pub fn outside_pause(mut transitions: EventReader<StateTransitionEvent<GameState>>) -> bool {
+    transitions.read().last().is_none_or(|transition| {
+        transition.exited != Some(GameState::Paused) && transition.entered != Some(GameState::Paused)
+    })
+}
+
+// Quitting from the pause menu resumes the view for a frame, so leaving it cleans it up as usual
+#[derive(Resource)]
+struct QuitToMenu;
+
+#[derive(Component)]
+struct PauseMenu;
+
+#[derive(Component, Clone, Copy)]
+enum PauseButton {
+    Resume,
+    Save,
+    Settings,
+    Quit,
+}
+
+impl PauseButton {
+    fn label(self) -> &'static str {
+        match self {
+            PauseButton::Resume => "Resume",
+            PauseButton::Save => "Save",
+            PauseButton::Settings => "Settings",
+            PauseButton::Quit => "Quit to menu",
+        }
+    }
+}
+
+// Open the pause menu with P, or close it again
+fn toggle_pause_menu(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    state: Res<State<GameState>>,
+    mut paused_from: ResMut<PausedFrom>,
+    mut next_state: ResMut<NextState<GameState>>,
+    name_prompts: Query<(), With<TownNamePrompt>>,
+) {
+    // The key could be part of a town's name
+    if !key_bindings.just_pressed(InputAction::PauseMenu, &keyboard_input) || !name_prompts.is_empty() {
+        return;
+    }
+    match paused_from.0.take() {
+        Some(view) => next_state.set(view),
+        None => {
+            paused_from.0 = Some(state.get().clone());
+            next_state.set(GameState::Paused);
+        }
+    }
+}
+
+// The paused view stays drawn behind the menu, so no camera is spawned
+fn setup_pause_menu(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.6).into(),
+                z_index: ZIndex::Global(20),
+                ..default()
+            },
+            PauseMenu,
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section(
+                "Paused",
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::linear_rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+            for button in [PauseButton::Resume, PauseButton::Save, PauseButton::Settings, PauseButton::Quit] {
+                let button_colors = ButtonColors::default();
+                children
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(200.0),
+                                height: Val::Px(50.0),
+                                margin: UiRect::top(Val::Px(10.0)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..Default::default()
+                            },
+                            background_color: button_colors.normal.into(),
+                            ..Default::default()
+                        },
+                        button_colors,
+                        button,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            button.label(),
+                            TextStyle {
+                                font_size: 30.0,
+                                color: Color::linear_rgb(0.9, 0.9, 0.9),
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+fn click_pause_button(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut paused_from: ResMut<PausedFrom>,
+    mut save_requests: EventWriter<SaveRequested>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &ButtonColors, &PauseButton),
         (Changed<Interaction>, With<Button>),
     >,
 ) {
-    for (interaction, mut color, button_colors, change_state, open_link) in &mut interaction_query {
+    for (interaction, mut color, button_colors, button) in &mut interaction_query {
         match *interaction {
-            Interaction::Pressed => {
-                if let Some(state) = change_state {
-                    next_state.set(state.0.clone());
-                } else if let Some(link) = open_link {
-                    if let Err(error) = webbrowser::open(link.0) {
-                        warn!("Failed to open link {error:?}");
+            Interaction::Pressed => match button {
+                PauseButton::Resume => {
+                    if let Some(view) = paused_from.0.take() {
+                        next_state.set(view);
                     }
                 }
-            }
+                PauseButton::Save => {
+                    save_requests.send(SaveRequested);
+                }
+                // Settings come back to the pause menu while the game is paused
+                PauseButton::Settings => next_state.set(GameState::Settings),
+                PauseButton::Quit => {
+                    if let Some(view) = paused_from.0.take() {
+                        commands.insert_resource(QuitToMenu);
+                        next_state.set(view);
+                    }
+                }
+            },
             Interaction::Hovered => {
                 *color = button_colors.hovered.into();
             }
@@ -215,7 +683,12 @@ fn click_play_button(
     }
 }
 
-fn cleanup_menu(mut commands: Commands, menu: Query<Entity, With<Menu>>) {
+fn quit_to_menu(mut commands: Commands, mut next_state: ResMut<NextState<GameState>>) {
+    commands.remove_resource::<QuitToMenu>();
+    next_state.set(GameState::Menu);
+}
+
+fn cleanup_pause_menu(mut commands: Commands, menu: Query<Entity, With<PauseMenu>>) {
     for entity in menu.iter() {
         commands.entity(entity).despawn_recursive();
     }
@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+use crate::clock::TimeOfDay;
+use crate::simulation::{Economy, Population};
+use crate::town::{BuildingType, ToolButton, Town};
+use crate::GameState;
+
+pub struct MilestonesPlugin;
+
+/// This plugin tracks the town's progress towards its milestones and unlocks new buildings
+impl Plugin for MilestonesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Milestones>()
+            .add_event::<MilestoneReached>()
+            .add_systems(
+                Update,
+                (check_milestones, update_locked_tools).run_if(in_state(GameState::TownView)),
+            );
+    }
+}
+
+// Condition a milestone is reached with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MilestoneGoal {
+    Population(i32),
+    // Town happiness above the threshold for the given number of days in a row
+    Happiness { threshold: f32, days: f32 },
+    // Funds above zero for the given number of days in a row
+    PositiveFunds { days: f32 },
+}
+
+pub struct Milestone {
+    pub name: &'static str,
+    pub goal: MilestoneGoal,
+    // Building tool that stays hidden until this milestone is reached
+    pub unlocks: Option<BuildingType>,
+    pub reached: bool,
+    // In-game day the current streak started, for goals that have to hold for a while
+    streak_since: Option<f32>,
+}
+
+impl Milestone {
+    fn new(name: &'static str, goal: MilestoneGoal, unlocks: Option<BuildingType>) -> Self {
+        Milestone {
+            name,
+            goal,
+            unlocks,
+            reached: false,
+            streak_since: None,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct Milestones {
+    pub milestones: Vec<Milestone>,
+}
+
+impl Default for Milestones {
+    fn default() -> Self {
+        Milestones {
+            milestones: vec![
+                Milestone::new("Village", MilestoneGoal::Population(100), Some(BuildingType::Park)),
+                Milestone::new(
+                    "Content citizens",
+                    MilestoneGoal::Happiness { threshold: 0.8, days: 10.0 },
+                    Some(BuildingType::School),
+                ),
+                Milestone::new(
+                    "In the black",
+                    MilestoneGoal::PositiveFunds { days: 30.0 },
+                    Some(BuildingType::Police),
+                ),
+                Milestone::new("Town", MilestoneGoal::Population(500), Some(BuildingType::Fire)),
+                Milestone::new("City", MilestoneGoal::Population(1000), Some(BuildingType::Hospital)),
+            ],
+        }
+    }
+}
+
+impl Milestones {
+    // Buildings not gated behind any milestone are always available
+    pub fn is_unlocked(&self, building_type: BuildingType) -> bool {
+        self.milestones
+            .iter()
+            .all(|milestone| milestone.reached || milestone.unlocks != Some(building_type))
+    }
+}
+
+#[derive(Event)]
+pub struct MilestoneReached {
+    pub name: &'static str,
+    pub unlocks: Option<BuildingType>,
+}
+
+fn check_milestones(
+    mut milestones: ResMut<Milestones>,
+    population: Res<Population>,
+    town: Res<Town>,
+    economy: Res<Economy>,
+    time_of_day: Res<TimeOfDay>,
+    mut reached_events: EventWriter<MilestoneReached>,
+) {
+    let now = time_of_day.elapsed_days();
+    for milestone in milestones.milestones.iter_mut().filter(|milestone| !milestone.reached) {
+        let reached = match milestone.goal {
+            MilestoneGoal::Population(target) => population.total >= target,
+            MilestoneGoal::Happiness { threshold, days } => {
+                streak_reached(&mut milestone.streak_since, town.happiness > threshold, now, days)
+            }
+            MilestoneGoal::PositiveFunds { days } => {
+                streak_reached(&mut milestone.streak_since, economy.funds > 0, now, days)
+            }
+        };
+
+        if reached {
+            milestone.reached = true;
+            reached_events.send(MilestoneReached {
+                name: milestone.name,
+                unlocks: milestone.unlocks,
+            });
+        }
+    }
+}
+
+// Whether a condition has held for the given number of days, restarting the streak when it fails
+fn streak_reached(since: &mut Option<f32>, holds: bool, now: f32, days: f32) -> bool {
+    if !holds {
+        *since = None;
+        return false;
+    }
+    now - *since.get_or_insert(now) >= days
+}
+
+// Hide the tools for buildings that haven't been unlocked yet
+fn update_locked_tools(milestones: Res<Milestones>, mut tools: Query<(&ToolButton, &mut Style)>) {
+    for (tool, mut style) in tools.iter_mut() {
+        let display = if milestones.is_unlocked(tool.building_type) {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+use bevy::ui::RelativeCursorPosition;
+use bevy::window::PrimaryWindow;
+use crate::tilemap::TownTiles;
+use crate::town::{TOWN_CELL_SPACING, TOWN_GRID_SIZE};
+use crate::GameState;
+
+pub struct MinimapPlugin;
+
+/// This plugin keeps the town minimap in sync with the town grid and the camera
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                update_minimap_cells,
+                recenter_camera_from_minimap,
+                update_minimap_viewport.after(recenter_camera_from_minimap),
+            ).run_if(in_state(GameState::TownView)),
+        );
+    }
+}
+
+// Size of the minimap on screen in pixels
+const MINIMAP_SIZE: f32 = 150.0;
+
+// Minimap UI node, showing one pixel per town cell
+#[derive(Component)]
+pub struct Minimap {
+    image: Handle<Image>,
+}
+
+// Rectangle on the minimap outlining what the camera currently sees
+#[derive(Component)]
+struct MinimapViewport;
+
+// Spawn the minimap in the top right corner of the town view
+pub fn spawn_minimap(commands: &mut Commands, images: &mut Assets<Image>) {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: TOWN_GRID_SIZE as u32,
+            height: TOWN_GRID_SIZE as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    );
+    // Keep the cells crisp when scaled up
+    image.sampler = ImageSampler::nearest();
+    let image = images.add(image);
+
+    commands
+        .spawn((
+            ImageBundle {
+                style: Style {
+                    width: Val::Px(MINIMAP_SIZE),
+                    height: Val::Px(MINIMAP_SIZE),
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(50.0),
+                    right: Val::Px(10.0),
+                    overflow: Overflow::clip(),
+                    ..default()
+                },
+                image: image.clone().into(),
+                ..default()
+            },
+            Interaction::default(),
+            RelativeCursorPosition::default(),
+            Minimap { image },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        border: UiRect::all(Val::Px(1.0)),
+                        ..default()
+                    },
+                    border_color: Color::WHITE.into(),
+                    ..default()
+                },
+                MinimapViewport,
+            ));
+        });
+}
+
+// Copy the tile colors into the minimap image when they change, or when the minimap is new
+fn update_minimap_cells(
+    minimap: Query<Ref<Minimap>>,
+    mut images: ResMut<Assets<Image>>,
+    tiles: Res<TownTiles>,
+) {
+    let Ok(minimap) = minimap.get_single() else {
+        return;
+    };
+    if !tiles.is_changed() && !minimap.is_added() {
+        return;
+    }
+    let Some(image) = images.get_mut(&minimap.image) else {
+        return;
+    };
+
+    for (pos, color) in tiles.iter() {
+        // Image rows go top to bottom while grid rows go bottom to top
+        let row = TOWN_GRID_SIZE - 1 - pos.y as usize;
+        let index = (row * TOWN_GRID_SIZE + pos.x as usize) * 4;
+        image.data[index..index + 4].copy_from_slice(&color.to_srgba().to_u8_array());
+    }
+}
+
+// Move the camera to the clicked (or dragged over) spot on the minimap
+fn recenter_camera_from_minimap(
+    minimap: Query<(&Interaction, &RelativeCursorPosition), With<Minimap>>,
+    mut camera: Query<&mut Transform, With<Camera2d>>,
+) {
+    let Ok((interaction, cursor)) = minimap.get_single() else {
+        return;
+    };
+    if *interaction != Interaction::Pressed {
+        return;
+    }
+    let Some(normalized) = cursor.normalized else {
+        return;
+    };
+
+    let grid = Vec2::new(normalized.x, 1.0 - normalized.y) * TOWN_GRID_SIZE as f32;
+    for mut transform in camera.iter_mut() {
+        transform.translation.x = (grid.x - 0.5 - TOWN_GRID_SIZE as f32 / 2.0) * TOWN_CELL_SPACING;
+        transform.translation.y = (grid.y - 0.5 - TOWN_GRID_SIZE as f32 / 2.0) * TOWN_CELL_SPACING;
+    }
+}
+
+// Keep the viewport rectangle in line with the camera's visible area
+fn update_minimap_viewport(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Transform, &OrthographicProjection), With<Camera2d>>,
+    mut viewport: Query<&mut Style, With<MinimapViewport>>,
+) {
+    let (Ok(window), Ok((transform, projection)), Ok(mut style)) =
+        (windows.get_single(), camera.get_single(), viewport.get_single_mut())
+    else {
+        return;
+    };
+
+    // Visible area in grid cells, with the origin at the grid's bottom left corner
+    let visible = Vec2::new(window.width(), window.height()) * projection.scale / TOWN_CELL_SPACING;
+    let center = transform.translation.truncate() / TOWN_CELL_SPACING
+        + Vec2::splat(TOWN_GRID_SIZE as f32 / 2.0 + 0.5);
+    let pixels_per_cell = MINIMAP_SIZE / TOWN_GRID_SIZE as f32;
+
+    style.left = Val::Px((center.x - visible.x / 2.0) * pixels_per_cell);
+    style.top = Val::Px((TOWN_GRID_SIZE as f32 - center.y - visible.y / 2.0) * pixels_per_cell);
+    style.width = Val::Px(visible.x * pixels_per_cell);
+    style.height = Val::Px(visible.y * pixels_per_cell);
+}
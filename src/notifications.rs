@@ -0,0 +1,420 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use crate::actions::{InputAction, KeyBindings};
+use crate::citizen::CitizenDied;
+use crate::clock::TimeOfDay;
+use crate::milestones::MilestoneReached;
+use crate::simulation::{DebtUnserviceable, LoanRepaid};
+use crate::GameState;
+use std::collections::VecDeque;
+
+pub struct NotificationsPlugin;
+
+/// This plugin collects what happens in the game into a log, shown as fading toasts and a scrollable list
+impl Plugin for NotificationsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Notifications>()
+            .add_event::<Notification>()
+            .add_systems(
+                Update,
+                (
+                    notify_town_events,
+                    record_notifications.after(notify_town_events),
+                    fade_toasts.after(record_notifications),
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    toggle_event_log,
+                    update_event_log.after(toggle_event_log).after(record_notifications),
+                    scroll_event_log.after(update_event_log),
+                ).run_if(in_state(GameState::TownView)),
+            );
+    }
+}
+
+// Entries the log keeps, older ones are dropped
+const LOG_CAPACITY: usize = 100;
+
+// The same message sent again within this many seconds counts towards the earlier entry
+// instead of showing another toast
+const REPEAT_SECONDS: f32 = 10.0;
+
+// Seconds a toast stays on screen, the last of them fading it out
+const TOAST_SECONDS: f32 = 5.0;
+const TOAST_FADE_SECONDS: f32 = 1.0;
+const MAX_TOASTS: usize = 5;
+
+const LOG_WIDTH: f32 = 360.0;
+const LOG_HEIGHT: f32 = 240.0;
+const LOG_LINE_HEIGHT: f32 = 18.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn color(&self) -> Color {
+        match self {
+            Severity::Info => Color::srgb(0.9, 0.9, 0.9),
+            Severity::Success => Color::srgb(1.0, 0.85, 0.2),
+            Severity::Warning => Color::srgb(1.0, 0.6, 0.2),
+            Severity::Critical => Color::srgb(0.95, 0.3, 0.3),
+        }
+    }
+}
+
+// Sent by any system with something to tell the player
+#[derive(Event, Debug, Clone)]
+pub struct Notification {
+    pub message: String,
+    pub severity: Severity,
+}
+
+impl Notification {
+    pub fn info(message: impl Into<String>) -> Self {
+        Notification {
+            message: message.into(),
+            severity: Severity::Info,
+        }
+    }
+
+    pub fn success(message: impl Into<String>) -> Self {
+        Notification {
+            message: message.into(),
+            severity: Severity::Success,
+        }
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Notification {
+            message: message.into(),
+            severity: Severity::Warning,
+        }
+    }
+
+    pub fn critical(message: impl Into<String>) -> Self {
+        Notification {
+            message: message.into(),
+            severity: Severity::Critical,
+        }
+    }
+}
+
+pub struct LogEntry {
+    pub message: String,
+    pub severity: Severity,
+    // In-game day the message was first sent
+    pub day: f32,
+    // Times the message was sent in a row, shown as "(x3)"
+    pub count: u32,
+    // Real time in seconds the message was last sent, for merging repeats
+    last_sent: f32,
+}
+
+impl LogEntry {
+    fn text(&self) -> String {
+        if self.count > 1 {
+            format!("{} (x{})", self.message, self.count)
+        } else {
+            self.message.clone()
+        }
+    }
+}
+
+// Recent notifications, newest last
+#[derive(Resource, Default)]
+pub struct Notifications {
+    pub log: VecDeque<LogEntry>,
+}
+
+impl Notifications {
+    // Log a notification, returning false if it only repeated a recent entry
+    fn push(&mut self, notification: &Notification, day: f32, now: f32) -> bool {
+        let repeat = self.log.iter_mut().rev().find(|entry| {
+            entry.message == notification.message && now - entry.last_sent < REPEAT_SECONDS
+        });
+        if let Some(entry) = repeat {
+            entry.count += 1;
+            entry.last_sent = now;
+            return false;
+        }
+
+        self.log.push_back(LogEntry {
+            message: notification.message.clone(),
+            severity: notification.severity,
+            day,
+            count: 1,
+            last_sent: now,
+        });
+        if self.log.len() > LOG_CAPACITY {
+            self.log.pop_front();
+        }
+        true
+    }
+}
+
+// Column the toasts stack in, below the HUD
+#[derive(Component)]
+struct ToastArea;
+
+#[derive(Component)]
+struct Toast {
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct EventLogPanel;
+
+// Lines of the event log, moved up to scroll
+#[derive(Component, Default)]
+struct EventLogList {
+    offset: f32,
+}
+
+// Turn the town's own events into notifications
+fn notify_town_events(
+    mut milestone_events: EventReader<MilestoneReached>,
+    mut repaid_events: EventReader<LoanRepaid>,
+    mut default_events: EventReader<DebtUnserviceable>,
+    mut died_events: EventReader<CitizenDied>,
+    mut notifications: EventWriter<Notification>,
+) {
+    for event in milestone_events.read() {
+        let mut message = format!("Milestone reached: {}", event.name);
+        if let Some(building_type) = event.unlocks {
+            message.push_str(&format!(" - {} unlocked", building_type.label()));
+        }
+        notifications.send(Notification::success(message));
+    }
+    for _ in repaid_events.read() {
+        notifications.send(Notification::success("All loans repaid"));
+    }
+    for event in default_events.read() {
+        notifications.send(Notification::critical(format!(
+            "Loan payments can't be met, ${} still owed",
+            event.principal
+        )));
+    }
+    for event in died_events.read() {
+        notifications.send(Notification::info(format!("{} died at {:.0}", event.name, event.age)));
+    }
+}
+
+// Log new notifications and show a toast for those that aren't repeats
+fn record_notifications(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_of_day: Res<TimeOfDay>,
+    mut events: EventReader<Notification>,
+    mut notifications: ResMut<Notifications>,
+    areas: Query<Entity, With<ToastArea>>,
+    toasts: Query<(Entity, &Toast)>,
+) {
+    let now = time.elapsed_seconds();
+    let fresh: Vec<Notification> = events
+        .read()
+        .filter(|notification| notifications.push(notification, time_of_day.elapsed_days(), now))
+        .cloned()
+        .collect();
+    if fresh.is_empty() {
+        return;
+    }
+
+    // Leaving a view despawns all UI, so the area is spawned again when needed
+    let area = areas.get_single().ok().unwrap_or_else(|| {
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(60.0),
+                        width: Val::Percent(100.0),
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        row_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                ToastArea,
+            ))
+            .id()
+    });
+
+    // Make room by dropping the toasts closest to fading out
+    let mut shown: Vec<(Entity, f32)> = toasts
+        .iter()
+        .map(|(entity, toast)| (entity, toast.timer.remaining_secs()))
+        .collect();
+    shown.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let excess = (shown.len() + fresh.len()).saturating_sub(MAX_TOASTS);
+    for (entity, _) in shown.into_iter().take(excess) {
+        commands.entity(entity).despawn_recursive();
+    }
+    for notification in fresh {
+        let toast = commands
+            .spawn((
+                TextBundle::from_section(
+                    notification.message,
+                    TextStyle {
+                        font_size: 20.0,
+                        color: notification.severity.color(),
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    padding: UiRect::axes(Val::Px(8.0), Val::Px(2.0)),
+                    ..default()
+                })
+                .with_background_color(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+                Toast {
+                    timer: Timer::from_seconds(TOAST_SECONDS, TimerMode::Once),
+                },
+            ))
+            .id();
+        commands.entity(area).add_child(toast);
+    }
+}
+
+fn fade_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut toasts: Query<(Entity, &mut Toast, &mut Text, &mut BackgroundColor)>,
+) {
+    for (entity, mut toast, mut text, mut background) in toasts.iter_mut() {
+        if toast.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let alpha = (toast.timer.remaining_secs() / TOAST_FADE_SECONDS).min(1.0);
+        let color = &mut text.sections[0].style.color;
+        *color = color.with_alpha(alpha);
+        background.0 = background.0.with_alpha(0.6 * alpha);
+    }
+}
+
+fn toggle_event_log(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    panels: Query<Entity, With<EventLogPanel>>,
+) {
+    if !key_bindings.just_pressed(InputAction::EventLog, &keyboard_input) {
+        return;
+    }
+    if let Ok(panel) = panels.get_single() {
+        commands.entity(panel).despawn_recursive();
+        return;
+    }
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(10.0),
+                    bottom: Val::Px(60.0),
+                    width: Val::Px(LOG_WIDTH),
+                    height: Val::Px(LOG_HEIGHT),
+                    padding: UiRect::all(Val::Px(6.0)),
+                    overflow: Overflow::clip(),
+                    ..default()
+                },
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.7).into(),
+                ..default()
+            },
+            Interaction::default(),
+            EventLogPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        width: Val::Percent(100.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                EventLogList::default(),
+            ));
+        });
+}
+
+// Fill the log with the recorded notifications, newest first
+fn update_event_log(
+    mut commands: Commands,
+    notifications: Res<Notifications>,
+    lists: Query<Entity, Added<EventLogList>>,
+    all_lists: Query<Entity, With<EventLogList>>,
+) {
+    let lists: Vec<Entity> = if notifications.is_changed() {
+        all_lists.iter().collect()
+    } else {
+        lists.iter().collect()
+    };
+
+    for list in lists {
+        commands.entity(list).despawn_descendants().with_children(|parent| {
+            if notifications.log.is_empty() {
+                parent.spawn(TextBundle::from_section(
+                    "Nothing has happened yet",
+                    TextStyle {
+                        font_size: 14.0,
+                        color: Color::srgb(0.6, 0.6, 0.6),
+                        ..default()
+                    },
+                ));
+            }
+            for entry in notifications.log.iter().rev() {
+                parent.spawn(
+                    TextBundle::from_section(
+                        format!("Day {:.0}: {}", entry.day.floor() + 1.0, entry.text()),
+                        TextStyle {
+                            font_size: 14.0,
+                            color: entry.severity.color(),
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        min_height: Val::Px(LOG_LINE_HEIGHT),
+                        ..default()
+                    }),
+                );
+            }
+        });
+    }
+}
+
+// Scroll the log with the mouse wheel while it's hovered
+fn scroll_event_log(
+    mut wheel_events: EventReader<MouseWheel>,
+    panels: Query<(&Interaction, &Node), With<EventLogPanel>>,
+    mut lists: Query<(&mut EventLogList, &mut Style, &Node)>,
+) {
+    let scrolled: f32 = wheel_events
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y * LOG_LINE_HEIGHT,
+            MouseScrollUnit::Pixel => event.y,
+        })
+        .sum();
+    let Ok((interaction, panel)) = panels.get_single() else {
+        return;
+    };
+    if scrolled == 0.0 || *interaction == Interaction::None {
+        return;
+    }
+
+    for (mut list, mut style, node) in lists.iter_mut() {
+        let max_offset = (node.size().y - panel.size().y + 12.0).max(0.0);
+        list.offset = (list.offset - scrolled).clamp(0.0, max_offset);
+        style.top = Val::Px(-list.offset);
+    }
+}
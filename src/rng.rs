@@ -0,0 +1,26 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+// Seeded random number generator for world generation and the simulation,
+// so a game can be reproduced from its seed
+#[derive(Resource)]
+pub struct GameRng {
+    pub seed: u64,
+    pub rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(rand::random())
+    }
+}
@@ -0,0 +1,512 @@
+use bevy::prelude::*;
+use bevy::utils::SystemTime;
+use crate::actions::{InputAction, KeyBindings};
+use crate::clock::TimeOfDay;
+use crate::game_over::reset_game;
+use crate::hud::format_thousands;
+use crate::island::Island;
+use crate::milestones::Milestones;
+use crate::rng::GameRng;
+use crate::simulation::{Economy, Loans, Population};
+use crate::town::{SelectedTown, Town, TownMap, TownSave, Towns};
+use crate::transit::TransitRoutes;
+use crate::GameState;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub struct SavePlugin;
+
+/// This plugin writes the game to save slots and shows the screen to load them from
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveSlot>()
+            .add_event::<SaveRequested>()
+            .add_systems(OnEnter(GameState::SaveSlots), setup_save_slots)
+            .add_systems(Update, click_slot_button.run_if(in_state(GameState::SaveSlots)))
+            .add_systems(OnExit(GameState::SaveSlots), cleanup_save_slots)
+            .add_systems(
+                Update,
+                (request_save, save_game.after(request_save)).run_if(
+                    in_state(GameState::IslandView)
+                        .or_else(in_state(GameState::TownView))
+                        .or_else(in_state(GameState::Paused)),
+                ),
+            )
+            .add_systems(Update, hide_save_message);
+    }
+}
+
+pub const SAVE_SLOT_COUNT: usize = 5;
+
+// Directory the slot files are written to, relative to the working directory
+const SAVE_DIRECTORY: &str = "saves";
+
+// Seconds the "saved" message stays on screen
+const SAVE_MESSAGE_SECONDS: f32 = 2.0;
+
+const BUTTON_COLOR: Color = Color::linear_rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED_COLOR: Color = Color::linear_rgb(0.25, 0.25, 0.25);
+
+// Slot the current game is saved to, picked when loading or on the first save
+#[derive(Resource, Default)]
+pub struct SaveSlot {
+    pub active: Option<usize>,
+}
+
+// Everything written to a save slot
+#[derive(Serialize, Deserialize)]
+pub struct SaveGame {
+    pub name: String,
+    // Seconds since the unix epoch
+    pub last_played: u64,
+    // Seed of the game's random number generator
+    #[serde(default)]
+    pub seed: u64,
+    pub island: Island,
+    // Layout of each town by its island position
+    #[serde(default)]
+    pub towns: Vec<(IVec2, TownSave)>,
+    pub population: Population,
+    pub economy: Economy,
+    pub loans: Loans,
+    pub happiness: f32,
+    pub time_of_day: TimeOfDay,
+    pub milestones_reached: Vec<String>,
+}
+
+fn slot_path(slot: usize) -> PathBuf {
+    PathBuf::from(SAVE_DIRECTORY).join(format!("slot_{}.json", slot + 1))
+}
+
+// Read a slot, `None` if nothing was saved to it yet
+pub fn read_slot(slot: usize) -> Result<Option<SaveGame>, String> {
+    read_save(&slot_path(slot))
+}
+
+pub fn write_slot(slot: usize, save: &SaveGame) -> Result<(), String> {
+    write_save(&slot_path(slot), save)
+}
+
+fn read_save(path: &Path) -> Result<Option<SaveGame>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.to_string()),
+    };
+    serde_json::from_str(&contents).map(Some).map_err(|error| error.to_string())
+}
+
+fn write_save(path: &Path, save: &SaveGame) -> Result<(), String> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+    }
+    let contents = serde_json::to_string_pretty(save).map_err(|error| error.to_string())?;
+    fs::write(path, contents).map_err(|error| error.to_string())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs())
+}
+
+// Rough time since a save was written, e.g. "5m ago"
+fn format_last_played(last_played: u64) -> String {
+    let seconds = now().saturating_sub(last_played);
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+// Message shown for a moment after saving (or failing to)
+#[derive(Component)]
+struct SaveMessage {
+    timer: Timer,
+}
+
+// Sent to write the game to its save slot
+#[derive(Event)]
+pub struct SaveRequested;
+
+// Save with F5
+fn request_save(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut save_requests: EventWriter<SaveRequested>,
+) {
+    if key_bindings.just_pressed(InputAction::Save, &keyboard_input) {
+        save_requests.send(SaveRequested);
+    }
+}
+
+// Write the game to the active slot when asked to, picking the first free slot if there is none yet
+#[allow(clippy::too_many_arguments)]
+fn save_game(
+    mut commands: Commands,
+    mut save_requests: EventReader<SaveRequested>,
+    mut save_slot: ResMut<SaveSlot>,
+    island: Option<Res<Island>>,
+    towns: Res<Towns>,
+    selected_town: Option<Res<SelectedTown>>,
+    town_map: Res<TownMap>,
+    transit_routes: Res<TransitRoutes>,
+    population: Res<Population>,
+    economy: Res<Economy>,
+    loans: Res<Loans>,
+    town: Res<Town>,
+    time_of_day: Res<TimeOfDay>,
+    milestones: Res<Milestones>,
+    game_rng: Res<GameRng>,
+) {
+    if save_requests.read().count() == 0 {
+        return;
+    }
+    let Some(island) = island else {
+        return;
+    };
+
+    let slot = save_slot
+        .active
+        .or_else(|| (0..SAVE_SLOT_COUNT).find(|&slot| matches!(read_slot(slot), Ok(None))));
+    let result = match slot {
+        Some(slot) => {
+            // The shown town's cells are newer than its stored layout
+            let mut towns = towns.towns.clone();
+            if let (Some(selected_town), true) = (selected_town, town_map.is_loaded()) {
+                let town_save = towns.entry(selected_town.0).or_default();
+                town_save.store_cells(town_map.iter());
+                town_save.routes = transit_routes.routes.clone();
+            }
+            let save = SaveGame {
+                name: island
+                    .towns
+                    .first()
+                    .and_then(|position| towns.get(position))
+                    .map_or("Unsettled island".to_string(), |town| town.name.clone()),
+                last_played: now(),
+                seed: game_rng.seed,
+                island: island.clone(),
+                towns: towns.into_iter().collect(),
+                population: population.clone(),
+                economy: economy.clone(),
+                loans: loans.clone(),
+                happiness: town.happiness,
+                time_of_day: time_of_day.clone(),
+                milestones_reached: milestones
+                    .milestones
+                    .iter()
+                    .filter(|milestone| milestone.reached)
+                    .map(|milestone| milestone.name.to_string())
+                    .collect(),
+            };
+            write_slot(slot, &save).map(|_| slot)
+        }
+        None => Err("all save slots are in use, load one to overwrite it".to_string()),
+    };
+
+    let message = match result {
+        Ok(slot) => {
+            save_slot.active = Some(slot);
+            format!("Saved to slot {}", slot + 1)
+        }
+        Err(error) => {
+            error!("Failed to save the game: {error}");
+            format!("Could not save: {error}")
+        }
+    };
+    commands.spawn((
+        TextBundle::from_section(
+            message,
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(60.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        SaveMessage {
+            timer: Timer::new(Duration::from_secs_f32(SAVE_MESSAGE_SECONDS), TimerMode::Once),
+        },
+    ));
+}
+
+fn hide_save_message(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut messages: Query<(Entity, &mut SaveMessage)>,
+) {
+    for (entity, mut message) in messages.iter_mut() {
+        if message.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+// Replace the current game with a saved one
+fn load_game(commands: &mut Commands, save: SaveGame) {
+    reset_game(commands);
+
+    let milestones = restore_milestones(&save.milestones_reached);
+
+    commands.insert_resource(save.island);
+    commands.insert_resource(Towns {
+        towns: save.towns.into_iter().collect(),
+    });
+    commands.insert_resource(save.population);
+    commands.insert_resource(save.economy);
+    commands.insert_resource(save.loans);
+    commands.insert_resource(Town {
+        happiness: save.happiness,
+        ..default()
+    });
+    commands.insert_resource(save.time_of_day);
+    commands.insert_resource(milestones);
+    commands.insert_resource(GameRng::new(save.seed));
+}
+
+// Milestones with the saved ones marked as reached, so their buildings stay unlocked
+fn restore_milestones(reached: &[String]) -> Milestones {
+    let mut milestones = Milestones::default();
+    for milestone in milestones.milestones.iter_mut() {
+        milestone.reached = reached.iter().any(|name| name == milestone.name);
+    }
+    milestones
+}
+
+#[derive(Component)]
+struct SaveSlotsScreen;
+
+#[derive(Component)]
+enum SlotButton {
+    Slot(usize),
+    Back,
+}
+
+// Shows why a slot couldn't be loaded
+#[derive(Component)]
+struct SlotError;
+
+fn setup_save_slots(mut commands: Commands) {
+    commands.spawn((Camera2dBundle::default(), SaveSlotsScreen));
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            },
+            SaveSlotsScreen,
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section(
+                "Load game",
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::linear_rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+
+            for slot in 0..SAVE_SLOT_COUNT {
+                let label = match read_slot(slot) {
+                    Ok(Some(save)) => format!(
+                        "{}. {} - population {} - {}",
+                        slot + 1,
+                        save.name,
+                        format_thousands(save.population.total),
+                        format_last_played(save.last_played),
+                    ),
+                    Ok(None) => format!("{}. Empty - start a new game", slot + 1),
+                    Err(_) => format!("{}. Unreadable save", slot + 1),
+                };
+                spawn_slot_button(children, label, SlotButton::Slot(slot));
+            }
+
+            children.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 18.0,
+                        color: Color::linear_rgb(0.9, 0.2, 0.2),
+                        ..default()
+                    },
+                ),
+                SlotError,
+            ));
+
+            spawn_slot_button(children, "Back".to_string(), SlotButton::Back);
+        });
+}
+
+fn spawn_slot_button(parent: &mut ChildBuilder, label: String, button: SlotButton) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(500.0),
+                    height: Val::Px(50.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::linear_rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn click_slot_button(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut save_slot: ResMut<SaveSlot>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &SlotButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut error_text: Query<&mut Text, With<SlotError>>,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match *button {
+                SlotButton::Slot(slot) => match read_slot(slot) {
+                    Ok(save) => {
+                        match save {
+                            Some(save) => load_game(&mut commands, save),
+                            None => reset_game(&mut commands),
+                        }
+                        save_slot.active = Some(slot);
+                        next_state.set(GameState::IslandView);
+                    }
+                    Err(error) => {
+                        warn!("Failed to load save slot {}: {error}", slot + 1);
+                        for mut text in error_text.iter_mut() {
+                            text.sections[0].value =
+                                format!("Slot {} could not be loaded: {error}", slot + 1);
+                        }
+                    }
+                },
+                SlotButton::Back => next_state.set(GameState::Menu),
+            },
+            Interaction::Hovered => *color = BUTTON_HOVERED_COLOR.into(),
+            Interaction::None => *color = BUTTON_COLOR.into(),
+        }
+    }
+}
+
+fn cleanup_save_slots(mut commands: Commands, screen: Query<Entity, With<SaveSlotsScreen>>) {
+    for entity in screen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::town::{BuildingType, CellSave, ZoneType};
+
+    // A file in its own directory under the system's temp directory, so tests don't share one
+    fn scratch_path(test: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("bevy_game_{}_{}", test, std::process::id()))
+            .join("slot_1.json")
+    }
+
+    // A town with a single road
+    fn town_with_road() -> TownSave {
+        TownSave {
+            cells: vec![CellSave {
+                position: IVec2::new(3, 3),
+                zone: ZoneType::None,
+                building: BuildingType::Road,
+                density: 0,
+            }],
+            ..default()
+        }
+    }
+
+    fn save_game(towns: Vec<(IVec2, TownSave)>) -> SaveGame {
+        SaveGame {
+            name: "Testville".to_string(),
+            last_played: 1_700_000_000,
+            seed: 42,
+            island: Island::generate(42, 32),
+            towns,
+            population: Population::default(),
+            economy: Economy::default(),
+            loans: Loans::default(),
+            happiness: 0.75,
+            time_of_day: TimeOfDay::default(),
+            milestones_reached: vec!["Village".to_string()],
+        }
+    }
+
+    #[test]
+    fn save_round_trips_through_a_file() {
+        let path = scratch_path("round_trip");
+        write_save(&path, &save_game(vec![(IVec2::new(8, 9), town_with_road())])).unwrap();
+        let loaded = read_save(&path).unwrap().expect("the save was just written");
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+
+        assert_eq!(loaded.name, "Testville");
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.happiness, 0.75);
+        assert_eq!(loaded.milestones_reached, vec!["Village".to_string()]);
+        assert_eq!(loaded.island.size(), 32);
+        let (position, town) = &loaded.towns[0];
+        assert_eq!(*position, IVec2::new(8, 9));
+        assert_eq!(town.cells.len(), 1);
+        assert_eq!(town.cells[0].position, IVec2::new(3, 3));
+        assert_eq!(town.cells[0].building, BuildingType::Road);
+
+        let milestones = restore_milestones(&loaded.milestones_reached);
+        let village = milestones.milestones.iter().find(|milestone| milestone.name == "Village").unwrap();
+        assert!(village.reached);
+        assert!(milestones.is_unlocked(BuildingType::Park));
+        assert!(!milestones.is_unlocked(BuildingType::Fire));
+    }
+
+    #[test]
+    fn empty_slot_reads_as_nothing_saved() {
+        let path = scratch_path("empty");
+        assert!(read_save(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn corrupt_slot_is_an_error() {
+        let path = scratch_path("corrupt");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "{ \"name\": \"Testville\", \"last_pl").unwrap();
+        let read = read_save(&path);
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+
+        assert!(read.is_err());
+    }
+}
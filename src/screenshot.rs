@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::utils::SystemTime;
+use bevy::window::PrimaryWindow;
+use crate::actions::{InputAction, KeyBindings};
+use crate::clock::TimeOfDay;
+use crate::export::town_file_stem;
+use crate::notifications::Notification;
+use crate::town::{get_cell_color, SelectedTown, TownMap, Towns, TOWN_GRID_SIZE};
+use crate::GameState;
+use image::{Rgba, RgbaImage};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub struct ScreenshotPlugin;
+
+/// This plugin saves screenshots of the island and town views, and top-down images of the town, to PNG files
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenshotResults>()
+            .init_resource::<HiddenUi>()
+            .add_systems(
+                Update,
+                take_screenshot.run_if(in_state(GameState::IslandView).or_else(in_state(GameState::TownView))),
+            )
+            .add_systems(Update, export_town_image.run_if(in_state(GameState::TownView)))
+            .add_systems(Update, (report_screenshots, hide_screenshot_message));
+    }
+}
+
+// Directory the screenshots are written to, relative to the working directory
+const SCREENSHOT_DIRECTORY: &str = "screenshots";
+
+// Pixels along each side of a cell in exported town images
+const TOWN_IMAGE_CELL_PIXELS: u32 = 8;
+
+// Pixels left between cells, as the town view leaves a gap between its tiles
+const TOWN_IMAGE_GAP_PIXELS: u32 = 1;
+
+// Seconds the "screenshot saved" message stays on screen
+const SCREENSHOT_MESSAGE_SECONDS: f32 = 2.0;
+
+// Outcome of each screenshot, filled in by the renderer once the frame is captured
+#[derive(Resource, Default)]
+struct ScreenshotResults(Arc<Mutex<Vec<Result<PathBuf, String>>>>);
+
+// UI hidden for a clean shot, shown again once the screenshot is taken
+#[derive(Resource, Default)]
+struct HiddenUi {
+    roots: Vec<Entity>,
+}
+
+#[derive(Component)]
+struct ScreenshotMessage {
+    timer: Timer,
+}
+
+fn screenshot_path() -> PathBuf {
+    let since_epoch = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    PathBuf::from(SCREENSHOT_DIRECTORY).join(format!(
+        "screenshot_{}_{:03}.png",
+        since_epoch.as_secs(),
+        since_epoch.subsec_millis()
+    ))
+}
+
+// File for an exported town image, named after the town
+fn town_image_path(name: &str) -> PathBuf {
+    PathBuf::from(SCREENSHOT_DIRECTORY).join(format!("{}.png", town_file_stem(name)))
+}
+
+// Draw the town's cells top-down in their own colors, whatever the camera and overlay show
+fn render_town_image(town_map: &TownMap, time_of_day: &TimeOfDay, cell_pixels: u32) -> RgbaImage {
+    let size = TOWN_GRID_SIZE as u32 * cell_pixels;
+    let gap = TOWN_IMAGE_GAP_PIXELS.min(cell_pixels - 1);
+    let mut image = RgbaImage::from_pixel(size, size, Rgba([0, 0, 0, 255]));
+    for cell in town_map.iter() {
+        let color = Rgba(get_cell_color(cell, None, time_of_day.season()).to_srgba().to_u8_array());
+        // Image rows go top to bottom while grid rows go bottom to top
+        let left = cell.position.x as u32 * cell_pixels;
+        let top = (TOWN_GRID_SIZE as u32 - 1 - cell.position.y as u32) * cell_pixels;
+        for y in top..top + cell_pixels - gap {
+            for x in left..left + cell_pixels - gap {
+                image.put_pixel(x, y, color);
+            }
+        }
+    }
+    image
+}
+
+fn save_image(image: Image, path: &Path) -> Result<(), String> {
+    fs::create_dir_all(SCREENSHOT_DIRECTORY).map_err(|error| error.to_string())?;
+    let image = image.try_into_dynamic().map_err(|error| error.to_string())?;
+    // The alpha channel holds brightness with HDR, the picture looks right without it
+    image.to_rgb8().save(path).map_err(|error| error.to_string())
+}
+
+// Capture the window when the screenshot key is pressed, without the UI while the clean shot
+// modifier is held
+fn take_screenshot(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    window: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+    results: Res<ScreenshotResults>,
+    mut ui_roots: Query<(Entity, &mut Visibility), (With<Node>, Without<Parent>)>,
+    mut hidden_ui: ResMut<HiddenUi>,
+) {
+    if !key_bindings.just_pressed(InputAction::Screenshot, &keyboard_input) {
+        return;
+    }
+    let Ok(window) = window.get_single() else {
+        return;
+    };
+
+    let path = screenshot_path();
+    let results = results.0.clone();
+    let requested = screenshot_manager.take_screenshot(window, move |image| {
+        let result = save_image(image, &path).map(|_| path);
+        results.lock().unwrap().push(result);
+    });
+    if requested.is_err() {
+        // The previous screenshot is still being taken
+        return;
+    }
+
+    if key_bindings.pressed(InputAction::CleanScreenshot, &keyboard_input) {
+        for (entity, mut visibility) in ui_roots.iter_mut() {
+            if *visibility != Visibility::Hidden {
+                *visibility = Visibility::Hidden;
+                hidden_ui.roots.push(entity);
+            }
+        }
+    }
+}
+
+// Write a top-down image of the shown town
+fn export_town_image(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    town_map: Res<TownMap>,
+    time_of_day: Res<TimeOfDay>,
+    towns: Res<Towns>,
+    selected_town: Option<Res<SelectedTown>>,
+    mut notifications: EventWriter<Notification>,
+) {
+    if !key_bindings.just_pressed(InputAction::ExportTownImage, &keyboard_input) {
+        return;
+    }
+
+    let name = selected_town
+        .and_then(|selected_town| towns.towns.get(&selected_town.0))
+        .map_or("", |town| town.name.as_str());
+    let path = town_image_path(name);
+    let image = render_town_image(&town_map, &time_of_day, TOWN_IMAGE_CELL_PIXELS);
+    let result = fs::create_dir_all(SCREENSHOT_DIRECTORY)
+        .map_err(|error| error.to_string())
+        .and_then(|_| image.save(&path).map_err(|error| error.to_string()));
+    match result {
+        Ok(()) => notifications.send(Notification::info(format!("Town image saved to {}", path.display()))),
+        Err(error) => {
+            error!("Failed to export the town image: {error}");
+            notifications.send(Notification::warning(format!("Could not save the town image: {error}")))
+        }
+    };
+}
+
+// Bring back the UI after a clean shot and tell the player where the screenshot went
+fn report_screenshots(
+    mut commands: Commands,
+    results: Res<ScreenshotResults>,
+    mut hidden_ui: ResMut<HiddenUi>,
+    mut visibilities: Query<&mut Visibility>,
+) {
+    let finished: Vec<Result<PathBuf, String>> = results.0.lock().unwrap().drain(..).collect();
+    if finished.is_empty() {
+        return;
+    }
+
+    for entity in hidden_ui.roots.drain(..) {
+        if let Ok(mut visibility) = visibilities.get_mut(entity) {
+            *visibility = Visibility::Inherited;
+        }
+    }
+
+    for result in finished {
+        let message = match result {
+            Ok(path) => format!("Screenshot saved to {}", path.display()),
+            Err(error) => {
+                error!("Failed to save a screenshot: {error}");
+                format!("Could not save the screenshot: {error}")
+            }
+        };
+        commands.spawn((
+            TextBundle::from_section(
+                message,
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(90.0),
+                left: Val::Px(10.0),
+                ..default()
+            }),
+            ScreenshotMessage {
+                timer: Timer::new(Duration::from_secs_f32(SCREENSHOT_MESSAGE_SECONDS), TimerMode::Once),
+            },
+        ));
+    }
+}
+
+fn hide_screenshot_message(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut messages: Query<(Entity, &mut ScreenshotMessage)>,
+) {
+    for (entity, mut message) in messages.iter_mut() {
+        if message.timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,600 @@
+use bevy::prelude::*;
+use crate::actions::{key_name, InputAction, KeyBindings};
+use crate::audio::BuildSound;
+use crate::clock::{Season, TimeOfDay};
+use crate::grid::Grid;
+use crate::menu::outside_pause;
+use crate::notifications::Notification;
+use crate::simulation::Economy;
+use crate::town::{
+    cursor_grid_position, get_cell_color, grid_to_world, BuildingType, CellCommand, CellSave, Construction,
+    PlaceError, TownCell, TownMap, ZoneType, TOWN_CELL_SPACING, TOWN_GRID_SIZE,
+};
+use crate::GameState;
+
+pub struct SelectionPlugin;
+
+/// This plugin lets the player drag a rectangle over the town and zone, bulldoze or copy
+/// the cells in it at once, and paste copied cells elsewhere
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Selection>()
+            .init_resource::<Clipboard>()
+            .add_systems(
+                Update,
+                (
+                    toggle_select_tool,
+                    toggle_paste,
+                    paste_clipboard.after(toggle_paste),
+                    // Clicks stamping a paste don't start a selection
+                    drag_selection.after(toggle_select_tool).after(paste_clipboard),
+                    apply_selection_action.after(drag_selection),
+                    copy_with_keys,
+                    update_selection_count,
+                    draw_selection,
+                ).run_if(in_state(GameState::TownView)),
+            )
+            .add_systems(
+                OnExit(GameState::TownView),
+                (close_select_tool, despawn_paste_ghost).run_if(outside_pause),
+            );
+    }
+}
+
+const SELECTION_COLOR: Color = Color::srgb(0.3, 0.8, 1.0);
+
+// Rectangle of cells dragged out with the select tool, from the cell the drag started on to
+// the one under the cursor
+#[derive(Resource, Default)]
+pub(crate) struct Selection {
+    active: bool,
+    dragging: bool,
+    start: Option<IVec2>,
+    end: Option<IVec2>,
+}
+
+impl Selection {
+    // Lowest and highest corners of the selected rectangle
+    fn bounds(&self) -> Option<(IVec2, IVec2)> {
+        let (start, end) = (self.start?, self.end?);
+        Some((start.min(end), start.max(end)))
+    }
+
+    fn cells(&self) -> impl Iterator<Item = IVec2> {
+        let (min, max) = self.bounds().unwrap_or((IVec2::ONE, IVec2::ZERO));
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+    }
+
+    fn count(&self) -> usize {
+        self.bounds()
+            .map_or(0, |(min, max)| ((max.x - min.x + 1) * (max.y - min.y + 1)) as usize)
+    }
+}
+
+// Cells copied from a selection, positioned from the selection's lowest corner
+#[derive(Resource, Default)]
+pub struct Clipboard {
+    pub cells: Vec<CellSave>,
+}
+
+// Preview of the copied cells following the cursor, its lowest corner on the cell under it.
+// Pasting lasts until the cancel key so a pattern can be stamped again and again
+#[derive(Component)]
+pub(crate) struct PasteGhost;
+
+// Copied cell in the preview, positioned from the preview's corner
+#[derive(Component)]
+pub(crate) struct GhostCell(IVec2);
+
+const GHOST_ALPHA: f32 = 0.5;
+
+// Toolbar button turning the select tool on and off
+#[derive(Component)]
+struct SelectButton;
+
+// Panel with the selection's size and what can be done with it
+#[derive(Component)]
+struct SelectionPanel;
+
+#[derive(Component)]
+struct SelectionCount;
+
+#[derive(Component, Clone, Copy)]
+enum SelectionAction {
+    Zone(ZoneType),
+    Bulldoze,
+    Copy,
+}
+
+impl SelectionAction {
+    // e.g. "Zone R" for residential
+    fn label(&self) -> String {
+        match self {
+            SelectionAction::Zone(zone_type) => format!("Zone {}", &zone_type.label()[..1]),
+            SelectionAction::Bulldoze => "Bulldoze".to_string(),
+            SelectionAction::Copy => "Copy".to_string(),
+        }
+    }
+}
+
+const SELECTION_ACTIONS: [SelectionAction; 5] = [
+    SelectionAction::Zone(ZoneType::Residential),
+    SelectionAction::Zone(ZoneType::Commercial),
+    SelectionAction::Zone(ZoneType::Industrial),
+    SelectionAction::Bulldoze,
+    SelectionAction::Copy,
+];
+
+// Spawn the toolbar button for the select tool
+pub fn spawn_select_button(parent: &mut ChildBuilder) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(80.0),
+                    height: Val::Px(40.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::srgb(0.2, 0.4, 0.5).into(),
+                ..default()
+            },
+            SelectButton,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Select",
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+// Turn the select tool on with the toolbar button, and off with the button again or the cancel key
+fn toggle_select_tool(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<SelectButton>)>,
+    mut selection: ResMut<Selection>,
+    panels: Query<Entity, With<SelectionPanel>>,
+) {
+    let clicked = buttons.iter().any(|interaction| *interaction == Interaction::Pressed);
+    let cancel = key_bindings.just_pressed(InputAction::Cancel, &keyboard_input);
+
+    if !selection.active {
+        if clicked {
+            selection.active = true;
+            spawn_selection_panel(&mut commands);
+        }
+        return;
+    }
+    if !clicked && !cancel {
+        return;
+    }
+    *selection = Selection::default();
+    for entity in panels.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn spawn_selection_panel(commands: &mut Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(60.0),
+                    left: Val::Px(110.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(4.0),
+                    padding: UiRect::all(Val::Px(6.0)),
+                    ..default()
+                },
+                background_color: Color::srgba(0.1, 0.1, 0.1, 0.7).into(),
+                ..default()
+            },
+            Interaction::default(),
+            SelectionPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 16.0,
+                        color: SELECTION_COLOR,
+                        ..default()
+                    },
+                ),
+                SelectionCount,
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for action in SELECTION_ACTIONS {
+                        parent
+                            .spawn((
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(70.0),
+                                        height: Val::Px(30.0),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    background_color: Color::srgb(0.3, 0.3, 0.3).into(),
+                                    ..default()
+                                },
+                                action,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    action.label(),
+                                    TextStyle {
+                                        font_size: 14.0,
+                                        color: Color::WHITE,
+                                        ..default()
+                                    },
+                                ));
+                            });
+                    }
+                });
+        });
+}
+
+// Drag out the rectangle while the select tool is on. The drag has to start on the grid, past
+// its edge the rectangle stops at the last row or column. Clicks are swallowed so they don't
+// also build on the town
+pub(crate) fn drag_selection(
+    mut mouse_button_input: ResMut<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    ui_interactions: Query<&Interaction, With<Node>>,
+    mut selection: ResMut<Selection>,
+) {
+    if !selection.active {
+        return;
+    }
+    if mouse_button_input.just_released(MouseButton::Left) {
+        selection.dragging = false;
+    }
+    let pressed = mouse_button_input.just_pressed(MouseButton::Left);
+    if pressed && ui_interactions.iter().any(|interaction| *interaction != Interaction::None) {
+        return;
+    }
+    if pressed {
+        mouse_button_input.clear_just_pressed(MouseButton::Left);
+    }
+    if !pressed && !selection.dragging {
+        return;
+    }
+
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), camera_q.get_single()) else {
+        return;
+    };
+    let Some(world_position) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+    else {
+        return;
+    };
+    let grid = (world_position / TOWN_CELL_SPACING + Vec2::splat(TOWN_GRID_SIZE as f32 / 2.0))
+        .round()
+        .as_ivec2();
+    let clamped = grid.clamp(IVec2::ZERO, IVec2::splat(TOWN_GRID_SIZE as i32 - 1));
+    if pressed {
+        if clamped != grid {
+            return;
+        }
+        selection.dragging = true;
+        selection.start = Some(clamped);
+    }
+    selection.end = Some(clamped);
+}
+
+// Carry out the chosen action on every selected cell
+fn apply_selection_action(
+    buttons: Query<(&Interaction, &SelectionAction), Changed<Interaction>>,
+    selection: Res<Selection>,
+    town_map: Res<TownMap>,
+    mut clipboard: ResMut<Clipboard>,
+    mut cell_commands: EventWriter<CellCommand>,
+    mut notifications: EventWriter<Notification>,
+) {
+    for (interaction, action) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some((min, _)) = selection.bounds() else {
+            notifications.send(Notification::info("Drag over the town to select cells first"));
+            continue;
+        };
+        match *action {
+            SelectionAction::Zone(zone_type) => {
+                cell_commands.send_batch(selection.cells().map(|position| CellCommand::Zone(position, zone_type)));
+            }
+            SelectionAction::Bulldoze => {
+                // Empty cells have nothing to demolish
+                let occupied = selection.cells().filter(|&position| {
+                    town_map
+                        .get(position)
+                        .is_some_and(|cell| cell.building != BuildingType::None || cell.zone != ZoneType::None)
+                });
+                cell_commands.send_batch(occupied.map(CellCommand::Demolish));
+            }
+            SelectionAction::Copy => {
+                clipboard.cells = copy_cells(&town_map, selection.cells(), min);
+                notifications.send(Notification::info(format!("Copied {} cells", clipboard.cells.len())));
+            }
+        }
+    }
+}
+
+fn copy_cells(town_map: &TownMap, cells: impl Iterator<Item = IVec2>, corner: IVec2) -> Vec<CellSave> {
+    cells
+        .filter_map(|position| town_map.get(position))
+        .map(|cell| CellSave {
+            position: cell.position - corner,
+            zone: cell.zone,
+            building: cell.building,
+            density: cell.density,
+        })
+        .collect()
+}
+
+fn ctrl_pressed(keyboard_input: &ButtonInput<KeyCode>) -> bool {
+    keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+}
+
+// Copy the selection with Ctrl+C
+fn copy_with_keys(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    selection: Res<Selection>,
+    town_map: Res<TownMap>,
+    mut clipboard: ResMut<Clipboard>,
+    mut notifications: EventWriter<Notification>,
+) {
+    if !ctrl_pressed(&keyboard_input) || !key_bindings.just_pressed(InputAction::CopySelection, &keyboard_input) {
+        return;
+    }
+    let Some((min, _)) = selection.bounds() else {
+        return;
+    };
+    clipboard.cells = copy_cells(&town_map, selection.cells(), min);
+    notifications.send(Notification::info(format!("Copied {} cells", clipboard.cells.len())));
+}
+
+// Start pasting the copied cells with Ctrl+V, stop with the cancel key
+fn toggle_paste(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    clipboard: Res<Clipboard>,
+    time_of_day: Res<TimeOfDay>,
+    ghosts: Query<Entity, With<PasteGhost>>,
+    mut notifications: EventWriter<Notification>,
+) {
+    if key_bindings.just_pressed(InputAction::Cancel, &keyboard_input) {
+        for entity in ghosts.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    if !ctrl_pressed(&keyboard_input) || !key_bindings.just_pressed(InputAction::Paste, &keyboard_input) {
+        return;
+    }
+    if clipboard.cells.is_empty() {
+        notifications.send(Notification::info("Nothing to paste, copy a selection with Ctrl+C first"));
+        return;
+    }
+    for entity in ghosts.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_paste_ghost(&mut commands, &clipboard.cells, time_of_day.season());
+}
+
+// Start pasting the given cells, any paste already going on should be despawned first
+pub(crate) fn spawn_paste_ghost(commands: &mut Commands, cells: &[CellSave], season: Season) {
+    commands
+        .spawn((SpatialBundle::HIDDEN_IDENTITY, PasteGhost))
+        .with_children(|parent| {
+            for copied in cells.iter() {
+                let cell = TownCell {
+                    position: copied.position,
+                    zone: copied.zone,
+                    building: copied.building,
+                    density: copied.density,
+                    accessible: true,
+                    elevation: 0.0,
+                    slope: 0.0,
+                    water: false,
+                };
+                let color = get_cell_color(&cell, None, season).with_alpha(GHOST_ALPHA);
+                parent.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color,
+                            custom_size: Some(Vec2::splat(TOWN_CELL_SPACING)),
+                            ..default()
+                        },
+                        transform: Transform::from_translation((copied.position.as_vec2() * TOWN_CELL_SPACING).extend(0.0)),
+                        ..default()
+                    },
+                    GhostCell(copied.position),
+                ));
+            }
+        });
+}
+
+// Move the preview to the cursor and stamp the copied cells there on a click. Cells falling off
+// the grid are left out, the rest follow the building tools' rules and the whole paste is
+// refused when the town can't afford it. Clicks are swallowed so they don't also build on the town
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn paste_clipboard(
+    mut mouse_button_input: ResMut<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    ui_interactions: Query<&Interaction, With<Node>>,
+    mut ghosts: Query<(&mut Transform, &mut Visibility), With<PasteGhost>>,
+    mut ghost_cells: Query<(&GhostCell, &mut Visibility), Without<PasteGhost>>,
+    clipboard: Res<Clipboard>,
+    economy: Res<Economy>,
+    town_map: Res<TownMap>,
+    construction: Res<Construction>,
+    mut cell_commands: EventWriter<CellCommand>,
+    mut sounds: EventWriter<BuildSound>,
+    mut notifications: EventWriter<Notification>,
+) {
+    let Ok((mut transform, mut visibility)) = ghosts.get_single_mut() else {
+        return;
+    };
+    let over_ui = ui_interactions.iter().any(|interaction| *interaction != Interaction::None);
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), camera_q.get_single()) else {
+        return;
+    };
+    let Some(corner) = cursor_grid_position(window, camera, camera_transform).filter(|_| !over_ui) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    transform.translation = grid_to_world(corner, 2.5);
+    *visibility = Visibility::Visible;
+    for (ghost_cell, mut visibility) in ghost_cells.iter_mut() {
+        *visibility = if Grid::is_in_bounds(corner + ghost_cell.0, TOWN_GRID_SIZE) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+
+    if !mouse_button_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    mouse_button_input.clear_just_pressed(MouseButton::Left);
+
+    let pasted: Vec<CellSave> = clipboard
+        .cells
+        .iter()
+        .map(|copied| CellSave {
+            position: corner + copied.position,
+            ..*copied
+        })
+        .filter(|copied| Grid::is_in_bounds(copied.position, TOWN_GRID_SIZE))
+        .collect();
+    let cost = paste_cost(&pasted, &town_map, &construction);
+    if cost > economy.funds {
+        notifications.send(Notification::warning(PlaceError::InsufficientFunds(cost).to_string()));
+        sounds.send(BuildSound::Refused);
+        return;
+    }
+    for copied in pasted {
+        if copied.building != BuildingType::None {
+            cell_commands.send(CellCommand::Build(copied.position, copied.building));
+        }
+        if copied.zone != ZoneType::None {
+            cell_commands.send(CellCommand::Zone(copied.position, copied.zone));
+        }
+    }
+}
+
+// What stamping the cells costs, priced like the building tools on the ground below them and less
+// the refund for what they're built over. Cells already holding the building cost nothing
+fn paste_cost(pasted: &[CellSave], town_map: &TownMap, construction: &Construction) -> i32 {
+    pasted
+        .iter()
+        .filter(|copied| copied.building != BuildingType::None)
+        .filter_map(|copied| {
+            let cell = town_map.get(copied.position)?;
+            let current = construction.building_at(cell.position).unwrap_or(cell.building);
+            (current != copied.building).then(|| cell.build_cost(copied.building) - current.refund())
+        })
+        .sum()
+}
+
+fn despawn_paste_ghost(mut commands: Commands, ghosts: Query<Entity, With<PasteGhost>>) {
+    for entity in ghosts.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn update_selection_count(
+    selection: Res<Selection>,
+    key_bindings: Res<KeyBindings>,
+    mut texts: Query<&mut Text, With<SelectionCount>>,
+) {
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = match selection.bounds() {
+            Some((min, max)) => format!(
+                "{} cells selected ({} x {}), Ctrl+C to copy",
+                selection.count(),
+                max.x - min.x + 1,
+                max.y - min.y + 1
+            ),
+            None => format!(
+                "Drag over the town to select cells, {} to stop",
+                key_name(key_bindings.key(InputAction::Cancel))
+            ),
+        };
+    }
+}
+
+// Outline the selected rectangle, around the outer edges of its cells
+fn draw_selection(mut gizmos: Gizmos, selection: Res<Selection>) {
+    let Some((min, max)) = selection.bounds() else {
+        return;
+    };
+    let to_world = |pos: Vec2| (pos - Vec2::splat(TOWN_GRID_SIZE as f32 / 2.0)) * TOWN_CELL_SPACING;
+    let low = to_world(min.as_vec2() - Vec2::splat(0.5));
+    let high = to_world(max.as_vec2() + Vec2::splat(0.5));
+    gizmos.rect_2d((low + high) / 2.0, 0.0, high - low, SELECTION_COLOR);
+}
+
+fn close_select_tool(mut selection: ResMut<Selection>) {
+    *selection = Selection::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn road_at(x: i32, y: i32) -> CellSave {
+        CellSave {
+            position: IVec2::new(x, y),
+            zone: ZoneType::None,
+            building: BuildingType::Road,
+            density: 0,
+        }
+    }
+
+    #[test]
+    fn paste_is_priced_on_the_target_cells() {
+        let mut town_map = TownMap::default();
+        let construction = Construction::default();
+        let flat = IVec2::new(1, 1);
+        let steep = IVec2::new(2, 1);
+        let built = IVec2::new(3, 1);
+        let paved = IVec2::new(4, 1);
+        town_map.cell_mut(steep).unwrap().slope = 1.0;
+        town_map.cell_mut(built).unwrap().building = BuildingType::Park;
+        town_map.cell_mut(paved).unwrap().building = BuildingType::Road;
+
+        let cost = |cell: CellSave| paste_cost(&[cell], &town_map, &construction);
+        assert_eq!(cost(road_at(flat.x, flat.y)), 10);
+        // Steep ground doubles the price
+        assert_eq!(cost(road_at(steep.x, steep.y)), 20);
+        // The park built over is refunded half of its price
+        assert_eq!(cost(road_at(built.x, built.y)), 10 - 75);
+        assert_eq!(cost(road_at(paved.x, paved.y)), 0);
+    }
+}
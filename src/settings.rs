@@ -0,0 +1,417 @@
+use bevy::prelude::*;
+use crate::actions::{key_name, InputAction, KeyBindings};
+use crate::audio::AudioSettings;
+use crate::disasters::DisasterFrequency;
+use crate::menu::PausedFrom;
+use crate::widgets::{spawn_slider, Slider};
+use crate::GameState;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+pub struct SettingsPlugin;
+
+/// This plugin shows the settings screen, with the volumes and key bindings, and keeps the settings
+/// in a file between runs
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = read_settings().unwrap_or_else(|error| {
+            warn!("Failed to read the settings, using the defaults: {error}");
+            Settings::default()
+        });
+        app.insert_resource(settings.audio)
+            .insert_resource(settings.key_bindings)
+            .insert_resource(settings.disasters)
+            .init_resource::<Rebinding>()
+            .add_systems(OnEnter(GameState::Settings), setup_settings)
+            .add_systems(
+                Update,
+                (
+                    click_settings_button,
+                    // A click starting a rebind isn't a key press, so this sees the key after it
+                    capture_rebind_key.after(click_settings_button),
+                    update_binding_labels.after(capture_rebind_key),
+                    apply_volume_sliders,
+                    update_mute_label,
+                    update_disasters_label,
+                )
+                    .run_if(in_state(GameState::Settings)),
+            )
+            .add_systems(OnExit(GameState::Settings), (cleanup_settings, save_settings));
+    }
+}
+
+// File the settings are written to, relative to the working directory
+const SETTINGS_PATH: &str = "settings.json";
+
+const BUTTON_COLOR: Color = Color::linear_rgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED_COLOR: Color = Color::linear_rgb(0.25, 0.25, 0.25);
+
+// Everything written to the settings file
+#[derive(Default, Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub audio: AudioSettings,
+    #[serde(default)]
+    pub key_bindings: KeyBindings,
+    #[serde(default)]
+    pub disasters: DisasterFrequency,
+}
+
+// Read the settings file, the defaults if it wasn't written yet
+pub fn read_settings() -> Result<Settings, String> {
+    let contents = match fs::read_to_string(SETTINGS_PATH) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Settings::default()),
+        Err(error) => return Err(error.to_string()),
+    };
+    serde_json::from_str(&contents).map_err(|error| error.to_string())
+}
+
+pub fn write_settings(settings: &Settings) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(settings).map_err(|error| error.to_string())?;
+    fs::write(SETTINGS_PATH, contents).map_err(|error| error.to_string())
+}
+
+#[derive(Component)]
+struct SettingsScreen;
+
+// Volume a slider controls
+#[derive(Component, Clone, Copy)]
+enum VolumeSlider {
+    Master,
+    Music,
+    Sfx,
+}
+
+#[derive(Component)]
+enum SettingsButton {
+    Mute,
+    Disasters,
+    Rebind(InputAction),
+    Back,
+}
+
+#[derive(Component)]
+struct MuteLabel;
+
+#[derive(Component)]
+struct DisastersLabel;
+
+// Text of a key binding button
+#[derive(Component)]
+struct BindingLabel(InputAction);
+
+// Shows why a key couldn't be bound
+#[derive(Component)]
+struct RebindMessage;
+
+// Action waiting for the next key press to be bound to it
+#[derive(Resource, Default)]
+struct Rebinding {
+    action: Option<InputAction>,
+}
+
+fn mute_label(muted: bool) -> &'static str {
+    if muted {
+        "Sound: off"
+    } else {
+        "Sound: on"
+    }
+}
+
+fn disasters_label(frequency: DisasterFrequency) -> String {
+    format!("Disasters: {}", frequency.label())
+}
+
+fn binding_label(action: InputAction, key_bindings: &KeyBindings, rebinding: &Rebinding) -> String {
+    if rebinding.action == Some(action) {
+        format!("{}: press a key", action.label())
+    } else {
+        format!("{}: {}", action.label(), key_name(key_bindings.key(action)))
+    }
+}
+
+fn setup_settings(
+    mut commands: Commands,
+    audio_settings: Res<AudioSettings>,
+    key_bindings: Res<KeyBindings>,
+    disasters: Res<DisasterFrequency>,
+    paused_from: Res<PausedFrom>,
+) {
+    let text_style = |font_size: f32| TextStyle {
+        font_size,
+        color: Color::linear_rgb(0.9, 0.9, 0.9),
+        ..default()
+    };
+    let column = || NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(6.0),
+            ..default()
+        },
+        ..default()
+    };
+
+    // Opened from the pause menu, the paused view's camera is still there and its world is dimmed
+    let paused = paused_from.0.is_some();
+    if !paused {
+        commands.spawn((Camera2dBundle::default(), SettingsScreen));
+    }
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                background_color: if paused { Color::srgba(0.0, 0.0, 0.0, 0.8) } else { Color::NONE }.into(),
+                z_index: ZIndex::Global(20),
+                ..default()
+            },
+            SettingsScreen,
+        ))
+        .with_children(|children| {
+            children.spawn(TextBundle::from_section("Settings", text_style(40.0)));
+
+            children
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(40.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|columns| {
+                    // Audio
+                    columns.spawn(column()).with_children(|audio| {
+                        for (label, slider, value) in [
+                            ("Master volume", VolumeSlider::Master, audio_settings.master),
+                            ("Music volume", VolumeSlider::Music, audio_settings.music),
+                            ("Effects volume", VolumeSlider::Sfx, audio_settings.sfx),
+                        ] {
+                            audio
+                                .spawn(NodeBundle {
+                                    style: Style {
+                                        align_items: AlignItems::Center,
+                                        column_gap: Val::Px(10.0),
+                                        ..default()
+                                    },
+                                    ..default()
+                                })
+                                .with_children(|row| {
+                                    row.spawn(
+                                        TextBundle::from_section(label, text_style(20.0)).with_style(Style {
+                                            width: Val::Px(160.0),
+                                            ..default()
+                                        }),
+                                    );
+                                    spawn_slider(row, value, slider);
+                                });
+                        }
+                        spawn_settings_button(
+                            audio,
+                            mute_label(audio_settings.muted),
+                            SettingsButton::Mute,
+                            MuteLabel,
+                        );
+                        spawn_settings_button(
+                            audio,
+                            &disasters_label(*disasters),
+                            SettingsButton::Disasters,
+                            DisastersLabel,
+                        );
+                    });
+
+                    // Key bindings, click one and press the new key
+                    columns.spawn(column()).with_children(|bindings| {
+                        for action in InputAction::ALL {
+                            spawn_settings_button(
+                                bindings,
+                                &binding_label(action, &key_bindings, &Rebinding::default()),
+                                SettingsButton::Rebind(action),
+                                BindingLabel(action),
+                            );
+                        }
+                    });
+                });
+
+            children.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 18.0,
+                        color: Color::linear_rgb(0.9, 0.2, 0.2),
+                        ..default()
+                    },
+                ),
+                RebindMessage,
+            ));
+
+            spawn_settings_button(children, "Back", SettingsButton::Back, ());
+        });
+}
+
+// Spawn a settings button, `label` goes on its text
+fn spawn_settings_button(parent: &mut ChildBuilder, text: &str, button: SettingsButton, label: impl Bundle) {
+    let (height, font_size) = match button {
+        SettingsButton::Rebind(_) => (22.0, 15.0),
+        _ => (50.0, 24.0),
+    };
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(260.0),
+                    height: Val::Px(height),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        font_size,
+                        color: Color::linear_rgb(0.9, 0.9, 0.9),
+                        ..default()
+                    },
+                ),
+                label,
+            ));
+        });
+}
+
+fn click_settings_button(
+    mut next_state: ResMut<NextState<GameState>>,
+    paused_from: Res<PausedFrom>,
+    mut audio_settings: ResMut<AudioSettings>,
+    mut disasters: ResMut<DisasterFrequency>,
+    mut rebinding: ResMut<Rebinding>,
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &SettingsButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color, button) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => match *button {
+                SettingsButton::Mute => audio_settings.muted = !audio_settings.muted,
+                SettingsButton::Disasters => *disasters = disasters.next(),
+                // Clicking the waiting binding again leaves it as it was
+                SettingsButton::Rebind(action) => {
+                    rebinding.action = if rebinding.action == Some(action) {
+                        None
+                    } else {
+                        Some(action)
+                    };
+                }
+                SettingsButton::Back if paused_from.0.is_some() => next_state.set(GameState::Paused),
+                SettingsButton::Back => next_state.set(GameState::Menu),
+            },
+            Interaction::Hovered => *color = BUTTON_HOVERED_COLOR.into(),
+            Interaction::None => *color = BUTTON_COLOR.into(),
+        }
+    }
+}
+
+// Bind the next key pressed to the waiting action, unless another action already uses it
+fn capture_rebind_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut key_bindings: ResMut<KeyBindings>,
+    mut messages: Query<&mut Text, With<RebindMessage>>,
+) {
+    let Some(action) = rebinding.action else {
+        return;
+    };
+    let Some(&key) = keyboard_input.get_just_pressed().next() else {
+        return;
+    };
+    let message = match key_bindings.rebind(action, key) {
+        Ok(()) => String::new(),
+        Err(other) => format!("{} is already used for {}", key_name(key), other.label()),
+    };
+    for mut text in messages.iter_mut() {
+        text.sections[0].value = message.clone();
+    }
+    rebinding.action = None;
+}
+
+fn update_binding_labels(
+    key_bindings: Res<KeyBindings>,
+    rebinding: Res<Rebinding>,
+    mut labels: Query<(&mut Text, &BindingLabel)>,
+) {
+    if !key_bindings.is_changed() && !rebinding.is_changed() {
+        return;
+    }
+    for (mut text, label) in labels.iter_mut() {
+        text.sections[0].value = binding_label(label.0, &key_bindings, &rebinding);
+    }
+}
+
+fn apply_volume_sliders(
+    sliders: Query<(&Slider, &VolumeSlider), Changed<Slider>>,
+    mut audio_settings: ResMut<AudioSettings>,
+) {
+    for (slider, volume) in sliders.iter() {
+        match volume {
+            VolumeSlider::Master => audio_settings.master = slider.value,
+            VolumeSlider::Music => audio_settings.music = slider.value,
+            VolumeSlider::Sfx => audio_settings.sfx = slider.value,
+        }
+    }
+}
+
+fn update_mute_label(audio_settings: Res<AudioSettings>, mut labels: Query<&mut Text, With<MuteLabel>>) {
+    if !audio_settings.is_changed() {
+        return;
+    }
+    for mut text in labels.iter_mut() {
+        text.sections[0].value = mute_label(audio_settings.muted).to_string();
+    }
+}
+
+fn update_disasters_label(disasters: Res<DisasterFrequency>, mut labels: Query<&mut Text, With<DisastersLabel>>) {
+    if !disasters.is_changed() {
+        return;
+    }
+    for mut text in labels.iter_mut() {
+        text.sections[0].value = disasters_label(*disasters);
+    }
+}
+
+fn save_settings(
+    audio_settings: Res<AudioSettings>,
+    key_bindings: Res<KeyBindings>,
+    disasters: Res<DisasterFrequency>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    rebinding.action = None;
+    let settings = Settings {
+        audio: audio_settings.clone(),
+        key_bindings: key_bindings.clone(),
+        disasters: *disasters,
+    };
+    if let Err(error) = write_settings(&settings) {
+        error!("Failed to write the settings: {error}");
+    }
+}
+
+fn cleanup_settings(mut commands: Commands, screen: Query<Entity, With<SettingsScreen>>) {
+    for entity in screen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
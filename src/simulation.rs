@@ -1,34 +1,248 @@
 use bevy::prelude::*;
-use crate::town::{Town, TownCell, ZoneType, BuildingType};
+use crate::actions::{InputAction, KeyBindings};
+use crate::town::{Town, TownCell, TownMap, ZoneType, BuildingType, TOWN_GRID_SIZE};
+use crate::citizen::{CitizenBorn, CitizenDied, Commutes, Congestion, TrafficPollution, Wealth, WealthDistribution};
+use crate::clock::{Season, SeasonChanged, TimeOfDay};
+use crate::coverage::{compute_coverage, coverage_at};
+use crate::departments::DepartmentBonuses;
+use crate::disasters::Disasters;
+use crate::grid::{Grid, RadiusShape};
+use crate::island::{Deposit, Island};
+use crate::notifications::Notification;
+use crate::rng::GameRng;
+use crate::trade::Trade;
+use crate::menu::outside_pause;
 use crate::GameState;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
 pub struct SimulationPlugin;
 
+// Parts of a simulated frame, run one after the other. Left to the scheduler, systems sharing the
+// random number generator or the town's numbers run in an order that changes from one launch to
+// the next, and a seeded town plays out differently
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SimulationSet {
+    Clock,
+    // Zones developing
+    Growth,
+    Abandonment,
+    Departments,
+    TrafficLights,
+    Citizens,
+    // Economy, resources, land value and the rest of the town's numbers
+    Economy,
+    Disasters,
+    Incidents,
+    Heatmaps,
+}
+
 impl Plugin for SimulationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.init_resource::<Population>()
+            .init_resource::<Economy>()
+            .init_resource::<Resources>()
+            .init_resource::<Waterfront>()
+            .init_resource::<Demand>()
+            .init_resource::<LandValue>()
+            .init_resource::<Garbage>()
+            .init_resource::<Brownout>()
+            .init_resource::<Noise>()
+            .init_resource::<ParkCoverage>()
+            .init_resource::<Town>()
+            .init_resource::<GameSpeed>()
+            .init_resource::<Loans>()
+            .init_resource::<Bankruptcy>()
+            .add_event::<LoanRepaid>()
+            .add_event::<DebtUnserviceable>()
+            .configure_sets(
+                Update,
+                (
+                    SimulationSet::Clock,
+                    SimulationSet::Growth,
+                    SimulationSet::Abandonment,
+                    SimulationSet::Departments,
+                    SimulationSet::TrafficLights,
+                    SimulationSet::Citizens,
+                    SimulationSet::Economy,
+                    SimulationSet::Disasters,
+                    SimulationSet::Incidents,
+                    SimulationSet::Heatmaps,
+                )
+                    .chain(),
+            )
+            .add_systems(
             Update,
             (
+                control_game_speed,
+                update_noise,
+                update_land_value,
+                update_park_coverage,
                 update_population,
                 update_economy,
                 update_resources,
                 update_happiness,
-            ).run_if(in_state(GameState::TownView)),
-        );
+                update_demand,
+                check_bankruptcy,
+            )
+                .chain()
+                .in_set(SimulationSet::Economy)
+                .run_if(in_state(GameState::TownView)),
+        )
+        .add_systems(OnExit(GameState::TownView), (clear_garbage, clear_brownout).run_if(outside_pause));
     }
 }
 
 // Simulation parameters
 const BASE_POPULATION_GROWTH: f32 = 0.01;
-const BASE_HAPPINESS_DECAY: f32 = 0.001;
-const BASE_RESOURCE_CONSUMPTION: i32 = 1;
 
-// Population simulation
+// Highest tax rate the budget allows, and the rate every zone type starts at
+pub const MAX_TAX_RATE: f32 = 0.5;
+const DEFAULT_TAX_RATE: f32 = 0.1;
+
+// Demand lost per point of tax above the starting rate, or won below it
+const TAX_DEMAND_SENSITIVITY: f32 = 2.0;
+
+// Loan parameters, rates are per simulation tick
+pub const LOAN_AMOUNT: i32 = 5000;
+const LOAN_INTEREST_RATE: f32 = 0.002;
+const LOAN_TERM_TICKS: i32 = 300;
+// Borrowing is capped at this many ticks of taxed income
+const LOAN_LIMIT_TICKS: i32 = 120;
+
+// The game is lost once funds stay below the threshold for this many in-game days
+const BANKRUPTCY_FUNDS_THRESHOLD: i32 = 0;
+const BANKRUPTCY_GRACE_DAYS: f32 = 3.0;
+
+// Seconds of game time between economy and resource updates
+pub const SIMULATION_TICK_SECONDS: f32 = 1.0;
+
+// Land value parameters
+const BASE_LAND_VALUE: f32 = 0.5;
+const LAND_VALUE_RADIUS: i32 = 4;
+const PARK_LAND_VALUE_BONUS: f32 = 0.15;
+const SERVICE_LAND_VALUE_BONUS: f32 = 0.08;
+// Added to the neighborhood by every cell along a shore, so it's small
+const WATER_LAND_VALUE_BONUS: f32 = 0.03;
+const POLLUTION_LAND_VALUE_PENALTY: f32 = 0.12;
+const PROPERTY_TAX_PER_CELL: f32 = 10.0;
+
+// Parks reach this many cells, their effects falling off with distance
+const PARK_RADIUS: i32 = 5;
+// Boosts for a residential cell right next to a park, on top of the land value parks add
+const PARK_HAPPINESS_BONUS: f32 = 0.1;
+const PARK_TAX_BONUS: f32 = 0.3;
+pub const PARK_GROWTH_BONUS: f32 = 0.5;
+
+// Residential demand without citizens, raised by up to the bonus as citizens get wealthier
+const BASE_RESIDENTIAL_DEMAND: f32 = 0.5;
+const BASE_COMMERCIAL_DEMAND: f32 = 0.3;
+const BASE_INDUSTRIAL_DEMAND: f32 = 0.2;
+const WEALTH_RESIDENTIAL_DEMAND: f32 = 0.4;
+// Demand won or lost when a zone type is completely short of or swamped with capacity
+const BALANCE_DEMAND: f32 = 0.5;
+// Share of the jobs citizens look for at shops and offices, the rest are in industry
+const COMMERCIAL_JOB_SHARE: f32 = 0.5;
+// Share of the housing taken from which the town is short of homes, and the residential demand
+// added once every home is taken
+const HOUSING_SHORTAGE_OCCUPANCY: f32 = 0.9;
+const HOUSING_SHORTAGE_DEMAND: f32 = 0.5;
+
+// How much harder unemployment hits happiness in a town of only low-wealth citizens
+const LOW_WEALTH_UNEMPLOYMENT_SENSITIVITY: f32 = 0.5;
+
+// Happiness lost when all traffic is fully jammed
+const CONGESTION_HAPPINESS_PENALTY: f32 = 0.3;
+
+// Happiness lost when every road is fully polluted by exhaust
+const TRAFFIC_POLLUTION_HAPPINESS_PENALTY: f32 = 0.2;
+
+// Garbage a zoned cell produces per tick and level of density, industry makes twice as much
+const GARBAGE_PER_DENSITY_LEVEL: f32 = 0.2;
+// Garbage a cell holds before more just gets lost in the mess
+const MAX_CELL_GARBAGE: f32 = 10.0;
+// Garbage a facility collects per tick from the cells within its radius, closest first
+const LANDFILL_CAPACITY: f32 = 40.0;
+const LANDFILL_RADIUS: i32 = 10;
+const RECYCLING_CAPACITY: f32 = 80.0;
+const RECYCLING_RADIUS: i32 = 8;
+// Land value and happiness lost on and around cells buried in garbage
+const GARBAGE_LAND_VALUE_PENALTY: f32 = 0.2;
+const GARBAGE_HAPPINESS_PENALTY: f32 = 0.2;
+
+// Noise made by roads, growing with their traffic, and by industry, falling off over the radius
+const NOISE_RADIUS: i32 = 3;
+const ROAD_NOISE: f32 = 0.1;
+const TRAFFIC_NOISE: f32 = 0.5;
+const INDUSTRIAL_NOISE: f32 = 0.4;
+// Noise changes smaller than this aren't spread, traffic shifts a little every frame
+const NOISE_EPSILON: f32 = 0.01;
+// Land value and happiness lost on homes in full noise
+const NOISE_LAND_VALUE_PENALTY: f32 = 0.2;
+const NOISE_HAPPINESS_PENALTY: f32 = 0.15;
+
+// Happiness lost when the average commute is a long one
+const COMMUTE_HAPPINESS_PENALTY: f32 = 0.2;
+
+// Happiness lost when every zone has gone dark in a brownout
+const BROWNOUT_HAPPINESS_PENALTY: f32 = 0.3;
+
+// Happiness lost when none of the water demand is met
+const WATER_SHORTAGE_HAPPINESS_PENALTY: f32 = 0.5;
+
+// Simulation speed, paused with Space and set to 1x/2x/3x with the number keys
 #[derive(Resource)]
+pub struct GameSpeed {
+    pub multiplier: f32,
+    pub paused: bool,
+}
+
+impl Default for GameSpeed {
+    fn default() -> Self {
+        GameSpeed {
+            multiplier: 1.0,
+            paused: false,
+        }
+    }
+}
+
+impl GameSpeed {
+    // Game time that passed during the last frame
+    pub fn delta(&self, time: &Time) -> Duration {
+        if self.paused {
+            Duration::ZERO
+        } else {
+            time.delta().mul_f32(self.multiplier)
+        }
+    }
+
+    pub fn delta_seconds(&self, time: &Time) -> f32 {
+        self.delta(time).as_secs_f32()
+    }
+}
+
+// Population simulation
+#[derive(Resource, Clone, Serialize, Deserialize)]
 pub struct Population {
     pub total: i32,
     pub employed: i32,
     pub growth_rate: f32,
+    // Citizens the residential zones can house
+    #[serde(default)]
+    pub housing: i32,
+}
+
+impl Population {
+    // How short the town is of homes, 0 until most of the housing is taken and 1 once all of it is
+    pub fn housing_shortage(&self) -> f32 {
+        if self.housing <= 0 {
+            return 0.0;
+        }
+        let occupancy = self.total as f32 / self.housing as f32;
+        ((occupancy - HOUSING_SHORTAGE_OCCUPANCY) / (1.0 - HOUSING_SHORTAGE_OCCUPANCY)).clamp(0.0, 1.0)
+    }
 }
 
 impl Default for Population {
@@ -37,28 +251,150 @@ impl Default for Population {
             total: 0,
             employed: 0,
             growth_rate: BASE_POPULATION_GROWTH,
+            housing: 0,
         }
     }
 }
 
 // Economy simulation
-#[derive(Resource)]
+#[derive(Resource, Clone, Serialize, Deserialize)]
 pub struct Economy {
     pub funds: i32,
-    pub income: i32,
+    // Untaxed income from each zone type
+    #[serde(default)]
+    pub residential_income: i32,
+    #[serde(default)]
+    pub commercial_income: i32,
+    #[serde(default)]
+    pub industrial_income: i32,
     pub expenses: i32,
-    pub tax_rate: f32,
+    // Upkeep of the buildings
+    #[serde(default)]
+    pub maintenance: i32,
+    #[serde(default = "default_tax_rate")]
+    pub residential_tax_rate: f32,
+    #[serde(default = "default_tax_rate")]
+    pub commercial_tax_rate: f32,
+    #[serde(default = "default_tax_rate")]
+    pub industrial_tax_rate: f32,
+    // Funds from ore deposits on owned mountains, not taxed
+    #[serde(default)]
+    pub mining: i32,
+    // Funds from selling power to other towns, less what buying it cost
+    #[serde(default)]
+    pub trade: i32,
 }
 
 impl Default for Economy {
     fn default() -> Self {
         Economy {
             funds: 10000,
-            income: 0,
+            residential_income: 0,
+            commercial_income: 0,
+            industrial_income: 0,
             expenses: 0,
-            tax_rate: 0.1,
+            maintenance: 0,
+            residential_tax_rate: DEFAULT_TAX_RATE,
+            commercial_tax_rate: DEFAULT_TAX_RATE,
+            industrial_tax_rate: DEFAULT_TAX_RATE,
+            mining: 0,
+            trade: 0,
+        }
+    }
+}
+
+fn default_tax_rate() -> f32 {
+    DEFAULT_TAX_RATE
+}
+
+impl Economy {
+    pub fn tax_rate(&self, zone: ZoneType) -> f32 {
+        match zone {
+            ZoneType::Residential => self.residential_tax_rate,
+            ZoneType::Commercial => self.commercial_tax_rate,
+            ZoneType::Industrial => self.industrial_tax_rate,
+            ZoneType::None => 0.0,
+        }
+    }
+
+    pub fn set_tax_rate(&mut self, zone: ZoneType, rate: f32) {
+        let rate = rate.clamp(0.0, MAX_TAX_RATE);
+        match zone {
+            ZoneType::Residential => self.residential_tax_rate = rate,
+            ZoneType::Commercial => self.commercial_tax_rate = rate,
+            ZoneType::Industrial => self.industrial_tax_rate = rate,
+            ZoneType::None => {}
         }
     }
+
+    // Share of each zone type's income collected as taxes
+    pub fn tax_income(&self) -> i32 {
+        (self.residential_income as f32 * self.residential_tax_rate
+            + self.commercial_income as f32 * self.commercial_tax_rate
+            + self.industrial_income as f32 * self.industrial_tax_rate) as i32
+    }
+
+    // Change in funds per simulation tick
+    pub fn net_income(&self) -> i32 {
+        self.tax_income() + self.mining + self.trade - self.expenses - self.maintenance
+    }
+}
+
+// Money borrowed from the bank, repaid in installments every simulation tick
+#[derive(Resource, Default, Clone, Serialize, Deserialize)]
+pub struct Loans {
+    pub principal: i32,
+    pub installment: i32,
+    #[serde(skip)]
+    defaulting: bool,
+}
+
+impl Loans {
+    // Most that can be owed at once, always enough for a single loan
+    pub fn limit(economy: &Economy) -> i32 {
+        (economy.tax_income().max(0) * LOAN_LIMIT_TICKS).max(LOAN_AMOUNT)
+    }
+
+    pub fn can_borrow(&self, economy: &Economy) -> bool {
+        self.principal + LOAN_AMOUNT <= Loans::limit(economy)
+    }
+
+    // Take out a loan, returns false if it would exceed the limit
+    pub fn borrow(&mut self, economy: &mut Economy) -> bool {
+        if !self.can_borrow(economy) {
+            return false;
+        }
+        self.principal += LOAN_AMOUNT;
+        self.installment += LOAN_AMOUNT / LOAN_TERM_TICKS;
+        economy.funds += LOAN_AMOUNT;
+        true
+    }
+
+    pub fn interest(&self) -> i32 {
+        (self.principal as f32 * LOAN_INTEREST_RATE).ceil() as i32
+    }
+
+    // Interest plus repayment due this tick
+    pub fn payment(&self) -> i32 {
+        self.interest() + self.installment.min(self.principal)
+    }
+}
+
+// Sent when the last loan has been paid off
+#[derive(Event)]
+pub struct LoanRepaid;
+
+// Sent when loan payments push the funds below zero
+#[derive(Event)]
+pub struct DebtUnserviceable {
+    pub principal: i32,
+}
+
+// Tracks how long the town has been in debt
+#[derive(Resource, Default)]
+pub struct Bankruptcy {
+    // In-game time (in days) at which the funds dropped below the threshold
+    in_debt_since: Option<f32>,
 }
 
 // Resources simulation
@@ -68,6 +404,8 @@ pub struct Resources {
     pub water: ResourceInfo,
     pub goods: ResourceInfo,
     pub services: ResourceInfo,
+    // Produced by the zones and consumed by landfills, storage is what's left lying around
+    pub garbage: ResourceInfo,
 }
 
 #[derive(Default)]
@@ -78,6 +416,22 @@ pub struct ResourceInfo {
     pub max_storage: i32,
 }
 
+impl ResourceInfo {
+    // Whether demand outgrew production with nothing left in storage to make up for it
+    pub fn shortage(&self) -> bool {
+        self.storage == 0 && self.consumption > self.production
+    }
+
+    // Share of the demand that's met, between 0 and 1
+    pub fn supply_ratio(&self) -> f32 {
+        if self.shortage() {
+            self.production.max(0) as f32 / self.consumption as f32
+        } else {
+            1.0
+        }
+    }
+}
+
 impl Default for Resources {
     fn default() -> Self {
         Resources {
@@ -97,6 +451,10 @@ impl Default for Resources {
                 max_storage: 500,
                 ..Default::default()
             },
+            garbage: ResourceInfo {
+                max_storage: (MAX_CELL_GARBAGE * (TOWN_GRID_SIZE * TOWN_GRID_SIZE) as f32) as i32,
+                ..Default::default()
+            },
         }
     }
 }
@@ -112,18 +470,386 @@ pub struct Demand {
 impl Default for Demand {
     fn default() -> Self {
         Demand {
-            residential: 0.5,
-            commercial: 0.3,
-            industrial: 0.2,
+            residential: BASE_RESIDENTIAL_DEMAND,
+            commercial: BASE_COMMERCIAL_DEMAND,
+            industrial: BASE_INDUSTRIAL_DEMAND,
+        }
+    }
+}
+
+// Change the simulation speed from the keyboard
+fn control_game_speed(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut speed: ResMut<GameSpeed>,
+) {
+    if key_bindings.just_pressed(InputAction::Pause, &keyboard_input) {
+        speed.paused = !speed.paused;
+    }
+
+    for (action, multiplier) in [
+        (InputAction::NormalSpeed, 1.0),
+        (InputAction::FastSpeed, 2.0),
+        (InputAction::FastestSpeed, 3.0),
+    ] {
+        if key_bindings.just_pressed(action, &keyboard_input) {
+            speed.multiplier = multiplier;
+            speed.paused = false;
+        }
+    }
+}
+
+// Land value simulation, one value in [0, 1] per town cell
+#[derive(Resource)]
+pub struct LandValue {
+    pub values: [[f32; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+}
+
+impl Default for LandValue {
+    fn default() -> Self {
+        LandValue {
+            values: [[BASE_LAND_VALUE; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+        }
+    }
+}
+
+impl LandValue {
+    pub fn get(&self, pos: IVec2) -> f32 {
+        self.values[pos.y as usize][pos.x as usize]
+    }
+}
+
+// Sides of the shown town facing the sea on the island, the cells along them are waterfront
+#[derive(Resource, Default)]
+pub struct Waterfront {
+    pub sides: Vec<IVec2>,
+}
+
+impl Waterfront {
+    // Whether a town cell lies along an edge facing the water
+    pub fn contains(&self, pos: IVec2) -> bool {
+        let last = TOWN_GRID_SIZE as i32 - 1;
+        self.sides.iter().any(|side| match (side.x, side.y) {
+            (1, _) => pos.x == last,
+            (-1, _) => pos.x == 0,
+            (_, 1) => pos.y == last,
+            _ => pos.y == 0,
+        })
+    }
+}
+
+// How close each cell is to a park, 1 on a park falling off to 0 past `PARK_RADIUS`
+#[derive(Resource, Default)]
+pub struct ParkCoverage {
+    coverage: HashMap<IVec2, f32>,
+}
+
+impl ParkCoverage {
+    pub fn from_parks(parks: impl IntoIterator<Item = IVec2>) -> Self {
+        let parks: Vec<IVec2> = parks.into_iter().collect();
+        ParkCoverage {
+            coverage: compute_coverage(&parks, PARK_RADIUS, TOWN_GRID_SIZE),
+        }
+    }
+
+    pub fn get(&self, pos: IVec2) -> f32 {
+        coverage_at(&self.coverage, pos)
+    }
+}
+
+// Uncollected garbage lying on each town cell
+#[derive(Resource)]
+pub struct Garbage {
+    amounts: [[f32; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+    // Cells whose amount changed during the last economy tick
+    changed: HashSet<IVec2>,
+}
+
+impl Default for Garbage {
+    fn default() -> Self {
+        Garbage {
+            amounts: [[0.0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+            changed: HashSet::new(),
         }
     }
 }
 
+// Noise on each town cell from the roads and industry around it
+#[derive(Resource)]
+pub struct Noise {
+    values: [[f32; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+    // Noise each cell makes itself, as last spread to its surroundings
+    emitted: [[f32; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+    // Cells whose noise changed in the latest update
+    changed: Vec<IVec2>,
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Noise {
+            values: [[0.0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+            emitted: [[0.0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
+            changed: Vec::new(),
+        }
+    }
+}
+
+impl Noise {
+    // How loud a cell is, between 0 and 1
+    pub fn get(&self, pos: IVec2) -> f32 {
+        self.values[pos.y as usize][pos.x as usize].clamp(0.0, 1.0)
+    }
+}
+
+// Zones cut off from power while demand can't be met, in the order they went dark
+#[derive(Resource, Default)]
+pub struct Brownout {
+    dark: Vec<IVec2>,
+}
+
+impl Brownout {
+    pub fn is_dark(&self, pos: IVec2) -> bool {
+        self.dark.contains(&pos)
+    }
+
+    pub fn cells(&self) -> &[IVec2] {
+        &self.dark
+    }
+}
+
+impl Garbage {
+    // How buried a cell is, between 0 and 1
+    pub fn level(&self, pos: IVec2) -> f32 {
+        self.amounts[pos.y as usize][pos.x as usize] / MAX_CELL_GARBAGE
+    }
+
+    // Average level over the cells that have any
+    pub fn average(&self) -> f32 {
+        let (sum, dirty) = self
+            .amounts
+            .iter()
+            .flatten()
+            .filter(|&&amount| amount > 0.0)
+            .fold((0.0, 0), |(sum, dirty), &amount| (sum + amount, dirty + 1));
+        if dirty > 0 {
+            sum / dirty as f32 / MAX_CELL_GARBAGE
+        } else {
+            0.0
+        }
+    }
+
+    fn total(&self) -> f32 {
+        self.amounts.iter().flatten().sum()
+    }
+
+    fn add(&mut self, pos: IVec2, amount: f32) {
+        let value = &mut self.amounts[pos.y as usize][pos.x as usize];
+        let added = (*value + amount).min(MAX_CELL_GARBAGE);
+        if added != *value {
+            *value = added;
+            self.changed.insert(pos);
+        }
+    }
+
+    // Take up to `capacity` garbage from the cells around a facility, nearest first. Returns what was taken
+    fn collect(&mut self, facility: IVec2, radius: i32, capacity: f32) -> f32 {
+        let mut cells = Grid::cells_in_radius(facility, radius, RadiusShape::Manhattan, TOWN_GRID_SIZE);
+        cells.sort_by_key(|pos| Grid::manhattan_distance(*pos, facility));
+        let mut left = capacity;
+        for pos in cells {
+            if left <= 0.0 {
+                break;
+            }
+            let amount = &mut self.amounts[pos.y as usize][pos.x as usize];
+            let taken = amount.min(left);
+            if taken > 0.0 {
+                *amount -= taken;
+                left -= taken;
+                self.changed.insert(pos);
+            }
+        }
+        capacity - left
+    }
+}
+
+// How much a cell's contents push the land value of its neighborhood up or down
+fn land_value_influence(cell: &TownCell, season: Season, garbage: &Garbage) -> f32 {
+    let garbage_penalty = GARBAGE_LAND_VALUE_PENALTY * garbage.level(cell.position);
+    match cell.building {
+        BuildingType::Park => return PARK_LAND_VALUE_BONUS * season.park_appeal(),
+        BuildingType::Police
+        | BuildingType::Fire
+        | BuildingType::Hospital
+        | BuildingType::School => return SERVICE_LAND_VALUE_BONUS,
+        BuildingType::PowerPlant | BuildingType::Landfill => return -POLLUTION_LAND_VALUE_PENALTY,
+        _ => {}
+    }
+
+    if cell.zone == ZoneType::Industrial {
+        -POLLUTION_LAND_VALUE_PENALTY - garbage_penalty
+    } else {
+        -garbage_penalty
+    }
+}
+
+// Noise a cell makes, roads by the traffic on them
+fn noise_emitted(cell: &TownCell, congestion: &Congestion) -> f32 {
+    if cell.building.is_road() {
+        ROAD_NOISE + TRAFFIC_NOISE * congestion.traffic(cell.position)
+    } else if cell.zone == ZoneType::Industrial && cell.building == BuildingType::None {
+        INDUSTRIAL_NOISE
+    } else {
+        0.0
+    }
+}
+
+// Spread the change in noise of the cells making a different amount than before, rather than
+// recomputing every cell whenever traffic moves
+fn update_noise(
+    town_map: Res<TownMap>,
+    congestion: Res<Congestion>,
+    mut seen_revision: Local<u64>,
+    mut noise: ResMut<Noise>,
+) {
+    if town_map.revision() == *seen_revision && !congestion.is_changed() {
+        return;
+    }
+    *seen_revision = town_map.revision();
+
+    let noise = noise.as_mut();
+    let mut changed = HashSet::new();
+    for cell in town_map.iter() {
+        let pos = cell.position;
+        let emitted = noise_emitted(cell, &congestion);
+        let previous = &mut noise.emitted[pos.y as usize][pos.x as usize];
+        let delta = emitted - *previous;
+        if delta.abs() < NOISE_EPSILON {
+            continue;
+        }
+        *previous = emitted;
+        for target in Grid::cells_in_radius(pos, NOISE_RADIUS, RadiusShape::Manhattan, TOWN_GRID_SIZE) {
+            let falloff = 1.0 - Grid::manhattan_distance(pos, target) as f32 / (NOISE_RADIUS + 1) as f32;
+            noise.values[target.y as usize][target.x as usize] += delta * falloff;
+            changed.insert(target);
+        }
+    }
+    // Sorted, so the cells come in the same order on every launch
+    noise.changed = changed.into_iter().collect();
+    noise.changed.sort_by_key(|pos| (pos.y, pos.x));
+}
+
+// Update land value around cells that changed since the last frame
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_land_value(
+    mut land_value: ResMut<LandValue>,
+    town_map: Res<TownMap>,
+    mut seen_revision: Local<u64>,
+    waterfront: Res<Waterfront>,
+    time_of_day: Res<TimeOfDay>,
+    mut season_events: EventReader<SeasonChanged>,
+    garbage: Res<Garbage>,
+    noise: Res<Noise>,
+) {
+    // A new season changes the appeal of every park, so everything is recomputed
+    let season_changed = season_events.read().count() > 0;
+    let garbage_changed = garbage.is_changed() && !garbage.changed.is_empty();
+    if town_map.revision() == *seen_revision
+        && !season_changed
+        && !waterfront.is_changed()
+        && !garbage_changed
+        && !noise.is_changed()
+    {
+        return;
+    }
+    let season = time_of_day.season();
+
+    // Collect every cell whose value can be affected by a changed cell, another town's shore
+    // changes everything
+    let mut dirty = HashSet::new();
+    if waterfront.is_changed() {
+        dirty.extend(town_map.iter().map(|cell| cell.position));
+    }
+    let mut changed: Vec<IVec2> = if season_changed {
+        town_map.iter().map(|cell| cell.position).collect()
+    } else {
+        town_map.changed_since(*seen_revision).map(|cell| cell.position).collect()
+    };
+    *seen_revision = town_map.revision();
+    // Homes are worth less where it got louder or quieter
+    if noise.is_changed() {
+        dirty.extend(noise.changed.iter().copied());
+    }
+    // So do cells where garbage piled up or was taken away
+    if garbage_changed {
+        changed.extend(garbage.changed.iter().copied());
+    }
+    for pos in changed {
+        dirty.extend(Grid::cells_in_radius(pos, LAND_VALUE_RADIUS, RadiusShape::Manhattan, TOWN_GRID_SIZE));
+    }
+
+    // Snapshot the influence of every cell so dirty cells can look up their neighbors
+    let mut influence = [[0.0; TOWN_GRID_SIZE]; TOWN_GRID_SIZE];
+    for cell in town_map.iter() {
+        // Living by the water is worth more
+        let water_bonus = if waterfront.contains(cell.position) {
+            WATER_LAND_VALUE_BONUS
+        } else {
+            0.0
+        };
+        influence[cell.position.y as usize][cell.position.x as usize] =
+            land_value_influence(cell, season, &garbage) + water_bonus;
+    }
+
+    for pos in dirty {
+        let mut value = BASE_LAND_VALUE;
+        for source in Grid::cells_in_radius(pos, LAND_VALUE_RADIUS, RadiusShape::Manhattan, TOWN_GRID_SIZE) {
+            // Influence falls off linearly with distance
+            let distance = Grid::manhattan_distance(pos, source);
+            let falloff = 1.0 - distance as f32 / (LAND_VALUE_RADIUS + 1) as f32;
+            value += influence[source.y as usize][source.x as usize] * falloff;
+        }
+        if town_map.get(pos).is_some_and(|cell| cell.zone == ZoneType::Residential) {
+            value -= NOISE_LAND_VALUE_PENALTY * noise.get(pos);
+        }
+        land_value.values[pos.y as usize][pos.x as usize] = value.clamp(0.0, 1.0);
+    }
+}
+
+// Recompute the park coverage whenever the town changes
+fn update_park_coverage(
+    town_map: Res<TownMap>,
+    mut seen_revision: Local<u64>,
+    mut coverage: ResMut<ParkCoverage>,
+) {
+    if town_map.revision() == *seen_revision {
+        return;
+    }
+    *seen_revision = town_map.revision();
+
+    *coverage = ParkCoverage::from_parks(
+        town_map
+            .iter()
+            .filter(|cell| cell.building == BuildingType::Park)
+            .map(|cell| cell.position),
+    );
+}
+
 // Update population
+#[allow(clippy::too_many_arguments)]
 fn update_population(
     time: Res<Time>,
-    mut population: Option<ResMut<Population>>,
-    town_cells: Query<&TownCell>,
+    speed: Res<GameSpeed>,
+    population: Option<ResMut<Population>>,
+    town_map: Res<TownMap>,
+    land_value: Res<LandValue>,
+    time_of_day: Res<TimeOfDay>,
+    economy: Res<Economy>,
+    mut born_events: EventReader<CitizenBorn>,
+    mut died_events: EventReader<CitizenDied>,
+    resources: Res<Resources>,
+    bonuses: Res<DepartmentBonuses>,
+    mut short_of_housing: Local<bool>,
+    mut notifications: EventWriter<Notification>,
 ) {
     // Initialize population if it doesn't exist
     let mut population = match population {
@@ -131,37 +857,74 @@ fn update_population(
         None => return,
     };
     
-    // Count residential, commercial, and industrial zones
-    let mut residential_count = 0;
-    let mut commercial_count = 0;
-    let mut industrial_count = 0;
+    // Sum up housing and job capacity, which grow with zone density
+    let mut residential_appeal = 0.0;
+    let mut housing_capacity = 0;
+    let mut job_capacity = 0;
     
-    for cell in town_cells.iter() {
+    for cell in town_map.iter() {
         match cell.zone {
-            ZoneType::Residential => residential_count += 1,
-            ZoneType::Commercial => commercial_count += 1,
-            ZoneType::Industrial => industrial_count += 1,
+            ZoneType::Residential => {
+                // High-value areas attract more residents than low-value ones
+                residential_appeal += 0.5 + land_value.get(cell.position);
+                housing_capacity += bonuses.housing(cell.capacity());
+            }
+            ZoneType::Commercial | ZoneType::Industrial => job_capacity += cell.capacity(),
             _ => {}
         }
     }
     
-    // Calculate population growth based on available residential zones and happiness
-    let growth_factor = (residential_count as f32 * 0.1).min(10.0);
-    let growth = population.growth_rate * growth_factor * time.delta_seconds();
+    // Calculate population growth based on available residential zones and happiness,
+    // nobody moves into a town that runs short of water
+    let growth_factor = if resources.water.shortage() {
+        0.0
+    } else {
+        (residential_appeal * 0.1).min(10.0)
+    };
+    // Higher taxes slow down growth
+    let growth = population.growth_rate
+        * growth_factor
+        * (1.0 - economy.residential_tax_rate)
+        * time_of_day.season().growth()
+        * speed.delta_seconds(&time);
+    
+    // Citizens born and died in town count towards the population alongside its growth
+    let births = born_events.read().count() as i32;
+    let deaths = died_events.read().count() as i32;
     
-    // Update population
-    population.total += (growth * population.total as f32).round() as i32;
+    // Update population, which can't outgrow the available housing
+    population.total += (growth * population.total as f32).round() as i32 + births - deaths;
+    population.total = population.total.clamp(0, housing_capacity);
+    population.housing = housing_capacity;
+    
+    // Tell the player once growth is about to stall for lack of homes
+    let short = population.housing_shortage() > 0.0;
+    if short && !*short_of_housing {
+        notifications.send(Notification::warning("Housing shortage, zone more residential"));
+    }
+    *short_of_housing = short;
     
-    // Calculate employment based on commercial and industrial zones
-    let max_employment = (commercial_count + industrial_count) * 5; // Each zone can employ 5 citizens
-    population.employed = population.total.min(max_employment);
+    // Calculate employment based on commercial and industrial capacity
+    population.employed = population.total.min(job_capacity);
 }
 
 // Update economy
+#[allow(clippy::too_many_arguments)]
 fn update_economy(
     time: Res<Time>,
-    mut economy: Option<ResMut<Economy>>,
+    speed: Res<GameSpeed>,
+    mut timer: Local<Timer>,
+    economy: Option<ResMut<Economy>>,
     population: Option<Res<Population>>,
+    town_map: Res<TownMap>,
+    land_value: Res<LandValue>,
+    mut loans: ResMut<Loans>,
+    island: Option<Res<Island>>,
+    wealth: Res<WealthDistribution>,
+    trade: Res<Trade>,
+    parks: Res<ParkCoverage>,
+    mut repaid_events: EventWriter<LoanRepaid>,
+    mut default_events: EventWriter<DebtUnserviceable>,
 ) {
     // Initialize economy if it doesn't exist
     let mut economy = match economy {
@@ -174,26 +937,105 @@ fn update_economy(
         None => return,
     };
     
-    // Calculate income based on population and employment
+    // Initialize timer if needed
+    if timer.duration() == Duration::ZERO {
+        *timer = Timer::from_seconds(SIMULATION_TICK_SECONDS, TimerMode::Repeating);
+    }
+    
+    // Only update once per tick of game time
+    timer.tick(speed.delta(&time));
+    if !timer.just_finished() {
+        return;
+    }
+    
+    // Calculate income based on population and employment, wealthier citizens pay more
     let base_income = population.total as f32 * 1.0; // 1 fund per citizen
     let employment_bonus = population.employed as f32 * 2.0; // 2 additional funds per employed citizen
+    let wealth_multiplier = wealth.tax_multiplier();
+    
+    // Wages are earned at the shops and the industry in proportion to the jobs they offer
+    let (commercial_jobs, industrial_jobs) = town_map.iter().fold((0, 0), |(commercial, industrial), cell| {
+        match cell.zone {
+            ZoneType::Commercial => (commercial + cell.capacity(), industrial),
+            ZoneType::Industrial => (commercial, industrial + cell.capacity()),
+            _ => (commercial, industrial),
+        }
+    });
+    let commercial_share = if commercial_jobs + industrial_jobs > 0 {
+        commercial_jobs as f32 / (commercial_jobs + industrial_jobs) as f32
+    } else {
+        0.0
+    };
     
-    economy.income = (base_income + employment_bonus) as i32;
+    // Residential property tax scales with the land value of each residential cell
+    let property_tax: f32 = town_map
+        .iter()
+        .filter(|cell| cell.zone == ZoneType::Residential)
+        .map(|cell| {
+            let park_bonus = 1.0 + PARK_TAX_BONUS * parks.get(cell.position);
+            land_value.get(cell.position) * PROPERTY_TAX_PER_CELL * park_bonus
+        })
+        .sum();
+    
+    economy.residential_income = (base_income * wealth_multiplier + property_tax) as i32;
+    economy.commercial_income = (employment_bonus * wealth_multiplier * commercial_share) as i32;
+    economy.industrial_income = (employment_bonus * wealth_multiplier * (1.0 - commercial_share)) as i32;
     
     // Calculate expenses (maintenance, services, etc.)
     economy.expenses = (population.total as f32 * 0.5) as i32; // 0.5 funds per citizen
+    economy.maintenance = town_map.iter().map(|cell| cell.building.upkeep()).sum();
+    
+    // Owned ore deposits pay out every tick
+    economy.mining = island.map_or(0, |island| island.owned_yield(Deposit::Ore));
+    
+    // Power sold to and bought from the other towns
+    economy.trade = trade.income();
     
     // Update funds
-    let net_income = (economy.income as f32 * economy.tax_rate) as i32 - economy.expenses;
-    economy.funds += net_income;
+    economy.funds += economy.net_income();
+    
+    // Service outstanding loans
+    if loans.principal > 0 {
+        economy.funds -= loans.payment();
+        let repayment = loans.installment.min(loans.principal);
+        loans.principal -= repayment;
+        
+        if loans.principal == 0 {
+            loans.installment = 0;
+            loans.defaulting = false;
+            repaid_events.send(LoanRepaid);
+        } else if economy.funds < 0 {
+            // Only report once until the debt can be serviced again
+            if !loans.defaulting {
+                loans.defaulting = true;
+                default_events.send(DebtUnserviceable {
+                    principal: loans.principal,
+                });
+            }
+        } else {
+            loans.defaulting = false;
+        }
+    }
 }
 
 // Update resources
+#[allow(clippy::too_many_arguments)]
 fn update_resources(
     time: Res<Time>,
-    mut resources: Option<ResMut<Resources>>,
-    town_cells: Query<&TownCell>,
+    speed: Res<GameSpeed>,
+    mut timer: Local<Timer>,
+    resources: Option<ResMut<Resources>>,
+    town_map: Res<TownMap>,
     population: Option<Res<Population>>,
+    time_of_day: Res<TimeOfDay>,
+    island: Option<Res<Island>>,
+    bonuses: Res<DepartmentBonuses>,
+    disasters: Res<Disasters>,
+    trade: Res<Trade>,
+    mut garbage: ResMut<Garbage>,
+    mut brownout: ResMut<Brownout>,
+    mut game_rng: ResMut<GameRng>,
+    mut notifications: EventWriter<Notification>,
 ) {
     // Initialize resources if they don't exist
     let mut resources = match resources {
@@ -206,6 +1048,17 @@ fn update_resources(
         None => return,
     };
     
+    // Initialize timer if needed
+    if timer.duration() == Duration::ZERO {
+        *timer = Timer::from_seconds(SIMULATION_TICK_SECONDS, TimerMode::Repeating);
+    }
+    
+    // Only update once per tick of game time
+    timer.tick(speed.delta(&time));
+    if !timer.just_finished() {
+        return;
+    }
+    
     // Reset production and consumption
     resources.power.production = 0;
     resources.power.consumption = 0;
@@ -215,11 +1068,16 @@ fn update_resources(
     resources.goods.consumption = 0;
     resources.services.production = 0;
     resources.services.consumption = 0;
+    resources.garbage.production = 0;
+    resources.garbage.consumption = 0;
     
-    // Calculate production based on buildings
-    for cell in town_cells.iter() {
+    // Calculate production based on buildings, power plants are down during a blackout
+    for cell in town_map.iter() {
         match cell.building {
-            BuildingType::PowerPlant => resources.power.production += 100,
+            BuildingType::PowerPlant if disasters.blackout() => {}
+            BuildingType::PowerPlant => {
+                resources.power.production += (100.0 * bonuses.power_output).round() as i32
+            }
             BuildingType::WaterTower => resources.water.production += 100,
             _ => {}
         }
@@ -235,9 +1093,15 @@ fn update_resources(
         }
     }
     
+    // Owned stone deposits add to the goods
+    resources.goods.production += island.map_or(0, |island| island.owned_yield(Deposit::Stone));
+    
+    // Power bought from other towns adds to what the plants make, power sold leaves the town
+    resources.power.production += trade.power_imported - trade.power_exported;
+    
     // Calculate consumption based on population and buildings
     let population_consumption = (population.total as f32 * 0.1) as i32;
-    resources.power.consumption = population_consumption;
+    resources.power.consumption = (population_consumption as f32 * time_of_day.season().power_demand()) as i32;
     resources.water.consumption = population_consumption;
     resources.goods.consumption = (population.total as f32 * 0.05) as i32;
     resources.services.consumption = (population.total as f32 * 0.05) as i32;
@@ -248,20 +1112,154 @@ fn update_resources(
     resources.goods.storage += (resources.goods.production - resources.goods.consumption).max(-resources.goods.storage);
     resources.services.storage += (resources.services.production - resources.services.consumption).max(-resources.services.storage);
     
+    // Zones leave garbage on their cells, landfills and recycling centers take away what they can
+    garbage.changed.clear();
+    let mut produced = 0.0;
+    for cell in town_map.iter().filter(|cell| cell.zone != ZoneType::None) {
+        let multiplier = if cell.zone == ZoneType::Industrial { 2.0 } else { 1.0 };
+        let amount = GARBAGE_PER_DENSITY_LEVEL * (cell.density as f32 + 1.0) * multiplier;
+        garbage.add(cell.position, amount);
+        produced += amount;
+    }
+    let mut collected = 0.0;
+    for cell in town_map.iter() {
+        collected += match cell.building {
+            BuildingType::Landfill => garbage.collect(cell.position, LANDFILL_RADIUS, LANDFILL_CAPACITY),
+            BuildingType::RecyclingCenter => {
+                garbage.collect(cell.position, RECYCLING_RADIUS, RECYCLING_CAPACITY)
+            }
+            _ => 0.0,
+        };
+    }
+    resources.garbage.production = produced as i32;
+    resources.garbage.consumption = collected as i32;
+    resources.garbage.storage = garbage.total() as i32;
+    
     // Cap storage at max
     resources.power.storage = resources.power.storage.min(resources.power.max_storage);
     resources.water.storage = resources.water.storage.min(resources.water.max_storage);
     resources.goods.storage = resources.goods.storage.min(resources.goods.max_storage);
     resources.services.storage = resources.services.storage.min(resources.services.max_storage);
+    
+    // Once storage has run dry, as large a share of the zones goes dark as the share of power
+    // demand that isn't met. The last to go dark are the first to get power back
+    let zoned: HashSet<IVec2> = town_map
+        .iter()
+        .filter(|cell| cell.zone != ZoneType::None)
+        .map(|cell| cell.position)
+        .collect();
+    brownout.dark.retain(|pos| zoned.contains(pos));
+    let shortfall = resources.power.consumption - resources.power.production;
+    let dark = if resources.power.storage == 0 && shortfall > 0 {
+        (zoned.len() as f32 * shortfall as f32 / resources.power.consumption as f32).ceil() as usize
+    } else {
+        0
+    };
+    if dark < brownout.dark.len() {
+        brownout.dark.truncate(dark);
+    } else if dark > brownout.dark.len() {
+        // Picked from the cells in map order so a seeded game darkens the same zones
+        let lit: Vec<IVec2> = town_map
+            .iter()
+            .map(|cell| cell.position)
+            .filter(|pos| zoned.contains(pos) && !brownout.dark.contains(pos))
+            .collect();
+        let count = dark - brownout.dark.len();
+        let darkened: Vec<IVec2> = lit.choose_multiple(&mut game_rng.rng, count).copied().collect();
+        brownout.dark.extend(darkened);
+    }
+
+    // Warn while demand can't be met, repeats are merged into a single notification
+    if !brownout.dark.is_empty() {
+        notifications.send(Notification::warning("Brownout, zones are going dark, build more power plants"));
+    }
+    if resources.water.shortage() {
+        notifications.send(Notification::warning("Water shortage, the town stops growing until more water towers are built"));
+    }
+    if garbage.average() > 0.5 && resources.garbage.production > resources.garbage.consumption {
+        notifications.send(Notification::warning("Garbage is piling up, build landfills or recycling"));
+    }
+}
+
+// Demand for each zone type follows what the town is short of: homes when there are more jobs
+// than homes, and shops and industry when there are more citizens than jobs for them. Wealthier
+// citizens want more and better housing, and zone types taxed above the starting rate are less in demand
+#[allow(clippy::too_many_arguments)]
+fn update_demand(
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    mut timer: Local<Timer>,
+    town_map: Res<TownMap>,
+    population: Res<Population>,
+    wealth: Res<WealthDistribution>,
+    economy: Res<Economy>,
+    mut demand: ResMut<Demand>,
+) {
+    // Initialize timer if needed
+    if timer.duration() == Duration::ZERO {
+        *timer = Timer::from_seconds(SIMULATION_TICK_SECONDS, TimerMode::Repeating);
+    }
+    
+    // Only update once per tick of game time
+    timer.tick(speed.delta(&time));
+    if !timer.just_finished() {
+        return;
+    }
+    
+    let (mut housing, mut commercial_jobs, mut industrial_jobs) = (0, 0, 0);
+    for cell in town_map.iter() {
+        match cell.zone {
+            ZoneType::Residential => housing += cell.capacity(),
+            ZoneType::Commercial => commercial_jobs += cell.capacity(),
+            ZoneType::Industrial => industrial_jobs += cell.capacity(),
+            ZoneType::None => {}
+        }
+    }
+    // Between -1 with nothing wanted of the capacity there is and 1 with none of what's wanted there
+    let balance = |wanted: f32, capacity: i32| {
+        let most = wanted.max(capacity as f32);
+        if most > 0.0 {
+            (wanted - capacity as f32) / most
+        } else {
+            0.0
+        }
+    };
+    let workers = population.total as f32;
+    
+    let tax_factor = |zone| (1.0 - TAX_DEMAND_SENSITIVITY * (economy.tax_rate(zone) - DEFAULT_TAX_RATE)).max(0.0);
+    demand.residential = (BASE_RESIDENTIAL_DEMAND
+        + WEALTH_RESIDENTIAL_DEMAND
+            * (wealth.share(Wealth::High) + 0.5 * wealth.share(Wealth::Medium))
+        + BALANCE_DEMAND * balance((commercial_jobs + industrial_jobs) as f32, housing)
+        + HOUSING_SHORTAGE_DEMAND * population.housing_shortage())
+        * tax_factor(ZoneType::Residential);
+    demand.commercial = (BASE_COMMERCIAL_DEMAND
+        + BALANCE_DEMAND * balance(workers * COMMERCIAL_JOB_SHARE, commercial_jobs))
+        * tax_factor(ZoneType::Commercial);
+    demand.industrial = (BASE_INDUSTRIAL_DEMAND
+        + BALANCE_DEMAND * balance(workers * (1.0 - COMMERCIAL_JOB_SHARE), industrial_jobs))
+        * tax_factor(ZoneType::Industrial);
 }
 
 // Update happiness
+#[allow(clippy::too_many_arguments)]
 fn update_happiness(
     time: Res<Time>,
-    mut town: Option<ResMut<Town>>,
+    speed: Res<GameSpeed>,
+    town: Option<ResMut<Town>>,
     resources: Option<Res<Resources>>,
     population: Option<Res<Population>>,
     economy: Option<Res<Economy>>,
+    wealth: Res<WealthDistribution>,
+    congestion: Res<Congestion>,
+    commutes: Res<Commutes>,
+    traffic_pollution: Res<TrafficPollution>,
+    garbage: Res<Garbage>,
+    parks: Res<ParkCoverage>,
+    town_map: Res<TownMap>,
+    brownout: Res<Brownout>,
+    noise: Res<Noise>,
+    bonuses: Res<DepartmentBonuses>,
 ) {
     // Initialize town if it doesn't exist
     let mut town = match town {
@@ -284,28 +1282,220 @@ fn update_happiness(
         None => return,
     };
     
-    // Calculate happiness factors
-    let resource_factor = if resources.power.storage > 0 && resources.water.storage > 0 {
-        1.0
+    // Calculate happiness factors, the more of the water demand goes unmet the unhappier the town
+    let power_factor = if resources.power.storage > 0 { 1.0 } else { 0.5 };
+    let water_factor = 1.0 - WATER_SHORTAGE_HAPPINESS_PENALTY * (1.0 - resources.water.supply_ratio());
+    let resource_factor = power_factor * water_factor;
+    
+    let unemployment = if population.total > 0 {
+        1.0 - population.employed as f32 / population.total as f32
     } else {
-        0.5
+        0.0
     };
+    // Poorer towns feel unemployment more
+    let unemployment_sensitivity = 1.0 + LOW_WEALTH_UNEMPLOYMENT_SENSITIVITY * wealth.share(Wealth::Low);
+    let employment_factor = (1.0 - unemployment * unemployment_sensitivity).max(0.0);
+    
+    // Residents only feel their own taxes
+    let tax_factor = 1.0 - economy.residential_tax_rate;
     
-    let employment_factor = if population.total > 0 {
-        population.employed as f32 / population.total as f32
+    // Long commutes through traffic jams wear citizens down
+    let traffic_factor = 1.0 - CONGESTION_HAPPINESS_PENALTY * congestion.average();
+    // And so do long ways to work, however free the roads
+    let commute_factor = 1.0 - COMMUTE_HAPPINESS_PENALTY * commutes.burden();
+    let pollution_factor = 1.0 - TRAFFIC_POLLUTION_HAPPINESS_PENALTY * traffic_pollution.average();
+    // Uncollected garbage is pollution too
+    let garbage_factor = 1.0 - GARBAGE_HAPPINESS_PENALTY * garbage.average();
+    
+    // Homes near parks lift the mood, homes by loud roads and industry wear it down
+    let (park_sum, noise_sum, homes) = town_map
+        .iter()
+        .filter(|cell| cell.zone == ZoneType::Residential)
+        .fold((0.0, 0.0, 0), |(parks_sum, noise_sum, homes), cell| {
+            (parks_sum + parks.get(cell.position), noise_sum + noise.get(cell.position), homes + 1)
+        });
+    let (park_bonus, noise_factor) = if homes > 0 {
+        (
+            PARK_HAPPINESS_BONUS * park_sum / homes as f32,
+            1.0 - NOISE_HAPPINESS_PENALTY * noise_sum / homes as f32,
+        )
     } else {
-        1.0
+        (0.0, 1.0)
     };
     
-    let tax_factor = 1.0 - economy.tax_rate;
+    // Zones without power in a brownout
+    let zoned = town_map.iter().filter(|cell| cell.zone != ZoneType::None).count();
+    let brownout_factor = if zoned > 0 {
+        1.0 - BROWNOUT_HAPPINESS_PENALTY * brownout.cells().len() as f32 / zoned as f32
+    } else {
+        1.0
+    };
     
     // Calculate overall happiness
-    let target_happiness = resource_factor * employment_factor * tax_factor;
+    // Social Services lift the baseline
+    let target_happiness =
+        resource_factor * employment_factor * tax_factor * traffic_factor * commute_factor * pollution_factor
+            * garbage_factor
+            * brownout_factor
+            * noise_factor
+            + park_bonus
+            + bonuses.happiness;
     
     // Gradually adjust happiness towards target
-    let adjustment_rate = 0.1 * time.delta_seconds();
+    let adjustment_rate = 0.1 * speed.delta_seconds(&time);
     town.happiness += (target_happiness - town.happiness) * adjustment_rate;
     
     // Ensure happiness stays in range [0, 1]
     town.happiness = town.happiness.clamp(0.0, 1.0);
 }
+
+// Garbage belongs to the town it was left in
+fn clear_garbage(mut garbage: ResMut<Garbage>) {
+    *garbage = Garbage::default();
+}
+
+// Power comes back on in the next town
+fn clear_brownout(mut brownout: ResMut<Brownout>) {
+    brownout.dark.clear();
+}
+
+// End the game when the funds stay below the threshold for too long
+fn check_bankruptcy(
+    economy: Res<Economy>,
+    time_of_day: Res<TimeOfDay>,
+    mut bankruptcy: ResMut<Bankruptcy>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut notifications: EventWriter<Notification>,
+) {
+    if economy.funds >= BANKRUPTCY_FUNDS_THRESHOLD {
+        bankruptcy.in_debt_since = None;
+        return;
+    }
+
+    let now = time_of_day.elapsed_days();
+    if bankruptcy.in_debt_since.is_none() {
+        notifications.send(Notification::critical(format!(
+            "Funds are below ${}, the town goes bankrupt in {:.0} days",
+            BANKRUPTCY_FUNDS_THRESHOLD, BANKRUPTCY_GRACE_DAYS
+        )));
+    }
+    let since = *bankruptcy.in_debt_since.get_or_insert(now);
+    if now - since >= BANKRUPTCY_GRACE_DAYS {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{street, HeadlessSimulation};
+    use crate::town::CellSave;
+
+    #[test]
+    fn higher_taxes_bring_in_more_funds() {
+        // Funds of the same town after the same number of ticks, with one zone's rate changed
+        let funds_after = |zone: ZoneType, tax_rate: f32| {
+            let mut simulation = HeadlessSimulation::new(&street(), 3);
+            let world = simulation.app_mut().world_mut();
+            world.resource_mut::<Economy>().set_tax_rate(zone, tax_rate);
+            // Start with the homes lived in, so every zone type earns enough to tax from the first tick
+            world.resource_mut::<Population>().total = 40;
+            simulation.run_ticks(40);
+            simulation.economy().funds
+        };
+
+        let default = funds_after(ZoneType::None, 0.0);
+        for zone in [ZoneType::Residential, ZoneType::Commercial, ZoneType::Industrial] {
+            let untaxed = funds_after(zone, 0.0);
+            let highest = funds_after(zone, MAX_TAX_RATE);
+            assert!(untaxed < default, "{untaxed} with {zone:?} untaxed, {default} at the default rates");
+            assert!(default < highest, "{default} at the default rates, {highest} with {zone:?} at the highest");
+        }
+        // Rates past the highest are capped
+        assert_eq!(funds_after(ZoneType::Residential, 1.0), funds_after(ZoneType::Residential, MAX_TAX_RATE));
+    }
+
+    #[test]
+    fn garbage_lowers_land_value_where_it_piles_up() {
+        let home = TownCell {
+            position: IVec2::new(4, 4),
+            zone: ZoneType::Residential,
+            building: BuildingType::None,
+            density: 0,
+            accessible: true,
+            elevation: 0.0,
+            slope: 0.0,
+            water: false,
+        };
+        let mut garbage = Garbage::default();
+        let clean = land_value_influence(&home, Season::Summer, &garbage);
+
+        garbage.add(home.position, MAX_CELL_GARBAGE / 2.0);
+        assert!(land_value_influence(&home, Season::Summer, &garbage) < clean);
+        assert_eq!(garbage.changed, HashSet::from([home.position]));
+
+        // Collecting it marks the cell again, a full cell can't take more
+        garbage.changed.clear();
+        let landfill = IVec2::new(5, 4);
+        assert!(garbage.collect(landfill, LANDFILL_RADIUS, MAX_CELL_GARBAGE) > 0.0);
+        assert_eq!(garbage.changed, HashSet::from([home.position]));
+        assert_eq!(land_value_influence(&home, Season::Summer, &garbage), clean);
+
+        garbage.add(home.position, MAX_CELL_GARBAGE * 2.0);
+        garbage.changed.clear();
+        garbage.add(home.position, 1.0);
+        assert_eq!(garbage.level(home.position), 1.0);
+        assert!(garbage.changed.is_empty());
+    }
+
+    #[test]
+    fn removing_the_power_plant_causes_a_brownout() {
+        let mut simulation = HeadlessSimulation::new(&street(), 3);
+        // Start with the homes lived in, so the town draws power from the first tick
+        simulation.app_mut().world_mut().resource_mut::<Population>().total = 40;
+        simulation.run_ticks(30);
+        let world = simulation.app_mut().world_mut();
+        assert!(world.resource::<Brownout>().cells().is_empty());
+
+        let plant = IVec2::new(20, 21);
+        world.resource_mut::<TownMap>().cell_mut(plant).unwrap().building = BuildingType::None;
+        // Stored power carries the town for as many ticks as it lasts at the power drawn now, give
+        // or take a few for citizens born or dying meanwhile
+        let power = &world.resource::<Resources>().power;
+        let lasts = power.storage / power.consumption;
+        let mut ticks = 0;
+        while simulation.app_mut().world().resource::<Brownout>().cells().is_empty() {
+            assert!(ticks <= lasts + 10, "no brownout {ticks} ticks after the power plant was removed");
+            simulation.run_ticks(1);
+            ticks += 1;
+        }
+
+        let world = simulation.app_mut().world();
+        let town_map = world.resource::<TownMap>();
+        for &pos in world.resource::<Brownout>().cells() {
+            assert_ne!(town_map.get(pos).unwrap().zone, ZoneType::None);
+        }
+    }
+
+    #[test]
+    fn town_short_of_water_stops_growing_until_a_tower_is_built() {
+        let tower = IVec2::new(20, 19);
+        let cells: Vec<CellSave> = street().into_iter().filter(|cell| cell.position != tower).collect();
+        let mut simulation = HeadlessSimulation::new(&cells, 3);
+        // Start with the homes lived in, so the town draws water from the first tick
+        simulation.app_mut().world_mut().resource_mut::<Population>().total = 40;
+        simulation.run_ticks(60);
+        assert!(simulation.app_mut().world().resource::<Resources>().water.shortage());
+
+        // The homes have room for more, but nobody moves in or is born while the water runs short
+        let short = simulation.citizens();
+        assert!(short < simulation.population().housing as usize, "{short} citizens filled the homes");
+        simulation.run_ticks(40);
+        assert!(simulation.citizens() <= short);
+
+        let world = simulation.app_mut().world_mut();
+        world.resource_mut::<TownMap>().cell_mut(tower).unwrap().building = BuildingType::WaterTower;
+        simulation.run_ticks(40);
+        assert!(simulation.citizens() > short, "still {} citizens with water", simulation.citizens());
+    }
+}
@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::view::VisibilitySystems;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use crate::grid::Grid;
+use crate::town::{TOWN_CELL_SPACING, TOWN_GRID_SIZE};
+use crate::GameState;
+
+pub struct TilemapPlugin;
+
+/// This plugin draws the town cells as a few chunk meshes, recoloring the chunks in view when their tiles change
+impl Plugin for TilemapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TownTiles>().add_systems(
+            PostUpdate,
+            update_tilemap_colors
+                .before(VisibilitySystems::CheckVisibility)
+                .run_if(in_state(GameState::TownView)),
+        );
+    }
+}
+
+// Side of a tile in world units, the rest of the spacing is left as a gap between cells
+const TILE_SIZE: f32 = 10.0;
+
+// Tiles along each side of a chunk. Chunks out of the camera's view are culled and their colors
+// only rewritten once they come into view
+const CHUNK_SIZE: usize = 10;
+const CHUNKS_PER_SIDE: usize = TOWN_GRID_SIZE.div_ceil(CHUNK_SIZE);
+
+// Colors the town cells are drawn in, by grid position
+#[derive(Resource)]
+pub struct TownTiles {
+    colors: Vec<Color>,
+    // Chunks with colors their meshes don't show yet
+    dirty: Vec<bool>,
+}
+
+impl Default for TownTiles {
+    fn default() -> Self {
+        TownTiles {
+            colors: vec![Color::BLACK; TOWN_GRID_SIZE * TOWN_GRID_SIZE],
+            dirty: vec![true; CHUNKS_PER_SIDE * CHUNKS_PER_SIDE],
+        }
+    }
+}
+
+fn chunk_of(pos: IVec2) -> usize {
+    (pos.y as usize / CHUNK_SIZE) * CHUNKS_PER_SIDE + pos.x as usize / CHUNK_SIZE
+}
+
+// Grid positions of a chunk's tiles, row by row
+fn chunk_tiles(chunk: usize) -> impl Iterator<Item = IVec2> {
+    let origin = IVec2::new(
+        ((chunk % CHUNKS_PER_SIDE) * CHUNK_SIZE) as i32,
+        ((chunk / CHUNKS_PER_SIDE) * CHUNK_SIZE) as i32,
+    );
+    (0..CHUNK_SIZE as i32)
+        .flat_map(move |y| (0..CHUNK_SIZE as i32).map(move |x| origin + IVec2::new(x, y)))
+        .filter(|pos| Grid::is_in_bounds(*pos, TOWN_GRID_SIZE))
+}
+
+impl TownTiles {
+    pub fn get(&self, pos: IVec2) -> Color {
+        if !Grid::is_in_bounds(pos, TOWN_GRID_SIZE) {
+            return Color::BLACK;
+        }
+        self.colors[pos.y as usize * TOWN_GRID_SIZE + pos.x as usize]
+    }
+
+    pub fn set(&mut self, pos: IVec2, color: Color) {
+        if Grid::is_in_bounds(pos, TOWN_GRID_SIZE) {
+            self.colors[pos.y as usize * TOWN_GRID_SIZE + pos.x as usize] = color;
+            self.dirty[chunk_of(pos)] = true;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, Color)> + '_ {
+        self.colors.iter().enumerate().map(|(index, color)| {
+            let pos = IVec2::new((index % TOWN_GRID_SIZE) as i32, (index / TOWN_GRID_SIZE) as i32);
+            (pos, *color)
+        })
+    }
+
+    // Per vertex colors for a chunk's mesh, four vertices a tile
+    fn vertex_colors(&self, chunk: usize) -> Vec<[f32; 4]> {
+        chunk_tiles(chunk)
+            .flat_map(|pos| [self.get(pos).to_linear().to_f32_array(); 4])
+            .collect()
+    }
+}
+
+// Mesh holding a quad for every town cell of a chunk
+#[derive(Component)]
+pub struct TownTilemap {
+    chunk: usize,
+    mesh: Handle<Mesh>,
+}
+
+// Spawn the town's tilemap chunks, colored as the tiles currently are
+pub fn spawn_tilemap(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    tiles: &mut TownTiles,
+) {
+    let half = TILE_SIZE / 2.0;
+    let material = materials.add(ColorMaterial::default());
+    for chunk in 0..CHUNKS_PER_SIDE * CHUNKS_PER_SIDE {
+        let mut positions = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE * 4);
+        let mut indices = Vec::with_capacity(CHUNK_SIZE * CHUNK_SIZE * 6);
+        for pos in chunk_tiles(chunk) {
+            // Cells are centered on multiples of the spacing around the middle of the grid
+            let center = (pos.as_vec2() - Vec2::splat(TOWN_GRID_SIZE as f32 / 2.0)) * TOWN_CELL_SPACING;
+            let first = positions.len() as u32;
+            positions.extend([
+                [center.x - half, center.y - half, 0.0],
+                [center.x + half, center.y - half, 0.0],
+                [center.x + half, center.y + half, 0.0],
+                [center.x - half, center.y + half, 0.0],
+            ]);
+            indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+
+        let mesh = Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, tiles.vertex_colors(chunk))
+        .with_inserted_indices(Indices::U32(indices));
+        let mesh = meshes.add(mesh);
+
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(mesh.clone()),
+                material: material.clone(),
+                ..default()
+            },
+            TownTilemap { chunk, mesh },
+        ));
+    }
+    tiles.dirty.fill(false);
+}
+
+// Rewrite the colors of chunks in view that changed, those out of view wait until they're seen.
+// Visibility is last frame's, which is at most a frame late for chunks coming into view
+fn update_tilemap_colors(
+    mut tiles: ResMut<TownTiles>,
+    tilemap: Query<(&TownTilemap, &ViewVisibility)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    if !tiles.dirty.contains(&true) {
+        return;
+    }
+    // Clearing the flags isn't a change to the colors, the minimap only copies those
+    let tiles = tiles.bypass_change_detection();
+    for (tilemap, visibility) in tilemap.iter() {
+        if !tiles.dirty[tilemap.chunk] || !visibility.get() {
+            continue;
+        }
+        if let Some(mesh) = meshes.get_mut(&tilemap.mesh) {
+            mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, tiles.vertex_colors(tilemap.chunk));
+            tiles.dirty[tilemap.chunk] = false;
+        }
+    }
+}
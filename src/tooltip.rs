@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use crate::actions::{InputAction, KeyBindings};
+use crate::heatmaps::Heatmaps;
+use crate::menu::outside_pause;
+use crate::simulation::LandValue;
+use crate::town::{cursor_grid_position, TownCell, TownMap};
+use crate::GameState;
+
+pub struct TooltipPlugin;
+
+/// This plugin shows a tooltip describing the town cell under the cursor, and a panel keeping
+/// the details of an inspected cell on screen
+impl Plugin for TooltipPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InspectedCell>()
+            .add_systems(
+                Update,
+                (update_tooltip, update_inspect_panel).run_if(in_state(GameState::TownView)),
+            )
+            .add_systems(OnExit(GameState::TownView), stop_inspecting.run_if(outside_pause));
+    }
+}
+
+// Cell whose details are kept on screen until the cancel key is pressed
+#[derive(Resource, Default)]
+pub struct InspectedCell(pub Option<IVec2>);
+
+#[derive(Component)]
+struct InspectPanel;
+
+// Distance between the cursor and the tooltip in pixels
+const TOOLTIP_OFFSET: f32 = 16.0;
+
+#[derive(Component)]
+struct Tooltip;
+
+// Spawn the (initially hidden) tooltip
+pub fn spawn_tooltip(commands: &mut Commands) {
+    commands.spawn((
+        TextBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 14.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ),
+            style: Style {
+                position_type: PositionType::Absolute,
+                padding: UiRect::all(Val::Px(4.0)),
+                ..default()
+            },
+            background_color: Color::srgba(0.0, 0.0, 0.0, 0.8).into(),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..default()
+        },
+        Tooltip,
+    ));
+}
+
+// Describe the hovered cell next to the cursor, hiding the tooltip off-grid and over the UI
+fn update_tooltip(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    ui_interactions: Query<&Interaction, With<Node>>,
+    town_map: Res<TownMap>,
+    land_value: Res<LandValue>,
+    heatmaps: Res<Heatmaps>,
+    mut tooltip: Query<(&mut Text, &mut Style, &mut Visibility), With<Tooltip>>,
+) {
+    let Ok((mut text, mut style, mut visibility)) = tooltip.get_single_mut() else {
+        return;
+    };
+    *visibility = Visibility::Hidden;
+
+    if ui_interactions.iter().any(|interaction| *interaction != Interaction::None) {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), camera_q.get_single()) else {
+        return;
+    };
+    let (Some(cursor_position), Some(position)) = (
+        window.cursor_position(),
+        cursor_grid_position(window, camera, camera_transform),
+    ) else {
+        return;
+    };
+    let Some(cell) = town_map.get(position) else {
+        return;
+    };
+
+    text.sections[0].value = describe_cell(cell, &land_value, &heatmaps);
+    style.left = Val::Px(cursor_position.x + TOOLTIP_OFFSET);
+    style.top = Val::Px(cursor_position.y + TOOLTIP_OFFSET);
+    *visibility = Visibility::Visible;
+}
+
+fn describe_cell(cell: &TownCell, land_value: &LandValue, heatmaps: &Heatmaps) -> String {
+    format!(
+        "({}, {})\nZone: {:?} (density {})\nBuilding: {:?}\nAccessible: {}\n\
+         Land value: {:.0}%\nPollution: {:.0}%\nElevation: {:.0}%{}",
+        cell.position.x,
+        cell.position.y,
+        cell.zone,
+        cell.density,
+        cell.building,
+        if cell.accessible { "yes" } else { "no" },
+        land_value.get(cell.position) * 100.0,
+        heatmaps.pollution(cell.position) * 100.0,
+        cell.elevation * 100.0,
+        if cell.is_steep() { " (too steep to build)" } else { "" },
+    )
+}
+
+// Show the inspected cell's details in the corner, spawning the panel when a cell is first
+// inspected and closing it with the cancel key
+#[allow(clippy::too_many_arguments)]
+fn update_inspect_panel(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut inspected: ResMut<InspectedCell>,
+    town_map: Res<TownMap>,
+    land_value: Res<LandValue>,
+    heatmaps: Res<Heatmaps>,
+    mut panels: Query<(Entity, &mut Text), With<InspectPanel>>,
+) {
+    if key_bindings.just_pressed(InputAction::Cancel, &keyboard_input) {
+        inspected.0 = None;
+    }
+    let cell = inspected.0.and_then(|position| town_map.get(position));
+    match (cell, panels.get_single_mut()) {
+        (Some(cell), Ok((_, mut text))) => {
+            text.sections[0].value = describe_cell(cell, &land_value, &heatmaps);
+        }
+        (Some(cell), Err(_)) => {
+            commands.spawn((
+                TextBundle {
+                    text: Text::from_section(
+                        describe_cell(cell, &land_value, &heatmaps),
+                        TextStyle {
+                            font_size: 16.0,
+                            color: Color::WHITE,
+                            ..default()
+                        },
+                    ),
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        right: Val::Px(10.0),
+                        top: Val::Px(50.0),
+                        padding: UiRect::all(Val::Px(8.0)),
+                        ..default()
+                    },
+                    background_color: Color::srgba(0.1, 0.1, 0.1, 0.85).into(),
+                    ..default()
+                },
+                InspectPanel,
+            ));
+        }
+        (None, Ok((entity, _))) => commands.entity(entity).despawn_recursive(),
+        (None, Err(_)) => {}
+    }
+}
+
+// The inspected cell belongs to the town it was in
+fn stop_inspecting(mut inspected: ResMut<InspectedCell>) {
+    inspected.0 = None;
+}
@@ -1,28 +1,117 @@
 use bevy::prelude::*;
+use crate::actions::{InputAction, KeyBindings};
+use crate::audio::BuildSound;
+use crate::hud::spawn_hud;
+use crate::minimap::spawn_minimap;
+use crate::notifications::Notification;
+use crate::abandonment::Abandonment;
+use crate::connectivity::Connectivity;
+use crate::simulation::{
+    update_land_value, Brownout, Demand, Economy, GameSpeed, LandValue, Noise, ParkCoverage, Resources, SimulationSet,
+    PARK_GROWTH_BONUS,
+};
+use crate::budget::spawn_budget_button;
+use crate::citizen::{select_citizen, spawn_citizen_panel, Congestion};
+use crate::clock::{Season, SeasonChanged, TimeOfDay};
+use crate::grid::Grid;
+use crate::heatmaps::Heatmaps;
+use crate::tooltip::spawn_tooltip;
+use crate::tilemap::{spawn_tilemap, TownTiles, TownTilemap};
+use crate::traffic_lights::TrafficLights;
+use crate::blueprint::spawn_blueprints_button;
+use crate::selection::{drag_selection, paste_clipboard, spawn_select_button};
+use crate::transit::{edit_route, spawn_route_button, BusRoute, TransitRoutes};
+use crate::menu::outside_pause;
+use crate::rng::GameRng;
 use crate::GameState;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Duration;
 
 pub struct TownPlugin;
 
 /// This plugin handles the town view and simulation
 impl Plugin for TownPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::TownView), setup_town)
+        app.init_resource::<OverlayMode>()
+            .init_resource::<TownMap>()
+            .init_resource::<PlacementFlashes>()
+            .init_resource::<SelectedTool>()
+            .init_resource::<Construction>()
+            .init_resource::<GridLines>()
+            .init_resource::<Towns>()
+            .add_event::<RoadChanged>()
+            .add_event::<CellCommand>()
+            .add_systems(OnEnter(GameState::TownView), setup_town.run_if(outside_pause))
             .add_systems(
                 Update,
                 (
-                    handle_town_interaction,
-                    update_town_simulation,
+                    select_tool,
+                    // Clicks on citizens or while laying out a bus route don't use the tool
+                    handle_town_interaction
+                        .after(select_tool)
+                        .after(select_citizen)
+                        .after(edit_route)
+                        .after(drag_selection)
+                        .after(paste_clipboard),
+                    update_town_simulation.in_set(SimulationSet::Growth),
+                    apply_cell_commands,
+                    advance_construction
+                        .after(handle_town_interaction)
+                        .after(apply_cell_commands)
+                        .after(update_overlay_colors),
+                    fade_placement_flash,
+                    toggle_overlay,
+                    update_overlay_colors.after(update_land_value),
+                    tint_cells
+                        .after(update_town_simulation)
+                        .after(advance_construction)
+                        .after(update_overlay_colors),
+                    toggle_grid_lines,
+                    draw_grid_lines,
+                    rotate_one_way_tool.before(handle_town_interaction),
+                    draw_one_way_arrows,
+                    draw_traffic_lights,
                 ).run_if(in_state(GameState::TownView)),
             )
-            .add_systems(OnExit(GameState::TownView), cleanup_town);
+            .add_systems(OnExit(GameState::TownView), cleanup_town.run_if(outside_pause));
     }
 }
 
 // Town grid size (finer than island grid)
 pub const TOWN_GRID_SIZE: usize = 50;
 
+// World units between the centers of two town cells
+pub const TOWN_CELL_SPACING: f32 = 12.0;
+
+// Zoned cells develop from density 0 (just zoned) up to this level
+pub const MAX_DENSITY: u8 = 3;
+
+// Rise in elevation to a neighboring cell above which only roads can be built on a cell. Building
+// costs grow with the slope up to double the price just below it
+const STEEP_SLOPE: f32 = 0.06;
+
+// How much lighter cells are drawn at the highest elevation
+const ELEVATION_SHADING: f32 = 0.4;
+
+const WATER_COLOR: Color = Color::srgb(0.15, 0.35, 0.7);
+
+// Citizens housed (or employed) per zoned cell for each density level
+pub const CITIZENS_PER_DENSITY_LEVEL: i32 = 5;
+
+// Chance per simulation step that a zoned cell with full demand develops a level
+const DENSITY_GROWTH_CHANCE: f32 = 0.02;
+
+// Demand a zone type needs before its cells develop at all
+const DEVELOPMENT_DEMAND_THRESHOLD: f32 = 0.1;
+
+// Seconds of game time between rolls for zones to develop
+const DEVELOPMENT_INTERVAL_SECONDS: f32 = 0.5;
+
 // Zone types
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ZoneType {
     None,
     Residential,
@@ -31,18 +120,28 @@ pub enum ZoneType {
 }
 
 // Building types
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BuildingType {
     None,
     Road,
+    // Road traffic only drives along in the direction
+    OneWayRoad(RoadDirection),
+    // Wider and faster roads, highways keep buildings off their sides
+    Avenue,
+    Highway,
+    // Road over water
+    Bridge,
     TownHall,
     PowerPlant,
     WaterTower,
+    Landfill,
+    RecyclingCenter,
     Police,
     Fire,
     Hospital,
     School,
     Park,
+    BusStop,
     // Town Hall department modules
     LawAndOrder,  // Pentagon shape
     Education,    // Trapezoid shape
@@ -54,19 +153,544 @@ pub enum BuildingType {
     Upgrade,      // Square shape (can be attached to any department)
 }
 
-// Town cell component
+// Way traffic flows along a one-way road, north being up the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RoadDirection {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl RoadDirection {
+    // Step to the next cell in the direction
+    pub fn step(&self) -> IVec2 {
+        match self {
+            RoadDirection::North => IVec2::Y,
+            RoadDirection::East => IVec2::X,
+            RoadDirection::South => IVec2::NEG_Y,
+            RoadDirection::West => IVec2::NEG_X,
+        }
+    }
+
+    // A quarter turn clockwise
+    pub fn rotated(&self) -> RoadDirection {
+        match self {
+            RoadDirection::North => RoadDirection::East,
+            RoadDirection::East => RoadDirection::South,
+            RoadDirection::South => RoadDirection::West,
+            RoadDirection::West => RoadDirection::North,
+        }
+    }
+}
+
+impl ZoneType {
+    pub const ALL: [ZoneType; 4] = [
+        ZoneType::None,
+        ZoneType::Residential,
+        ZoneType::Commercial,
+        ZoneType::Industrial,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ZoneType::None => "Empty",
+            ZoneType::Residential => "Residential",
+            ZoneType::Commercial => "Commercial",
+            ZoneType::Industrial => "Industrial",
+        }
+    }
+}
+
+// Share of a building's cost refunded when it's cleared by zoning or building over it
+const DEMOLITION_REFUND: f32 = 0.5;
+
+impl BuildingType {
+    pub const ALL: [BuildingType; 25] = [
+        BuildingType::None,
+        BuildingType::Road,
+        BuildingType::OneWayRoad(RoadDirection::East),
+        BuildingType::Avenue,
+        BuildingType::Highway,
+        BuildingType::Bridge,
+        BuildingType::TownHall,
+        BuildingType::PowerPlant,
+        BuildingType::WaterTower,
+        BuildingType::Landfill,
+        BuildingType::RecyclingCenter,
+        BuildingType::Police,
+        BuildingType::Fire,
+        BuildingType::Hospital,
+        BuildingType::School,
+        BuildingType::Park,
+        BuildingType::BusStop,
+        BuildingType::LawAndOrder,
+        BuildingType::Education,
+        BuildingType::Transportation,
+        BuildingType::Health,
+        BuildingType::Energy,
+        BuildingType::Housing,
+        BuildingType::SocialServices,
+        BuildingType::Upgrade,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            BuildingType::None => "None",
+            BuildingType::Road => "Road",
+            BuildingType::OneWayRoad(_) => "One-way road",
+            BuildingType::Avenue => "Avenue",
+            BuildingType::Highway => "Highway",
+            BuildingType::Bridge => "Bridge",
+            BuildingType::TownHall => "Town Hall",
+            BuildingType::PowerPlant => "Power plant",
+            BuildingType::WaterTower => "Water tower",
+            BuildingType::Landfill => "Landfill",
+            BuildingType::RecyclingCenter => "Recycling center",
+            BuildingType::Police => "Police",
+            BuildingType::Fire => "Fire station",
+            BuildingType::Hospital => "Hospital",
+            BuildingType::School => "School",
+            BuildingType::Park => "Park",
+            BuildingType::BusStop => "Bus stop",
+            BuildingType::LawAndOrder => "Law and Order",
+            BuildingType::Education => "Education",
+            BuildingType::Transportation => "Transportation",
+            BuildingType::Health => "Health",
+            BuildingType::Energy => "Energy",
+            BuildingType::Housing => "Housing",
+            BuildingType::SocialServices => "Social Services",
+            BuildingType::Upgrade => "Upgrade",
+        }
+    }
+
+    // Funds it takes to build
+    pub fn cost(&self) -> i32 {
+        match self {
+            BuildingType::None => 0,
+            BuildingType::Road => 10,
+            BuildingType::OneWayRoad(_) => 15,
+            BuildingType::Avenue => 30,
+            BuildingType::Highway => 80,
+            BuildingType::Bridge => 100,
+            BuildingType::BusStop => 50,
+            BuildingType::Park => 150,
+            BuildingType::Landfill => 400,
+            BuildingType::Police | BuildingType::Fire => 500,
+            BuildingType::School => 600,
+            BuildingType::WaterTower | BuildingType::Hospital => 800,
+            BuildingType::RecyclingCenter => 1200,
+            BuildingType::TownHall => 1000,
+            BuildingType::PowerPlant => 2000,
+            BuildingType::LawAndOrder
+            | BuildingType::Education
+            | BuildingType::Transportation
+            | BuildingType::Health
+            | BuildingType::Energy
+            | BuildingType::Housing
+            | BuildingType::SocialServices => 1500,
+            BuildingType::Upgrade => 500,
+        }
+    }
+
+    // Funds it takes to keep running every simulation tick
+    pub fn upkeep(&self) -> i32 {
+        match self {
+            BuildingType::None | BuildingType::TownHall => 0,
+            BuildingType::Road | BuildingType::OneWayRoad(_) | BuildingType::BusStop => 1,
+            BuildingType::Park | BuildingType::Bridge | BuildingType::Avenue => 2,
+            BuildingType::Landfill | BuildingType::Highway => 5,
+            BuildingType::Police | BuildingType::Fire => 8,
+            BuildingType::WaterTower | BuildingType::School => 10,
+            BuildingType::Hospital | BuildingType::RecyclingCenter => 15,
+            BuildingType::PowerPlant => 25,
+            BuildingType::LawAndOrder
+            | BuildingType::Education
+            | BuildingType::Transportation
+            | BuildingType::Health
+            | BuildingType::Energy
+            | BuildingType::Housing
+            | BuildingType::SocialServices => 10,
+            BuildingType::Upgrade => 5,
+        }
+    }
+
+    // Whether it takes a while to build, roads and bridges are laid at once
+    fn needs_construction(&self) -> bool {
+        !matches!(self, BuildingType::None) && !self.is_road()
+    }
+
+    // Whether vehicles and pedestrians can travel over it
+    pub fn is_road(&self) -> bool {
+        matches!(
+            self,
+            BuildingType::Road
+                | BuildingType::OneWayRoad(_)
+                | BuildingType::Avenue
+                | BuildingType::Highway
+                | BuildingType::Bridge
+        )
+    }
+
+    // Multiplier on the speed vehicles drive along it
+    pub fn road_speed(&self) -> f32 {
+        match self {
+            BuildingType::Avenue => 1.5,
+            BuildingType::Highway => 2.0,
+            _ => 1.0,
+        }
+    }
+
+    // Multiplier on the vehicles it carries before traffic slows down
+    pub fn road_capacity(&self) -> f32 {
+        match self {
+            BuildingType::Avenue => 2.0,
+            BuildingType::Highway => 3.0,
+            _ => 1.0,
+        }
+    }
+
+    // Whether vehicles may move over it by the step, never against a one-way road's arrow.
+    // Pedestrians walk either way
+    pub fn allows_step(&self, step: IVec2) -> bool {
+        match self {
+            BuildingType::OneWayRoad(direction) => step != -direction.step(),
+            _ => true,
+        }
+    }
+
+    // Funds returned when it's demolished
+    pub fn refund(&self) -> i32 {
+        (self.cost() as f32 * DEMOLITION_REFUND) as i32
+    }
+}
+
+// Data overlay drawn on top of the town grid instead of the zone/building colors
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayMode {
+    #[default]
+    None,
+    LandValue,
+    Congestion,
+    Pollution,
+    Crime,
+    Happiness,
+    Power,
+    Water,
+    Noise,
+    Connectivity,
+}
+
+impl OverlayMode {
+    const ALL: [OverlayMode; 10] = [
+        OverlayMode::None,
+        OverlayMode::LandValue,
+        OverlayMode::Congestion,
+        OverlayMode::Pollution,
+        OverlayMode::Crime,
+        OverlayMode::Happiness,
+        OverlayMode::Power,
+        OverlayMode::Water,
+        OverlayMode::Noise,
+        OverlayMode::Connectivity,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            OverlayMode::None => "Overlay: off",
+            OverlayMode::LandValue => "Land value",
+            OverlayMode::Congestion => "Traffic",
+            OverlayMode::Pollution => "Pollution",
+            OverlayMode::Crime => "Crime",
+            OverlayMode::Happiness => "Happiness",
+            OverlayMode::Power => "Power",
+            OverlayMode::Water => "Water",
+            OverlayMode::Noise => "Noise",
+            OverlayMode::Connectivity => "Road access",
+        }
+    }
+
+    fn next(&self) -> OverlayMode {
+        let index = OverlayMode::ALL.iter().position(|mode| mode == self).unwrap_or(0);
+        OverlayMode::ALL[(index + 1) % OverlayMode::ALL.len()]
+    }
+}
+
+// Toolbar button cycling through the overlays
 #[derive(Component)]
+struct OverlayButton;
+
+#[derive(Component)]
+struct OverlayLabel;
+
+// Whether lines are drawn between the town cells, off by default to keep the town uncluttered
+#[derive(Resource, Default)]
+struct GridLines {
+    visible: bool,
+}
+
+const GRID_LINE_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.15);
+
+const ONE_WAY_ARROW_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.8);
+
+const GREEN_LIGHT_COLOR: Color = Color::srgb(0.2, 0.9, 0.3);
+const RED_LIGHT_COLOR: Color = Color::srgb(0.95, 0.2, 0.15);
+
+// Sent when a road is built on, removed from or changes its kind on a cell
+#[derive(Event)]
+pub struct RoadChanged {
+    pub position: IVec2,
+}
+
+// Whether replacing one building with another changes the roads, e.g. turning a road one-way
+pub(crate) fn road_changed(before: BuildingType, after: BuildingType) -> bool {
+    before != after && (before.is_road() || after.is_road())
+}
+
+// Town cell, kept in the town map
+#[derive(Debug, Clone, Copy)]
 pub struct TownCell {
     pub position: IVec2,
     pub zone: ZoneType,
     pub building: BuildingType,
+    pub density: u8,
     pub accessible: bool,
+    // Height of the ground from 0 to 1, and the largest rise to an orthogonal neighbor
+    pub elevation: f32,
+    pub slope: f32,
+    // Covered by a river or the sea, only bridges go on water
+    pub water: bool,
+}
+
+impl TownCell {
+    fn empty(position: IVec2) -> Self {
+        TownCell {
+            position,
+            zone: ZoneType::None,
+            building: BuildingType::None,
+            density: 0,
+            accessible: false,
+            elevation: 0.0,
+            slope: 0.0,
+            water: false,
+        }
+    }
+
+    pub fn is_steep(&self) -> bool {
+        self.slope > STEEP_SLOPE
+    }
+
+    // What building on this cell costs, more on sloped ground
+    pub fn build_cost(&self, building: BuildingType) -> i32 {
+        (building.cost() as f32 * (1.0 + (self.slope / STEEP_SLOPE).min(1.0))).round() as i32
+    }
+
+    // Citizens this cell can house or employ at its current density
+    pub fn capacity(&self) -> i32 {
+        if self.zone == ZoneType::None {
+            return 0;
+        }
+        CITIZENS_PER_DENSITY_LEVEL * (self.density as i32 + 1)
+    }
 }
 
+// Cells of the shown town by grid position. Cells changed through `cell_mut` are stamped with a
+// new revision, so systems deriving data from the cells can catch up on just what changed since
+// the revision they last saw
+#[derive(Resource)]
+pub struct TownMap {
+    cells: Vec<TownCell>,
+    changed_at: Vec<u64>,
+    revision: u64,
+    // Whether a town is shown, the cells are all empty otherwise
+    loaded: bool,
+}
+
+impl Default for TownMap {
+    fn default() -> Self {
+        TownMap {
+            cells: (0..TOWN_GRID_SIZE * TOWN_GRID_SIZE)
+                .map(|index| TownCell::empty(index_to_position(index)))
+                .collect(),
+            changed_at: vec![0; TOWN_GRID_SIZE * TOWN_GRID_SIZE],
+            revision: 0,
+            loaded: false,
+        }
+    }
+}
+
+fn position_to_index(pos: IVec2) -> Option<usize> {
+    Grid::is_in_bounds(pos, TOWN_GRID_SIZE).then(|| pos.y as usize * TOWN_GRID_SIZE + pos.x as usize)
+}
+
+fn index_to_position(index: usize) -> IVec2 {
+    IVec2::new((index % TOWN_GRID_SIZE) as i32, (index / TOWN_GRID_SIZE) as i32)
+}
+
+impl TownMap {
+    pub fn get(&self, pos: IVec2) -> Option<&TownCell> {
+        position_to_index(pos).map(|index| &self.cells[index])
+    }
+
+    // Mutable access to a cell, which counts as changing it
+    pub fn cell_mut(&mut self, pos: IVec2) -> Option<&mut TownCell> {
+        let index = position_to_index(pos)?;
+        self.revision += 1;
+        self.changed_at[index] = self.revision;
+        Some(&mut self.cells[index])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TownCell> {
+        self.cells.iter()
+    }
+
+    // Whether a vehicle may move from a cell to the next without going against the arrow of a
+    // one-way road on either of them. Cells off the map don't restrict it
+    pub fn follows_traffic(&self, from: IVec2, to: IVec2) -> bool {
+        let step = to - from;
+        [from, to]
+            .into_iter()
+            .filter_map(|pos| self.get(pos))
+            .all(|cell| cell.building.allows_step(step))
+    }
+
+    // Revision of the latest change, systems keep it to pass to `changed_since` next time
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    // Cells changed after the given revision
+    pub fn changed_since(&self, revision: u64) -> impl Iterator<Item = &TownCell> {
+        self.cells
+            .iter()
+            .zip(self.changed_at.iter())
+            .filter(move |(_, changed_at)| **changed_at > revision)
+            .map(|(cell, _)| cell)
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    // Set the ground's height under every cell, row by row from the bottom. Cells left out are
+    // at height 0, so a town without elevation is flat
+    pub fn set_elevation(&mut self, elevation: &[f32]) {
+        let height = |pos: IVec2| position_to_index(pos).map(|index| elevation.get(index).copied().unwrap_or(0.0));
+        for (index, cell) in self.cells.iter_mut().enumerate() {
+            let position = index_to_position(index);
+            let own = height(position).unwrap_or(0.0);
+            cell.elevation = own;
+            cell.slope = Grid::get_orthogonal_positions(position)
+                .into_iter()
+                .filter_map(height)
+                .map(|neighbor| (neighbor - own).abs())
+                .fold(0.0, f32::max);
+        }
+    }
+
+    // Cover the given cells with water and dry out the rest
+    pub fn set_water(&mut self, water: &[IVec2]) {
+        for cell in self.cells.iter_mut() {
+            cell.water = false;
+        }
+        for &pos in water {
+            if let Some(index) = position_to_index(pos) {
+                self.cells[index].water = true;
+            }
+        }
+    }
+
+    // Replace every cell with the shown town's, counting all of them as changed
+    fn load(&mut self, cells: impl Iterator<Item = TownCell>) {
+        self.revision += 1;
+        self.changed_at.fill(self.revision);
+        for cell in cells {
+            if let Some(index) = position_to_index(cell.position) {
+                self.cells[index] = cell;
+            }
+        }
+        self.loaded = true;
+    }
+
+    // Empty every cell once the town is no longer shown
+    fn unload(&mut self) {
+        self.load((0..self.cells.len()).map(|index| TownCell::empty(index_to_position(index))));
+        self.loaded = false;
+    }
+}
+
+// Layout of a town while it isn't shown, also what gets written to save files
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct TownSave {
+    #[serde(default)]
+    pub name: String,
+    // Only cells with a zone or building are stored
+    pub cells: Vec<CellSave>,
+    #[serde(default)]
+    pub routes: Vec<BusRoute>,
+    // Height of the ground under each cell, row by row from the bottom. Empty for flat towns
+    #[serde(default)]
+    pub elevation: Vec<f32>,
+    // Cells covered by water
+    #[serde(default)]
+    pub water: Vec<IVec2>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct CellSave {
+    pub position: IVec2,
+    pub zone: ZoneType,
+    pub building: BuildingType,
+    pub density: u8,
+}
+
+// Check stored cells lie within an area of the given size from the origin, are each listed once
+// and aren't denser than zones grow. The area is named in the errors, e.g. "grid"
+pub fn check_cells(cells: &[CellSave], width: i32, height: i32, area: &str) -> Result<(), String> {
+    let mut positions = HashSet::new();
+    for cell in cells.iter() {
+        let pos = cell.position;
+        if pos.x < 0 || pos.y < 0 || pos.x >= width || pos.y >= height {
+            return Err(format!("cell ({}, {}) is outside the {width}x{height} {area}", pos.x, pos.y));
+        }
+        if !positions.insert(pos) {
+            return Err(format!("cell ({}, {}) is listed twice", pos.x, pos.y));
+        }
+        if cell.density > MAX_DENSITY {
+            return Err(format!("cell ({}, {}) has density {} above {MAX_DENSITY}", pos.x, pos.y, cell.density));
+        }
+    }
+    Ok(())
+}
+
+impl TownSave {
+    // Replace the stored layout with the given cells
+    pub fn store_cells<'a>(&mut self, cells: impl Iterator<Item = &'a TownCell>) {
+        self.cells = cells
+            .filter(|cell| cell.zone != ZoneType::None || cell.building != BuildingType::None)
+            .map(|cell| CellSave {
+                position: cell.position,
+                zone: cell.zone,
+                building: cell.building,
+                density: cell.density,
+            })
+            .collect();
+    }
+}
+
+// Layouts of all towns on the island, by their island position
+#[derive(Resource, Default, Clone)]
+pub struct Towns {
+    pub towns: HashMap<IVec2, TownSave>,
+}
+
+// Island position of the town that's shown in the town view
+#[derive(Resource, Clone, Copy)]
+pub struct SelectedTown(pub IVec2);
+
 // Town resource
 #[derive(Resource)]
 pub struct Town {
-    pub grid: [[TownCell; TOWN_GRID_SIZE]; TOWN_GRID_SIZE],
     pub population: i32,
     pub happiness: f32,
     pub funds: i32,
@@ -74,71 +698,102 @@ pub struct Town {
     pub water: i32,
 }
 
-// Citizen component
-#[derive(Component)]
-pub struct Citizen {
-    pub home: IVec2,
-    pub workplace: IVec2,
-    pub happiness: f32,
-}
-
-// Vehicle component
-#[derive(Component)]
-pub struct Vehicle {
-    pub start: IVec2,
-    pub destination: IVec2,
-    pub progress: f32,
+impl Default for Town {
+    fn default() -> Self {
+        Town {
+            population: 0,
+            happiness: 0.5,
+            funds: 0,
+            power: 0,
+            water: 0,
+        }
+    }
 }
 
 // Setup the town view
-fn setup_town(mut commands: Commands) {
-    // Create a new town if it doesn't exist
-    // In a real implementation, we would load the town data based on the selected town
+#[allow(clippy::too_many_arguments)]
+fn setup_town(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut town_map: ResMut<TownMap>,
+    mut tiles: ResMut<TownTiles>,
+    time_of_day: Res<TimeOfDay>,
+    towns: Res<Towns>,
+    selected_town: Option<Res<SelectedTown>>,
+) {
+    if selected_town.is_none() {
+        warn!("Showing the town view without a selected town, changes won't be kept");
+    }
+    
+    // Restore the selected town's layout, newly founded towns start out empty
+    let town_save = selected_town.and_then(|selected_town| towns.towns.get(&selected_town.0));
+    commands.insert_resource(TransitRoutes {
+        routes: town_save.map(|save| save.routes.clone()).unwrap_or_default(),
+    });
     
     // Add a camera
     commands.spawn(Camera2dBundle::default());
     
-    // Create a simple town grid
-    for y in 0..TOWN_GRID_SIZE {
-        for x in 0..TOWN_GRID_SIZE {
-            let position = IVec2::new(x as i32, y as i32);
-            
-            // Create a town cell
-            let cell = TownCell {
-                position,
-                zone: ZoneType::None,
-                building: BuildingType::None,
-                accessible: false,
-            };
-            
-            // Spawn a sprite for each cell
-            commands.spawn((
-                SpriteBundle {
-                    sprite: Sprite {
-                        color: get_cell_color(&cell),
-                        custom_size: Some(Vec2::new(10.0, 10.0)),
-                        ..default()
-                    },
-                    transform: Transform::from_translation(Vec3::new(
-                        (x as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-                        (y as f32 - TOWN_GRID_SIZE as f32 / 2.0) * 12.0,
-                        0.0,
-                    )),
-                    ..default()
-                },
-                cell,
-            ));
+    // Lay out the town grid, all of it drawn as one tilemap
+    let saved_cells = town_save.map_or(&[][..], |save| save.cells.as_slice());
+    town_map.set_elevation(town_save.map_or(&[][..], |save| save.elevation.as_slice()));
+    town_map.set_water(town_save.map_or(&[][..], |save| save.water.as_slice()));
+    load_layout(&mut town_map, &mut tiles, saved_cells, time_of_day.season());
+    spawn_tilemap(&mut commands, &mut meshes, &mut materials, &mut tiles);
+    
+    // Add the stats bar and UI for tools
+    spawn_hud(&mut commands);
+    setup_town_ui(&mut commands, &mut images);
+}
+
+// Lay out the shown town from stored cells, those left out are empty. The ground keeps its
+// elevation and water
+pub fn load_layout(town_map: &mut TownMap, tiles: &mut TownTiles, cells: &[CellSave], season: Season) {
+    let mut saved_cells = [[None; TOWN_GRID_SIZE]; TOWN_GRID_SIZE];
+    for saved in cells {
+        if Grid::is_in_bounds(saved.position, TOWN_GRID_SIZE) {
+            saved_cells[saved.position.y as usize][saved.position.x as usize] = Some(*saved);
         }
     }
-    
-    // Add UI for tools
-    setup_town_ui(&mut commands);
+    let ground: Vec<(f32, f32, bool)> = town_map
+        .iter()
+        .map(|cell| (cell.elevation, cell.slope, cell.water))
+        .collect();
+    town_map.load((0..TOWN_GRID_SIZE * TOWN_GRID_SIZE).map(|index| {
+        let position = index_to_position(index);
+        let saved = saved_cells[position.y as usize][position.x as usize];
+        let (elevation, slope, water) = ground[index];
+        TownCell {
+            position,
+            zone: saved.map_or(ZoneType::None, |saved| saved.zone),
+            building: saved.map_or(BuildingType::None, |saved| saved.building),
+            density: saved.map_or(0, |saved| saved.density),
+            accessible: false,
+            elevation,
+            slope,
+            water,
+        }
+    }));
+    for cell in town_map.iter() {
+        tiles.set(cell.position, get_cell_color(cell, None, season));
+    }
 }
 
 // Setup town UI
-fn setup_town_ui(commands: &mut Commands) {
+fn setup_town_ui(commands: &mut Commands, images: &mut Assets<Image>) {
+    // Minimap in the corner, below the stats bar
+    spawn_minimap(commands, images);
+    
+    // Description of the hovered cell
+    spawn_tooltip(commands);
+    
+    // Details of the clicked citizen
+    spawn_citizen_panel(commands);
+    
     commands
-        .spawn(NodeBundle {
+        .spawn((NodeBundle {
             style: Style {
                 width: Val::Percent(100.0),
                 height: Val::Px(50.0),
@@ -148,22 +803,76 @@ fn setup_town_ui(commands: &mut Commands) {
                 align_items: AlignItems::Center,
                 ..default()
             },
-            background_color: Color::rgba(0.1, 0.1, 0.1, 0.7).into(),
+            background_color: Color::srgba(0.1, 0.1, 0.1, 0.7).into(),
             ..default()
-        })
+        }, Interaction::default()))
         .with_children(|parent| {
             // Road tool
             create_tool_button(parent, "Road", BuildingType::Road);
+            create_tool_button(parent, "One-way", BuildingType::OneWayRoad(RoadDirection::East));
+            create_tool_button(parent, "Avenue", BuildingType::Avenue);
+            create_tool_button(parent, "Highway", BuildingType::Highway);
+            create_tool_button(parent, "Bridge", BuildingType::Bridge);
             
             // Zone tools
-            create_zone_button(parent, "R", ZoneType::Residential, Color::rgb(0.0, 0.8, 0.0));
-            create_zone_button(parent, "C", ZoneType::Commercial, Color::rgb(0.0, 0.0, 0.8));
-            create_zone_button(parent, "I", ZoneType::Industrial, Color::rgb(0.8, 0.8, 0.0));
+            create_zone_button(parent, "R", ZoneType::Residential, Color::srgb(0.0, 0.8, 0.0));
+            create_zone_button(parent, "C", ZoneType::Commercial, Color::srgb(0.0, 0.0, 0.8));
+            create_zone_button(parent, "I", ZoneType::Industrial, Color::srgb(0.8, 0.8, 0.0));
             
             // Building tools
             create_tool_button(parent, "Town Hall", BuildingType::TownHall);
             create_tool_button(parent, "Power", BuildingType::PowerPlant);
             create_tool_button(parent, "Water", BuildingType::WaterTower);
+            create_tool_button(parent, "Landfill", BuildingType::Landfill);
+            create_tool_button(parent, "Recycling", BuildingType::RecyclingCenter);
+            
+            // Services, unlocked by milestones
+            create_tool_button(parent, "Park", BuildingType::Park);
+            create_tool_button(parent, "School", BuildingType::School);
+            create_tool_button(parent, "Police", BuildingType::Police);
+            create_tool_button(parent, "Fire", BuildingType::Fire);
+            create_tool_button(parent, "Hospital", BuildingType::Hospital);
+            
+            // Public transit
+            create_tool_button(parent, "Bus stop", BuildingType::BusStop);
+            spawn_route_button(parent);
+            
+            // Bulk operations on a rectangle of cells
+            spawn_select_button(parent);
+            spawn_blueprints_button(parent);
+            
+            // Data overlays
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(100.0),
+                            height: Val::Px(40.0),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        background_color: Color::srgb(0.3, 0.3, 0.3).into(),
+                        ..default()
+                    },
+                    OverlayButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            OverlayMode::None.label(),
+                            TextStyle {
+                                font_size: 16.0,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ),
+                        OverlayLabel,
+                    ));
+                });
+            
+            // Budget panel
+            spawn_budget_button(parent);
             
             // Back to island view button
             parent
@@ -175,7 +884,7 @@ fn setup_town_ui(commands: &mut Commands) {
                         align_items: AlignItems::Center,
                         ..default()
                     },
-                    background_color: Color::rgb(0.8, 0.2, 0.2).into(),
+                    background_color: Color::srgb(0.8, 0.2, 0.2).into(),
                     ..default()
                 })
                 .with_children(|parent| {
@@ -189,6 +898,30 @@ fn setup_town_ui(commands: &mut Commands) {
                     ));
                 });
         });
+    
+    // Town Hall departments and their upgrades
+    commands
+        .spawn((NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(10.0),
+                bottom: Val::Px(150.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            ..default()
+        }, Interaction::default()))
+        .with_children(|parent| {
+            create_tool_button(parent, "Law", BuildingType::LawAndOrder);
+            create_tool_button(parent, "Education", BuildingType::Education);
+            create_tool_button(parent, "Transport", BuildingType::Transportation);
+            create_tool_button(parent, "Health", BuildingType::Health);
+            create_tool_button(parent, "Energy", BuildingType::Energy);
+            create_tool_button(parent, "Housing", BuildingType::Housing);
+            create_tool_button(parent, "Social", BuildingType::SocialServices);
+            create_tool_button(parent, "Upgrade", BuildingType::Upgrade);
+        });
 }
 
 // Create a tool button
@@ -203,20 +936,23 @@ fn create_tool_button(parent: &mut ChildBuilder, name: &str, building_type: Buil
                     align_items: AlignItems::Center,
                     ..default()
                 },
-                background_color: Color::rgb(0.3, 0.3, 0.3).into(),
+                background_color: Color::srgb(0.3, 0.3, 0.3).into(),
                 ..default()
             },
             ToolButton { building_type, zone_type: ZoneType::None },
         ))
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_section(
-                name,
-                TextStyle {
-                    font_size: 16.0,
-                    color: Color::WHITE,
-                    ..default()
-                },
-            ));
+            parent.spawn(
+                TextBundle::from_section(
+                    format!("{}\n${}", name, building_type.cost()),
+                    TextStyle {
+                        font_size: 14.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                )
+                .with_text_justify(JustifyText::Center),
+            );
         });
 }
 
@@ -251,9 +987,9 @@ fn create_zone_button(parent: &mut ChildBuilder, name: &str, zone_type: ZoneType
 
 // Tool button component
 #[derive(Component)]
-struct ToolButton {
-    building_type: BuildingType,
-    zone_type: ZoneType,
+pub struct ToolButton {
+    pub building_type: BuildingType,
+    pub zone_type: ZoneType,
 }
 
 // Currently selected tool
@@ -263,18 +999,11 @@ struct SelectedTool {
     zone_type: Option<ZoneType>,
 }
 
-// Handle town interaction
-fn handle_town_interaction(
-    mut commands: Commands,
-    mut town_cells: Query<(&mut Sprite, &mut TownCell)>,
-    mouse_button_input: Res<ButtonInput<MouseButton>>,
-    windows: Query<&Window>,
-    camera_q: Query<(&Camera, &GlobalTransform)>,
+// Handle tool selection
+fn select_tool(
     tool_buttons: Query<(&Interaction, &ToolButton), (Changed<Interaction>, With<Button>)>,
-    mut selected_tool: Local<SelectedTool>,
-    mut next_state: ResMut<NextState<GameState>>,
+    mut selected_tool: ResMut<SelectedTool>,
 ) {
-    // Handle tool selection
     for (interaction, tool_button) in tool_buttons.iter() {
         if *interaction == Interaction::Pressed {
             if tool_button.building_type != BuildingType::None {
@@ -286,80 +1015,942 @@ fn handle_town_interaction(
             }
         }
     }
-    
-    // Handle mouse clicks
-    if mouse_button_input.just_pressed(MouseButton::Left) {
-        let window = windows.single();
-        let (camera, camera_transform) = camera_q.single();
-        
-        if let Some(cursor_position) = window.cursor_position() {
-            if let Some(world_position) = camera.viewport_to_world_2d(camera_transform, cursor_position) {
-                // Convert world position to grid position
-                let grid_x = ((world_position.x / 12.0) + TOWN_GRID_SIZE as f32 / 2.0).floor() as i32;
-                let grid_y = ((world_position.y / 12.0) + TOWN_GRID_SIZE as f32 / 2.0).floor() as i32;
-                
-                // Check if the position is within the grid
-                if grid_x >= 0 && grid_x < TOWN_GRID_SIZE as i32 && grid_y >= 0 && grid_y < TOWN_GRID_SIZE as i32 {
-                    // Apply the selected tool to the cell
-                    for (mut sprite, mut cell) in town_cells.iter_mut() {
-                        if cell.position.x == grid_x && cell.position.y == grid_y {
-                            if let Some(building_type) = selected_tool.building_type {
-                                cell.building = building_type;
-                                cell.zone = ZoneType::None;
-                            } else if let Some(zone_type) = selected_tool.zone_type {
-                                cell.zone = zone_type;
-                                // Only clear the building if it's not a road
-                                if cell.building != BuildingType::Road {
-                                    cell.building = BuildingType::None;
-                                }
-                            }
-                            
-                            // Update the cell color
-                            sprite.color = get_cell_color(&cell);
-                        }
+}
+
+// Build on a cell as the building tools do. The placement rules are checked with construction
+// sites counting as the building going up on them, and building over something else refunds
+// part of what it cost
+fn try_place_building(
+    position: IVec2,
+    building_type: BuildingType,
+    town_map: &mut TownMap,
+    construction: &mut Construction,
+    economy: &mut Economy,
+    road_events: &mut EventWriter<RoadChanged>,
+) -> Result<(), PlaceError> {
+    let planned = |cell: &TownCell| construction.building_at(cell.position).unwrap_or(cell.building);
+    let neighbors: Vec<BuildingType> = Grid::get_orthogonal_positions(position)
+        .into_iter()
+        .filter_map(|neighbor| town_map.get(neighbor))
+        .map(planned)
+        .collect();
+    let town_halls = town_map
+        .iter()
+        .filter(|cell| planned(cell) == BuildingType::TownHall)
+        .count();
+    // Nothing to build on off the grid
+    let Some(mut cell) = town_map.get(position).copied() else {
+        return Ok(());
+    };
+    cell.building = planned(&cell);
+    if cell.building == building_type {
+        return Ok(());
+    }
+    can_place(building_type, &cell, &neighbors, town_halls)?;
+    let cost = cell.build_cost(building_type) - cell.building.refund();
+    if cost > economy.funds {
+        return Err(PlaceError::InsufficientFunds(cost));
+    }
+
+    economy.funds -= cost;
+    let previous = cell.building;
+    let Some(cell) = town_map.cell_mut(position) else {
+        return Ok(());
+    };
+    // Roads are laid at once, buildings take a moment to go up
+    if building_type.needs_construction() {
+        cell.building = BuildingType::None;
+        construction.start(position, building_type);
+    } else {
+        cell.building = building_type;
+        construction.sites.remove(&position);
+    }
+    cell.zone = ZoneType::None;
+    cell.density = 0;
+    if road_changed(previous, cell.building) {
+        road_events.send(RoadChanged { position });
+    }
+    Ok(())
+}
+
+// Zone a cell as the zone tools do, clearing whatever but a road is on it or going up on it
+// for part of what it cost. Plays the sound of what was done, nothing when it's zoned already
+fn try_zone_cell(
+    position: IVec2,
+    zone_type: ZoneType,
+    town_map: &mut TownMap,
+    construction: &mut Construction,
+    economy: &mut Economy,
+    road_events: &mut EventWriter<RoadChanged>,
+    sounds: &mut EventWriter<BuildSound>,
+) -> Result<(), PlaceError> {
+    let Some(mut cell) = town_map.get(position).copied() else {
+        return Ok(());
+    };
+    // Construction sites count as the building going up on them
+    let abandoned = construction.building_at(position);
+    cell.building = abandoned.unwrap_or(cell.building);
+    can_zone(&cell)?;
+    let clears = abandoned.is_some() || (!cell.building.is_road() && cell.building != BuildingType::None);
+    if cell.zone == zone_type && !clears {
+        return Ok(());
+    }
+
+    let previous = cell.building;
+    let Some(cell) = town_map.cell_mut(position) else {
+        return Ok(());
+    };
+    cell.zone = zone_type;
+    cell.density = 0;
+    construction.sites.remove(&position);
+    // Only clear the building if it's not a road
+    if clears {
+        if !previous.is_road() {
+            economy.funds += previous.refund();
+        }
+        cell.building = BuildingType::None;
+        sounds.send(BuildSound::Demolish);
+    } else {
+        sounds.send(BuildSound::Zone);
+    }
+    if road_changed(previous, cell.building) {
+        road_events.send(RoadChanged { position });
+    }
+    Ok(())
+}
+
+// Clear a cell, refunding part of what its building cost. A building still going up is
+// refunded like a finished one. Returns whether there was anything to clear
+fn try_demolish(
+    position: IVec2,
+    town_map: &mut TownMap,
+    construction: &mut Construction,
+    economy: &mut Economy,
+    road_events: &mut EventWriter<RoadChanged>,
+) -> Result<bool, PlaceError> {
+    let Some(cell) = town_map.get(position) else {
+        return Ok(false);
+    };
+    if cell.building == BuildingType::TownHall {
+        return Err(PlaceError::TownHallInTheWay);
+    }
+    let site = construction.building_at(position);
+    if cell.building == BuildingType::None && cell.zone == ZoneType::None && site.is_none() {
+        return Ok(false);
+    }
+
+    construction.sites.remove(&position);
+    let Some(cell) = town_map.cell_mut(position) else {
+        return Ok(false);
+    };
+    economy.funds += site.unwrap_or(cell.building).refund();
+    let previous = cell.building;
+    cell.building = BuildingType::None;
+    cell.zone = ZoneType::None;
+    cell.density = 0;
+    if road_changed(previous, cell.building) {
+        road_events.send(RoadChanged { position });
+    }
+    Ok(true)
+}
+
+// Tint a refused cell red for a moment and say why it was refused
+fn refuse_placement(
+    position: IVec2,
+    error: PlaceError,
+    tiles: &mut TownTiles,
+    flashes: &mut PlacementFlashes,
+    notifications: &mut EventWriter<Notification>,
+    sounds: &mut EventWriter<BuildSound>,
+) {
+    tiles.set(position, PLACEMENT_ERROR_COLOR);
+    flashes.cells.push((position, Timer::from_seconds(PLACEMENT_FLASH_SECONDS, TimerMode::Once)));
+    notifications.send(Notification::warning(error.to_string()));
+    sounds.send(BuildSound::Refused);
+}
+
+// Update a changed cell's color, the overlay repaints itself once land value is recomputed
+fn repaint_cell(position: IVec2, town_map: &TownMap, tiles: &mut TownTiles, overlay: &OverlayMode, season: Season) {
+    if *overlay != OverlayMode::None {
+        return;
+    }
+    if let Some(cell) = town_map.get(position) {
+        tiles.set(position, get_cell_color(cell, None, season));
+    }
+}
+
+// Handle town interaction
+#[allow(clippy::too_many_arguments)]
+fn handle_town_interaction(
+    mut town_map: ResMut<TownMap>,
+    mut tiles: ResMut<TownTiles>,
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    selected_tool: Res<SelectedTool>,
+    mut construction: ResMut<Construction>,
+    overlay: Res<OverlayMode>,
+    mut road_events: EventWriter<RoadChanged>,
+    ui_interactions: Query<&Interaction, With<Node>>,
+    mut flashes: ResMut<PlacementFlashes>,
+    time_of_day: Res<TimeOfDay>,
+    mut economy: ResMut<Economy>,
+    mut sounds: EventWriter<BuildSound>,
+    mut notifications: EventWriter<Notification>,
+) {
+    // Clicks on UI elements (tool buttons, minimap) shouldn't reach the cells below
+    let over_ui = ui_interactions.iter().any(|interaction| *interaction != Interaction::None);
+    if !mouse_button_input.just_pressed(MouseButton::Left) || over_ui {
+        return;
+    }
+    let window = windows.single();
+    let (camera, camera_transform) = camera_q.single();
+    let Some(position) = cursor_grid_position(window, camera, camera_transform) else {
+        return;
+    };
+
+    // Apply the selected tool to the cell, refused cells flash red
+    if let Some(building_type) = selected_tool.building_type {
+        let placed = try_place_building(
+            position,
+            building_type,
+            &mut town_map,
+            &mut construction,
+            &mut economy,
+            &mut road_events,
+        );
+        match placed {
+            Ok(()) => {
+                sounds.send(BuildSound::Building);
+                repaint_cell(position, &town_map, &mut tiles, &overlay, time_of_day.season());
+            }
+            Err(error) => refuse_placement(position, error, &mut tiles, &mut flashes, &mut notifications, &mut sounds),
+        }
+    } else if let Some(zone_type) = selected_tool.zone_type {
+        let zoned = try_zone_cell(
+            position,
+            zone_type,
+            &mut town_map,
+            &mut construction,
+            &mut economy,
+            &mut road_events,
+            &mut sounds,
+        );
+        match zoned {
+            Ok(()) => repaint_cell(position, &town_map, &mut tiles, &overlay, time_of_day.season()),
+            Err(error) => refuse_placement(position, error, &mut tiles, &mut flashes, &mut notifications, &mut sounds),
+        }
+    }
+}
+
+// Changes to a single cell asked for outside the build tools, e.g. from the context menu
+#[derive(Event, Clone, Copy)]
+pub enum CellCommand {
+    // Clear the cell, refunding part of what its building cost
+    Demolish(IVec2),
+    // Build an upgrade on a free cell next to the department on the cell
+    Upgrade(IVec2),
+    // Zone the cell as the zone tool would, cells it can't zone are left as they are
+    Zone(IVec2, ZoneType),
+    // Build on the cell as the building tools would, following the same placement rules
+    Build(IVec2, BuildingType),
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_cell_commands(
+    mut commands: EventReader<CellCommand>,
+    mut town_map: ResMut<TownMap>,
+    mut tiles: ResMut<TownTiles>,
+    mut construction: ResMut<Construction>,
+    mut economy: ResMut<Economy>,
+    overlay: Res<OverlayMode>,
+    time_of_day: Res<TimeOfDay>,
+    mut road_events: EventWriter<RoadChanged>,
+    mut flashes: ResMut<PlacementFlashes>,
+    mut sounds: EventWriter<BuildSound>,
+    mut notifications: EventWriter<Notification>,
+) {
+    for command in commands.read() {
+        match *command {
+            CellCommand::Demolish(position) => {
+                match try_demolish(position, &mut town_map, &mut construction, &mut economy, &mut road_events) {
+                    Ok(true) => {
+                        sounds.send(BuildSound::Demolish);
+                        repaint_cell(position, &town_map, &mut tiles, &overlay, time_of_day.season());
+                    }
+                    Ok(false) => {}
+                    Err(error) => {
+                        refuse_placement(position, error, &mut tiles, &mut flashes, &mut notifications, &mut sounds)
+                    }
+                }
+            }
+            CellCommand::Zone(position, zone_type) => {
+                let zoned = try_zone_cell(
+                    position,
+                    zone_type,
+                    &mut town_map,
+                    &mut construction,
+                    &mut economy,
+                    &mut road_events,
+                    &mut sounds,
+                );
+                // Cells that can't be zoned are left as they are, without a fuss
+                if zoned.is_ok() {
+                    repaint_cell(position, &town_map, &mut tiles, &overlay, time_of_day.season());
+                }
+            }
+            CellCommand::Build(position, building_type) => {
+                let placed = try_place_building(
+                    position,
+                    building_type,
+                    &mut town_map,
+                    &mut construction,
+                    &mut economy,
+                    &mut road_events,
+                );
+                match placed {
+                    Ok(()) => {
+                        sounds.send(BuildSound::Building);
+                        repaint_cell(position, &town_map, &mut tiles, &overlay, time_of_day.season());
+                    }
+                    Err(error) => {
+                        refuse_placement(position, error, &mut tiles, &mut flashes, &mut notifications, &mut sounds)
+                    }
+                }
+            }
+            CellCommand::Upgrade(position) => {
+                let department = town_map.get(position).map_or(BuildingType::None, |cell| cell.building);
+                if !department.is_department() {
+                    continue;
+                }
+                // An empty neighbor the upgrade could go on under the placement rules, with
+                // construction sites counting as the building going up on them
+                let planned = |cell: &TownCell| construction.building_at(cell.position).unwrap_or(cell.building);
+                let town_halls = town_map
+                    .iter()
+                    .filter(|cell| planned(cell) == BuildingType::TownHall)
+                    .count();
+                let free = Grid::get_orthogonal_positions(position)
+                    .into_iter()
+                    .filter_map(|neighbor| town_map.get(neighbor))
+                    .filter(|cell| planned(cell) == BuildingType::None)
+                    .find(|cell| {
+                        let neighbors: Vec<BuildingType> = Grid::get_orthogonal_positions(cell.position)
+                            .into_iter()
+                            .filter_map(|neighbor| town_map.get(neighbor))
+                            .map(planned)
+                            .collect();
+                        can_place(BuildingType::Upgrade, cell, &neighbors, town_halls).is_ok()
+                    });
+                let Some(neighbor) = free.map(|cell| cell.position) else {
+                    notifications.send(Notification::warning(format!(
+                        "There's no free cell next to {} for an upgrade",
+                        department.label()
+                    )));
+                    sounds.send(BuildSound::Refused);
+                    continue;
+                };
+                let placed = try_place_building(
+                    neighbor,
+                    BuildingType::Upgrade,
+                    &mut town_map,
+                    &mut construction,
+                    &mut economy,
+                    &mut road_events,
+                );
+                match placed {
+                    Ok(()) => {
+                        sounds.send(BuildSound::Building);
+                    }
+                    Err(error) => {
+                        refuse_placement(neighbor, error, &mut tiles, &mut flashes, &mut notifications, &mut sounds)
                     }
                 }
             }
         }
     }
-    
-    // Handle back button
-    if mouse_button_input.just_pressed(MouseButton::Right) {
-        next_state.set(GameState::IslandView);
+}
+
+// Seconds of game time a building takes to go up, it doesn't count in the simulation until then
+const CONSTRUCTION_SECONDS: f32 = 1.5;
+
+// Color a construction site starts out in, turning into the building's color as it goes up
+const SCAFFOLDING_COLOR: Color = Color::srgb(0.75, 0.55, 0.25);
+
+pub struct ConstructionSite {
+    pub building: BuildingType,
+    pub timer: Timer,
+}
+
+// Buildings going up, by cell. Their cells stay empty until the building is finished
+#[derive(Resource, Default)]
+pub struct Construction {
+    pub sites: HashMap<IVec2, ConstructionSite>,
+}
+
+impl Construction {
+    pub fn building_at(&self, pos: IVec2) -> Option<BuildingType> {
+        self.sites.get(&pos).map(|site| site.building)
     }
+
+    // Start a building on a cell, carrying on if it's already going up there
+    fn start(&mut self, pos: IVec2, building: BuildingType) {
+        if self.building_at(pos) != Some(building) {
+            self.sites.insert(pos, ConstructionSite {
+                building,
+                timer: Timer::from_seconds(CONSTRUCTION_SECONDS, TimerMode::Once),
+            });
+        }
+    }
+
+    // Put up every building at once, e.g. when the town is left
+    fn finish_all(&mut self, town_map: &mut TownMap) {
+        for (position, site) in self.sites.drain() {
+            if let Some(cell) = town_map.cell_mut(position) {
+                cell.building = site.building;
+            }
+        }
+    }
+}
+
+// Finish buildings whose construction time is up, meanwhile fading the scaffolding into the building
+fn advance_construction(
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    mut construction: ResMut<Construction>,
+    mut town_map: ResMut<TownMap>,
+    mut tiles: ResMut<TownTiles>,
+    mut overlay: ResMut<OverlayMode>,
+    time_of_day: Res<TimeOfDay>,
+) {
+    if construction.sites.is_empty() {
+        return;
+    }
+    let delta = speed.delta(&time);
+    let mut finished = Vec::new();
+    for (&position, site) in construction.sites.iter_mut() {
+        if site.timer.tick(delta).finished() {
+            finished.push(position);
+            continue;
+        }
+        let Some(cell) = town_map.get(position) else {
+            continue;
+        };
+        if *overlay == OverlayMode::None {
+            let built = TownCell { building: site.building, ..*cell };
+            let color = get_cell_color(&built, None, time_of_day.season());
+            tiles.set(position, SCAFFOLDING_COLOR.mix(&color, site.timer.fraction()));
+        }
+    }
+
+    for position in finished {
+        let (Some(site), Some(cell)) = (construction.sites.remove(&position), town_map.cell_mut(position)) else {
+            continue;
+        };
+        cell.building = site.building;
+        if *overlay == OverlayMode::None {
+            tiles.set(position, get_cell_color(cell, None, time_of_day.season()));
+        } else {
+            // Let the overlay repaint the cell
+            overlay.set_changed();
+        }
+    }
+}
+
+// Why a building or zone can't go on a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceError {
+    // The town already has its Town Hall
+    SecondTownHall,
+    // The Town Hall can't be built over
+    TownHallInTheWay,
+    NoAdjacentTownHall,
+    NoAdjacentDepartment,
+    // Only roads go on ground this steep
+    TooSteep,
+    // Bridges go on water and nothing else does
+    NotOverWater,
+    OverWater,
+    // Only roads go right beside a highway
+    BesideHighway,
+    // Funds the placement would take
+    InsufficientFunds(i32),
+}
+
+impl fmt::Display for PlaceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlaceError::SecondTownHall => write!(f, "The town already has a Town Hall"),
+            PlaceError::TownHallInTheWay => write!(f, "The Town Hall can't be built over"),
+            PlaceError::NoAdjacentTownHall => {
+                write!(f, "Departments have to be built next to the Town Hall")
+            }
+            PlaceError::NoAdjacentDepartment => {
+                write!(f, "Upgrades have to be built next to a department")
+            }
+            PlaceError::TooSteep => write!(f, "The ground is too steep, only roads can go here"),
+            PlaceError::NotOverWater => write!(f, "Bridges can only be built over water"),
+            PlaceError::OverWater => write!(f, "Only bridges can be built over water"),
+            PlaceError::BesideHighway => write!(f, "Only roads can be built right beside a highway"),
+            PlaceError::InsufficientFunds(cost) => {
+                write!(f, "Not enough funds, this costs ${}", cost)
+            }
+        }
+    }
+}
+
+// Check whether a building can go on a cell, given the buildings on the orthogonal neighbors
+// and the number of Town Halls in the town
+pub fn can_place(
+    building: BuildingType,
+    cell: &TownCell,
+    neighbors: &[BuildingType],
+    town_halls: usize,
+) -> Result<(), PlaceError> {
+    if cell.building == building {
+        return Ok(());
+    }
+    if cell.building == BuildingType::TownHall {
+        return Err(PlaceError::TownHallInTheWay);
+    }
+    if building == BuildingType::TownHall && town_halls > 0 {
+        return Err(PlaceError::SecondTownHall);
+    }
+    if building.is_department() && !neighbors.contains(&BuildingType::TownHall) {
+        return Err(PlaceError::NoAdjacentTownHall);
+    }
+    if building == BuildingType::Upgrade && !neighbors.iter().any(BuildingType::is_department) {
+        return Err(PlaceError::NoAdjacentDepartment);
+    }
+    if building == BuildingType::Bridge && !cell.water {
+        return Err(PlaceError::NotOverWater);
+    }
+    if building != BuildingType::Bridge && cell.water {
+        return Err(PlaceError::OverWater);
+    }
+    if !building.is_road() && cell.is_steep() {
+        return Err(PlaceError::TooSteep);
+    }
+    let is_building = |building: &BuildingType| *building != BuildingType::None && !building.is_road();
+    if (building == BuildingType::Highway && neighbors.iter().any(is_building))
+        || (is_building(&building) && neighbors.contains(&BuildingType::Highway))
+    {
+        return Err(PlaceError::BesideHighway);
+    }
+    Ok(())
+}
+
+// Check whether a cell can be zoned
+pub fn can_zone(cell: &TownCell) -> Result<(), PlaceError> {
+    if cell.building == BuildingType::TownHall {
+        return Err(PlaceError::TownHallInTheWay);
+    }
+    if cell.water {
+        return Err(PlaceError::OverWater);
+    }
+    if cell.is_steep() {
+        return Err(PlaceError::TooSteep);
+    }
+    Ok(())
+}
+
+// Refused cells are tinted this color for a moment
+const PLACEMENT_ERROR_COLOR: Color = Color::srgb(0.9, 0.1, 0.1);
+const PLACEMENT_FLASH_SECONDS: f32 = 0.3;
+
+// Cells showing that a placement on them was refused, until their timers finish
+#[derive(Resource, Default)]
+struct PlacementFlashes {
+    cells: Vec<(IVec2, Timer)>,
+}
+
+// Restore flashed cells to their normal color
+fn fade_placement_flash(
+    time: Res<Time>,
+    mut flashes: ResMut<PlacementFlashes>,
+    town_map: Res<TownMap>,
+    mut tiles: ResMut<TownTiles>,
+    mut overlay: ResMut<OverlayMode>,
+    time_of_day: Res<TimeOfDay>,
+) {
+    if flashes.cells.is_empty() {
+        return;
+    }
+    for (_, timer) in flashes.cells.iter_mut() {
+        timer.tick(time.delta());
+    }
+    let (faded, flashing): (Vec<_>, Vec<_>) =
+        flashes.cells.drain(..).partition(|(_, timer)| timer.finished());
+    flashes.cells = flashing;
+
+    for (position, _) in faded {
+        let Some(cell) = town_map.get(position) else {
+            continue;
+        };
+        if *overlay == OverlayMode::None {
+            tiles.set(position, get_cell_color(cell, None, time_of_day.season()));
+        } else {
+            // Let the overlay repaint the cell
+            overlay.set_changed();
+        }
+    }
+}
+
+// Convert a world position to the town grid cell under it, if any
+pub fn world_to_grid(world_position: Vec2) -> Option<IVec2> {
+    let grid = (world_position / TOWN_CELL_SPACING + Vec2::splat(TOWN_GRID_SIZE as f32 / 2.0))
+        .round()
+        .as_ivec2();
+    Grid::is_in_bounds(grid, TOWN_GRID_SIZE).then_some(grid)
+}
+
+// Convert a town grid cell to the world position of its center, at the given depth
+pub fn grid_to_world(pos: IVec2, z: f32) -> Vec3 {
+    ((pos.as_vec2() - Vec2::splat(TOWN_GRID_SIZE as f32 / 2.0)) * TOWN_CELL_SPACING).extend(z)
+}
+
+// Get the town grid cell under the cursor, if any
+pub fn cursor_grid_position(window: &Window, camera: &Camera, camera_transform: &GlobalTransform) -> Option<IVec2> {
+    let cursor_position = window.cursor_position()?;
+    let world_position = camera.viewport_to_world_2d(camera_transform, cursor_position)?;
+    world_to_grid(world_position)
 }
 
 // Update town simulation
-fn update_town_simulation(time: Res<Time>, mut town_cells: Query<(&mut Sprite, &mut TownCell)>) {
-    // This would be where we update the simulation
-    // For now, we'll just update the colors of cells with zones to simulate development
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_town_simulation(
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    mut timer: Local<Timer>,
+    mut town_map: ResMut<TownMap>,
+    mut tiles: ResMut<TownTiles>,
+    overlay: Res<OverlayMode>,
+    demand: Res<Demand>,
+    land_value: Res<LandValue>,
+    parks: Res<ParkCoverage>,
+    resources: Option<Res<Resources>>,
+    brownout: Res<Brownout>,
+    abandonment: Res<Abandonment>,
+    time_of_day: Res<TimeOfDay>,
+    mut game_rng: ResMut<GameRng>,
+) {
+    // Initialize timer if needed
+    if timer.duration() == Duration::ZERO {
+        *timer = Timer::from_seconds(DEVELOPMENT_INTERVAL_SECONDS, TimerMode::Repeating);
+    }
     
-    // Only update every 0.5 seconds
-    if (time.elapsed_seconds() * 2.0).floor() % 2.0 != 0.0 {
+    // Zones develop once per interval of game time, so not while the game is paused
+    timer.tick(speed.delta(&time));
+    if !timer.just_finished() {
         return;
     }
     
-    for (mut sprite, cell) in town_cells.iter_mut() {
-        if cell.zone != ZoneType::None && cell.building == BuildingType::None {
-            // Randomly update some cells to simulate development
-            if rand::random::<f32>() < 0.01 {
-                sprite.color = match cell.zone {
-                    ZoneType::Residential => Color::rgb(0.0, 0.7, 0.0),
-                    ZoneType::Commercial => Color::rgb(0.0, 0.0, 0.7),
-                    ZoneType::Industrial => Color::rgb(0.7, 0.7, 0.0),
-                    ZoneType::None => Color::rgb(0.2, 0.2, 0.2),
-                };
+    // Zones develop slower without power and water
+    let service_factor = match resources {
+        Some(resources) if resources.power.storage <= 0 || resources.water.storage <= 0 => 0.5,
+        _ => 1.0,
+    };
+    
+    let mut developed = Vec::new();
+    for cell in town_map.iter() {
+        if cell.zone == ZoneType::None || cell.building != BuildingType::None || cell.density >= MAX_DENSITY {
+            continue;
+        }
+        
+        // Nothing gets built in the dark, or where the last occupants gave up
+        if brownout.is_dark(cell.position) || abandonment.is_abandoned(cell.position) {
+            continue;
+        }
+        
+        // Demand for the zone type gates whether and how fast its cells densify
+        let zone_demand = match cell.zone {
+            ZoneType::Residential => demand.residential,
+            ZoneType::Commercial => demand.commercial,
+            ZoneType::Industrial => demand.industrial,
+            ZoneType::None => 0.0,
+        };
+        if zone_demand < DEVELOPMENT_DEMAND_THRESHOLD {
+            continue;
+        }
+        
+        // Homes near parks are sought after
+        let park_factor = if cell.zone == ZoneType::Residential {
+            1.0 + PARK_GROWTH_BONUS * parks.get(cell.position)
+        } else {
+            1.0
+        };
+        let chance = DENSITY_GROWTH_CHANCE
+            * zone_demand
+            * (0.5 + land_value.get(cell.position))
+            * service_factor
+            * park_factor;
+        if game_rng.rng.gen::<f32>() < chance {
+            developed.push(cell.position);
+        }
+    }
+    
+    for position in developed {
+        let Some(cell) = town_map.cell_mut(position) else {
+            continue;
+        };
+        cell.density += 1;
+        
+        // Development colors would paint over the active overlay
+        if *overlay == OverlayMode::None {
+            tiles.set(position, get_cell_color(cell, None, time_of_day.season()));
+        }
+    }
+}
+
+// Toggle the land value (L) and congestion (T) overlays, or cycle through all of them with the
+// overlay button or O, keys as bound
+fn toggle_overlay(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<OverlayButton>)>,
+    mut overlay: ResMut<OverlayMode>,
+    mut labels: Query<&mut Text, With<OverlayLabel>>,
+) {
+    let clicked = buttons.iter().any(|interaction| *interaction == Interaction::Pressed);
+    let toggled = if key_bindings.just_pressed(InputAction::LandValueOverlay, &keyboard_input) {
+        Some(OverlayMode::LandValue)
+    } else if key_bindings.just_pressed(InputAction::CongestionOverlay, &keyboard_input) {
+        Some(OverlayMode::Congestion)
+    } else {
+        None
+    };
+    *overlay = match toggled {
+        Some(mode) if *overlay == mode => OverlayMode::None,
+        Some(mode) => mode,
+        None if clicked || key_bindings.just_pressed(InputAction::CycleOverlay, &keyboard_input) => {
+            overlay.next()
+        }
+        None => return,
+    };
+    for mut text in labels.iter_mut() {
+        text.sections[0].value = overlay.label().to_string();
+    }
+}
+
+fn toggle_grid_lines(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut grid_lines: ResMut<GridLines>,
+) {
+    if key_bindings.just_pressed(InputAction::GridLines, &keyboard_input) {
+        grid_lines.visible = !grid_lines.visible;
+    }
+}
+
+// Draw the cell boundaries, gizmo lines keep their width on screen whatever the zoom
+fn draw_grid_lines(mut gizmos: Gizmos, grid_lines: Res<GridLines>) {
+    if !grid_lines.visible {
+        return;
+    }
+    // Cells are centered on multiples of the spacing, the grid's center is half a cell off
+    gizmos
+        .grid_2d(
+            Vec2::splat(-TOWN_CELL_SPACING / 2.0),
+            0.0,
+            UVec2::splat(TOWN_GRID_SIZE as u32),
+            Vec2::splat(TOWN_CELL_SPACING),
+            GRID_LINE_COLOR,
+        )
+        .outer_edges();
+}
+
+// Turn the one-way road tool a quarter clockwise (R, as bound), its button keeps the direction
+// for the next time it's picked
+fn rotate_one_way_tool(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut selected_tool: ResMut<SelectedTool>,
+    mut tool_buttons: Query<&mut ToolButton>,
+) {
+    if !key_bindings.just_pressed(InputAction::RotateRoad, &keyboard_input) {
+        return;
+    }
+    let Some(BuildingType::OneWayRoad(direction)) = selected_tool.building_type else {
+        return;
+    };
+    let rotated = BuildingType::OneWayRoad(direction.rotated());
+    selected_tool.building_type = Some(rotated);
+    for mut button in tool_buttons.iter_mut() {
+        if matches!(button.building_type, BuildingType::OneWayRoad(_)) {
+            button.building_type = rotated;
+        }
+    }
+}
+
+// Draw an arrow along every one-way road, pointing the way traffic flows
+fn draw_one_way_arrows(mut gizmos: Gizmos, town_map: Res<TownMap>) {
+    for cell in town_map.iter() {
+        let BuildingType::OneWayRoad(direction) = cell.building else {
+            continue;
+        };
+        let center = grid_to_world(cell.position, 0.0).truncate();
+        let half = direction.step().as_vec2() * TOWN_CELL_SPACING * 0.3;
+        gizmos
+            .arrow_2d(center - half, center + half, ONE_WAY_ARROW_COLOR)
+            .with_tip_length(TOWN_CELL_SPACING * 0.25);
+    }
+}
+
+// Draw the lights on every intersection, a bar across it for each way traffic goes through
+// colored by whether that way is green
+fn draw_traffic_lights(mut gizmos: Gizmos, lights: Res<TrafficLights>) {
+    let east_west_green = lights.east_west_green();
+    let color = |green: bool| if green { GREEN_LIGHT_COLOR } else { RED_LIGHT_COLOR };
+    let half = TOWN_CELL_SPACING * 0.3;
+    for &pos in lights.intersections() {
+        let center = grid_to_world(pos, 0.0).truncate();
+        gizmos.line_2d(center - Vec2::X * half, center + Vec2::X * half, color(east_west_green));
+        gizmos.line_2d(center - Vec2::Y * half, center + Vec2::Y * half, color(!east_west_green));
+    }
+}
+
+// Repaint the town when the overlay mode, the overlaid data or the season changes
+#[allow(clippy::too_many_arguments)]
+fn update_overlay_colors(
+    overlay: Res<OverlayMode>,
+    land_value: Res<LandValue>,
+    congestion: Res<Congestion>,
+    heatmaps: Res<Heatmaps>,
+    noise: Res<Noise>,
+    connectivity: Res<Connectivity>,
+    town_map: Res<TownMap>,
+    mut tiles: ResMut<TownTiles>,
+    time_of_day: Res<TimeOfDay>,
+    mut season_events: EventReader<SeasonChanged>,
+) {
+    let season_changed = season_events.read().count() > 0;
+    let data_changed = match *overlay {
+        OverlayMode::None => false,
+        OverlayMode::LandValue => land_value.is_changed(),
+        OverlayMode::Congestion => congestion.is_changed(),
+        OverlayMode::Pollution
+        | OverlayMode::Crime
+        | OverlayMode::Happiness
+        | OverlayMode::Power
+        | OverlayMode::Water => heatmaps.is_changed(),
+        OverlayMode::Noise => noise.is_changed(),
+        OverlayMode::Connectivity => connectivity.is_changed(),
+    };
+    if !overlay.is_changed() && !data_changed && !season_changed {
+        return;
+    }
+
+    // Every overlay uses the same ramp, green where things are good and red where they're bad
+    for cell in town_map.iter() {
+        let pos = cell.position;
+        let value = match *overlay {
+            OverlayMode::None => None,
+            OverlayMode::LandValue => Some(land_value.get(pos)),
+            // Free flowing roads are green and jammed ones red, other cells keep their colors
+            OverlayMode::Congestion if cell.building.is_road() => {
+                Some(1.0 - congestion.level(pos))
             }
+            OverlayMode::Congestion => None,
+            OverlayMode::Pollution => Some(1.0 - heatmaps.pollution(pos)),
+            OverlayMode::Crime => Some(1.0 - heatmaps.crime(pos)),
+            // Cells nobody lives on keep their colors
+            OverlayMode::Happiness => heatmaps.happiness(pos),
+            OverlayMode::Power => Some(heatmaps.power(pos)),
+            OverlayMode::Water => Some(heatmaps.water(pos)),
+            OverlayMode::Noise => Some(1.0 - noise.get(pos)),
+            // Green where the roads lead to the Town Hall and red where they don't, empty cells
+            // keep their colors
+            OverlayMode::Connectivity if cell.zone == ZoneType::None && cell.building == BuildingType::None => None,
+            OverlayMode::Connectivity => Some(if connectivity.is_connected(pos) { 1.0 } else { 0.0 }),
+        };
+        // Only touch tiles whose color changes, most don't between two updates
+        let color = get_cell_color(cell, value, time_of_day.season());
+        if tiles.get(pos) != color {
+            tiles.set(pos, color);
         }
     }
 }
 
+// Zones dark in a brownout are drawn dimmed, and abandoned ones faded to a dull brown
+const BROWNOUT_COLOR: Color = Color::srgb(0.05, 0.05, 0.1);
+const BROWNOUT_TINT: f32 = 0.6;
+const ABANDONED_COLOR: Color = Color::srgb(0.4, 0.3, 0.2);
+const ABANDONED_TINT: f32 = 0.7;
+
+// Tint the zones without power or abandoned, and draw them in their own colors again once
+// they recover. Other systems repaint cells as they change, so the tint is put back on every frame
+fn tint_cells(
+    brownout: Res<Brownout>,
+    abandonment: Res<Abandonment>,
+    town_map: Res<TownMap>,
+    overlay: Res<OverlayMode>,
+    time_of_day: Res<TimeOfDay>,
+    mut tiles: ResMut<TownTiles>,
+    mut tinted: Local<Vec<IVec2>>,
+) {
+    // Overlays paint over every cell, the tint included
+    if *overlay != OverlayMode::None {
+        tinted.clear();
+        return;
+    }
+
+    let season = time_of_day.season();
+    for pos in tinted.drain(..).filter(|pos| !brownout.is_dark(*pos) && !abandonment.is_abandoned(*pos)) {
+        if let Some(cell) = town_map.get(pos) {
+            tiles.set(pos, get_cell_color(cell, None, season));
+        }
+    }
+    // Abandoned cells are tinted as such even when dark
+    let tints = brownout
+        .cells()
+        .iter()
+        .copied()
+        .filter(|pos| !abandonment.is_abandoned(*pos))
+        .map(|pos| (pos, BROWNOUT_COLOR, BROWNOUT_TINT))
+        .chain(abandonment.cells().map(|pos| (pos, ABANDONED_COLOR, ABANDONED_TINT)));
+    for (pos, tint, amount) in tints {
+        let Some(cell) = town_map.get(pos) else {
+            continue;
+        };
+        let color = get_cell_color(cell, None, season).mix(&tint, amount);
+        if tiles.get(pos) != color {
+            tiles.set(pos, color);
+        }
+        tinted.push(pos);
+    }
+}
+
 // Clean up the town view
-fn cleanup_town(mut commands: Commands, query: Query<Entity, With<TownCell>>, ui: Query<Entity, With<Node>>, camera: Query<Entity, With<Camera2d>>) {
-    // Remove all town cells
-    for entity in query.iter() {
+#[allow(clippy::too_many_arguments)]
+fn cleanup_town(
+    mut commands: Commands,
+    mut town_map: ResMut<TownMap>,
+    mut flashes: ResMut<PlacementFlashes>,
+    mut construction: ResMut<Construction>,
+    tilemap: Query<Entity, With<TownTilemap>>,
+    ui: Query<Entity, With<Node>>,
+    camera: Query<Entity, With<Camera2d>>,
+    mut towns: ResMut<Towns>,
+    selected_town: Option<Res<SelectedTown>>,
+    transit_routes: Res<TransitRoutes>,
+) {
+    // Store the layout for when the town is visited again. The selection only lasts while the
+    // town is shown, the island picks the next one before switching back to the town view
+    // Buildings still going up are stored as finished
+    construction.finish_all(&mut town_map);
+    if let Some(selected_town) = selected_town {
+        let town_save = towns.towns.entry(selected_town.0).or_default();
+        town_save.store_cells(town_map.iter());
+        town_save.routes = transit_routes.routes.clone();
+        commands.remove_resource::<SelectedTown>();
+    }
+    
+    // Remove the town's tiles
+    for entity in tilemap.iter() {
         commands.entity(entity).despawn();
     }
+    town_map.unload();
+    flashes.cells.clear();
     
     // Remove UI
     for entity in ui.iter() {
@@ -372,33 +1963,246 @@ fn cleanup_town(mut commands: Commands, query: Query<Entity, With<TownCell>>, ui
     }
 }
 
-// Helper function to get the color for a cell based on its zone and building
-fn get_cell_color(cell: &TownCell) -> Color {
+// Helper function to get the color for a cell based on its zone and building,
+// or on a low (red) to high (green) ramp when an overlay value in [0, 1] is given
+pub(crate) fn get_cell_color(cell: &TownCell, overlay: Option<f32>, season: Season) -> Color {
+    if let Some(value) = overlay {
+        let value = value.clamp(0.0, 1.0);
+        return Color::srgb(1.0 - value, value, 0.2);
+    }
+
+    // Higher ground is drawn lighter, flat towns keep their plain colors
+    let color = get_ground_color(cell, season).to_srgba();
+    let shade = 1.0 + ELEVATION_SHADING * cell.elevation.clamp(0.0, 1.0);
+    Color::srgba(
+        (color.red * shade).min(1.0),
+        (color.green * shade).min(1.0),
+        (color.blue * shade).min(1.0),
+        color.alpha,
+    )
+}
+
+fn get_ground_color(cell: &TownCell, season: Season) -> Color {
     match cell.building {
+        BuildingType::None if cell.water => WATER_COLOR,
         BuildingType::None => {
+            // Denser zones are drawn in progressively brighter shades
+            let shade = 0.5 + 0.15 * cell.density as f32;
             match cell.zone {
-                ZoneType::None => Color::rgb(0.2, 0.2, 0.2),
-                ZoneType::Residential => Color::rgb(0.0, 0.5, 0.0),
-                ZoneType::Commercial => Color::rgb(0.0, 0.0, 0.5),
-                ZoneType::Industrial => Color::rgb(0.5, 0.5, 0.0),
+                ZoneType::None => Color::srgb(0.2, 0.2, 0.2),
+                ZoneType::Residential => Color::srgb(0.0, shade, 0.0),
+                ZoneType::Commercial => Color::srgb(0.0, 0.0, shade),
+                ZoneType::Industrial => Color::srgb(shade, shade, 0.0),
             }
         }
-        BuildingType::Road => Color::rgb(0.3, 0.3, 0.3),
-        BuildingType::TownHall => Color::rgb(0.8, 0.2, 0.2),
-        BuildingType::PowerPlant => Color::rgb(0.8, 0.8, 0.0),
-        BuildingType::WaterTower => Color::rgb(0.0, 0.5, 0.8),
-        BuildingType::Police => Color::rgb(0.0, 0.0, 0.8),
-        BuildingType::Fire => Color::rgb(0.8, 0.0, 0.0),
-        BuildingType::Hospital => Color::rgb(0.8, 0.0, 0.8),
-        BuildingType::School => Color::rgb(0.0, 0.8, 0.8),
-        BuildingType::Park => Color::rgb(0.0, 0.8, 0.0),
-        BuildingType::LawAndOrder => Color::rgb(0.5, 0.0, 0.5),
-        BuildingType::Education => Color::rgb(0.0, 0.5, 0.5),
-        BuildingType::Transportation => Color::rgb(0.5, 0.5, 0.0),
-        BuildingType::Health => Color::rgb(0.8, 0.0, 0.0),
-        BuildingType::Energy => Color::rgb(0.8, 0.8, 0.0),
-        BuildingType::Housing => Color::rgb(0.0, 0.0, 0.8),
-        BuildingType::SocialServices => Color::rgb(0.0, 0.8, 0.0),
-        BuildingType::Upgrade => Color::rgb(0.5, 0.5, 0.5),
+        BuildingType::Road => Color::srgb(0.3, 0.3, 0.3),
+        BuildingType::OneWayRoad(_) => Color::srgb(0.25, 0.25, 0.32),
+        BuildingType::Avenue => Color::srgb(0.42, 0.42, 0.4),
+        BuildingType::Highway => Color::srgb(0.18, 0.2, 0.24),
+        BuildingType::Bridge => Color::srgb(0.55, 0.4, 0.25),
+        BuildingType::TownHall => Color::srgb(0.8, 0.2, 0.2),
+        BuildingType::PowerPlant => Color::srgb(0.8, 0.8, 0.0),
+        BuildingType::WaterTower => Color::srgb(0.0, 0.5, 0.8),
+        BuildingType::Landfill => Color::srgb(0.45, 0.35, 0.2),
+        BuildingType::RecyclingCenter => Color::srgb(0.2, 0.6, 0.5),
+        BuildingType::Police => Color::srgb(0.0, 0.0, 0.8),
+        BuildingType::Fire => Color::srgb(0.8, 0.0, 0.0),
+        BuildingType::Hospital => Color::srgb(0.8, 0.0, 0.8),
+        BuildingType::School => Color::srgb(0.0, 0.8, 0.8),
+        BuildingType::BusStop => Color::srgb(0.95, 0.75, 0.1),
+        BuildingType::Park => season.tint_foliage(Color::srgb(0.0, 0.8, 0.0)),
+        BuildingType::LawAndOrder => Color::srgb(0.5, 0.0, 0.5),
+        BuildingType::Education => Color::srgb(0.0, 0.5, 0.5),
+        BuildingType::Transportation => Color::srgb(0.5, 0.5, 0.0),
+        BuildingType::Health => Color::srgb(0.8, 0.0, 0.0),
+        BuildingType::Energy => Color::srgb(0.8, 0.8, 0.0),
+        BuildingType::Housing => Color::srgb(0.0, 0.0, 0.8),
+        BuildingType::SocialServices => Color::srgb(0.0, 0.8, 0.0),
+        BuildingType::Upgrade => Color::srgb(0.5, 0.5, 0.5),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::layout_cell;
+
+    fn flat_cell() -> TownCell {
+        TownCell::empty(IVec2::new(5, 5))
+    }
+
+    fn steep_cell() -> TownCell {
+        TownCell {
+            slope: STEEP_SLOPE * 2.0,
+            ..flat_cell()
+        }
+    }
+
+    #[test]
+    fn steep_ground_only_takes_narrow_roads() {
+        assert_eq!(can_place(BuildingType::Road, &steep_cell(), &[], 1), Ok(()));
+        for building in [BuildingType::Park, BuildingType::PowerPlant] {
+            assert_eq!(can_place(building, &steep_cell(), &[], 1), Err(PlaceError::TooSteep));
+        }
+        assert_eq!(can_zone(&steep_cell()), Err(PlaceError::TooSteep));
+        assert_eq!(can_zone(&flat_cell()), Ok(()));
+    }
+
+    #[test]
+    fn sloped_ground_costs_up_to_double() {
+        assert_eq!(flat_cell().build_cost(BuildingType::Park), 150);
+        let sloped = TownCell {
+            slope: STEEP_SLOPE / 2.0,
+            ..flat_cell()
+        };
+        assert_eq!(sloped.build_cost(BuildingType::Park), 225);
+        assert_eq!(steep_cell().build_cost(BuildingType::Park), 300);
+    }
+
+    fn water_cell() -> TownCell {
+        TownCell {
+            water: true,
+            ..flat_cell()
+        }
+    }
+
+    #[test]
+    fn bridges_go_over_water_and_nothing_else_does() {
+        assert_eq!(can_place(BuildingType::Bridge, &water_cell(), &[], 1), Ok(()));
+        assert_eq!(can_place(BuildingType::Bridge, &flat_cell(), &[], 1), Err(PlaceError::NotOverWater));
+        for building in [BuildingType::Road, BuildingType::Park] {
+            assert_eq!(can_place(building, &water_cell(), &[], 1), Err(PlaceError::OverWater));
+        }
+        assert_eq!(can_zone(&water_cell()), Err(PlaceError::OverWater));
+    }
+
+    #[test]
+    fn only_roads_go_right_beside_a_highway() {
+        assert_eq!(can_place(BuildingType::Highway, &flat_cell(), &[BuildingType::Road, BuildingType::None], 1), Ok(()));
+        assert_eq!(
+            can_place(BuildingType::Highway, &flat_cell(), &[BuildingType::Park], 1),
+            Err(PlaceError::BesideHighway)
+        );
+        assert_eq!(
+            can_place(BuildingType::School, &flat_cell(), &[BuildingType::Highway], 1),
+            Err(PlaceError::BesideHighway)
+        );
+        assert_eq!(can_place(BuildingType::Avenue, &flat_cell(), &[BuildingType::Highway], 1), Ok(()));
+        assert!(BuildingType::Highway.cost() > BuildingType::Avenue.cost());
+        assert!(BuildingType::Avenue.cost() > BuildingType::Road.cost());
+    }
+
+    #[test]
+    fn vehicles_cross_water_on_bridges() {
+        use crate::citizen::Vehicle;
+        use crate::headless::{layout_cell, HeadlessSimulation};
+
+        assert!(BuildingType::Bridge.is_road());
+
+        // Homes west of a river at x = 15 and workplaces east of it, joined by one bridge
+        let river = 15;
+        let mut cells = Vec::new();
+        for x in 10..21 {
+            let building = if x == river { BuildingType::Bridge } else { BuildingType::Road };
+            cells.push(layout_cell(x, 20, ZoneType::None, building));
+        }
+        for x in 10..river {
+            cells.push(layout_cell(x, 21, ZoneType::Residential, BuildingType::None));
+            cells.push(layout_cell(x + 6, 21, ZoneType::Commercial, BuildingType::None));
+            cells.push(layout_cell(x + 6, 19, ZoneType::Industrial, BuildingType::None));
+        }
+        cells.push(layout_cell(10, 19, ZoneType::None, BuildingType::PowerPlant));
+        cells.push(layout_cell(11, 19, ZoneType::None, BuildingType::WaterTower));
+
+        let mut simulation = HeadlessSimulation::new(&cells, 7);
+        let world = simulation.app_mut().world_mut();
+        let mut town_map = world.resource_mut::<TownMap>();
+        for y in 0..TOWN_GRID_SIZE as i32 {
+            town_map.cell_mut(IVec2::new(river, y)).unwrap().water = true;
+        }
+
+        let mut crossed = false;
+        for _ in 0..60 {
+            simulation.run_ticks(1);
+            let world = simulation.app_mut().world_mut();
+            let paths: Vec<Vec<IVec2>> = world
+                .query::<&Vehicle>()
+                .iter(world)
+                .map(|vehicle| vehicle.path.clone())
+                .collect();
+            let town_map = world.resource::<TownMap>();
+            for path in paths {
+                // Vehicles only ever drive onto water over the bridge
+                assert!(path.iter().all(|&pos| {
+                    let cell = town_map.get(pos).unwrap();
+                    !cell.water || cell.building == BuildingType::Bridge
+                }));
+                crossed |= path.contains(&IVec2::new(river, 20))
+                    && path.iter().any(|pos| pos.x < river)
+                    && path.iter().any(|pos| pos.x > river);
+            }
+        }
+        assert!(crossed, "no vehicle drove across the bridge");
+    }
+
+    #[test]
+    fn town_hall_is_built_once_and_never_over() {
+        assert_eq!(can_place(BuildingType::TownHall, &flat_cell(), &[], 0), Ok(()));
+        assert_eq!(
+            can_place(BuildingType::TownHall, &flat_cell(), &[], 1),
+            Err(PlaceError::SecondTownHall)
+        );
+        let town_hall = TownCell {
+            building: BuildingType::TownHall,
+            ..flat_cell()
+        };
+        assert_eq!(can_place(BuildingType::Road, &town_hall, &[], 1), Err(PlaceError::TownHallInTheWay));
+        assert_eq!(can_zone(&town_hall), Err(PlaceError::TownHallInTheWay));
+    }
+
+    #[test]
+    fn departments_and_upgrades_need_their_neighbors() {
+        let department = BuildingType::Education;
+        assert_eq!(
+            can_place(department, &flat_cell(), &[BuildingType::Road], 1),
+            Err(PlaceError::NoAdjacentTownHall)
+        );
+        assert_eq!(can_place(department, &flat_cell(), &[BuildingType::TownHall], 1), Ok(()));
+        assert_eq!(
+            can_place(BuildingType::Upgrade, &flat_cell(), &[BuildingType::TownHall], 1),
+            Err(PlaceError::NoAdjacentDepartment)
+        );
+        assert_eq!(can_place(BuildingType::Upgrade, &flat_cell(), &[department], 1), Ok(()));
+    }
+
+    #[test]
+    fn cells_outside_the_area_are_refused() {
+        let inside = vec![layout_cell(3, 1, ZoneType::None, BuildingType::Road)];
+        assert_eq!(check_cells(&inside, 4, 2, "blueprint"), Ok(()));
+        for (x, y) in [(4, 0), (0, 2), (-1, 0), (0, -1)] {
+            let cells = vec![layout_cell(x, y, ZoneType::None, BuildingType::Road)];
+            let error = check_cells(&cells, 4, 2, "blueprint").unwrap_err();
+            assert!(error.contains("outside the 4x2 blueprint"), "{error}");
+        }
+    }
+
+    #[test]
+    fn cell_listed_twice_is_refused() {
+        let cells = vec![
+            layout_cell(3, 3, ZoneType::None, BuildingType::Road),
+            layout_cell(3, 3, ZoneType::Residential, BuildingType::None),
+        ];
+        let error = check_cells(&cells, 10, 10, "grid").unwrap_err();
+        assert!(error.contains("listed twice"), "{error}");
+    }
+
+    #[test]
+    fn cell_denser_than_zones_grow_is_refused() {
+        let mut home = layout_cell(3, 3, ZoneType::Residential, BuildingType::None);
+        home.density = MAX_DENSITY;
+        assert_eq!(check_cells(&[home], 10, 10, "grid"), Ok(()));
+        home.density = MAX_DENSITY + 1;
+        let error = check_cells(&[home], 10, 10, "grid").unwrap_err();
+        assert!(error.contains("above"), "{error}");
     }
 }
@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+use crate::grid::{CornerCutting, Grid};
+use crate::island::{Island, IslandCellType, ISLAND_CELL_SPACING};
+use crate::simulation::{GameSpeed, Resources};
+use crate::town::{BuildingType, SelectedTown, TownSave, Towns, ZoneType, CITIZENS_PER_DENSITY_LEVEL};
+use crate::menu::outside_pause;
+use crate::GameState;
+use std::time::Duration;
+
+pub struct TradePlugin;
+
+/// This plugin connects the towns on the island and lets them trade surplus power
+impl Plugin for TradePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Trade>()
+            .add_systems(Update, update_trade_routes)
+            .add_systems(Update, balance_trade.run_if(in_state(GameState::TownView)))
+            .add_systems(Update, draw_trade_routes.run_if(in_state(GameState::IslandView)))
+            .add_systems(OnExit(GameState::TownView), stop_trade.run_if(outside_pause));
+    }
+}
+
+// Seconds of game time between trades, matching the simulation tick
+const TRADE_INTERVAL_SECONDS: f32 = 1.0;
+
+// Funds per unit of power sold to or bought from another town
+const POWER_EXPORT_PRICE: f32 = 0.5;
+const POWER_IMPORT_PRICE: f32 = 0.8;
+
+// Power a plant produces and a citizen uses, as the simulation counts them for the shown town
+const POWER_PER_PLANT: i32 = 100;
+const POWER_PER_CITIZEN: f32 = 0.1;
+
+const ROAD_ROUTE_COLOR: Color = Color::srgb(0.9, 0.7, 0.3);
+const FERRY_ROUTE_COLOR: Color = Color::srgb(0.4, 0.8, 1.0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteKind {
+    // Over land, bridging rivers
+    Road,
+    // Across the sea between two coastal towns
+    Ferry,
+}
+
+pub struct TradeRoute {
+    pub towns: (IVec2, IVec2),
+    pub kind: RouteKind,
+    // Island cells the route passes, from the first town to the second
+    pub path: Vec<IVec2>,
+}
+
+impl TradeRoute {
+    fn partner_of(&self, town: IVec2) -> Option<IVec2> {
+        match self.towns {
+            (a, b) if a == town => Some(b),
+            (a, b) if b == town => Some(a),
+            _ => None,
+        }
+    }
+}
+
+// Routes between the towns and what the shown town traded on the last tick
+#[derive(Resource, Default)]
+pub struct Trade {
+    pub routes: Vec<TradeRoute>,
+    pub power_imported: i32,
+    pub power_exported: i32,
+}
+
+impl Trade {
+    // Funds earned per tick, negative while the town buys more than it sells
+    pub fn income(&self) -> i32 {
+        (self.power_exported as f32 * POWER_EXPORT_PRICE - self.power_imported as f32 * POWER_IMPORT_PRICE)
+            as i32
+    }
+}
+
+// Power a town that isn't shown has left over, negative if it's short. Its citizens are
+// estimated from the housing it has built
+fn stored_power_surplus(town: &TownSave) -> i32 {
+    let plants = town
+        .cells
+        .iter()
+        .filter(|cell| cell.building == BuildingType::PowerPlant)
+        .count() as i32;
+    let citizens: i32 = town
+        .cells
+        .iter()
+        .filter(|cell| cell.zone == ZoneType::Residential)
+        .map(|cell| CITIZENS_PER_DENSITY_LEVEL * (cell.density as i32 + 1))
+        .sum();
+    plants * POWER_PER_PLANT - (citizens as f32 * POWER_PER_CITIZEN) as i32
+}
+
+fn is_coastal(island: &Island, pos: IVec2) -> bool {
+    Grid::get_orthogonal_positions(pos).into_iter().any(|neighbor| {
+        Grid::is_in_bounds(neighbor, island.size())
+            && island.grid[neighbor.y as usize][neighbor.x as usize] == IslandCellType::Water
+    })
+}
+
+// Connect every pair of towns, by road where land joins them and by ferry between coastal towns.
+// Roads run diagonally across open land but never squeeze past water on either side
+fn find_route(island: &Island, from: IVec2, to: IVec2) -> Option<TradeRoute> {
+    let passable = |pos: IVec2| {
+        matches!(
+            island.grid[pos.y as usize][pos.x as usize],
+            IslandCellType::Land | IslandCellType::Forest | IslandCellType::River | IslandCellType::Town
+        )
+    };
+    if let Some(path) = Grid::find_path_diagonal(from, to, passable, CornerCutting::NoCorners, island.size()) {
+        return Some(TradeRoute {
+            towns: (from, to),
+            kind: RouteKind::Road,
+            path,
+        });
+    }
+    (is_coastal(island, from) && is_coastal(island, to)).then(|| TradeRoute {
+        towns: (from, to),
+        kind: RouteKind::Ferry,
+        path: vec![from, to],
+    })
+}
+
+fn update_trade_routes(island: Option<Res<Island>>, mut trade: ResMut<Trade>) {
+    let Some(island) = island else {
+        if !trade.routes.is_empty() {
+            trade.routes.clear();
+        }
+        return;
+    };
+    if !island.is_changed() {
+        return;
+    }
+
+    let towns = &island.towns;
+    trade.routes = towns
+        .iter()
+        .enumerate()
+        .flat_map(|(index, &from)| towns[index + 1..].iter().map(move |&to| (from, to)))
+        .filter_map(|(from, to)| find_route(&island, from, to))
+        .collect();
+}
+
+// Sell the shown town's spare power to connected towns that are short, or buy what it lacks
+// from those with some left over
+fn balance_trade(
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    mut timer: Local<Timer>,
+    mut trade: ResMut<Trade>,
+    resources: Res<Resources>,
+    towns: Res<Towns>,
+    selected_town: Option<Res<SelectedTown>>,
+) {
+    if timer.duration() == Duration::ZERO {
+        *timer = Timer::from_seconds(TRADE_INTERVAL_SECONDS, TimerMode::Repeating);
+    }
+
+    timer.tick(speed.delta(&time));
+    if !timer.just_finished() {
+        return;
+    }
+    let Some(selected_town) = selected_town else {
+        return;
+    };
+
+    // Last tick's trade is part of the production, leave it out to see what the town makes itself
+    let power = &resources.power;
+    let mut surplus = power.production - trade.power_imported + trade.power_exported - power.consumption;
+
+    let partners: Vec<i32> = trade
+        .routes
+        .iter()
+        .filter_map(|route| route.partner_of(selected_town.0))
+        .filter_map(|partner| towns.towns.get(&partner))
+        .map(stored_power_surplus)
+        .collect();
+
+    let (mut imported, mut exported) = (0, 0);
+    for partner in partners {
+        if surplus > 0 && partner < 0 {
+            let amount = surplus.min(-partner);
+            exported += amount;
+            surplus -= amount;
+        } else if surplus < 0 && partner > 0 {
+            let amount = partner.min(-surplus);
+            imported += amount;
+            surplus += amount;
+        }
+    }
+    trade.power_imported = imported;
+    trade.power_exported = exported;
+}
+
+fn stop_trade(mut trade: ResMut<Trade>) {
+    trade.power_imported = 0;
+    trade.power_exported = 0;
+}
+
+fn draw_trade_routes(mut gizmos: Gizmos, trade: Res<Trade>, island: Option<Res<Island>>) {
+    let Some(island) = island else {
+        return;
+    };
+    let size = island.size() as f32;
+    let to_world = |pos: &IVec2| (pos.as_vec2() - Vec2::splat(size / 2.0)) * ISLAND_CELL_SPACING;
+    for route in trade.routes.iter() {
+        let color = match route.kind {
+            RouteKind::Road => ROAD_ROUTE_COLOR,
+            RouteKind::Ferry => FERRY_ROUTE_COLOR,
+        };
+        gizmos.linestrip_2d(route.path.iter().map(to_world), color);
+    }
+}
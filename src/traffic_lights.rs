@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+use crate::departments::DepartmentBonuses;
+use crate::grid::Grid;
+use crate::simulation::{GameSpeed, SimulationSet};
+use crate::town::TownMap;
+use crate::GameState;
+use std::collections::HashSet;
+
+pub struct TrafficLightsPlugin;
+
+/// This plugin finds the road intersections and runs the traffic lights on them
+impl Plugin for TrafficLightsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrafficLights>()
+            .add_systems(
+                Update,
+                update_traffic_lights
+                    .in_set(SimulationSet::TrafficLights)
+                    .run_if(in_state(GameState::TownView)),
+            );
+    }
+}
+
+// Seconds for a light to go green both ways, before the Transportation department shortens it
+const LIGHT_CYCLE_SECONDS: f32 = 6.0;
+
+// Road neighbors that make a road cell an intersection
+const INTERSECTION_ROADS: usize = 3;
+
+#[derive(Resource, Default)]
+pub struct TrafficLights {
+    // Road cells where three or four roads meet
+    intersections: HashSet<IVec2>,
+    // Seconds into the current cycle, east-west traffic goes first
+    elapsed: f32,
+    cycle: f32,
+}
+
+impl TrafficLights {
+    pub fn is_intersection(&self, pos: IVec2) -> bool {
+        self.intersections.contains(&pos)
+    }
+
+    pub fn intersections(&self) -> impl Iterator<Item = &IVec2> {
+        self.intersections.iter()
+    }
+
+    // Whether the lights are green for traffic going east or west, otherwise it's north or south
+    pub fn east_west_green(&self) -> bool {
+        self.cycle <= 0.0 || self.elapsed < self.cycle / 2.0
+    }
+
+    // Whether a vehicle may drive from a cell onto the next, only red lights on the way into
+    // an intersection stop it
+    pub fn allows(&self, from: IVec2, to: IVec2) -> bool {
+        !self.is_intersection(to) || ((to - from).x != 0) == self.east_west_green()
+    }
+}
+
+// Road cells with enough road neighbors to be an intersection
+fn find_intersections(town_map: &TownMap) -> HashSet<IVec2> {
+    let is_road = |pos: IVec2| town_map.get(pos).is_some_and(|cell| cell.building.is_road());
+    town_map
+        .iter()
+        .map(|cell| cell.position)
+        .filter(|&pos| is_road(pos))
+        .filter(|&pos| {
+            Grid::get_orthogonal_positions(pos)
+                .into_iter()
+                .filter(|&neighbor| is_road(neighbor))
+                .count()
+                >= INTERSECTION_ROADS
+        })
+        .collect()
+}
+
+// Find the intersections whenever the town changes and step the lights through their cycle
+fn update_traffic_lights(
+    town_map: Res<TownMap>,
+    mut seen_revision: Local<u64>,
+    time: Res<Time>,
+    speed: Res<GameSpeed>,
+    bonuses: Res<DepartmentBonuses>,
+    mut lights: ResMut<TrafficLights>,
+) {
+    if town_map.revision() != *seen_revision {
+        *seen_revision = town_map.revision();
+        lights.intersections = find_intersections(&town_map);
+    }
+    lights.cycle = LIGHT_CYCLE_SECONDS * bonuses.light_cycle;
+    lights.elapsed = (lights.elapsed + speed.delta_seconds(&time)) % lights.cycle;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::town::BuildingType;
+
+    #[test]
+    fn intersections_are_where_three_or_more_roads_meet() {
+        // A T junction at (5, 5) and a crossroads at (5, 8), joined by a street
+        let mut town_map = TownMap::default();
+        let roads = (3..8)
+            .map(|x| IVec2::new(x, 5))
+            .chain((6..11).map(|y| IVec2::new(5, y)))
+            .chain([IVec2::new(4, 8), IVec2::new(6, 8)]);
+        for pos in roads {
+            town_map.cell_mut(pos).unwrap().building = BuildingType::Road;
+        }
+        let intersections = find_intersections(&town_map);
+        assert_eq!(intersections, HashSet::from([IVec2::new(5, 5), IVec2::new(5, 8)]));
+    }
+
+    #[test]
+    fn red_lights_only_stop_traffic_going_into_the_intersection() {
+        let crossing = IVec2::new(5, 5);
+        let mut lights = TrafficLights {
+            intersections: HashSet::from([crossing]),
+            elapsed: 1.0,
+            cycle: LIGHT_CYCLE_SECONDS,
+        };
+        assert!(lights.east_west_green());
+        assert!(lights.allows(IVec2::new(4, 5), crossing));
+        assert!(!lights.allows(IVec2::new(5, 4), crossing));
+        // Leaving the intersection or driving elsewhere is never held up
+        assert!(lights.allows(crossing, IVec2::new(5, 6)));
+        assert!(lights.allows(IVec2::new(1, 1), IVec2::new(1, 2)));
+
+        lights.elapsed = LIGHT_CYCLE_SECONDS * 0.75;
+        assert!(!lights.east_west_green());
+        assert!(lights.allows(IVec2::new(5, 4), crossing));
+        assert!(!lights.allows(IVec2::new(4, 5), crossing));
+    }
+}
@@ -0,0 +1,367 @@
+use bevy::prelude::*;
+use crate::actions::{key_name, InputAction, KeyBindings};
+use crate::citizen::{Congestion, Dispatched, Vehicle, VehicleKind, MIN_PATH_COST};
+use crate::grid::Grid;
+use crate::town::{cursor_grid_position, grid_to_world, BuildingType, TownMap, TOWN_GRID_SIZE};
+use crate::menu::outside_pause;
+use crate::GameState;
+use serde::{Deserialize, Serialize};
+
+pub struct TransitPlugin;
+
+/// This plugin runs buses along the town's routes and lets the player lay out new routes
+impl Plugin for TransitPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TransitRoutes>()
+            .init_resource::<RouteEditor>()
+            .add_systems(
+                Update,
+                (
+                    toggle_route_editor,
+                    edit_route.after(toggle_route_editor),
+                    update_route_hint,
+                    spawn_route_buses,
+                    advance_route_buses,
+                    draw_routes,
+                ).run_if(in_state(GameState::TownView)),
+            )
+            .add_systems(OnExit(GameState::TownView), close_route_editor.run_if(outside_pause));
+    }
+}
+
+// Citizens walk at most this many cells to or from a bus stop
+const BUS_STOP_WALK_DISTANCE: i32 = 4;
+
+// How far a bus stop may be from the road its buses stop on
+const MAX_STOP_ROAD_DISTANCE: i32 = 2;
+
+const ROUTE_COLOR: Color = Color::srgb(0.95, 0.75, 0.1);
+
+// Stops a bus visits in order, looping back to the first after the last
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct BusRoute {
+    pub stops: Vec<IVec2>,
+}
+
+impl BusRoute {
+    // Whether a citizen could ride this route from one place to another
+    pub fn serves(&self, from: IVec2, to: IVec2) -> bool {
+        let near_stop = |pos: IVec2| {
+            self.stops
+                .iter()
+                .any(|stop| Grid::manhattan_distance(*stop, pos) <= BUS_STOP_WALK_DISTANCE)
+        };
+        near_stop(from) && near_stop(to)
+    }
+}
+
+// Bus routes of the shown town, stored with its layout when leaving it
+#[derive(Resource, Default, Clone)]
+pub struct TransitRoutes {
+    pub routes: Vec<BusRoute>,
+}
+
+impl TransitRoutes {
+    pub fn serves(&self, from: IVec2, to: IVec2) -> bool {
+        self.routes.iter().any(|route| route.serves(from, to))
+    }
+}
+
+// Route being laid out, stops are added by clicking them in order
+#[derive(Resource, Default)]
+pub(crate) struct RouteEditor {
+    editing: bool,
+    stops: Vec<IVec2>,
+}
+
+// Bus driving a route, heading for the stop at `next_stop`
+#[derive(Component)]
+struct RouteBus {
+    route: usize,
+    next_stop: usize,
+}
+
+// Toolbar button starting and finishing a route
+#[derive(Component)]
+struct RouteButton;
+
+// Instructions shown while a route is laid out
+#[derive(Component)]
+struct RouteHint;
+
+// Spawn the toolbar button for the route editor
+pub fn spawn_route_button(parent: &mut ChildBuilder) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(80.0),
+                    height: Val::Px(40.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::srgb(0.5, 0.4, 0.1).into(),
+                ..default()
+            },
+            RouteButton,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Bus route",
+                TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+// Start a route with the toolbar button, finish it with the button again or the finish key and drop it with the cancel key
+fn toggle_route_editor(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    buttons: Query<&Interaction, (Changed<Interaction>, With<RouteButton>)>,
+    mut editor: ResMut<RouteEditor>,
+    mut routes: ResMut<TransitRoutes>,
+    hints: Query<Entity, With<RouteHint>>,
+) {
+    let clicked = buttons.iter().any(|interaction| *interaction == Interaction::Pressed);
+    let finish = clicked || key_bindings.just_pressed(InputAction::FinishRoute, &keyboard_input);
+    let cancel = key_bindings.just_pressed(InputAction::Cancel, &keyboard_input);
+
+    if !editor.editing {
+        if clicked {
+            editor.editing = true;
+            editor.stops.clear();
+            commands.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 18.0,
+                        color: ROUTE_COLOR,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(60.0),
+                    right: Val::Px(10.0),
+                    ..default()
+                }),
+                RouteHint,
+            ));
+        }
+        return;
+    }
+
+    if !finish && !cancel {
+        return;
+    }
+    if finish && editor.stops.len() >= 2 {
+        let stops = std::mem::take(&mut editor.stops);
+        routes.routes.push(BusRoute { stops });
+    }
+    editor.editing = false;
+    editor.stops.clear();
+    for entity in hints.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// Add clicked bus stops to the route being laid out. Clicks are swallowed while editing
+// so they don't also build on the town
+pub(crate) fn edit_route(
+    mut mouse_button_input: ResMut<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    ui_interactions: Query<&Interaction, With<Node>>,
+    town_map: Res<TownMap>,
+    mut editor: ResMut<RouteEditor>,
+) {
+    if !editor.editing || !mouse_button_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+    if ui_interactions.iter().any(|interaction| *interaction != Interaction::None) {
+        return;
+    }
+    mouse_button_input.clear_just_pressed(MouseButton::Left);
+
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.get_single(), camera_q.get_single()) else {
+        return;
+    };
+    let Some(position) = cursor_grid_position(window, camera, camera_transform) else {
+        return;
+    };
+    let is_stop = town_map
+        .iter()
+        .any(|cell| cell.position == position && cell.building == BuildingType::BusStop);
+    if is_stop && editor.stops.last() != Some(&position) {
+        editor.stops.push(position);
+    }
+}
+
+fn update_route_hint(
+    editor: Res<RouteEditor>,
+    key_bindings: Res<KeyBindings>,
+    mut hints: Query<&mut Text, With<RouteHint>>,
+) {
+    for mut text in hints.iter_mut() {
+        text.sections[0].value = format!(
+            "New route: {} stops\nClick bus stops in order, {} to finish, {} to cancel",
+            editor.stops.len(),
+            key_name(key_bindings.key(InputAction::FinishRoute)),
+            key_name(key_bindings.key(InputAction::Cancel))
+        );
+    }
+}
+
+fn close_route_editor(mut editor: ResMut<RouteEditor>) {
+    *editor = RouteEditor::default();
+}
+
+// Road cell a bus stops on for a bus stop
+fn stop_road(roads: &[IVec2], stop: IVec2) -> Option<IVec2> {
+    roads
+        .iter()
+        .copied()
+        .filter(|road| Grid::manhattan_distance(*road, stop) <= MAX_STOP_ROAD_DISTANCE)
+        .min_by_key(|road| Grid::manhattan_distance(*road, stop))
+}
+
+// Route along the roads between two stops, avoiding jams and keeping to one-way roads like other traffic
+fn route_leg(
+    roads: &[IVec2],
+    town_map: &TownMap,
+    congestion: &Congestion,
+    from: IVec2,
+    to: IVec2,
+) -> Option<Vec<IVec2>> {
+    let start = stop_road(roads, from)?;
+    let goal = stop_road(roads, to)?;
+    Grid::find_path_directed(
+        start,
+        goal,
+        |step_from, step_to| roads.contains(&step_to) && town_map.follows_traffic(step_from, step_to),
+        |pos| congestion.path_cost(town_map, pos),
+        MIN_PATH_COST,
+        TOWN_GRID_SIZE,
+    )
+}
+
+fn road_positions(town_map: &TownMap) -> Vec<IVec2> {
+    town_map
+        .iter()
+        .filter(|cell| cell.building.is_road())
+        .map(|cell| cell.position)
+        .collect()
+}
+
+// Put a bus on every route that doesn't have one yet
+fn spawn_route_buses(
+    mut commands: Commands,
+    routes: Res<TransitRoutes>,
+    buses: Query<&RouteBus>,
+    town_map: Res<TownMap>,
+    congestion: Res<Congestion>,
+) {
+    let unserved: Vec<usize> = (0..routes.routes.len())
+        .filter(|index| !buses.iter().any(|bus| bus.route == *index))
+        .collect();
+    if unserved.is_empty() {
+        return;
+    }
+
+    let roads = road_positions(&town_map);
+    for index in unserved {
+        let stops = &routes.routes[index].stops;
+        if stops.len() < 2 {
+            continue;
+        }
+        let Some(path) = route_leg(&roads, &town_map, &congestion, stops[0], stops[1]) else {
+            continue;
+        };
+
+        let kind = VehicleKind::Bus;
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: kind.color(),
+                    custom_size: Some(kind.size()),
+                    ..default()
+                },
+                transform: Transform::from_translation(grid_to_world(path[0], 0.5)),
+                ..default()
+            },
+            Vehicle {
+                kind,
+                start: path[0],
+                destination: *path.last().unwrap(),
+                path,
+                path_index: 0,
+                speed: kind.speed_range().start,
+                waiting: 0.0,
+            },
+            Dispatched,
+            RouteBus {
+                route: index,
+                next_stop: 1,
+            },
+        ));
+    }
+}
+
+// Send buses that reached a stop on to the next one, retiring them if their route is gone
+fn advance_route_buses(
+    mut commands: Commands,
+    routes: Res<TransitRoutes>,
+    mut buses: Query<(Entity, &mut RouteBus, &mut Vehicle)>,
+    town_map: Res<TownMap>,
+    congestion: Res<Congestion>,
+) {
+    let mut roads = None;
+    for (entity, mut bus, mut vehicle) in buses.iter_mut() {
+        if vehicle.path_index + 1 < vehicle.path.len() {
+            continue;
+        }
+        let Some(route) = routes.routes.get(bus.route).filter(|route| route.stops.len() >= 2) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        let roads = roads.get_or_insert_with(|| road_positions(&town_map));
+        let from = route.stops[bus.next_stop % route.stops.len()];
+        bus.next_stop = (bus.next_stop + 1) % route.stops.len();
+        let to = route.stops[bus.next_stop];
+        match route_leg(roads, &town_map, &congestion, from, to) {
+            Some(path) => {
+                vehicle.start = path[0];
+                vehicle.destination = *path.last().unwrap();
+                vehicle.path = path;
+                vehicle.path_index = 0;
+                vehicle.waiting = 0.0;
+            }
+            // The stop or the road to it is gone, the route gets a fresh bus once it's reachable
+            None => {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+// Draw the routes, and the one being laid out, as lines between their stops
+fn draw_routes(mut gizmos: Gizmos, routes: Res<TransitRoutes>, editor: Res<RouteEditor>) {
+    let to_world = |stop: &IVec2| grid_to_world(*stop, 0.0).truncate();
+    for route in routes.routes.iter() {
+        let mut points: Vec<Vec2> = route.stops.iter().map(to_world).collect();
+        if let Some(&first) = points.first() {
+            points.push(first);
+        }
+        gizmos.linestrip_2d(points, ROUTE_COLOR.with_alpha(0.6));
+    }
+    if editor.editing {
+        gizmos.linestrip_2d(editor.stops.iter().map(to_world), Color::WHITE);
+    }
+}
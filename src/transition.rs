@@ -0,0 +1,129 @@
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::ui::{FocusPolicy, UiSystem};
+use crate::actions::gamepad::gamepad_cursor;
+use crate::GameState;
+
+pub struct TransitionPlugin;
+
+/// This plugin fades the screen to black and back around changes of the game state,
+/// ignoring input until the next state has faded in
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Transition>()
+            .add_systems(
+                PreUpdate,
+                block_input
+                    .after(InputSystem)
+                    .after(gamepad_cursor)
+                    .before(UiSystem::Focus)
+                    .run_if(|transition: Res<Transition>| transition.is_active()),
+            )
+            .add_systems(PostUpdate, fade_transition);
+    }
+}
+
+// Seconds the screen takes to go black, and again to come back
+const DEFAULT_FADE_SECONDS: f32 = 0.25;
+
+// Fade between two states. A state change is held back until the screen is black
+#[derive(Resource)]
+pub struct Transition {
+    pub fade_seconds: f32,
+    // State to change to once the screen is black
+    target: Option<GameState>,
+    // From 0 when nothing is covered to 1 when the screen is black
+    opacity: f32,
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        Transition {
+            fade_seconds: DEFAULT_FADE_SECONDS,
+            target: None,
+            opacity: 0.0,
+        }
+    }
+}
+
+impl Transition {
+    pub fn is_active(&self) -> bool {
+        self.target.is_some() || self.opacity > 0.0
+    }
+}
+
+// Covers the screen while fading
+#[derive(Component)]
+struct TransitionOverlay;
+
+// Hold back state changes until the screen has faded out, then fade the next state in.
+// The pause menu opens and closes right away, it's drawn over the view it pauses
+fn fade_transition(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut transition: ResMut<Transition>,
+    mut overlay: Query<(Entity, &mut BackgroundColor), With<TransitionOverlay>>,
+) {
+    if let NextState::Pending(target) = next_state.as_ref() {
+        let instant = *target == GameState::Paused || *state.get() == GameState::Paused;
+        if !instant && target != state.get() {
+            transition.target = Some(target.clone());
+            next_state.reset();
+        }
+    }
+    if !transition.is_active() {
+        return;
+    }
+
+    let step = time.delta_seconds() / transition.fade_seconds.max(f32::EPSILON);
+    if transition.target.is_some() {
+        transition.opacity = (transition.opacity + step).min(1.0);
+        if transition.opacity >= 1.0 {
+            if let Some(target) = transition.target.take() {
+                next_state.set(target);
+            }
+        }
+    } else {
+        transition.opacity = (transition.opacity - step).max(0.0);
+    }
+
+    // Leaving a view despawns its UI, the overlay included, so it's spawned again when missing
+    let color = Color::srgba(0.0, 0.0, 0.0, transition.opacity);
+    match overlay.get_single_mut() {
+        Ok((entity, _)) if transition.opacity <= 0.0 => commands.entity(entity).despawn_recursive(),
+        Ok((_, mut background)) => *background = color.into(),
+        Err(_) if transition.opacity > 0.0 => {
+            commands.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        position_type: PositionType::Absolute,
+                        ..default()
+                    },
+                    background_color: color.into(),
+                    focus_policy: FocusPolicy::Block,
+                    z_index: ZIndex::Global(100),
+                    ..default()
+                },
+                TransitionOverlay,
+            ));
+        }
+        Err(_) => {}
+    }
+}
+
+// Clicks and key presses during a fade would reach the state on either side of it. Runs after
+// the gamepad cursor, which presses the mouse buttons too
+fn block_input(
+    mut keyboard_input: ResMut<ButtonInput<KeyCode>>,
+    mut mouse_button_input: ResMut<ButtonInput<MouseButton>>,
+    mut keyboard_events: ResMut<Events<KeyboardInput>>,
+) {
+    keyboard_input.reset_all();
+    mouse_button_input.reset_all();
+    keyboard_events.clear();
+}
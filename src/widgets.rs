@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+
+pub struct WidgetsPlugin;
+
+/// This plugin drives the reusable UI widgets shared by the different screens
+impl Plugin for WidgetsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_sliders);
+    }
+}
+
+// Horizontal slider holding a value in [0, 1], set by clicking or dragging along its track
+#[derive(Component)]
+pub struct Slider {
+    pub value: f32,
+}
+
+// Filled part of a slider's track
+#[derive(Component)]
+struct SliderFill;
+
+// Spawn a slider with the given initial value and extra components (usually a marker)
+pub fn spawn_slider(parent: &mut ChildBuilder, value: f32, extra: impl Bundle) {
+    parent
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Px(200.0),
+                    height: Val::Px(16.0),
+                    ..default()
+                },
+                background_color: Color::srgb(0.2, 0.2, 0.2).into(),
+                ..default()
+            },
+            Interaction::default(),
+            RelativeCursorPosition::default(),
+            Slider {
+                value: value.clamp(0.0, 1.0),
+            },
+            extra,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(value.clamp(0.0, 1.0) * 100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: Color::srgb(0.6, 0.6, 0.6).into(),
+                    ..default()
+                },
+                SliderFill,
+            ));
+        });
+}
+
+// Move pressed sliders to the cursor and resize their fill
+fn update_sliders(
+    mut sliders: Query<(&Interaction, &RelativeCursorPosition, &mut Slider, &Children)>,
+    mut fills: Query<&mut Style, With<SliderFill>>,
+) {
+    for (interaction, cursor, mut slider, children) in sliders.iter_mut() {
+        if *interaction == Interaction::Pressed {
+            if let Some(normalized) = cursor.normalized {
+                let value = normalized.x.clamp(0.0, 1.0);
+                if value != slider.value {
+                    slider.value = value;
+                }
+            }
+        }
+
+        if slider.is_changed() {
+            for &child in children.iter() {
+                if let Ok(mut style) = fills.get_mut(child) {
+                    style.width = Val::Percent(slider.value * 100.0);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use bevy::prelude::IVec2;
+use bevy_game::headless::{BuildingType, CellSave, HeadlessSimulation, Population, ZoneType};
+
+fn cell(x: i32, y: i32, zone: ZoneType, building: BuildingType) -> CellSave {
+    CellSave {
+        position: IVec2::new(x, y),
+        zone,
+        building,
+        density: 0,
+    }
+}
+
+// A street with ten residential cells on one side, two power plants and a water tower at its end
+// and a few shops and workplaces on the other side
+fn small_town() -> Vec<CellSave> {
+    let mut cells = Vec::new();
+    for x in 10..24 {
+        cells.push(cell(x, 20, ZoneType::None, BuildingType::Road));
+    }
+    for x in 10..20 {
+        cells.push(cell(x, 21, ZoneType::Residential, BuildingType::None));
+    }
+    for x in 10..14 {
+        cells.push(cell(x, 19, ZoneType::Commercial, BuildingType::None));
+    }
+    for x in 14..18 {
+        cells.push(cell(x, 19, ZoneType::Industrial, BuildingType::None));
+    }
+    cells.push(cell(22, 21, ZoneType::None, BuildingType::PowerPlant));
+    cells.push(cell(23, 21, ZoneType::None, BuildingType::PowerPlant));
+    cells.push(cell(22, 19, ZoneType::None, BuildingType::WaterTower));
+    cells.push(cell(23, 19, ZoneType::None, BuildingType::TownHall));
+    cells
+}
+
+// Developed density of every cell, the part of a layout the simulation changes
+fn densities(simulation: &HeadlessSimulation) -> Vec<(IVec2, u8)> {
+    simulation.cells().iter().map(|cell| (cell.position, cell.density)).collect()
+}
+
+#[test]
+fn residential_street_with_power_fills_its_homes() {
+    let mut simulation = HeadlessSimulation::new(&small_town(), 7);
+    simulation.run_ticks(150);
+
+    // Ten undeveloped residential cells house 50 citizens
+    assert!(simulation.is_running());
+    assert!(simulation.citizens() >= 50, "only {} citizens", simulation.citizens());
+}
+
+#[test]
+fn same_seed_simulates_the_same_town() {
+    let mut first = HeadlessSimulation::new(&small_town(), 7);
+    let mut second = HeadlessSimulation::new(&small_town(), 7);
+    first.run_ticks(200);
+    second.run_ticks(200);
+
+    assert_eq!(first.citizens(), second.citizens());
+    assert_eq!(first.economy().funds, second.economy().funds);
+    assert_eq!(densities(&first), densities(&second));
+}
+
+#[test]
+fn same_seed_runs_through_the_same_brownout() {
+    let mut runs = [HeadlessSimulation::new(&small_town(), 7), HeadlessSimulation::new(&small_town(), 7)];
+    for simulation in runs.iter_mut() {
+        // Start with the homes lived in, so the town draws power from the first tick
+        simulation.app_mut().world_mut().resource_mut::<Population>().total = 40;
+        simulation.run_ticks(60);
+        simulation.clear_cell(IVec2::new(22, 21));
+        simulation.clear_cell(IVec2::new(23, 21));
+        // Long enough for the stored power to run out and zones to go dark
+        simulation.run_ticks(300);
+    }
+    let [mut first, mut second] = runs;
+
+    assert!(!first.brownout().is_empty(), "no brownout after the power plants were removed");
+    assert_eq!(first.brownout(), second.brownout());
+    assert_eq!(first.population().total, second.population().total);
+    assert_eq!(first.economy().funds, second.economy().funds);
+    assert_eq!(first.town().happiness, second.town().happiness);
+    assert_eq!(densities(&first), densities(&second));
+    assert_eq!(first.citizens(), second.citizens());
+}